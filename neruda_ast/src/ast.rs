@@ -18,7 +18,7 @@ pub fn find_imports(tree: &ParseResult, text: &str) -> Vec<ImportKind> {
                     if txt != "string" {
                         continue;
                     }
-                    let content = &text[tok.index + 1..tok.index + tok.len - 1];
+                    let content = unquote(&text[tok.span()]);
                     if content.starts_with("#") {
                         result.push(ImportKind::Runtime(content[1..].to_string()));
                     } else {
@@ -36,9 +36,7 @@ pub fn read_string(node: &Nodes, text: &str) -> String {
     match node {
         Nodes::Node(_) => String::new(),
         Nodes::Token(tok) => match &tok.kind {
-            TokenKinds::Complex(txt) => {
-                text[tok.index + 1..tok.index + tok.len - 1].to_string()
-            }
+            TokenKinds::Complex(_) => unquote(&text[tok.span()]).to_string(),
             _ => String::new(),
         },
     }
@@ -48,9 +46,7 @@ pub fn try_read_string(node: &Nodes, text: &str) -> Option<String> {
     match node {
         Nodes::Node(_) => None,
         Nodes::Token(tok) => match &tok.kind {
-            TokenKinds::Complex(txt) => {
-                Some(text[tok.index + 1..tok.index + tok.len - 1].to_string())
-            }
+            TokenKinds::Complex(_) => Some(unquote(&text[tok.span()]).to_string()),
             _ => None,
         },
     }
@@ -1,4 +1,6 @@
 pub mod ast;
+#[rustfmt::skip]
+pub mod typed_ast;
 
 extern crate alloc;
 use alloc::string::*;
@@ -7,7 +9,7 @@ use alloc::vec;
 type Map = std::collections::HashMap<String, VariableKind>;
 
 use ruparse::grammar;
-use ruparse::{grammar::*, lexer::*, Parser};
+use ruparse::{grammar::*, lexer::*, preprocessor::*, Parser};
 
 /// Generates a grammar for Neruda programming language
 pub fn gen_parser() -> Parser {
@@ -51,198 +53,60 @@ pub fn gen_parser() -> Parser {
     ];
     parser.lexer.add_tokens(&tokens);
 
-    let preprocessor: Preprocessor = |text_, tokens| {
-        let mut new_tokens = vec![];
-        let mut i = 0;
-        'main: while i < tokens.len() {
-            let token = &tokens[i];
-            match &token.kind {
-                TokenKinds::Text => {
-                    let text = &text_[token.index..token.index + token.len];
-
-                    // test for number
-                    // strip suffix character (u, i, f, etc.)
-                    let c = text.chars().last().unwrap();
-                    let text1 = if c.is_alphabetic() {
-                        &text[..text.len() - text.chars().last().unwrap().len_utf8()]
-                    } else {
-                        text
-                    };
-                    match text1.parse::<u64>() {
-                        Ok(_) => {
-                            if tokens[i + 1].kind != TokenKinds::Token(".".to_string()) {
-                                // it's an integer (but could be another type if it has a suffix)
-                                new_tokens.push(Token {
-                                    kind: TokenKinds::Complex(
-                                        match c {
-                                            'u' => "uint",
-                                            'i' => "int",
-                                            'f' => "float",
-                                            'c' => "char",
-                                            _ => "int",
-                                        }
-                                        .to_string(),
-                                    ),
-                                    index: token.index,
-                                    len: token.len,
-                                    location: token.location.clone(),
-                                });
-                                i += 1;
-                                continue 'main;
-                            }
-                            // it's a float (suffix is not allowed)
-                            match tokens[i + 2].kind {
-                                TokenKinds::Text => {
-                                    let token = &tokens[i + 2];
-                                    let text = &text_[token.index..token.index + token.len];
-                                    match text.parse::<u64>() {
-                                        Ok(_) => {
-                                            // it's a float with a decimal value
-                                            new_tokens.push(Token {
-                                                index: tokens[i].index,
-                                                len: tokens[i].len
-                                                    + tokens[i + 1].len
-                                                    + tokens[i + 2].len,
-                                                location: token.location.clone(),
-                                                kind: TokenKinds::Complex("float".to_string()),
-                                            });
-                                            i += 3;
-                                            continue 'main;
-                                        }
-                                        Err(_) => {
-                                            // it's a float without a decimal value even though it has a decimal point (error)
-                                            Err(PreprocessorError {
-                                                message: "Expected a float".to_string(),
-                                                location: token.location.clone(),
-                                                len: token.len
-                                                    + tokens[i + 1].len
-                                                    + tokens[i + 2].len,
-                                            })?
-                                        }
-                                    }
-                                }
-                                _ => {
-                                    // it's a float without a decimal value
-                                    new_tokens.push(Token {
-                                        index: token.index,
-                                        len: token.len + tokens[i + 1].len,
-                                        location: token.location.clone(),
-                                        kind: TokenKinds::Complex("float".to_string()),
-                                    });
-                                    i += 2;
-                                    continue 'main;
-                                }
-                            }
-                        }
-                        Err(_) => (),
-                    }
-                    new_tokens.push(token.clone());
-                }
-                TokenKinds::Token(tok) => match tok.as_str() {
-                    "\"" => {
-                        let mut j = i + 1;
-                        while j < tokens.len() {
-                            let current = &tokens[j];
-                            if current.kind == TokenKinds::Token("\"".to_string())
-                                && tokens[j - 1].kind != TokenKinds::Token("\\".to_string())
-                            {
-                                new_tokens.push(Token {
-                                    index: token.index,
-                                    len: current.index - token.index + current.len,
-                                    location: token.location.clone(),
-                                    kind: TokenKinds::Complex("string".to_string()),
-                                });
-                                i = j + 1;
-                                continue 'main;
-                            }
-                            j += 1;
-                        }
-                        let current = &tokens[j - 1];
-                        Err(PreprocessorError {
-                            message: "Expected a closing quote".to_string(),
-                            location: token.location.clone(),
-                            len: current.index - token.index + current.len,
-                        })?;
-                    }
-                    "//" => {
-                        i += 1;
-                        // first check if it's a doc comment
-                        if tokens[i].kind == TokenKinds::Token("/".to_string()) {
-                            i += 1;
-                            let start = i;
-                            loop {
-                                match &tokens[i].kind {
-                                    TokenKinds::Control(_) => {
-                                        i += 1;
-                                        let doc_comment = Token {
-                                            index: tokens[start].index,
-                                            len: tokens[i - 1].index + tokens[i - 1].len
-                                                - tokens[start].index,
-                                            location: tokens[start].location.clone(),
-                                            kind: TokenKinds::Complex("doc_comment".to_string()),
-                                        };
-                                        new_tokens.push(doc_comment);
-                                        continue 'main;
-                                    }
-                                    _ => (),
-                                }
-                                i += 1;
-                            }
-                        }
-                        // it's a normal comment
-                        loop {
-                            match &tokens[i].kind {
-                                TokenKinds::Control(_) => {
-                                    i += 1;
-                                    continue 'main;
-                                }
-                                _ => (),
-                            }
-                            i += 1;
-                        }
-                    }
-                    _ => {
-                        new_tokens.push(token.clone());
-                    }
-                },
-                TokenKinds::Whitespace => (),
-                TokenKinds::Control(ControlTokenKind::Eol) => (),
-                _ => {
-                    new_tokens.push(token.clone());
-                }
-            }
-            i += 1;
-        }
-        Ok(new_tokens)
-    };
-    parser.lexer.preprocessors.push(preprocessor);
-
-    let operators = Enumerator {
-        name: "operators".to_string(),
-        values: vec![
-            MatchToken::Token(TokenKinds::Token("+=".to_string())),
-            MatchToken::Token(TokenKinds::Token("-=".to_string())),
-            MatchToken::Token(TokenKinds::Token("*=".to_string())),
-            MatchToken::Token(TokenKinds::Token("/=".to_string())),
-            MatchToken::Token(TokenKinds::Token("+".to_string())),
-            MatchToken::Token(TokenKinds::Token("-".to_string())),
-            MatchToken::Token(TokenKinds::Token("*".to_string())),
-            MatchToken::Token(TokenKinds::Token("/".to_string())),
-            MatchToken::Token(TokenKinds::Token("<=".to_string())),
-            MatchToken::Token(TokenKinds::Token(">=".to_string())),
-            MatchToken::Token(TokenKinds::Token("<".to_string())),
-            MatchToken::Token(TokenKinds::Token(">".to_string())),
-            MatchToken::Token(TokenKinds::Token("==".to_string())),
-            MatchToken::Token(TokenKinds::Token("=".to_string())),
-            MatchToken::Token(TokenKinds::Token("!=".to_string())),
-            MatchToken::Token(TokenKinds::Token("&&".to_string())),
-            MatchToken::Token(TokenKinds::Token("||".to_string())),
-        ],
-    };
+    // operators are not lexed inside of strings and comments
+    let mut string = LexerMode::new("string", "\"", "\"");
+    string.add_token("\\\"".to_string());
+    string.add_token("\\\\".to_string());
+    parser.lexer.add_mode(string);
+    let mut comment = LexerMode::new("comment", "//", "\n");
+    // third slash of doc comments
+    comment.add_token("/".to_string());
+    parser.lexer.add_mode(comment);
+
+    parser.lexer.steps.push(PreprocessorStep::Regions(vec![
+        // strings, escaped quotes are tokens of the string mode
+        Region {
+            start: vec!["\"".to_string()],
+            end: Some("\"".to_string()),
+            escape: None,
+            kind: Some("string".to_string()),
+            include_start: true,
+            include_end: true,
+        },
+        // doc comments (///)
+        Region {
+            start: vec!["//".to_string(), "/".to_string()],
+            end: None,
+            escape: None,
+            kind: Some("doc_comment".to_string()),
+            include_start: false,
+            include_end: true,
+        },
+        // comments
+        Region {
+            start: vec!["//".to_string()],
+            end: None,
+            escape: None,
+            kind: None,
+            include_start: false,
+            include_end: false,
+        },
+    ]));
     parser
-        .grammar
-        .enumerators
-        .insert(operators.name.clone(), operators);
+        .lexer
+        .steps
+        .push(PreprocessorStep::Numbers(NumberLiterals {
+            integer: "int".to_string(),
+            float: "float".to_string(),
+            decimal_point: ".".to_string(),
+            suffixes: vec![
+                ('u', "uint".to_string()),
+                ('i', "int".to_string()),
+                ('f', "float".to_string()),
+                ('c', "char".to_string()),
+            ],
+        }));
+    parser.lexer.steps.push(PreprocessorStep::StripWhitespace);
 
     let keywords = Enumerator {
         name: "keywords".to_string(),
@@ -339,6 +203,7 @@ pub fn gen_parser() -> Parser {
         ],
         variables,
         docs: Some("An array literal is a list of values separated by commas and enclosed in square brackets.".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(array.name.clone(), array);
 
@@ -381,6 +246,7 @@ pub fn gen_parser() -> Parser {
         ],
         variables,
         docs: Some("An array builder is a way to initialize an array with a single value repeated a number of times.".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -422,6 +288,7 @@ pub fn gen_parser() -> Parser {
         ],
         variables,
         docs: Some("An entry is a list of nodes that define the structure of a file.".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(entry.name.clone(), entry);
 
@@ -474,6 +341,7 @@ pub fn gen_parser() -> Parser {
         docs: Some("An import statement is used to include the contents of another file in the current file.\n
                     example:
                     ```import \"path/to/file\";```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(import.name.clone(), import);
 
@@ -515,6 +383,7 @@ pub fn gen_parser() -> Parser {
         docs: Some("A use statement is used to include the contents of another file in the current file.\n
                     example:
                     ```use path.to.file.*;```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_use.name.clone(), kw_use);
 
@@ -570,6 +439,7 @@ pub fn gen_parser() -> Parser {
         docs: Some("A use path is a path to file contents that are to be included in the current file. Use '*' to include all contents.\n
                     example:
                     ```path.to.file.*```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(use_path.name.clone(), use_path);
 
@@ -608,6 +478,7 @@ pub fn gen_parser() -> Parser {
         docs: Some("A use multiple paths is a list of paths to file contents that are to be included in the current file.\n
                     example:
                     ```{ path1, path2, path3.* }```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -700,6 +571,7 @@ pub fn gen_parser() -> Parser {
                         return a + b;
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(function.name.clone(), function);
 
@@ -734,6 +606,7 @@ pub fn gen_parser() -> Parser {
                         return a + b;
                     }
                     ```".to_string()),
+        sync: vec![TokenKinds::Token("}".to_string())],
     };
     parser.grammar.nodes.insert(block.name.clone(), block);
 
@@ -827,6 +700,7 @@ pub fn gen_parser() -> Parser {
                         return a + b;
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -892,6 +766,7 @@ pub fn gen_parser() -> Parser {
                         float b;
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(type_.name.clone(), type_);
 
@@ -953,6 +828,7 @@ pub fn gen_parser() -> Parser {
                     [int]
                     &[[Foo]]
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1017,6 +893,7 @@ pub fn gen_parser() -> Parser {
                     (int, float, string)
                     &(int, float, (string, &&char))
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1050,6 +927,7 @@ pub fn gen_parser() -> Parser {
                     ```
                     int, float, string
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1098,33 +976,46 @@ pub fn gen_parser() -> Parser {
                     ```
                     path.to.file
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(path.name.clone(), path);
 
+    // binary operators from the lowest to the highest precedence
+    let operator_level = |name: &str, operators: &[&str], kind| OperatorLevel {
+        name: name.to_string(),
+        operators: operators
+            .iter()
+            .map(|op| MatchToken::Token(TokenKinds::Token(op.to_string())))
+            .collect(),
+        kind,
+    };
+    let operators = vec![
+        operator_level(
+            "assignment",
+            &["=", "+=", "-=", "*=", "/="],
+            OperatorKind::Right,
+        ),
+        operator_level("or", &["||"], OperatorKind::Left),
+        operator_level("and", &["&&"], OperatorKind::Left),
+        operator_level(
+            "comparison",
+            &["==", "!=", "<=", ">=", "<", ">"],
+            OperatorKind::Left,
+        ),
+        operator_level("sum", &["+", "-"], OperatorKind::Left),
+        operator_level("product", &["*", "/"], OperatorKind::Left),
+    ];
+
     let mut variables = Map::new();
-    variables.insert("nodes".to_string(), grammar::VariableKind::NodeList);
+    variables.insert("value".to_string(), grammar::VariableKind::Node);
     variables.insert("closure".to_string(), grammar::VariableKind::Node);
     let expression = Node {
         name: "expression".to_string(),
-        rules: vec![
-            Rule::Is {
-                token: MatchToken::Node("value".to_string()),
-                rules: vec![],
-                parameters: vec![
-                    Parameters::Set("nodes".to_string()),
-                    Parameters::HardError(true),
-                ],
-            },
-            Rule::While {
-                token: MatchToken::Enumerator("operators".to_string()),
-                rules: vec![Rule::Is {
-                    token: MatchToken::Node("value".to_string()),
-                    rules: vec![],
-                    parameters: vec![Parameters::Set("nodes".to_string())],
-                }],
-                parameters: vec![Parameters::Set("nodes".to_string())],
-            },
-        ],
+        rules: vec![Rule::Precedence {
+            operand: MatchToken::Node("value".to_string()),
+            levels: operators,
+            parameters: vec![Parameters::Set("value".to_string())],
+        }],
         variables,
         docs: Some("An expression is a combination of values and operators that can be evaluated to a single value.\n
                     example:
@@ -1132,6 +1023,7 @@ pub fn gen_parser() -> Parser {
                     5 + 10
                     a * b
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1180,6 +1072,7 @@ pub fn gen_parser() -> Parser {
                     5 + 10;
                     a * b;
                     ```".to_string()),
+        sync: vec![TokenKinds::Token(";".to_string())],
     };
     parser
         .grammar
@@ -1270,6 +1163,7 @@ pub fn gen_parser() -> Parser {
                     5
                     a
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(value.name.clone(), value);
 
@@ -1297,6 +1191,7 @@ pub fn gen_parser() -> Parser {
                     a.*
                     a.**.a
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1365,6 +1260,7 @@ pub fn gen_parser() -> Parser {
                         return a + b + c.len();
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(closure.name.clone(), closure);
 
@@ -1401,6 +1297,7 @@ pub fn gen_parser() -> Parser {
                         return a + b + c.len();
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1445,6 +1342,7 @@ pub fn gen_parser() -> Parser {
                     (a, b, c)
                     (a, (b, c), d)
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1523,6 +1421,7 @@ pub fn gen_parser() -> Parser {
                         return a + b;
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1580,6 +1479,7 @@ pub fn gen_parser() -> Parser {
                     *a
                     **a
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1615,6 +1515,7 @@ pub fn gen_parser() -> Parser {
                     (a, b, c)
                     (a, (b, c), d)
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1658,6 +1559,7 @@ pub fn gen_parser() -> Parser {
                     a(0).b
                     a(0).b.c
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(tail.name.clone(), tail);
 
@@ -1711,6 +1613,7 @@ pub fn gen_parser() -> Parser {
                         b: 10,
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(instance.name.clone(), instance);
 
@@ -1746,6 +1649,7 @@ pub fn gen_parser() -> Parser {
                     a: 5
                     b: 10
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1768,6 +1672,7 @@ pub fn gen_parser() -> Parser {
                     a
                     b
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(field.name.clone(), field);
 
@@ -1815,6 +1720,7 @@ pub fn gen_parser() -> Parser {
                         b: 10,
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(tail_dot.name.clone(), tail_dot);
 
@@ -1846,6 +1752,7 @@ pub fn gen_parser() -> Parser {
                     a[0]
                     a[b]
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(index.name.clone(), index);
 
@@ -1885,6 +1792,7 @@ pub fn gen_parser() -> Parser {
                     a(b, c)
                     a(b, c, d)
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(call.name.clone(), call);
 
@@ -1928,6 +1836,7 @@ pub fn gen_parser() -> Parser {
                     a, b, c
                     a, b, c, d
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -1963,6 +1872,7 @@ pub fn gen_parser() -> Parser {
                     a: 5
                     b: a + 5
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -2031,6 +1941,7 @@ pub fn gen_parser() -> Parser {
                     let a: int = 5;
                     let b = a + 5;
                     ```".to_string()),
+        sync: vec![TokenKinds::Token(";".to_string())],
     };
     parser.grammar.nodes.insert(kw_let.name.clone(), kw_let);
 
@@ -2076,6 +1987,7 @@ pub fn gen_parser() -> Parser {
                         return a;
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_if.name.clone(), kw_if);
 
@@ -2128,6 +2040,7 @@ pub fn gen_parser() -> Parser {
                         return a + 5;
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -2160,6 +2073,7 @@ pub fn gen_parser() -> Parser {
                         return a + 5;
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_else.name.clone(), kw_else);
 
@@ -2200,6 +2114,7 @@ pub fn gen_parser() -> Parser {
                         a += 1;
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_while.name.clone(), kw_while);
 
@@ -2234,6 +2149,7 @@ pub fn gen_parser() -> Parser {
                         a += 1;
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_loop.name.clone(), kw_loop);
 
@@ -2285,6 +2201,7 @@ pub fn gen_parser() -> Parser {
                         print(a);
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_for.name.clone(), kw_for);
 
@@ -2317,6 +2234,7 @@ pub fn gen_parser() -> Parser {
                     return 5;
                     return a + 5;
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -2360,6 +2278,7 @@ pub fn gen_parser() -> Parser {
                     break a;
                     break a + 5;
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_break.name.clone(), kw_break);
 
@@ -2392,6 +2311,7 @@ pub fn gen_parser() -> Parser {
                     continue;
                     continue a;
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -2426,6 +2346,7 @@ pub fn gen_parser() -> Parser {
                     a:
                     b:
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -2496,6 +2417,7 @@ pub fn gen_parser() -> Parser {
                         }
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_class.name.clone(), kw_class);
 
@@ -2551,6 +2473,7 @@ pub fn gen_parser() -> Parser {
                     a: int;
                     b: int;
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -2612,6 +2535,7 @@ pub fn gen_parser() -> Parser {
                         c,
                     }
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_enum.name.clone(), kw_enum);
 
@@ -2710,6 +2634,7 @@ pub fn gen_parser() -> Parser {
                     b = 5,
                     c,
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -2761,6 +2686,7 @@ pub fn gen_parser() -> Parser {
                     type A = int;
                     type B = int;
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(kw_type.name.clone(), kw_type);
 
@@ -2810,6 +2736,7 @@ pub fn gen_parser() -> Parser {
                     <T, U(Add, Send)>
                     <T>
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -2873,6 +2800,7 @@ pub fn gen_parser() -> Parser {
                     T
                     U(Add, Send)
                     ```".to_string()),
+        sync: vec![],
     };
     parser.grammar.nodes.insert(
         generic_ident_declaration.name.clone(),
@@ -2925,6 +2853,7 @@ pub fn gen_parser() -> Parser {
                     <int, float>
                     <T, char>
                     ```".to_string()),
+        sync: vec![],
     };
     parser
         .grammar
@@ -2946,6 +2875,165 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn serialized_lexer() {
+        let parser = gen_parser();
+        let json = serde_json::to_string(&parser).unwrap();
+        let loaded: Parser = serde_json::from_str(&json).unwrap();
+
+        let text = r#"
+/// docs
+let a = "string // with \"comment\"" + 5.5 + 6u + 7.; // comment
+"#;
+        let tokens = parser.lexer.lex_utf8(text).unwrap();
+        assert_eq!(loaded.lexer.lex_utf8(text).unwrap(), tokens);
+
+        let kinds: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKinds::Complex(kind) => Some(kind.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(kinds, vec!["doc_comment", "string", "float", "uint", "float"]);
+        assert!(tokens.iter().all(|t| !t.kind.is_whitespace()));
+    }
+
+    #[test]
+    fn error_recovery() {
+        let parser = gen_parser();
+        let text = r#"
+fun main() {
+    let a = 5;
+    let = 6;
+    let b: = 7;
+    a + b;
+}
+"#;
+        let tokens = parser.lexer.lex_utf8(text).unwrap();
+        assert!(parser.parse(&tokens, text).is_err());
+
+        let recovered = parser.parse_recovering(&tokens, text);
+        assert_eq!(recovered.errors.len(), 2, "{:?}", recovered.errors);
+        assert!(!recovered.result.entry.is_error());
+    }
+
+    #[test]
+    fn dangling_operator() {
+        let parser = gen_parser();
+        let text = "fun main() {\n    let a = 1 + ;\n}\n";
+        let tokens = parser.lexer.lex_utf8(text).unwrap();
+        // the expression can not backtrack once the operator matched
+        let err = parser.parse(&tokens, text).unwrap_err();
+        assert_eq!(&text[err.span()], ";");
+        assert_eq!((err.location().line, err.location().column), (2, 17));
+    }
+
+    #[test]
+    fn lossless_tree() {
+        let parser = gen_parser();
+        let text = r#"
+/// docs
+fun main() {
+    let a = "string // with \"comment\""; // comment
+
+    a  +  5.5;
+}
+"#;
+        let tokens = parser.lexer.lex_utf8(text).unwrap();
+        let result = parser.parse(&tokens, text).unwrap();
+        assert_eq!(result.cst(&tokens).to_string(), text);
+    }
+
+    #[test]
+    fn typed_ast_is_up_to_date() {
+        let parser = gen_parser();
+        let code = ruparse::codegen::Generator::new().generate(&parser.grammar);
+        check_generated("src/typed_ast.rs", include_str!("typed_ast.rs"), &code);
+    }
+
+    #[test]
+    fn grammar_reference() {
+        let parser = gen_parser();
+        let text = ruparse::dsl::print(&parser.grammar);
+        let parsed = ruparse::dsl::parse(&text).unwrap();
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&parser.grammar).unwrap()
+        );
+        let diagrams = ruparse::railroad::export(&parser.grammar);
+        assert_eq!(
+            diagrams.len(),
+            parser.grammar.nodes.len() + parser.grammar.enumerators.len()
+        );
+        check_generated("neruda.grammar", include_str!("../neruda.grammar"), &text);
+    }
+
+    /// Compares a file generated from the grammar with the checked in one
+    ///
+    /// Run the tests with `NERUDA_REGENERATE=1` to write the generated file instead
+    fn check_generated(path: &str, checked_in: &str, generated: &str) {
+        if std::env::var_os("NERUDA_REGENERATE").is_some() {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(path);
+            std::fs::write(path, generated).unwrap();
+        } else {
+            assert!(
+                generated == checked_in,
+                "{} was generated from an older grammar, regenerate it with NERUDA_REGENERATE=1",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn typed_ast() {
+        use ruparse::codegen::{Binary, FromNode};
+        use typed_ast::*;
+
+        let parser = gen_parser();
+        let text = r##"
+import "#io"
+
+fun main() {
+    let a = 5 + 6 * 7;
+}
+"##;
+        let tokens = parser.lexer.lex_utf8(text).unwrap();
+        let tree = parser.parse(&tokens, text).unwrap();
+
+        let globals = Globals::from_globals(&tree.globals, text).unwrap();
+        assert_eq!(globals.imports.len(), 1);
+
+        let entry = Entry::from_node(&tree.entry, text).unwrap();
+        let main = match &entry.list[..] {
+            [EntryNodes::KWImport(_), EntryNodes::KWFunction(main)] => main,
+            list => panic!("unexpected nodes {:?}", list),
+        };
+        let name = main.identifier.as_ref().unwrap();
+        assert_eq!(&text[name.span()], "main");
+
+        let body = main.body.as_ref().unwrap();
+        let value = match &body.nodes[..] {
+            [BlockLine::KWLet(statement)] => match &statement.value {
+                Some(Expressions::Expression(expression)) => expression.value.as_ref().unwrap(),
+                value => panic!("unexpected value {:?}", value),
+            },
+            nodes => panic!("unexpected nodes {:?}", nodes),
+        };
+        let product = match value {
+            ExpressionValue::Sum(sum) => match &**sum {
+                Binary {
+                    left: ExpressionValue::Value(_),
+                    right: ExpressionValue::Product(product),
+                    ..
+                } => product,
+                sum => panic!("unexpected sum {:?}", sum),
+            },
+            value => panic!("unexpected value {:?}", value),
+        };
+        assert_eq!(&text[product.span.clone()], "6 * 7");
+    }
+
     #[test]
     fn it_works() {
         let start = std::time::Instant::now();
@@ -2954,11 +3042,11 @@ mod tests {
         let validation = parser.grammar.validate(&parser.lexer);
 
         for error in validation.errors.iter() {
-            println!("{:?}", error);
+            println!("{}", error);
         }
 
         for warning in validation.warnings.iter() {
-            println!("{:?}", warning);
+            println!("{}", warning);
         }
 
         assert!(validation.pass(), "Grammar is not valid"); // change .pass() to .success() for production
@@ -0,0 +1,2364 @@
+// @generated by ruparse::codegen, do not edit
+
+use ::ruparse::codegen::{self, ConvertError, FromNode, FromNodes};
+use ::ruparse::lexer::{Token, TokenKinds};
+use ::ruparse::parser::{Node, Nodes, VariableKind};
+use ::ruparse::Map;
+
+/// A break statement is a statement that exits a loop.
+///
+/// example:
+/// ```text
+/// break;
+/// break a;
+/// break a + 5;
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWBreak {
+    pub expression: Option<Expressions>,
+    pub label: Option<Box<LoopLabel>>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWBreak {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWBreak {
+            expression: codegen::node_variable(&node.variables, "KWBreak", "expression", text)?,
+            label: codegen::node_variable(&node.variables, "KWBreak", "label", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWBreak {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWBreak", text)
+    }
+}
+
+/// A class is a type that contains fields and functions.
+///
+/// example:
+/// ```text
+/// class A {
+///     a: int;
+///     b: int;
+///     fn c() -> int {
+///         return a + b;
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWClass {
+    pub docs: Vec<Token>,
+    pub generic: Option<Box<GenericDeclaration>>,
+    pub identifier: Option<Token>,
+    pub members: Vec<ClassMembers>,
+    pub public: bool,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWClass {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWClass {
+            docs: codegen::list_variable(&node.variables, "KWClass", "docs", text)?,
+            generic: codegen::node_variable(&node.variables, "KWClass", "generic", text)?,
+            identifier: codegen::node_variable(&node.variables, "KWClass", "identifier", text)?,
+            members: codegen::list_variable(&node.variables, "KWClass", "members", text)?,
+            public: codegen::bool_variable(&node.variables, "KWClass", "public")?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWClass {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWClass", text)
+    }
+}
+
+/// A continue statement is a statement that skips the rest of the loop and continues to the next iteration.
+///
+/// example:
+/// ```text
+/// continue;
+/// continue a;
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWContinue {
+    pub label: Option<Box<LoopLabel>>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWContinue {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWContinue {
+            label: codegen::node_variable(&node.variables, "KWContinue", "label", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWContinue {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWContinue", text)
+    }
+}
+
+/// An else statement is a block of code that is executed if the previous conditions are false.
+///
+/// example:
+/// ```text
+/// if a == 5 {
+///     return a;
+/// } else {
+///     return a + 5;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWElse {
+    pub body: Option<Box<Block>>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWElse {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWElse {
+            body: codegen::node_variable(&node.variables, "KWElse", "body", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWElse {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWElse", text)
+    }
+}
+
+/// An else if statement is a conditional statement that executes a block of code if a condition is true and the previous conditions are false.
+///
+/// example:
+/// ```text
+/// if a == 5 {
+///     return a;
+/// } else if a == 10 {
+///     return a + 5;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWElseIf {
+    pub body: Option<Box<Block>>,
+    pub condition: Option<Expressions>,
+    pub next: Option<KWElseIfNext>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWElseIf {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWElseIf {
+            body: codegen::node_variable(&node.variables, "KWElseIf", "body", text)?,
+            condition: codegen::node_variable(&node.variables, "KWElseIf", "condition", text)?,
+            next: codegen::node_variable(&node.variables, "KWElseIf", "next", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWElseIf {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWElseIf", text)
+    }
+}
+
+/// An enum is a type that contains a list of values.
+///
+/// example:
+/// ```text
+/// enum A {
+///     a,
+///     b,
+///     c,
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWEnum {
+    pub docs: Vec<Token>,
+    pub identifier: Option<Token>,
+    pub members: Vec<EnumMembers>,
+    pub public: bool,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWEnum {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWEnum {
+            docs: codegen::list_variable(&node.variables, "KWEnum", "docs", text)?,
+            identifier: codegen::node_variable(&node.variables, "KWEnum", "identifier", text)?,
+            members: codegen::list_variable(&node.variables, "KWEnum", "members", text)?,
+            public: codegen::bool_variable(&node.variables, "KWEnum", "public")?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWEnum {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWEnum", text)
+    }
+}
+
+/// A for statement is a loop that executes a block of code for each element in a list.
+///
+/// example:
+/// ```text
+/// for a in [1, 2, 3] {
+///     print(a);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWFor {
+    pub body: Option<Box<Block>>,
+    pub expression: Option<Expressions>,
+    pub identifier: Option<ParameterIdents>,
+    pub label: Option<Box<LoopLabel>>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWFor {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWFor {
+            body: codegen::node_variable(&node.variables, "KWFor", "body", text)?,
+            expression: codegen::node_variable(&node.variables, "KWFor", "expression", text)?,
+            identifier: codegen::node_variable(&node.variables, "KWFor", "identifier", text)?,
+            label: codegen::node_variable(&node.variables, "KWFor", "label", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWFor {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWFor", text)
+    }
+}
+
+/// A function is a block of code that can be called by other parts of the program.
+///
+/// example:
+/// ```text
+/// fun add<T(Add)>(a: T, b: T) -> T {
+///     return a + b;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWFunction {
+    pub body: Option<Box<Block>>,
+    pub docs: Vec<Token>,
+    pub generic: Option<Box<GenericDeclaration>>,
+    pub identifier: Option<Token>,
+    pub parameters: Vec<Parameter>,
+    pub public: bool,
+    pub return_type: Option<Types>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWFunction {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWFunction {
+            body: codegen::node_variable(&node.variables, "KWFunction", "body", text)?,
+            docs: codegen::list_variable(&node.variables, "KWFunction", "docs", text)?,
+            generic: codegen::node_variable(&node.variables, "KWFunction", "generic", text)?,
+            identifier: codegen::node_variable(&node.variables, "KWFunction", "identifier", text)?,
+            parameters: codegen::list_variable(&node.variables, "KWFunction", "parameters", text)?,
+            public: codegen::bool_variable(&node.variables, "KWFunction", "public")?,
+            return_type: codegen::node_variable(&node.variables, "KWFunction", "return_type", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWFunction {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWFunction", text)
+    }
+}
+
+/// An if statement is a conditional statement that executes a block of code if a condition is true.
+///
+/// example:
+/// ```text
+/// if a == 5 {
+///     return a;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWIf {
+    pub body: Option<Box<Block>>,
+    pub condition: Option<Expressions>,
+    pub next: Option<KWIfNext>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWIf {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWIf {
+            body: codegen::node_variable(&node.variables, "KWIf", "body", text)?,
+            condition: codegen::node_variable(&node.variables, "KWIf", "condition", text)?,
+            next: codegen::node_variable(&node.variables, "KWIf", "next", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWIf {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWIf", text)
+    }
+}
+
+/// An import statement is used to include the contents of another file in the current file.
+///
+/// example:
+/// ```import "path/to/file";```
+#[derive(Debug, Clone)]
+pub struct KWImport {
+    pub alias: Option<Token>,
+    pub file: Option<Token>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWImport {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWImport {
+            alias: codegen::node_variable(&node.variables, "KWImport", "alias", text)?,
+            file: codegen::node_variable(&node.variables, "KWImport", "file", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWImport {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWImport", text)
+    }
+}
+
+/// A let statement is a variable declaration.
+///
+/// example:
+/// ```text
+/// let a: int = 5;
+/// let b = a + 5;
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWLet {
+    pub identifier: Option<Token>,
+    pub r#type: Option<Types>,
+    pub value: Option<Expressions>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWLet {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWLet {
+            identifier: codegen::node_variable(&node.variables, "KWLet", "identifier", text)?,
+            r#type: codegen::node_variable(&node.variables, "KWLet", "type", text)?,
+            value: codegen::node_variable(&node.variables, "KWLet", "value", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWLet {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWLet", text)
+    }
+}
+
+/// A loop statement is a loop that executes a block of code indefinitely.
+///
+/// example:
+/// ```text
+/// loop {
+///     a += 1;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWLoop {
+    pub body: Option<Box<Block>>,
+    pub label: Option<Box<LoopLabel>>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWLoop {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWLoop {
+            body: codegen::node_variable(&node.variables, "KWLoop", "body", text)?,
+            label: codegen::node_variable(&node.variables, "KWLoop", "label", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWLoop {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWLoop", text)
+    }
+}
+
+/// A return statement is a statement that returns a value from a function.
+///
+/// example:
+/// ```text
+/// return 5;
+/// return a + 5;
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWReturn {
+    pub expression: Option<Expressions>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWReturn {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWReturn {
+            expression: codegen::node_variable(&node.variables, "KWReturn", "expression", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWReturn {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWReturn", text)
+    }
+}
+
+/// A type is a type alias.
+///
+/// example:
+/// ```text
+/// type A = int;
+/// type B = int;
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWType {
+    pub docs: Vec<Token>,
+    pub identifier: Option<Token>,
+    pub r#type: Option<Types>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWType {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWType {
+            docs: codegen::list_variable(&node.variables, "KWType", "docs", text)?,
+            identifier: codegen::node_variable(&node.variables, "KWType", "identifier", text)?,
+            r#type: codegen::node_variable(&node.variables, "KWType", "type", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWType {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWType", text)
+    }
+}
+
+/// A use statement is used to include the contents of another file in the current file.
+///
+/// example:
+/// ```use path.to.file.*;```
+#[derive(Debug, Clone)]
+pub struct KWUse {
+    pub path: Option<Box<UsePath>>,
+    pub root: Option<Token>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWUse {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWUse {
+            path: codegen::node_variable(&node.variables, "KWUse", "path", text)?,
+            root: codegen::node_variable(&node.variables, "KWUse", "root", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWUse {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWUse", text)
+    }
+}
+
+/// A while statement is a loop that executes a block of code while a condition is true.
+///
+/// example:
+/// ```text
+/// while a < 5 {
+///     a += 1;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KWWhile {
+    pub body: Option<Box<Block>>,
+    pub condition: Option<Expressions>,
+    pub label: Option<Box<LoopLabel>>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for KWWhile {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(KWWhile {
+            body: codegen::node_variable(&node.variables, "KWWhile", "body", text)?,
+            condition: codegen::node_variable(&node.variables, "KWWhile", "condition", text)?,
+            label: codegen::node_variable(&node.variables, "KWWhile", "label", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for KWWhile {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "KWWhile", text)
+    }
+}
+
+/// An anonymous function is a function that is defined without a name. It can be assigned to a variable or passed as an argument to another function.
+///
+/// example:
+/// ```text
+/// fun(a: int, b: int) -> int {
+///     return a + b;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnonymousFunction {
+    pub body: Option<Box<Block>>,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Option<Types>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for AnonymousFunction {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(AnonymousFunction {
+            body: codegen::node_variable(&node.variables, "anonymous_function", "body", text)?,
+            parameters: codegen::list_variable(&node.variables, "anonymous_function", "parameters", text)?,
+            return_type: codegen::node_variable(&node.variables, "anonymous_function", "return_type", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for AnonymousFunction {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "anonymous_function", text)
+    }
+}
+
+/// An array literal is a list of values separated by commas and enclosed in square brackets.
+#[derive(Debug, Clone)]
+pub struct Array {
+    pub body: Option<ArrayTypes>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Array {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Array {
+            body: codegen::node_variable(&node.variables, "array", "body", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Array {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "array", text)
+    }
+}
+
+/// An array builder is a way to initialize an array with a single value repeated a number of times.
+#[derive(Debug, Clone)]
+pub struct ArrayBuilder {
+    pub times: Option<Expressions>,
+    pub value: Option<Expressions>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for ArrayBuilder {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(ArrayBuilder {
+            times: codegen::node_variable(&node.variables, "array_builder", "times", text)?,
+            value: codegen::node_variable(&node.variables, "array_builder", "value", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for ArrayBuilder {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "array_builder", text)
+    }
+}
+
+/// An array type is a type that represents a list of values of the same type.
+///
+/// example:
+/// ```text
+/// [int]
+/// &[[Foo]]
+/// ```
+#[derive(Debug, Clone)]
+pub struct ArrayType {
+    pub refs: i32,
+    pub r#type: Option<Box<Type>>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for ArrayType {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(ArrayType {
+            refs: codegen::number_variable(&node.variables, "array_type", "refs")?,
+            r#type: codegen::node_variable(&node.variables, "array_type", "type", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for ArrayType {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "array_type", text)
+    }
+}
+
+/// A block is a list of statements enclosed in curly braces.
+///
+/// example:
+/// ```text
+/// {
+///     let a = 5;
+///     let b = 10;
+///     return a + b;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub nodes: Vec<BlockLine>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Block {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Block {
+            nodes: codegen::list_variable(&node.variables, "block", "nodes", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Block {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "block", text)
+    }
+}
+
+/// A call is a function that is called with a list of arguments.
+///
+/// example:
+/// ```text
+/// a()
+/// a(b, c)
+/// a(b, c, d)
+/// ```
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub arguments: Option<Box<ValuesList>>,
+    pub generic: Option<Box<GenericExpression>>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Call {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Call {
+            arguments: codegen::node_variable(&node.variables, "call", "arguments", text)?,
+            generic: codegen::node_variable(&node.variables, "call", "generic", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Call {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "call", text)
+    }
+}
+
+/// A class field is a field that is declared in a class.
+///
+/// example:
+/// ```text
+/// a: int;
+/// b: int;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClassField {
+    pub docs: Vec<Token>,
+    pub identifier: Option<Token>,
+    pub r#type: Option<Types>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for ClassField {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(ClassField {
+            docs: codegen::list_variable(&node.variables, "class_field", "docs", text)?,
+            identifier: codegen::node_variable(&node.variables, "class_field", "identifier", text)?,
+            r#type: codegen::node_variable(&node.variables, "class_field", "type", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for ClassField {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "class_field", text)
+    }
+}
+
+/// A closure is a block of code that can be passed around as a value.
+///
+/// example:
+/// ```text
+/// (a, b, ..c) -> {
+///     return a + b + c.len();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Closure {
+    pub body: Option<Box<Block>>,
+    pub parameters: Vec<ClosureParameter>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Closure {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Closure {
+            body: codegen::node_variable(&node.variables, "closure", "body", text)?,
+            parameters: codegen::list_variable(&node.variables, "closure", "parameters", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Closure {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "closure", text)
+    }
+}
+
+/// A closure parameter is a variable that is passed to a closure.
+///
+/// example:
+/// ```text
+/// (a, b, ..c) -> {
+///     return a + b + c.len();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClosureParameter {
+    pub identifier: Option<ParameterIdents>,
+    pub rest: bool,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for ClosureParameter {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(ClosureParameter {
+            identifier: codegen::node_variable(&node.variables, "closure_parameter", "identifier", text)?,
+            rest: codegen::bool_variable(&node.variables, "closure_parameter", "rest")?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for ClosureParameter {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "closure_parameter", text)
+    }
+}
+
+/// An entry is a list of nodes that define the structure of a file.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub list: Vec<EntryNodes>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Entry {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Entry {
+            list: codegen::list_variable(&node.variables, "entry", "list", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Entry {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "entry", text)
+    }
+}
+
+/// An enum variant is a value that is contained in an enum.
+///
+/// example:
+/// ```text
+/// a,
+/// b = 5,
+/// c,
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+    pub docs: Vec<Token>,
+    pub identifier: Option<Token>,
+    pub parameters: Vec<Parameter>,
+    pub value: Option<Expressions>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for EnumVariant {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(EnumVariant {
+            docs: codegen::list_variable(&node.variables, "enum_variant", "docs", text)?,
+            identifier: codegen::node_variable(&node.variables, "enum_variant", "identifier", text)?,
+            parameters: codegen::list_variable(&node.variables, "enum_variant", "parameters", text)?,
+            value: codegen::node_variable(&node.variables, "enum_variant", "value", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for EnumVariant {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "enum_variant", text)
+    }
+}
+
+/// An expression is a combination of values and operators that can be evaluated to a single value.
+///
+/// example:
+/// ```text
+/// 5 + 10
+/// a * b
+/// ```
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub closure: Option<Nodes>,
+    pub value: Option<ExpressionValue>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Expression {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Expression {
+            closure: codegen::node_variable(&node.variables, "expression", "closure", text)?,
+            value: codegen::node_variable(&node.variables, "expression", "value", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Expression {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "expression", text)
+    }
+}
+
+/// A field is an identifier that is used to access a value in an instance.
+///
+/// example:
+/// ```text
+/// a
+/// b
+/// ```
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub field: Option<Token>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Field {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Field {
+            field: codegen::node_variable(&node.variables, "field", "field", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Field {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "field", text)
+    }
+}
+
+/// A generic declaration is a list of generic identifiers.
+///
+/// example:
+/// ```text
+/// <T, U(Add, Send)>
+/// <T>
+/// ```
+#[derive(Debug, Clone)]
+pub struct GenericDeclaration {
+    pub identifiers: Vec<GenericIdentDeclaration>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for GenericDeclaration {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(GenericDeclaration {
+            identifiers: codegen::list_variable(&node.variables, "generic_declaration", "identifiers", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for GenericDeclaration {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "generic_declaration", text)
+    }
+}
+
+/// A generic expression is a list of types.
+///
+/// example:
+/// ```text
+/// <int, float>
+/// <T, char>
+/// ```
+#[derive(Debug, Clone)]
+pub struct GenericExpression {
+    pub types: Vec<Types>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for GenericExpression {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(GenericExpression {
+            types: codegen::list_variable(&node.variables, "generic_expression", "types", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for GenericExpression {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "generic_expression", text)
+    }
+}
+
+/// A generic identifier declaration is an identifier followed by a list of traits.
+///
+/// example:
+/// ```text
+/// T
+/// U(Add, Send)
+/// ```
+#[derive(Debug, Clone)]
+pub struct GenericIdentDeclaration {
+    pub identifier: Option<Token>,
+    pub traits: Vec<Path>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for GenericIdentDeclaration {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(GenericIdentDeclaration {
+            identifier: codegen::node_variable(&node.variables, "generic_ident_declaration", "identifier", text)?,
+            traits: codegen::list_variable(&node.variables, "generic_ident_declaration", "traits", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for GenericIdentDeclaration {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "generic_ident_declaration", text)
+    }
+}
+
+/// An index is a value that is used to access an element in an array.
+///
+/// example:
+/// ```text
+/// a[0]
+/// a[b]
+/// ```
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub index: Option<Expressions>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Index {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Index {
+            index: codegen::node_variable(&node.variables, "index", "index", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Index {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "index", text)
+    }
+}
+
+/// An instance is a list of fields separated by commas enclosed in curly braces.
+///
+/// example:
+/// ```text
+/// {
+///     a: 5,
+///     b: 10,
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub fields: Vec<InstanceField>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Instance {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Instance {
+            fields: codegen::list_variable(&node.variables, "instance", "fields", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Instance {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "instance", text)
+    }
+}
+
+/// An instance field is a field that is assigned a value in an instance.
+///
+/// example:
+/// ```text
+/// a: 5
+/// b: 10
+/// ```
+#[derive(Debug, Clone)]
+pub struct InstanceField {
+    pub expression: Option<Expressions>,
+    pub identifier: Option<Token>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for InstanceField {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(InstanceField {
+            expression: codegen::node_variable(&node.variables, "instance_field", "expression", text)?,
+            identifier: codegen::node_variable(&node.variables, "instance_field", "identifier", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for InstanceField {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "instance_field", text)
+    }
+}
+
+/// A loop label is an identifier followed by a colon.
+///
+/// example:
+/// ```text
+/// a:
+/// b:
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoopLabel {
+    pub identifier: Option<Token>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for LoopLabel {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(LoopLabel {
+            identifier: codegen::node_variable(&node.variables, "loop_label", "identifier", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for LoopLabel {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "loop_label", text)
+    }
+}
+
+/// A named expression is an identifier followed by a colon and an expression.
+///
+/// example:
+/// ```text
+/// a: 5
+/// b: a + 5
+/// ```
+#[derive(Debug, Clone)]
+pub struct NamedExpression {
+    pub expression: Option<Expressions>,
+    pub identifier: Option<Token>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for NamedExpression {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(NamedExpression {
+            expression: codegen::node_variable(&node.variables, "named_expression", "expression", text)?,
+            identifier: codegen::node_variable(&node.variables, "named_expression", "identifier", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for NamedExpression {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "named_expression", text)
+    }
+}
+
+/// A parameter is a variable that is passed to a function.
+///
+/// example:
+/// ```text
+/// fun add(a: int, b: int = 2) -> int {
+///     return a + b;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub default: Option<Box<Expression>>,
+    pub docs: Vec<Token>,
+    pub identifier: Option<ParameterIdents>,
+    pub rest: bool,
+    pub r#type: Option<Types>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Parameter {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Parameter {
+            default: codegen::node_variable(&node.variables, "parameter", "default", text)?,
+            docs: codegen::list_variable(&node.variables, "parameter", "docs", text)?,
+            identifier: codegen::node_variable(&node.variables, "parameter", "identifier", text)?,
+            rest: codegen::bool_variable(&node.variables, "parameter", "rest")?,
+            r#type: codegen::node_variable(&node.variables, "parameter", "type", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Parameter {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "parameter", text)
+    }
+}
+
+/// A parenthesis is a list of values separated by commas enclosed in parentheses.
+///
+/// example:
+/// ```text
+/// (a, b, c)
+/// (a, (b, c), d)
+/// ```
+#[derive(Debug, Clone)]
+pub struct Parenthesis {
+    pub values: Option<Box<ValuesList>>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Parenthesis {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Parenthesis {
+            values: codegen::node_variable(&node.variables, "parenthesis", "values", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Parenthesis {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "parenthesis", text)
+    }
+}
+
+/// A path is a list of identifiers separated by dots.
+///
+/// example:
+/// ```text
+/// path.to.file
+/// ```
+#[derive(Debug, Clone)]
+pub struct Path {
+    pub path: Vec<Token>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Path {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Path {
+            path: codegen::list_variable(&node.variables, "path", "path", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Path {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "path", text)
+    }
+}
+
+/// A statement is an expression followed by a semicolon.
+///
+/// example:
+/// ```text
+/// 5 + 10;
+/// a * b;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub expression: Option<Expressions>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Statement {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Statement {
+            expression: codegen::node_variable(&node.variables, "statement", "expression", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Statement {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "statement", text)
+    }
+}
+
+/// A tail is a list of tail options that are applied to a value.
+///
+/// example:
+/// ```text
+/// a
+/// a.b
+/// a.b.c
+/// a[0]
+/// a[0].b
+/// a[0].b.c
+/// a(0)
+/// a(0).b
+/// a(0).b.c
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tail {
+    pub tail: Vec<TailOptions>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Tail {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Tail {
+            tail: codegen::list_variable(&node.variables, "tail", "tail", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Tail {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "tail", text)
+    }
+}
+
+/// A tail deref is a list of dereference operators that are applied to a value.
+///
+/// example:
+/// ```text
+/// a
+/// a.*
+/// a.**.a
+/// ```
+#[derive(Debug, Clone)]
+pub struct TailDerefs {
+    pub amount: i32,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for TailDerefs {
+    fn from_node(node: &Node, _text: &str) -> Result<Self, ConvertError> {
+        Ok(TailDerefs {
+            amount: codegen::number_variable(&node.variables, "tail_derefs", "amount")?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for TailDerefs {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "tail_derefs", text)
+    }
+}
+
+/// A tail dot is a field, dereference, or instance that is accessed from a value.
+///
+/// example:
+/// ```text
+/// a
+/// a.b
+/// a.*
+/// a.**
+/// a.{
+///     a: 5,
+///     b: 10,
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TailDot {
+    pub node: Option<TailDotNode>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for TailDot {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(TailDot {
+            node: codegen::node_variable(&node.variables, "tail_dot", "node", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for TailDot {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "tail_dot", text)
+    }
+}
+
+/// A tuple parameter is a list of identifiers separated by commas enclosed in parentheses.
+///
+/// example:
+/// ```text
+/// (a, b, c)
+/// (a, (b, c), d)
+/// ```
+#[derive(Debug, Clone)]
+pub struct TupleParameter {
+    pub identifiers: Vec<TupleParameterIdentifiers>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for TupleParameter {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(TupleParameter {
+            identifiers: codegen::list_variable(&node.variables, "tuple_parameter", "identifiers", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for TupleParameter {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "tuple_parameter", text)
+    }
+}
+
+/// A tuple type is a type that represents a list of values of different types.
+///
+/// example:
+/// ```text
+/// (int, float, string)
+/// &(int, float, (string, &&char))
+/// ```
+#[derive(Debug, Clone)]
+pub struct TupleType {
+    pub refs: i32,
+    pub types: Vec<TypeList>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for TupleType {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(TupleType {
+            refs: codegen::number_variable(&node.variables, "tuple_type", "refs")?,
+            types: codegen::list_variable(&node.variables, "tuple_type", "types", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for TupleType {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "tuple_type", text)
+    }
+}
+
+/// A type defines memory layout and operations that can be performed on a value.
+///
+/// example:
+/// ```text
+/// int
+/// float
+/// struct MyStruct {
+///     int a;
+///     float b;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Type {
+    pub generic: Option<Box<GenericExpression>>,
+    pub path: Option<Box<Path>>,
+    pub refs: i32,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Type {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Type {
+            generic: codegen::node_variable(&node.variables, "type", "generic", text)?,
+            path: codegen::node_variable(&node.variables, "type", "path", text)?,
+            refs: codegen::number_variable(&node.variables, "type", "refs")?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Type {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "type", text)
+    }
+}
+
+/// A type list is a list of types separated by commas.
+///
+/// example:
+/// ```text
+/// int, float, string
+/// ```
+#[derive(Debug, Clone)]
+pub struct TypeList {
+    pub types: Vec<TypeListTypes>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for TypeList {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(TypeList {
+            types: codegen::list_variable(&node.variables, "type_list", "types", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for TypeList {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "type_list", text)
+    }
+}
+
+/// A use multiple paths is a list of paths to file contents that are to be included in the current file.
+///
+/// example:
+/// ```{ path1, path2, path3.* }```
+#[derive(Debug, Clone)]
+pub struct UseMultiplePaths {
+    pub paths: Vec<UsePath>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for UseMultiplePaths {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(UseMultiplePaths {
+            paths: codegen::list_variable(&node.variables, "use_multiple_paths", "paths", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for UseMultiplePaths {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "use_multiple_paths", text)
+    }
+}
+
+/// A use path is a path to file contents that are to be included in the current file. Use '*' to include all contents.
+///
+/// example:
+/// ```path.to.file.*```
+#[derive(Debug, Clone)]
+pub struct UsePath {
+    pub path: Vec<UsePathPath>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for UsePath {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(UsePath {
+            path: codegen::list_variable(&node.variables, "use_path", "path", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for UsePath {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "use_path", text)
+    }
+}
+
+/// A value is a literal, variable, or expression that can be evaluated to a single value.
+///
+/// example:
+/// ```text
+/// 5
+/// a
+/// ```
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub alloc: bool,
+    pub body: Option<ValueBody>,
+    pub dealloc: bool,
+    pub refs: Option<Box<ValueRefs>>,
+    pub tail: Option<Box<Tail>>,
+    pub unaries: Vec<UnaryOperators>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for Value {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Value {
+            alloc: codegen::bool_variable(&node.variables, "value", "alloc")?,
+            body: codegen::node_variable(&node.variables, "value", "body", text)?,
+            dealloc: codegen::bool_variable(&node.variables, "value", "dealloc")?,
+            refs: codegen::node_variable(&node.variables, "value", "refs", text)?,
+            tail: codegen::node_variable(&node.variables, "value", "tail", text)?,
+            unaries: codegen::list_variable(&node.variables, "value", "unaries", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for Value {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "value", text)
+    }
+}
+
+/// A value ref is a list of reference and dereference operators that are applied to a value.
+///
+/// example:
+/// ```text
+/// a
+/// &a
+/// &&a
+/// *a
+/// **a
+/// ```
+#[derive(Debug, Clone)]
+pub struct ValueRefs {
+    pub refs: i32,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for ValueRefs {
+    fn from_node(node: &Node, _text: &str) -> Result<Self, ConvertError> {
+        Ok(ValueRefs {
+            refs: codegen::number_variable(&node.variables, "value_refs", "refs")?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for ValueRefs {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "value_refs", text)
+    }
+}
+
+/// A values list is a list of values separated by commas.
+///
+/// example:
+/// ```text
+/// a, b, c
+/// a, b, c, d
+/// ```
+#[derive(Debug, Clone)]
+pub struct ValuesList {
+    pub values: Vec<ListValues>,
+    /// Byte range of the node in the text
+    pub span: core::ops::Range<usize>,
+}
+
+impl FromNode for ValuesList {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(ValuesList {
+            values: codegen::list_variable(&node.variables, "values_list", "values", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+impl FromNodes for ValuesList {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        codegen::convert_node(nodes, "values_list", text)
+    }
+}
+
+/// Global variables of the grammar
+#[derive(Debug, Clone)]
+pub struct Globals {
+    pub imports: Vec<Token>,
+}
+
+impl Globals {
+    pub fn from_globals(
+        globals: &Map<String, VariableKind>,
+        text: &str,
+    ) -> Result<Self, ConvertError> {
+        Ok(Globals {
+            imports: codegen::list_variable(globals, "globals", "imports", text)?,
+        })
+    }
+}
+
+/// Values of the variable `next` of [`KWElseIf`]
+#[derive(Debug, Clone)]
+pub enum KWElseIfNext {
+    KWElse(Box<KWElse>),
+    KWElseIf(Box<KWElseIf>),
+}
+
+impl FromNodes for KWElseIfNext {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "KWElse" {
+                return Ok(KWElseIfNext::KWElse(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWElseIf" {
+                return Ok(KWElseIfNext::KWElseIf(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("next", nodes))
+    }
+}
+
+/// Values of the variable `next` of [`KWIf`]
+#[derive(Debug, Clone)]
+pub enum KWIfNext {
+    KWElse(Box<KWElse>),
+    KWElseIf(Box<KWElseIf>),
+}
+
+impl FromNodes for KWIfNext {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "KWElse" {
+                return Ok(KWIfNext::KWElse(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWElseIf" {
+                return Ok(KWIfNext::KWElseIf(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("next", nodes))
+    }
+}
+
+/// Operators and operands of the variable `value` of [`Expression`]
+#[derive(Debug, Clone)]
+pub enum ExpressionValue {
+    Value(Box<Value>),
+    Assignment(Box<codegen::Binary<ExpressionValue>>),
+    Or(Box<codegen::Binary<ExpressionValue>>),
+    And(Box<codegen::Binary<ExpressionValue>>),
+    Comparison(Box<codegen::Binary<ExpressionValue>>),
+    Sum(Box<codegen::Binary<ExpressionValue>>),
+    Product(Box<codegen::Binary<ExpressionValue>>),
+}
+
+impl FromNodes for ExpressionValue {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "value" {
+                return Ok(ExpressionValue::Value(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "assignment" {
+                return Ok(ExpressionValue::Assignment(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "or" {
+                return Ok(ExpressionValue::Or(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "and" {
+                return Ok(ExpressionValue::And(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "comparison" {
+                return Ok(ExpressionValue::Comparison(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "sum" {
+                return Ok(ExpressionValue::Sum(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "product" {
+                return Ok(ExpressionValue::Product(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("value", nodes))
+    }
+}
+
+/// Values of the variable `node` of [`TailDot`]
+#[derive(Debug, Clone)]
+pub enum TailDotNode {
+    Field(Box<Field>),
+    Instance(Box<Instance>),
+    TailDerefs(Box<TailDerefs>),
+}
+
+impl FromNodes for TailDotNode {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "field" {
+                return Ok(TailDotNode::Field(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "instance" {
+                return Ok(TailDotNode::Instance(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "tail_derefs" {
+                return Ok(TailDotNode::TailDerefs(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("node", nodes))
+    }
+}
+
+/// Values of the variable `identifiers` of [`TupleParameter`]
+#[derive(Debug, Clone)]
+pub enum TupleParameterIdentifiers {
+    ParameterIdents(ParameterIdents),
+    Token(Token),
+}
+
+impl FromNodes for TupleParameterIdentifiers {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Ok(value) = FromNodes::from_nodes(nodes, text) {
+            return Ok(TupleParameterIdentifiers::ParameterIdents(value));
+        }
+        if let Nodes::Token(token) = nodes {
+            return Ok(TupleParameterIdentifiers::Token(token.clone()));
+        }
+        Err(ConvertError::unexpected("identifiers", nodes))
+    }
+}
+
+/// Values of the variable `types` of [`TypeList`]
+#[derive(Debug, Clone)]
+pub enum TypeListTypes {
+    Types(Types),
+    Token(Token),
+}
+
+impl FromNodes for TypeListTypes {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Ok(value) = FromNodes::from_nodes(nodes, text) {
+            return Ok(TypeListTypes::Types(value));
+        }
+        if let Nodes::Token(token) = nodes {
+            return Ok(TypeListTypes::Token(token.clone()));
+        }
+        Err(ConvertError::unexpected("types", nodes))
+    }
+}
+
+/// Values of the variable `path` of [`UsePath`]
+#[derive(Debug, Clone)]
+pub enum UsePathPath {
+    UseMultiplePaths(Box<UseMultiplePaths>),
+    Token(Token),
+}
+
+impl FromNodes for UsePathPath {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "use_multiple_paths" {
+                return Ok(UsePathPath::UseMultiplePaths(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        if let Nodes::Token(token) = nodes {
+            return Ok(UsePathPath::Token(token.clone()));
+        }
+        Err(ConvertError::unexpected("path", nodes))
+    }
+}
+
+/// Values of the variable `body` of [`Value`]
+#[derive(Debug, Clone)]
+pub enum ValueBody {
+    AnonymousFunction(Box<AnonymousFunction>),
+    Parenthesis(Box<Parenthesis>),
+    Literals(Literals),
+    Token(Token),
+}
+
+impl FromNodes for ValueBody {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "anonymous_function" {
+                return Ok(ValueBody::AnonymousFunction(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "parenthesis" {
+                return Ok(ValueBody::Parenthesis(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        if let Ok(value) = FromNodes::from_nodes(nodes, text) {
+            return Ok(ValueBody::Literals(value));
+        }
+        if let Nodes::Token(token) = nodes {
+            return Ok(ValueBody::Token(token.clone()));
+        }
+        Err(ConvertError::unexpected("body", nodes))
+    }
+}
+
+/// Values of the enumerator `array_types`
+#[derive(Debug, Clone)]
+pub enum ArrayTypes {
+    ArrayBuilder(Box<ArrayBuilder>),
+    ValuesList(Box<ValuesList>),
+}
+
+impl FromNodes for ArrayTypes {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "array_builder" {
+                return Ok(ArrayTypes::ArrayBuilder(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "values_list" {
+                return Ok(ArrayTypes::ValuesList(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("array_types", nodes))
+    }
+}
+
+/// Values of the enumerator `block_line`
+#[derive(Debug, Clone)]
+pub enum BlockLine {
+    KWIf(Box<KWIf>),
+    KWLet(Box<KWLet>),
+    KWFor(Box<KWFor>),
+    KWLoop(Box<KWLoop>),
+    KWWhile(Box<KWWhile>),
+    KWEnum(Box<KWEnum>),
+    KWReturn(Box<KWReturn>),
+    KWContinue(Box<KWContinue>),
+    KWType(Box<KWType>),
+    KWBreak(Box<KWBreak>),
+    KWClass(Box<KWClass>),
+    KWImport(Box<KWImport>),
+    KWFunction(Box<KWFunction>),
+    Statement(Box<Statement>),
+    Semi(Token),
+}
+
+impl FromNodes for BlockLine {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "KWIf" {
+                return Ok(BlockLine::KWIf(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWLet" {
+                return Ok(BlockLine::KWLet(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWFor" {
+                return Ok(BlockLine::KWFor(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWLoop" {
+                return Ok(BlockLine::KWLoop(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWWhile" {
+                return Ok(BlockLine::KWWhile(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWEnum" {
+                return Ok(BlockLine::KWEnum(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWReturn" {
+                return Ok(BlockLine::KWReturn(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWContinue" {
+                return Ok(BlockLine::KWContinue(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWType" {
+                return Ok(BlockLine::KWType(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWBreak" {
+                return Ok(BlockLine::KWBreak(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWClass" {
+                return Ok(BlockLine::KWClass(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWImport" {
+                return Ok(BlockLine::KWImport(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWFunction" {
+                return Ok(BlockLine::KWFunction(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "statement" {
+                return Ok(BlockLine::Statement(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        if let Nodes::Token(token) = nodes {
+            if matches!(&token.kind, TokenKinds::Token(kind) if kind == ";") {
+                return Ok(BlockLine::Semi(token.clone()));
+            }
+        }
+        Err(ConvertError::unexpected("block_line", nodes))
+    }
+}
+
+/// Values of the enumerator `class_members`
+#[derive(Debug, Clone)]
+pub enum ClassMembers {
+    ClassField(Box<ClassField>),
+    KWFunction(Box<KWFunction>),
+}
+
+impl FromNodes for ClassMembers {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "class_field" {
+                return Ok(ClassMembers::ClassField(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWFunction" {
+                return Ok(ClassMembers::KWFunction(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("class_members", nodes))
+    }
+}
+
+/// Values of the enumerator `entry_nodes`
+#[derive(Debug, Clone)]
+pub enum EntryNodes {
+    KWFunction(Box<KWFunction>),
+    KWClass(Box<KWClass>),
+    KWUse(Box<KWUse>),
+    KWImport(Box<KWImport>),
+    KWType(Box<KWType>),
+    KWEnum(Box<KWEnum>),
+}
+
+impl FromNodes for EntryNodes {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "KWFunction" {
+                return Ok(EntryNodes::KWFunction(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWClass" {
+                return Ok(EntryNodes::KWClass(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWUse" {
+                return Ok(EntryNodes::KWUse(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWImport" {
+                return Ok(EntryNodes::KWImport(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWType" {
+                return Ok(EntryNodes::KWType(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWEnum" {
+                return Ok(EntryNodes::KWEnum(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("entry_nodes", nodes))
+    }
+}
+
+/// Values of the enumerator `enum_members`
+#[derive(Debug, Clone)]
+pub enum EnumMembers {
+    KWFunction(Box<KWFunction>),
+    EnumVariant(Box<EnumVariant>),
+}
+
+impl FromNodes for EnumMembers {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "KWFunction" {
+                return Ok(EnumMembers::KWFunction(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "enum_variant" {
+                return Ok(EnumMembers::EnumVariant(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("enum_members", nodes))
+    }
+}
+
+/// Values of the enumerator `expressions`
+#[derive(Debug, Clone)]
+pub enum Expressions {
+    KWIf(Box<KWIf>),
+    KWLoop(Box<KWLoop>),
+    Closure(Box<Closure>),
+    Expression(Box<Expression>),
+}
+
+impl FromNodes for Expressions {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "KWIf" {
+                return Ok(Expressions::KWIf(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "KWLoop" {
+                return Ok(Expressions::KWLoop(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "closure" {
+                return Ok(Expressions::Closure(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "expression" {
+                return Ok(Expressions::Expression(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("expressions", nodes))
+    }
+}
+
+/// Values of the enumerator `keywords`
+#[derive(Debug, Clone)]
+pub enum Keywords {
+    If(Token),
+    Else(Token),
+    While(Token),
+    Use(Token),
+    For(Token),
+    Return(Token),
+    Break(Token),
+    Continue(Token),
+    Fun(Token),
+    Let(Token),
+    Enum(Token),
+    Class(Token),
+    Delete(Token),
+    New(Token),
+    Trait(Token),
+    Type(Token),
+    Impl(Token),
+    Const(Token),
+    As(Token),
+    Switch(Token),
+}
+
+impl FromNodes for Keywords {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Token(token) = nodes {
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "if" {
+                return Ok(Keywords::If(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "else" {
+                return Ok(Keywords::Else(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "while" {
+                return Ok(Keywords::While(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "use" {
+                return Ok(Keywords::Use(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "for" {
+                return Ok(Keywords::For(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "return" {
+                return Ok(Keywords::Return(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "break" {
+                return Ok(Keywords::Break(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "continue" {
+                return Ok(Keywords::Continue(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "fun" {
+                return Ok(Keywords::Fun(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "let" {
+                return Ok(Keywords::Let(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "enum" {
+                return Ok(Keywords::Enum(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "class" {
+                return Ok(Keywords::Class(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "delete" {
+                return Ok(Keywords::Delete(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "new" {
+                return Ok(Keywords::New(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "trait" {
+                return Ok(Keywords::Trait(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "type" {
+                return Ok(Keywords::Type(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "impl" {
+                return Ok(Keywords::Impl(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "const" {
+                return Ok(Keywords::Const(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "as" {
+                return Ok(Keywords::As(token.clone()));
+            }
+            if matches!(token.kind, TokenKinds::Text) && &text[token.span()] == "switch" {
+                return Ok(Keywords::Switch(token.clone()));
+            }
+        }
+        Err(ConvertError::unexpected("keywords", nodes))
+    }
+}
+
+/// Values of the enumerator `list_values`
+#[derive(Debug, Clone)]
+pub enum ListValues {
+    NamedExpression(Box<NamedExpression>),
+    Expressions(Expressions),
+}
+
+impl FromNodes for ListValues {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "named_expression" {
+                return Ok(ListValues::NamedExpression(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        if let Ok(value) = FromNodes::from_nodes(nodes, text) {
+            return Ok(ListValues::Expressions(value));
+        }
+        Err(ConvertError::unexpected("list_values", nodes))
+    }
+}
+
+/// Values of the enumerator `literals`
+#[derive(Debug, Clone)]
+pub enum Literals {
+    String(Token),
+    Char(Token),
+    Numbers(Numbers),
+    Array(Box<Array>),
+    Token(Token),
+}
+
+impl FromNodes for Literals {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "array" {
+                return Ok(Literals::Array(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        if let Nodes::Token(token) = nodes {
+            if matches!(&token.kind, TokenKinds::Complex(kind) if kind == "string") {
+                return Ok(Literals::String(token.clone()));
+            }
+            if matches!(&token.kind, TokenKinds::Complex(kind) if kind == "char") {
+                return Ok(Literals::Char(token.clone()));
+            }
+        }
+        if let Ok(value) = FromNodes::from_nodes(nodes, text) {
+            return Ok(Literals::Numbers(value));
+        }
+        if let Nodes::Token(token) = nodes {
+            return Ok(Literals::Token(token.clone()));
+        }
+        Err(ConvertError::unexpected("literals", nodes))
+    }
+}
+
+/// Values of the enumerator `numbers`
+#[derive(Debug, Clone)]
+pub enum Numbers {
+    Int(Token),
+    Float(Token),
+    Uint(Token),
+}
+
+impl FromNodes for Numbers {
+    fn from_nodes(nodes: &Nodes, _text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Token(token) = nodes {
+            if matches!(&token.kind, TokenKinds::Complex(kind) if kind == "int") {
+                return Ok(Numbers::Int(token.clone()));
+            }
+            if matches!(&token.kind, TokenKinds::Complex(kind) if kind == "float") {
+                return Ok(Numbers::Float(token.clone()));
+            }
+            if matches!(&token.kind, TokenKinds::Complex(kind) if kind == "uint") {
+                return Ok(Numbers::Uint(token.clone()));
+            }
+        }
+        Err(ConvertError::unexpected("numbers", nodes))
+    }
+}
+
+/// Values of the enumerator `parameter_idents`
+#[derive(Debug, Clone)]
+pub enum ParameterIdents {
+    Text(Token),
+    TupleParameter(Box<TupleParameter>),
+}
+
+impl FromNodes for ParameterIdents {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "tuple_parameter" {
+                return Ok(ParameterIdents::TupleParameter(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        if let Nodes::Token(token) = nodes {
+            if matches!(token.kind, TokenKinds::Text) {
+                return Ok(ParameterIdents::Text(token.clone()));
+            }
+        }
+        Err(ConvertError::unexpected("parameter_idents", nodes))
+    }
+}
+
+/// Values of the enumerator `tail_options`
+#[derive(Debug, Clone)]
+pub enum TailOptions {
+    TailDot(Box<TailDot>),
+    Index(Box<Index>),
+    Call(Box<Call>),
+}
+
+impl FromNodes for TailOptions {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "tail_dot" {
+                return Ok(TailOptions::TailDot(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "index" {
+                return Ok(TailOptions::Index(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "call" {
+                return Ok(TailOptions::Call(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("tail_options", nodes))
+    }
+}
+
+/// Values of the enumerator `types`
+#[derive(Debug, Clone)]
+pub enum Types {
+    Type(Box<Type>),
+    ArrayType(Box<ArrayType>),
+    TupleType(Box<TupleType>),
+}
+
+impl FromNodes for Types {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Node(node) = nodes {
+            if node.name == "type" {
+                return Ok(Types::Type(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "array_type" {
+                return Ok(Types::ArrayType(Box::new(FromNode::from_node(node, text)?)));
+            }
+            if node.name == "tuple_type" {
+                return Ok(Types::TupleType(Box::new(FromNode::from_node(node, text)?)));
+            }
+        }
+        Err(ConvertError::unexpected("types", nodes))
+    }
+}
+
+/// Values of the enumerator `unary_operators`
+#[derive(Debug, Clone)]
+pub enum UnaryOperators {
+    Bang(Token),
+    Minus(Token),
+}
+
+impl FromNodes for UnaryOperators {
+    fn from_nodes(nodes: &Nodes, _text: &str) -> Result<Self, ConvertError> {
+        if let Nodes::Token(token) = nodes {
+            if matches!(&token.kind, TokenKinds::Token(kind) if kind == "!") {
+                return Ok(UnaryOperators::Bang(token.clone()));
+            }
+            if matches!(&token.kind, TokenKinds::Token(kind) if kind == "-") {
+                return Ok(UnaryOperators::Minus(token.clone()));
+            }
+        }
+        Err(ConvertError::unexpected("unary_operators", nodes))
+    }
+}
@@ -1,19 +1,23 @@
 use crate::{
-    lexer::Token,
+    grammar,
+    lexer::{Token, TokenKinds},
     parser::{self},
+    Map,
 };
+use core::fmt;
+use serde::{Deserialize, Serialize};
 
 // Choose between std and alloc
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {
         extern crate std;
         use std::prelude::v1::*;
+        use std::vec;
     } else {
         extern crate alloc;
         use alloc::string::*;
         use alloc::vec::*;
         use alloc::vec;
-        use core::fmt;
         use alloc::format;
     }
 }
@@ -47,6 +51,14 @@ impl<'a> parser::Nodes {
         }
     }
 
+    /// Byte range in the text
+    pub fn span(&self) -> core::ops::Range<usize> {
+        match self {
+            parser::Nodes::Node(node) => node.span(),
+            parser::Nodes::Token(tok) => tok.span(),
+        }
+    }
+
     /// Returns value of variable that is a number
     ///
     /// Panics if the variable is not a number or if it does not exist
@@ -86,9 +98,69 @@ impl<'a> parser::Nodes {
             parser::Nodes::Token(tok) => panic!("No variables found for token: {:?}", tok.kind),
         }
     }
+
+    /// Returns the node
+    ///
+    /// Fails if the type is token
+    pub fn try_node(&self) -> Result<&parser::Node, AccessError> {
+        match self {
+            parser::Nodes::Node(node) => Ok(node),
+            parser::Nodes::Token(tok) => Err(AccessError::IsToken(tok.kind.clone())),
+        }
+    }
+
+    /// Returns name of node
+    ///
+    /// Fails if the type is token
+    pub fn try_name(&self) -> Result<&str, AccessError> {
+        self.try_node().map(|node| node.name.as_str())
+    }
+
+    /// Returns token type
+    ///
+    /// Fails if the type is node
+    pub fn try_token(&self) -> Result<&Token, AccessError> {
+        match self {
+            parser::Nodes::Node(node) => Err(AccessError::IsNode(node.name.clone())),
+            parser::Nodes::Token(tok) => Ok(tok),
+        }
+    }
+
+    /// Returns value of variable that is a number
+    ///
+    /// Fails if the type is token, if the variable is not a number or if it does not exist
+    pub fn try_number(&self, variable: &str) -> Result<i32, AccessError> {
+        self.try_node()?.try_number(variable)
+    }
+
+    /// Returns value of variable that is a bool
+    ///
+    /// Fails if the type is token, if the variable is not a bool or if it does not exist
+    pub fn try_bool(&self, variable: &str) -> Result<bool, AccessError> {
+        self.try_node()?.try_bool(variable)
+    }
+
+    /// Returns value of variable that is a node
+    ///
+    /// Fails if the type is token, if the variable is not a node or if it does not exist
+    pub fn try_get(&self, variable: &str) -> Result<Option<&parser::Nodes>, AccessError> {
+        self.try_node()?.try_get(variable)
+    }
+
+    /// Returns value of variable that is a list of nodes
+    ///
+    /// Fails if the type is token, if the variable is not a list of nodes or if it does not exist
+    pub fn try_list(&self, variable: &str) -> Result<&Vec<parser::Nodes>, AccessError> {
+        self.try_node()?.try_list(variable)
+    }
 }
 
 impl<'a> parser::Node {
+    /// Returns true if the node failed to parse and the parser recovered from the error
+    pub fn is_error(&self) -> bool {
+        self.recovered
+    }
+
     /// Returns value of variable that is a number
     ///
     /// Panics if the variable is not a number or if it does not exist
@@ -152,9 +224,309 @@ impl<'a> parser::Node {
             None => panic!("No variable {} found for node: {:?}", variable, self.name),
         }
     }
+
+    /// Byte range of the node in the text
+    ///
+    /// Ends with the last token the node consumed, whitespace after it is not included
+    pub fn span(&self) -> core::ops::Range<usize> {
+        self.first_string_idx..self.last_string_idx
+    }
+
+    /// Returns a variable of the node
+    ///
+    /// Fails if the variable does not exist
+    pub fn try_variable(&self, variable: &str) -> Result<&parser::VariableKind, AccessError> {
+        self.read().variable(variable)
+    }
+
+    /// Returns value of variable that is a number
+    ///
+    /// Fails if the variable is not a number or if it does not exist
+    pub fn try_number(&self, variable: &str) -> Result<i32, AccessError> {
+        self.read().number(variable)
+    }
+
+    /// Returns value of variable that is a bool
+    ///
+    /// Fails if the variable is not a bool or if it does not exist
+    pub fn try_bool(&self, variable: &str) -> Result<bool, AccessError> {
+        self.read().bool(variable)
+    }
+
+    /// Returns value of variable that is a node
+    ///
+    /// Fails if the variable is not a node or if it does not exist
+    pub fn try_get(&self, variable: &str) -> Result<Option<&parser::Nodes>, AccessError> {
+        self.read().node(variable)
+    }
+
+    /// Returns value of variable that is a list of nodes
+    ///
+    /// Fails if the variable is not a list of nodes or if it does not exist
+    pub fn try_list(&self, variable: &str) -> Result<&Vec<parser::Nodes>, AccessError> {
+        self.read().list(variable)
+    }
+
+    /// Returns value of variable that is a span
+    ///
+    /// Fails if the variable is not a span or if it does not exist
+    pub fn try_span(&self, variable: &str) -> Result<Option<core::ops::Range<usize>>, AccessError> {
+        self.read().span(variable)
+    }
+
+    /// Returns value of variable that is a list of tokens
+    ///
+    /// Fails if the variable is not a list of tokens or if it does not exist
+    pub fn try_tokens(&self, variable: &str) -> Result<&Vec<Token>, AccessError> {
+        self.read().tokens(variable)
+    }
+
+    /// Returns value of variable that is a text
+    ///
+    /// Fails if the variable is not a text or if it does not exist
+    pub fn try_text(&self, variable: &str) -> Result<Option<&str>, AccessError> {
+        self.read().text(variable)
+    }
+
+    /// Returns the nodes of a list variable that were parsed as the grammar node `name`
+    ///
+    /// Tokens and other nodes in the list are skipped
+    pub fn try_list_of(
+        &'a self,
+        variable: &str,
+        name: &'a str,
+    ) -> Result<impl Iterator<Item = &'a parser::Node> + 'a, AccessError> {
+        Ok(self
+            .try_list(variable)?
+            .iter()
+            .filter_map(move |nodes| match nodes {
+                parser::Nodes::Node(node) if node.name == name => Some(node),
+                _ => None,
+            }))
+    }
+
+    /// Returns the values of all variables in the order they appear in the text
+    ///
+    /// Empty node variables are skipped and lists are flattened
+    pub fn children(&self) -> vec::IntoIter<&parser::Nodes> {
+        let mut children = Vec::new();
+        for variable in self.variables.values() {
+            match variable {
+                parser::VariableKind::Node(Some(node)) => children.push(node),
+                parser::VariableKind::NodeList(list) => children.extend(list),
+                _ => (),
+            }
+        }
+        children.sort_by_key(|child| child.span().start);
+        children.into_iter()
+    }
+
+    /// Returns the child nodes that were parsed as the grammar node `name`
+    ///
+    /// Only direct children are returned, in the order they appear in the text
+    pub fn children_named(&'a self, name: &'a str) -> impl Iterator<Item = &'a parser::Node> {
+        self.children().filter_map(move |child| match child {
+            parser::Nodes::Node(node) if node.name == name => Some(node),
+            _ => None,
+        })
+    }
+
+    fn read(&self) -> Variables<'_> {
+        Variables::new(&self.name, &self.variables)
+    }
+}
+
+/// Reads the variables of a node or the globals by their kind
+///
+/// Used by the accessors of nodes and by the generated typed trees
+pub(crate) struct Variables<'a> {
+    /// Name of the node the variables belong to, for the errors
+    node: &'a str,
+    variables: &'a Map<String, parser::VariableKind>,
+}
+
+impl<'a> Variables<'a> {
+    pub(crate) fn new(node: &'a str, variables: &'a Map<String, parser::VariableKind>) -> Self {
+        Variables { node, variables }
+    }
+
+    pub(crate) fn variable(&self, variable: &str) -> Result<&'a parser::VariableKind, AccessError> {
+        self.variables
+            .get(variable)
+            .ok_or_else(|| AccessError::MissingVariable {
+                node: self.node.to_string(),
+                variable: variable.to_string(),
+            })
+    }
+
+    pub(crate) fn number(&self, variable: &str) -> Result<i32, AccessError> {
+        match self.variable(variable)? {
+            parser::VariableKind::Number(num) => Ok(*num),
+            kind => Err(self.wrong_kind(variable, grammar::VariableKind::Number, kind)),
+        }
+    }
+
+    pub(crate) fn bool(&self, variable: &str) -> Result<bool, AccessError> {
+        match self.variable(variable)? {
+            parser::VariableKind::Boolean(bool) => Ok(*bool),
+            kind => Err(self.wrong_kind(variable, grammar::VariableKind::Boolean, kind)),
+        }
+    }
+
+    pub(crate) fn node(&self, variable: &str) -> Result<Option<&'a parser::Nodes>, AccessError> {
+        match self.variable(variable)? {
+            parser::VariableKind::Node(node) => Ok(node.as_ref()),
+            kind => Err(self.wrong_kind(variable, grammar::VariableKind::Node, kind)),
+        }
+    }
+
+    pub(crate) fn list(&self, variable: &str) -> Result<&'a Vec<parser::Nodes>, AccessError> {
+        match self.variable(variable)? {
+            parser::VariableKind::NodeList(array) => Ok(array),
+            kind => Err(self.wrong_kind(variable, grammar::VariableKind::NodeList, kind)),
+        }
+    }
+
+    pub(crate) fn span(
+        &self,
+        variable: &str,
+    ) -> Result<Option<core::ops::Range<usize>>, AccessError> {
+        match self.variable(variable)? {
+            parser::VariableKind::Span(span) => Ok(span.clone()),
+            kind => Err(self.wrong_kind(variable, grammar::VariableKind::Span, kind)),
+        }
+    }
+
+    pub(crate) fn tokens(&self, variable: &str) -> Result<&'a Vec<Token>, AccessError> {
+        match self.variable(variable)? {
+            parser::VariableKind::TokenList(list) => Ok(list),
+            kind => Err(self.wrong_kind(variable, grammar::VariableKind::TokenList, kind)),
+        }
+    }
+
+    pub(crate) fn text(&self, variable: &str) -> Result<Option<&'a str>, AccessError> {
+        match self.variable(variable)? {
+            parser::VariableKind::Text(text) => Ok(text.as_deref()),
+            kind => Err(self.wrong_kind(variable, grammar::VariableKind::Text, kind)),
+        }
+    }
+
+    fn wrong_kind(
+        &self,
+        variable: &str,
+        expected: grammar::VariableKind,
+        found: &parser::VariableKind,
+    ) -> AccessError {
+        AccessError::WrongKind {
+            node: self.node.to_string(),
+            variable: variable.to_string(),
+            expected,
+            found: found.kind(),
+        }
+    }
+}
+
+impl parser::VariableKind {
+    /// Kind of the variable as declared in the grammar
+    pub fn kind(&self) -> grammar::VariableKind {
+        match self {
+            parser::VariableKind::Node(_) => grammar::VariableKind::Node,
+            parser::VariableKind::NodeList(_) => grammar::VariableKind::NodeList,
+            parser::VariableKind::Boolean(_) => grammar::VariableKind::Boolean,
+            parser::VariableKind::Number(_) => grammar::VariableKind::Number,
+            parser::VariableKind::Span(_) => grammar::VariableKind::Span,
+            parser::VariableKind::TokenList(_) => grammar::VariableKind::TokenList,
+            parser::VariableKind::Text(_) => grammar::VariableKind::Text,
+        }
+    }
+}
+
+/// Error of reading a value from a parsed tree
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum AccessError {
+    /// The node does not have the variable
+    MissingVariable { node: String, variable: String },
+    /// The variable holds a different kind of value
+    WrongKind {
+        node: String,
+        variable: String,
+        expected: grammar::VariableKind,
+        found: grammar::VariableKind,
+    },
+    /// Expected a node, found a token
+    IsToken(TokenKinds),
+    /// Expected a token, found a node
+    IsNode(String),
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessError::MissingVariable { node, variable } => {
+                write!(f, "No variable {} found for node: {:?}", variable, node)
+            }
+            AccessError::WrongKind {
+                node,
+                variable,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Variable {} of node {:?} is {:?}, expected {:?}",
+                variable, node, found, expected
+            ),
+            AccessError::IsToken(kind) => write!(f, "Expected a node, found token: {:?}", kind),
+            AccessError::IsNode(name) => write!(f, "Expected a token, found node: {:?}", name),
+        }
+    }
 }
 
-impl parser::ParseResult {
+#[cfg(feature = "std")]
+impl std::error::Error for AccessError {}
+
+impl<'src> parser::ParseResult<'src> {
+    /// Text that was parsed
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the text of the node or token
+    pub fn slice(&self, nodes: &parser::Nodes) -> &str {
+        &self.text[nodes.span()]
+    }
+
+    /// Returns the text captured by a variable of the node
+    ///
+    /// Nodes, tokens and spans are views into the parsed text, text variables hold
+    /// their own text and lists cover everything from the first to the last value.
+    /// Returns None if nothing was captured.
+    /// Fails if the variable is a bool or a number or if it does not exist
+    pub fn capture<'a>(
+        &'a self,
+        node: &'a parser::Node,
+        variable: &str,
+    ) -> Result<Option<&'a str>, AccessError> {
+        let span = match node.try_variable(variable)? {
+            parser::VariableKind::Node(nodes) => nodes.as_ref().map(|nodes| nodes.span()),
+            parser::VariableKind::NodeList(list) => match (list.first(), list.last()) {
+                (Some(first), Some(last)) => Some(first.span().start..last.span().end),
+                _ => None,
+            },
+            parser::VariableKind::Span(span) => span.clone(),
+            parser::VariableKind::TokenList(list) => match (list.first(), list.last()) {
+                (Some(first), Some(last)) => Some(first.index..last.index + last.len),
+                _ => None,
+            },
+            parser::VariableKind::Text(text) => return Ok(text.as_deref()),
+            kind => {
+                return Err(node
+                    .read()
+                    .wrong_kind(variable, grammar::VariableKind::Text, kind))
+            }
+        };
+        Ok(span.map(|span| &self.text[span]))
+    }
+
     /// Returns stringified version of the node
     ///
     /// This operation is O(1)
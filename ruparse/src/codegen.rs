@@ -0,0 +1,1217 @@
+//! Typed AST generated from a grammar
+//!
+//! The generator emits a struct for every node of the grammar with a field for each
+//! of its variables and an enum for every enumerator with a variant for each of its values.
+//! Fields are typed by what the variables are set to in the rules, a variable that is
+//! only set to `<ident>` nodes holds `Ident` and a variable that is set to tokens holds
+//! tokens. Variables set to values of different types get an enum of their own.
+//!
+//! The generator is meant to be run from a build script and the output included
+//! in a module of its own:
+//!
+//! ```ignore
+//! // build.rs
+//! let grammar: ruparse::grammar::Grammar = serde_json::from_str(&json).unwrap();
+//! let code = ruparse::codegen::Generator::new().generate(&grammar);
+//! std::fs::write(out_dir.join("ast.rs"), code).unwrap();
+//!
+//! // lib.rs
+//! mod ast {
+//!     include!(concat!(env!("OUT_DIR"), "/ast.rs"));
+//! }
+//! ```
+//!
+//! Parsed trees are converted with [`FromNode`]:
+//!
+//! ```ignore
+//! use ruparse::codegen::FromNode;
+//!
+//! let entry = ast::Entry::from_node(&result.entry, text)?;
+//! ```
+use core::fmt::{self, Write};
+use core::ops::Range;
+
+use crate::{
+    api::{AccessError, Variables},
+    diagnostic::dedent,
+    grammar::{Commands, Grammar, MatchToken, OperatorKind, Parameters, Rule, VariableKind},
+    lexer::{ControlTokenKind, Token, TokenKinds},
+    parser::{self, Node, Nodes},
+    Map,
+};
+
+// Choose between std and alloc
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        extern crate std;
+        use std::prelude::v1::*;
+        use std::collections::{BTreeMap, BTreeSet};
+    } else {
+        extern crate alloc;
+        use alloc::string::*;
+        use alloc::vec::*;
+        use alloc::vec;
+        use alloc::format;
+        use alloc::boxed::Box;
+        use alloc::collections::{BTreeMap, BTreeSet};
+    }
+}
+
+/// Converts a parsed node to a generated type
+pub trait FromNode: Sized {
+    /// `text` is the parsed text, it is needed to tell apart words
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError>;
+}
+
+/// Converts a value of a variable to a generated type
+pub trait FromNodes: Sized {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError>;
+}
+
+impl FromNodes for Token {
+    fn from_nodes(nodes: &Nodes, _text: &str) -> Result<Self, ConvertError> {
+        match nodes {
+            Nodes::Token(token) => Ok(token.clone()),
+            Nodes::Node(_) => Err(ConvertError::unexpected("token", nodes)),
+        }
+    }
+}
+
+impl FromNodes for Nodes {
+    fn from_nodes(nodes: &Nodes, _text: &str) -> Result<Self, ConvertError> {
+        Ok(nodes.clone())
+    }
+}
+
+impl<T: FromNodes> FromNodes for Box<T> {
+    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {
+        T::from_nodes(nodes, text).map(Box::new)
+    }
+}
+
+/// Node of a binary operator created by a precedence rule
+#[derive(Debug, Clone)]
+pub struct Binary<T> {
+    pub left: T,
+    pub operator: Token,
+    pub right: T,
+    /// Byte range of the node in the text
+    pub span: Range<usize>,
+}
+
+impl<T: FromNodes> FromNode for Binary<T> {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Binary {
+            left: required(&node.variables, &node.name, "left", text)?,
+            operator: required(&node.variables, &node.name, "operator", text)?,
+            right: required(&node.variables, &node.name, "right", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+/// Node of a prefix or postfix operator created by a precedence rule
+#[derive(Debug, Clone)]
+pub struct Unary<T> {
+    pub operator: Token,
+    pub operand: T,
+    /// Byte range of the node in the text
+    pub span: Range<usize>,
+}
+
+impl<T: FromNodes> FromNode for Unary<T> {
+    fn from_node(node: &Node, text: &str) -> Result<Self, ConvertError> {
+        Ok(Unary {
+            operator: required(&node.variables, &node.name, "operator", text)?,
+            operand: required(&node.variables, &node.name, "operand", text)?,
+            span: node.span(),
+        })
+    }
+}
+
+/// Error of converting a parsed tree to the generated types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConvertError {
+    /// The value does not fit the type
+    Unexpected {
+        /// Grammar name of the expected value
+        expected: String,
+        /// Node name or token kind of the value
+        found: String,
+        /// Byte range of the value in the text
+        span: Range<usize>,
+    },
+    /// The variable is missing or holds a different kind of value than the field
+    Access(AccessError),
+}
+
+impl ConvertError {
+    pub fn unexpected(expected: &str, found: &Nodes) -> ConvertError {
+        let (found, span) = match found {
+            Nodes::Node(node) => (node.name.clone(), node.span()),
+            Nodes::Token(token) => (format!("{:?}", token.kind), token.span()),
+        };
+        ConvertError::Unexpected {
+            expected: expected.to_string(),
+            found,
+            span,
+        }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConvertError::Unexpected {
+                expected, found, ..
+            } => {
+                write!(f, "Expected {}, found {}", expected, found)
+            }
+            ConvertError::Access(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<AccessError> for ConvertError {
+    fn from(err: AccessError) -> Self {
+        ConvertError::Access(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConvertError {}
+
+/// Converts a parsed node by the name of the grammar node
+///
+/// Used by the generated structs
+pub fn convert_node<T: FromNode>(nodes: &Nodes, name: &str, text: &str) -> Result<T, ConvertError> {
+    match nodes {
+        Nodes::Node(node) if node.name == name => T::from_node(node, text),
+        _ => Err(ConvertError::unexpected(name, nodes)),
+    }
+}
+
+/// Reads a variable that holds a single value
+pub fn node_variable<T: FromNodes>(
+    variables: &Map<String, parser::VariableKind>,
+    node: &str,
+    name: &str,
+    text: &str,
+) -> Result<Option<T>, ConvertError> {
+    match Variables::new(node, variables).node(name)? {
+        Some(nodes) => T::from_nodes(nodes, text).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Reads a variable that holds a list of values
+pub fn list_variable<T: FromNodes>(
+    variables: &Map<String, parser::VariableKind>,
+    node: &str,
+    name: &str,
+    text: &str,
+) -> Result<Vec<T>, ConvertError> {
+    Variables::new(node, variables)
+        .list(name)?
+        .iter()
+        .map(|nodes| T::from_nodes(nodes, text))
+        .collect()
+}
+
+/// Reads a boolean variable
+pub fn bool_variable(
+    variables: &Map<String, parser::VariableKind>,
+    node: &str,
+    name: &str,
+) -> Result<bool, ConvertError> {
+    Ok(Variables::new(node, variables).bool(name)?)
+}
+
+/// Reads a number variable
+pub fn number_variable(
+    variables: &Map<String, parser::VariableKind>,
+    node: &str,
+    name: &str,
+) -> Result<i32, ConvertError> {
+    Ok(Variables::new(node, variables).number(name)?)
+}
+
+/// Reads a span variable
+pub fn span_variable(
+    variables: &Map<String, parser::VariableKind>,
+    node: &str,
+    name: &str,
+) -> Result<Option<Range<usize>>, ConvertError> {
+    Ok(Variables::new(node, variables).span(name)?)
+}
+
+/// Reads a variable that holds a list of tokens
+pub fn tokens_variable(
+    variables: &Map<String, parser::VariableKind>,
+    node: &str,
+    name: &str,
+) -> Result<Vec<Token>, ConvertError> {
+    Ok(Variables::new(node, variables).tokens(name)?.clone())
+}
+
+/// Reads a text variable
+pub fn text_variable(
+    variables: &Map<String, parser::VariableKind>,
+    node: &str,
+    name: &str,
+) -> Result<Option<String>, ConvertError> {
+    Ok(Variables::new(node, variables)
+        .text(name)?
+        .map(String::from))
+}
+
+/// Reads a variable that is always set, like the parts of operator nodes
+fn required<T: FromNodes>(
+    variables: &Map<String, parser::VariableKind>,
+    node: &str,
+    name: &str,
+    text: &str,
+) -> Result<T, ConvertError> {
+    node_variable(variables, node, name, text)?.ok_or_else(|| {
+        ConvertError::Access(AccessError::MissingVariable {
+            node: node.to_string(),
+            variable: name.to_string(),
+        })
+    })
+}
+
+/// Generates the Rust source of the typed AST for a grammar
+#[derive(Debug, Clone)]
+pub struct Generator {
+    /// Path of this crate in the generated code
+    pub crate_path: String,
+}
+
+impl Generator {
+    pub fn new() -> Generator {
+        Generator {
+            crate_path: "::ruparse".to_string(),
+        }
+    }
+
+    /// Generates the types for all the nodes, enumerators and globals of the grammar
+    ///
+    /// The output is deterministic, so it can be checked into the repository
+    pub fn generate(&self, grammar: &Grammar) -> String {
+        Context::new(grammar).generate(&self.crate_path)
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Names used by the generated code that the generated types must not shadow
+const RESERVED: &[&str] = &[
+    "Self",
+    "Option",
+    "Some",
+    "None",
+    "Box",
+    "Vec",
+    "Result",
+    "Ok",
+    "Err",
+    "String",
+    "Token",
+    "TokenKinds",
+    "ControlTokenKind",
+    "Node",
+    "Nodes",
+    "VariableKind",
+    "Map",
+    "ConvertError",
+    "FromNode",
+    "FromNodes",
+];
+
+/// What a variable can be set to
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Source {
+    /// Grammar node by its name
+    Node(String),
+    /// Enumerator by its name
+    Enumerator(String),
+    /// Result of a precedence rule by the name of its generated enum
+    Precedence(String),
+    Token,
+    /// Anything, the value is kept as `Nodes`
+    Untyped,
+}
+
+/// Generated struct for a node or the globals
+struct StructDef {
+    name: String,
+    /// Grammar name of the node, None for the globals
+    node: Option<String>,
+    docs: String,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    /// Name of the field
+    name: String,
+    /// Name of the variable in the grammar
+    variable: String,
+    kind: VariableKind,
+    /// Type of the values
+    ty: String,
+    /// Values of a single node are boxed, the types can be recursive
+    boxed: bool,
+}
+
+/// Generated enum for an enumerator, a precedence rule or a variable
+struct EnumDef {
+    name: String,
+    /// Grammar name of the enumerator or the variable used in errors
+    expected: String,
+    docs: String,
+    variants: Vec<Variant>,
+}
+
+struct Variant {
+    name: String,
+    kind: VariantKind,
+}
+
+enum VariantKind {
+    /// Grammar node with its generated struct
+    Node(String, String),
+    /// Node of an operator level
+    Operator(String, OperatorKind),
+    /// Token matched by the condition
+    Token(String),
+    /// Generated enum that is tried in order
+    Nested(String),
+    AnyToken,
+}
+
+struct Context<'a> {
+    grammar: &'a Grammar,
+    /// Generated names of the grammar nodes
+    nodes: BTreeMap<String, String>,
+    /// Generated names of the enumerators
+    enumerators: BTreeMap<String, String>,
+    taken: BTreeSet<String>,
+    structs: Vec<StructDef>,
+    enums: Vec<EnumDef>,
+    /// Sources of the global variables collected from all the nodes
+    globals: BTreeMap<String, BTreeSet<Source>>,
+}
+
+impl<'a> Context<'a> {
+    fn new(grammar: &'a Grammar) -> Context<'a> {
+        let mut ctx = Context {
+            grammar,
+            nodes: BTreeMap::new(),
+            enumerators: BTreeMap::new(),
+            taken: RESERVED.iter().map(|name| name.to_string()).collect(),
+            structs: Vec::new(),
+            enums: Vec::new(),
+            globals: BTreeMap::new(),
+        };
+        // The globals go first so they keep their name
+        if !grammar.globals.is_empty() {
+            ctx.unique("Globals");
+        }
+        for name in sorted(grammar.nodes.keys()) {
+            let generated = ctx.unique(&camel_case(name));
+            ctx.nodes.insert(name.clone(), generated);
+        }
+        for name in sorted(grammar.enumerators.keys()) {
+            let generated = ctx.unique(&camel_case(name));
+            ctx.enumerators.insert(name.clone(), generated);
+        }
+        ctx
+    }
+
+    fn generate(mut self, path: &str) -> String {
+        for name in sorted(self.grammar.nodes.keys()) {
+            self.node_struct(name);
+        }
+        for name in sorted(self.grammar.enumerators.keys()) {
+            self.enumerator_enum(name);
+        }
+        if !self.grammar.globals.is_empty() {
+            self.globals_struct();
+        }
+
+        let mut body = String::new();
+        for def in &self.structs {
+            write_struct(&mut body, def);
+        }
+        for def in &self.enums {
+            write_enum(&mut body, def);
+        }
+
+        let mut out = String::new();
+        let _ = writeln!(out, "// @generated by ruparse::codegen, do not edit");
+        let _ = writeln!(out);
+        // Only the names used for this grammar are imported
+        let imports: [(&str, &[&str]); 4] = [
+            (
+                "codegen",
+                &["self", "ConvertError", "FromNode", "FromNodes"],
+            ),
+            ("lexer", &["ControlTokenKind", "Token", "TokenKinds"]),
+            ("parser", &["Node", "Nodes", "VariableKind"]),
+            ("", &["Map"]),
+        ];
+        for (module, names) in imports {
+            let names: Vec<&str> = names
+                .iter()
+                .copied()
+                .filter(|name| match *name {
+                    "self" => uses(&body, module),
+                    name => uses(&body, name),
+                })
+                .collect();
+            let module = match module {
+                "" => String::new(),
+                module => format!("::{}", module),
+            };
+            match names.as_slice() {
+                [] => (),
+                [name] => {
+                    let _ = writeln!(out, "use {}{}::{};", path, module, name);
+                }
+                names => {
+                    let _ = writeln!(out, "use {}{}::{{{}}};", path, module, names.join(", "));
+                }
+            }
+        }
+        out.push_str(&body);
+        out
+    }
+
+    fn unique(&mut self, name: &str) -> String {
+        let name = unique_in(&self.taken, name);
+        self.taken.insert(name.clone());
+        name
+    }
+
+    fn node_struct(&mut self, name: &str) {
+        let node = &self.grammar.nodes[name];
+        let generated = self.nodes[name].clone();
+        let mut sources = BTreeMap::new();
+        self.collect(&generated, &node.rules, &mut sources);
+
+        let docs = match &node.docs {
+            Some(docs) => dedent(docs),
+            None => format!("Node `{}`", name),
+        };
+        let of = format!("[`{}`]", generated);
+        let fields = self.fields(&generated, &of, &node.variables, &sources);
+        self.structs.push(StructDef {
+            name: generated,
+            node: Some(name.to_string()),
+            docs,
+            fields,
+        });
+    }
+
+    fn globals_struct(&mut self) {
+        let sources = core::mem::take(&mut self.globals);
+        let fields = self.fields("Global", "the globals", &self.grammar.globals, &sources);
+        self.structs.push(StructDef {
+            name: "Globals".to_string(),
+            node: None,
+            docs: "Global variables of the grammar".to_string(),
+            fields,
+        });
+    }
+
+    /// `owner` is the prefix of the generated enums and `of` names the owner in their docs
+    fn fields(
+        &mut self,
+        owner: &str,
+        of: &str,
+        variables: &Map<String, VariableKind>,
+        sources: &BTreeMap<String, BTreeSet<Source>>,
+    ) -> Vec<Field> {
+        let mut names = BTreeSet::new();
+        names.insert("span".to_string());
+        let mut fields = Vec::new();
+        for variable in sorted(variables.keys()) {
+            let kind = variables[variable].clone();
+            let (ty, boxed) = match kind {
+                VariableKind::Node | VariableKind::NodeList => {
+                    let empty = BTreeSet::new();
+                    let sources = sources.get(variable).unwrap_or(&empty);
+                    self.value_type(owner, of, variable, sources)
+                }
+                VariableKind::Boolean => ("bool".to_string(), false),
+                VariableKind::Number => ("i32".to_string(), false),
+                VariableKind::Span => ("Option<core::ops::Range<usize>>".to_string(), false),
+                VariableKind::TokenList => ("Vec<Token>".to_string(), false),
+                VariableKind::Text => ("Option<String>".to_string(), false),
+            };
+            let name = unique_in(&names, &snake_case(variable));
+            names.insert(name.clone());
+            fields.push(Field {
+                name,
+                variable: variable.clone(),
+                kind,
+                ty,
+                boxed,
+            });
+        }
+        fields
+    }
+
+    /// Type of a value of a variable and whether it is boxed
+    fn value_type(
+        &mut self,
+        owner: &str,
+        of: &str,
+        variable: &str,
+        sources: &BTreeSet<Source>,
+    ) -> (String, bool) {
+        let mut iter = sources.iter();
+        match (iter.next(), iter.next()) {
+            (None, _) | (Some(Source::Untyped), None) => ("Nodes".to_string(), false),
+            (Some(source), None) => self.source_type(source),
+            _ if sources.contains(&Source::Untyped) => ("Nodes".to_string(), false),
+            _ => {
+                let name = self.unique(&format!("{}{}", owner, camel_case(variable)));
+                let variants = sources
+                    .iter()
+                    .map(|source| self.source_variant(source))
+                    .collect();
+                self.enums.push(EnumDef {
+                    name: name.clone(),
+                    expected: variable.to_string(),
+                    docs: format!("Values of the variable `{}` of {}", variable, of),
+                    variants: unique_variants(variants),
+                });
+                (name, false)
+            }
+        }
+    }
+
+    fn source_type(&self, source: &Source) -> (String, bool) {
+        match source {
+            Source::Node(name) => (self.nodes[name].clone(), true),
+            Source::Enumerator(name) => (self.enumerators[name].clone(), false),
+            Source::Precedence(name) => (name.clone(), false),
+            Source::Token => ("Token".to_string(), false),
+            Source::Untyped => ("Nodes".to_string(), false),
+        }
+    }
+
+    fn source_variant(&self, source: &Source) -> Variant {
+        match source {
+            Source::Node(name) => Variant {
+                name: self.nodes[name].clone(),
+                kind: VariantKind::Node(name.clone(), self.nodes[name].clone()),
+            },
+            Source::Enumerator(name) => Variant {
+                name: self.enumerators[name].clone(),
+                kind: VariantKind::Nested(self.enumerators[name].clone()),
+            },
+            Source::Precedence(name) => Variant {
+                name: name.clone(),
+                kind: VariantKind::Nested(name.clone()),
+            },
+            Source::Token | Source::Untyped => Variant {
+                name: "Token".to_string(),
+                kind: VariantKind::AnyToken,
+            },
+        }
+    }
+
+    fn token_source(&self, token: &MatchToken) -> Source {
+        match token {
+            MatchToken::Node(name) if self.nodes.contains_key(name) => Source::Node(name.clone()),
+            MatchToken::Enumerator(name) if self.enumerators.contains_key(name) => {
+                Source::Enumerator(name.clone())
+            }
+            MatchToken::Token(_) | MatchToken::Word(_) | MatchToken::Any => Source::Token,
+            MatchToken::Node(_) | MatchToken::Enumerator(_) => Source::Untyped,
+        }
+    }
+
+    /// Collects what the variables of a node are set to
+    fn collect(
+        &mut self,
+        owner: &str,
+        rules: &[Rule],
+        sources: &mut BTreeMap<String, BTreeSet<Source>>,
+    ) {
+        for rule in rules {
+            match rule {
+                Rule::Is {
+                    token,
+                    rules,
+                    parameters,
+                }
+                | Rule::While {
+                    token,
+                    rules,
+                    parameters,
+                }
+                | Rule::Until {
+                    token,
+                    rules,
+                    parameters,
+                } => {
+                    let source = self.token_source(token);
+                    self.set(parameters, &source, sources);
+                    self.collect(owner, rules, sources);
+                }
+                // The parameters are only used if the token matches
+                Rule::Isnt { rules, .. } | Rule::Loop { rules } => {
+                    self.collect(owner, rules, sources)
+                }
+                Rule::IsOneOf { tokens } | Rule::UntilOneOf { tokens } => {
+                    for one_of in tokens {
+                        let source = self.token_source(&one_of.token);
+                        self.set(&one_of.parameters, &source, sources);
+                        self.collect(owner, &one_of.rules, sources);
+                    }
+                }
+                Rule::Maybe {
+                    token,
+                    is,
+                    isnt,
+                    parameters,
+                } => {
+                    let source = self.token_source(token);
+                    self.set(parameters, &source, sources);
+                    self.collect(owner, is, sources);
+                    self.collect(owner, isnt, sources);
+                }
+                Rule::MaybeOneOf { is_one_of, isnt } => {
+                    for one_of in is_one_of {
+                        let source = self.token_source(&one_of.token);
+                        self.set(&one_of.parameters, &source, sources);
+                        self.collect(owner, &one_of.rules, sources);
+                    }
+                    self.collect(owner, isnt, sources);
+                }
+                Rule::Precedence {
+                    operand,
+                    levels,
+                    parameters,
+                } => {
+                    let variable = parameters.iter().find_map(|parameter| match parameter {
+                        Parameters::Set(name) | Parameters::Global(name) => Some(name),
+                        _ => None,
+                    });
+                    // The tree is not stored anywhere
+                    let variable = match variable {
+                        Some(variable) => variable,
+                        None => continue,
+                    };
+                    let name = self.unique(&format!("{}{}", owner, camel_case(variable)));
+                    let operand = self.token_source(operand);
+                    let mut variants = vec![self.source_variant(&operand)];
+                    for level in levels {
+                        variants.push(Variant {
+                            name: camel_case(&level.name),
+                            kind: VariantKind::Operator(level.name.clone(), level.kind),
+                        });
+                    }
+                    self.enums.push(EnumDef {
+                        name: name.clone(),
+                        expected: variable.to_string(),
+                        docs: format!(
+                            "Operators and operands of the variable `{}` of [`{}`]",
+                            variable, owner
+                        ),
+                        variants: unique_variants(variants),
+                    });
+                    self.set(parameters, &Source::Precedence(name), sources);
+                }
+                Rule::Command {
+                    command:
+                        Commands::Compare { rules, .. } | Commands::CompareLiteral { rules, .. },
+                } => self.collect(owner, rules, sources),
+                Rule::Command { .. } => (),
+                Rule::Debug { .. } => (),
+            }
+        }
+    }
+
+    fn set(
+        &mut self,
+        parameters: &[Parameters],
+        source: &Source,
+        sources: &mut BTreeMap<String, BTreeSet<Source>>,
+    ) {
+        for parameter in parameters {
+            match parameter {
+                Parameters::Set(name) => {
+                    sources
+                        .entry(name.clone())
+                        .or_default()
+                        .insert(source.clone());
+                }
+                Parameters::Global(name) => {
+                    self.globals
+                        .entry(name.clone())
+                        .or_default()
+                        .insert(source.clone());
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn enumerator_enum(&mut self, name: &str) {
+        let enumerator = &self.grammar.enumerators[name];
+        let mut variants = Vec::new();
+        for value in &enumerator.values {
+            let variant = match value {
+                MatchToken::Token(kind) => Variant {
+                    name: kind_name(kind),
+                    kind: VariantKind::Token(kind_condition(kind)),
+                },
+                MatchToken::Word(word) => Variant {
+                    name: symbol_name(word),
+                    kind: VariantKind::Token(format!(
+                        "matches!(token.kind, TokenKinds::Text) && &text[token.span()] == {:?}",
+                        word
+                    )),
+                },
+                MatchToken::Any => Variant {
+                    name: "Any".to_string(),
+                    kind: VariantKind::AnyToken,
+                },
+                MatchToken::Node(_) | MatchToken::Enumerator(_) => {
+                    self.source_variant(&self.token_source(value))
+                }
+            };
+            variants.push(variant);
+        }
+        self.enums.push(EnumDef {
+            name: self.enumerators[name].clone(),
+            expected: name.to_string(),
+            docs: format!("Values of the enumerator `{}`", name),
+            variants: unique_variants(variants),
+        });
+    }
+}
+
+fn write_struct(out: &mut String, def: &StructDef) {
+    let _ = writeln!(out);
+    let mut code = false;
+    for line in def.docs.lines() {
+        // Examples in the docs are written in the parsed language, not in Rust
+        let line = match line.trim_start().starts_with("```") {
+            true if !code && line.trim() == "```" => {
+                code = true;
+                format!("{}text", line)
+            }
+            true => {
+                code = !code;
+                line.to_string()
+            }
+            false => line.to_string(),
+        };
+        let _ = writeln!(out, "///{}{}", if line.is_empty() { "" } else { " " }, line);
+    }
+    let _ = writeln!(out, "#[derive(Debug, Clone)]");
+    let _ = writeln!(out, "pub struct {} {{", def.name);
+    for field in &def.fields {
+        let ty = match (&field.kind, field.boxed) {
+            (VariableKind::Node, true) => format!("Option<Box<{}>>", field.ty),
+            (VariableKind::Node, false) => format!("Option<{}>", field.ty),
+            (VariableKind::NodeList, _) => format!("Vec<{}>", field.ty),
+            _ => field.ty.clone(),
+        };
+        let _ = writeln!(out, "    pub {}: {},", field.name, ty);
+    }
+    if def.node.is_some() {
+        let _ = writeln!(out, "    /// Byte range of the node in the text");
+        let _ = writeln!(out, "    pub span: core::ops::Range<usize>,");
+    }
+    let _ = writeln!(out, "}}");
+    let _ = writeln!(out);
+
+    let uses_text = def
+        .fields
+        .iter()
+        .any(|field| matches!(field.kind, VariableKind::Node | VariableKind::NodeList));
+    let text = if uses_text { "text" } else { "_text" };
+    let (owner, variables) = match &def.node {
+        Some(node) => {
+            let _ = writeln!(out, "impl FromNode for {} {{", def.name);
+            let _ = writeln!(
+                out,
+                "    fn from_node(node: &Node, {}: &str) -> Result<Self, ConvertError> {{",
+                text
+            );
+            (format!("{:?}", node), "&node.variables")
+        }
+        None => {
+            let _ = writeln!(out, "impl {} {{", def.name);
+            let _ = writeln!(out, "    pub fn from_globals(");
+            let _ = writeln!(out, "        globals: &Map<String, VariableKind>,");
+            let _ = writeln!(out, "        {}: &str,", text);
+            let _ = writeln!(out, "    ) -> Result<Self, ConvertError> {{");
+            ("\"globals\"".to_string(), "globals")
+        }
+    };
+    let _ = writeln!(out, "        Ok({} {{", def.name);
+    for field in &def.fields {
+        let read = match field.kind {
+            VariableKind::Node => "node_variable",
+            VariableKind::NodeList => "list_variable",
+            VariableKind::Boolean => "bool_variable",
+            VariableKind::Number => "number_variable",
+            VariableKind::Span => "span_variable",
+            VariableKind::TokenList => "tokens_variable",
+            VariableKind::Text => "text_variable",
+        };
+        let text = match field.kind {
+            VariableKind::Node | VariableKind::NodeList => ", text",
+            VariableKind::Boolean
+            | VariableKind::Number
+            | VariableKind::Span
+            | VariableKind::TokenList
+            | VariableKind::Text => "",
+        };
+        let _ = writeln!(
+            out,
+            "            {}: codegen::{}({}, {}, {:?}{})?,",
+            field.name, read, variables, owner, field.variable, text
+        );
+    }
+    if def.node.is_some() {
+        let _ = writeln!(out, "            span: node.span(),");
+    }
+    let _ = writeln!(out, "        }})");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+
+    if let Some(node) = &def.node {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "impl FromNodes for {} {{", def.name);
+        let _ = writeln!(
+            out,
+            "    fn from_nodes(nodes: &Nodes, text: &str) -> Result<Self, ConvertError> {{"
+        );
+        let _ = writeln!(
+            out,
+            "        codegen::convert_node(nodes, {:?}, text)",
+            node
+        );
+        let _ = writeln!(out, "    }}");
+        let _ = writeln!(out, "}}");
+    }
+}
+
+fn write_enum(out: &mut String, def: &EnumDef) {
+    let _ = writeln!(out);
+    let _ = writeln!(out, "/// {}", def.docs);
+    let _ = writeln!(out, "#[derive(Debug, Clone)]");
+    let _ = writeln!(out, "pub enum {} {{", def.name);
+    for variant in &def.variants {
+        let ty = match &variant.kind {
+            VariantKind::Node(_, ty) => format!("Box<{}>", ty),
+            VariantKind::Operator(_, OperatorKind::Left | OperatorKind::Right) => {
+                format!("Box<codegen::Binary<{}>>", def.name)
+            }
+            VariantKind::Operator(_, OperatorKind::Prefix | OperatorKind::Postfix) => {
+                format!("Box<codegen::Unary<{}>>", def.name)
+            }
+            VariantKind::Token(_) | VariantKind::AnyToken => "Token".to_string(),
+            VariantKind::Nested(ty) => ty.clone(),
+        };
+        let _ = writeln!(out, "    {}({}),", variant.name, ty);
+    }
+    let _ = writeln!(out, "}}");
+    let _ = writeln!(out);
+
+    let uses_text = def.variants.iter().any(|variant| match &variant.kind {
+        VariantKind::Token(condition) => condition.contains("text"),
+        VariantKind::AnyToken => false,
+        _ => true,
+    });
+    let _ = writeln!(out, "impl FromNodes for {} {{", def.name);
+    let _ = writeln!(
+        out,
+        "    fn from_nodes(nodes: &Nodes, {}: &str) -> Result<Self, ConvertError> {{",
+        if uses_text { "text" } else { "_text" }
+    );
+    // Nodes and tokens are told apart by their names and kinds first,
+    // the nested enums are tried one by one afterwards
+    let nodes: Vec<&Variant> = def
+        .variants
+        .iter()
+        .filter(|variant| {
+            matches!(
+                variant.kind,
+                VariantKind::Node(..) | VariantKind::Operator(..)
+            )
+        })
+        .collect();
+    if !nodes.is_empty() {
+        let _ = writeln!(out, "        if let Nodes::Node(node) = nodes {{");
+        for variant in nodes {
+            let name = match &variant.kind {
+                VariantKind::Node(name, _) | VariantKind::Operator(name, _) => name,
+                _ => continue,
+            };
+            let _ = writeln!(out, "            if node.name == {:?} {{", name);
+            let _ = writeln!(
+                out,
+                "                return Ok({}::{}(Box::new(FromNode::from_node(node, text)?)));",
+                def.name, variant.name
+            );
+            let _ = writeln!(out, "            }}");
+        }
+        let _ = writeln!(out, "        }}");
+    }
+    let tokens: Vec<(&Variant, &String)> = def
+        .variants
+        .iter()
+        .filter_map(|variant| match &variant.kind {
+            VariantKind::Token(condition) => Some((variant, condition)),
+            _ => None,
+        })
+        .collect();
+    if !tokens.is_empty() {
+        let _ = writeln!(out, "        if let Nodes::Token(token) = nodes {{");
+        for (variant, condition) in tokens {
+            let _ = writeln!(out, "            if {} {{", condition);
+            let _ = writeln!(
+                out,
+                "                return Ok({}::{}(token.clone()));",
+                def.name, variant.name
+            );
+            let _ = writeln!(out, "            }}");
+        }
+        let _ = writeln!(out, "        }}");
+    }
+    for variant in &def.variants {
+        if let VariantKind::Nested(_) = variant.kind {
+            let _ = writeln!(
+                out,
+                "        if let Ok(value) = FromNodes::from_nodes(nodes, text) {{"
+            );
+            let _ = writeln!(
+                out,
+                "            return Ok({}::{}(value));",
+                def.name, variant.name
+            );
+            let _ = writeln!(out, "        }}");
+        }
+    }
+    for variant in &def.variants {
+        if let VariantKind::AnyToken = variant.kind {
+            let _ = writeln!(out, "        if let Nodes::Token(token) = nodes {{");
+            let _ = writeln!(
+                out,
+                "            return Ok({}::{}(token.clone()));",
+                def.name, variant.name
+            );
+            let _ = writeln!(out, "        }}");
+            break;
+        }
+    }
+    let _ = writeln!(
+        out,
+        "        Err(ConvertError::unexpected({:?}, nodes))",
+        def.expected
+    );
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+}
+
+/// Whether the name is used as an identifier in the code
+fn uses(code: &str, name: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    code.match_indices(name).any(|(idx, _)| {
+        let before = code[..idx].chars().next_back();
+        let after = code[idx + name.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    })
+}
+
+fn sorted<'b>(names: impl Iterator<Item = &'b String>) -> Vec<&'b String> {
+    let mut names: Vec<&String> = names.collect();
+    names.sort();
+    names
+}
+
+/// Adds a number to the name until it is not taken
+fn unique_in(taken: &BTreeSet<String>, name: &str) -> String {
+    let mut unique = name.to_string();
+    let mut n = 2;
+    while taken.contains(&unique) {
+        unique = format!("{}{}", name, n);
+        n += 1;
+    }
+    unique
+}
+
+fn unique_variants(variants: Vec<Variant>) -> Vec<Variant> {
+    let mut taken = BTreeSet::new();
+    variants
+        .into_iter()
+        .map(|variant| {
+            let name = unique_in(&taken, &variant.name);
+            taken.insert(name.clone());
+            Variant { name, ..variant }
+        })
+        .collect()
+}
+
+/// Condition of the generated code that matches a token of the kind
+fn kind_condition(kind: &TokenKinds) -> String {
+    match kind {
+        TokenKinds::Token(token) => format!(
+            "matches!(&token.kind, TokenKinds::Token(kind) if kind == {:?})",
+            token
+        ),
+        TokenKinds::Complex(name) => format!(
+            "matches!(&token.kind, TokenKinds::Complex(kind) if kind == {:?})",
+            name
+        ),
+        TokenKinds::Text => "matches!(token.kind, TokenKinds::Text)".to_string(),
+        TokenKinds::Whitespace => "matches!(token.kind, TokenKinds::Whitespace)".to_string(),
+        TokenKinds::Control(ControlTokenKind::Eol) => {
+            "matches!(token.kind, TokenKinds::Control(ControlTokenKind::Eol))".to_string()
+        }
+        TokenKinds::Control(ControlTokenKind::Eof) => {
+            "matches!(token.kind, TokenKinds::Control(ControlTokenKind::Eof))".to_string()
+        }
+    }
+}
+
+fn kind_name(kind: &TokenKinds) -> String {
+    match kind {
+        TokenKinds::Token(token) => symbol_name(token),
+        TokenKinds::Complex(name) => camel_case(name),
+        TokenKinds::Text => "Text".to_string(),
+        TokenKinds::Whitespace => "Whitespace".to_string(),
+        TokenKinds::Control(ControlTokenKind::Eol) => "Eol".to_string(),
+        TokenKinds::Control(ControlTokenKind::Eof) => "Eof".to_string(),
+    }
+}
+
+/// Name of a token made of words and symbols, `+=` is `PlusEq`
+fn symbol_name(token: &str) -> String {
+    let mut name = String::new();
+    let mut word = String::new();
+    for c in token.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            name.push_str(&camel_case(&word));
+            word.clear();
+        }
+        let symbol = match c {
+            '+' => "Plus",
+            '-' => "Minus",
+            '*' => "Star",
+            '/' => "Slash",
+            '%' => "Percent",
+            '=' => "Eq",
+            '!' => "Bang",
+            '<' => "Lt",
+            '>' => "Gt",
+            '&' => "Amp",
+            '|' => "Pipe",
+            '^' => "Caret",
+            '~' => "Tilde",
+            '.' => "Dot",
+            ',' => "Comma",
+            ':' => "Colon",
+            ';' => "Semi",
+            '(' => "LParen",
+            ')' => "RParen",
+            '[' => "LBracket",
+            ']' => "RBracket",
+            '{' => "LBrace",
+            '}' => "RBrace",
+            '"' => "Quote",
+            '\'' => "Apostrophe",
+            '#' => "Hash",
+            '@' => "At",
+            '$' => "Dollar",
+            '?' => "Question",
+            '\\' => "Backslash",
+            '`' => "Backtick",
+            '_' => "",
+            c => {
+                name.push_str(&format!("U{:X}", c as u32));
+                continue;
+            }
+        };
+        name.push_str(symbol);
+    }
+    if !word.is_empty() {
+        name.push_str(&camel_case(&word));
+    }
+    match name.chars().next() {
+        None => "Empty".to_string(),
+        Some(c) if c.is_numeric() => format!("N{}", name),
+        Some(_) => name,
+    }
+}
+
+/// `ends_with_x` is `EndsWithX`, names that are already in camel case are kept
+fn camel_case(name: &str) -> String {
+    let mut result = String::new();
+    for part in name.split(|c: char| !c.is_alphanumeric()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            result.extend(first.to_uppercase());
+            result.push_str(chars.as_str());
+        }
+    }
+    match result.chars().next() {
+        None => "Value".to_string(),
+        Some(c) if c.is_numeric() => format!("N{}", result),
+        Some(_) => result,
+    }
+}
+
+/// `returnType` is `return_type` and `KWFunction` is `kw_function`, keywords are escaped
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !result.is_empty() && !result.ends_with('_') {
+                result.push('_');
+            }
+            continue;
+        }
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            let boundary =
+                prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower);
+            if boundary && !result.ends_with('_') {
+                result.push('_');
+            }
+        }
+        result.extend(c.to_lowercase());
+    }
+    let result = result.trim_end_matches('_').to_string();
+    match result.as_str() {
+        "" => "value".to_string(),
+        "self" | "super" | "crate" => format!("{}_", result),
+        "as" | "break" | "const" | "continue" | "else" | "enum" | "extern" | "false" | "fn"
+        | "for" | "if" | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move" | "mut"
+        | "pub" | "ref" | "return" | "static" | "struct" | "trait" | "true" | "type" | "unsafe"
+        | "use" | "where" | "while" | "async" | "await" | "dyn" | "abstract" | "become" | "box"
+        | "do" | "final" | "macro" | "override" | "priv" | "typeof" | "unsized" | "virtual"
+        | "yield" | "try" | "gen" => format!("r#{}", result),
+        _ if result.starts_with(|c: char| c.is_numeric()) => format!("_{}", result),
+        _ => result,
+    }
+}
@@ -0,0 +1,429 @@
+//! Building grammars out of other grammars
+//!
+//! `Grammar::merge` adds the nodes, enumerators and globals of another grammar,
+//! `Grammar::import_as` does the same after moving everything under a namespace,
+//! so `expr` of a grammar imported as `math` is referenced as `math::expr`.
+//! Names that are defined in both grammars are reported as conflicts and nothing is merged.
+//!
+//! ```rust
+//! use ruparse::grammar::{Grammar, Node};
+//! # use ruparse::Map;
+//!
+//! let mut math = Grammar::new();
+//! math.add_node(Node {
+//!     name: "expr".to_string(),
+//!     rules: vec![],
+//!     variables: Map::new(),
+//!     docs: None,
+//!     sync: vec![],
+//! });
+//! let mut grammar = Grammar::new();
+//! grammar.import_as(&math, "math").unwrap();
+//! assert!(grammar.nodes.contains_key("math::expr"));
+//! ```
+use core::fmt;
+
+use crate::{
+    grammar::{Commands, Grammar, MatchToken, Parameters, Rule, Rules, VariableKind},
+    lexer::Lexer,
+    Map,
+};
+
+// Choose between std and alloc
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        extern crate std;
+        use std::prelude::v1::*;
+    } else {
+        extern crate alloc;
+        use alloc::string::*;
+        use alloc::vec::*;
+        use alloc::format;
+    }
+}
+
+/// Separates the namespace from the name, `math::expr`
+pub const SEPARATOR: &str = "::";
+
+/// Name in the namespace
+pub fn qualify(namespace: &str, name: &str) -> String {
+    format!("{}{}{}", namespace, SEPARATOR, name)
+}
+
+/// Splits a name into its namespace and the name in it
+///
+/// Nested namespaces stay together, `a::b::c` is split into `a::b` and `c`
+pub fn split(name: &str) -> Option<(&str, &str)> {
+    name.rsplit_once(SEPARATOR)
+}
+
+/// Name defined differently in the grammars that are merged
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conflict {
+    /// Node defined in both grammars
+    Node(String),
+    /// Enumerator defined in both grammars with different values
+    Enumerator(String),
+    /// Global variable of different kinds
+    Global {
+        name: String,
+        kind: VariableKind,
+        other: VariableKind,
+    },
+    /// Pattern token with the same name matching something else
+    Pattern(String),
+    /// Lexer mode with the same name and different tokens
+    Mode(String),
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Node(name) => write!(f, "Node {} is defined twice", name),
+            Conflict::Enumerator(name) => {
+                write!(f, "Enumerator {} is defined with different values", name)
+            }
+            Conflict::Global { name, kind, other } => write!(
+                f,
+                "Global {} is defined as {:?} and as {:?}",
+                name, kind, other
+            ),
+            Conflict::Pattern(name) => {
+                write!(f, "Pattern {} is defined with a different pattern", name)
+            }
+            Conflict::Mode(name) => write!(f, "Mode {} is defined differently", name),
+        }
+    }
+}
+
+/// Conflicts that stopped a merge
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeError {
+    pub conflicts: Vec<Conflict>,
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Grammars can not be merged:")?;
+        for conflict in &self.conflicts {
+            write!(f, "\n  {}", conflict)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MergeError {}
+
+impl Grammar {
+    /// Names of the other grammar that are already defined differently
+    ///
+    /// Enumerators and globals defined the same way in both grammars are shared
+    pub fn conflicts(&self, other: &Grammar) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for name in other.nodes.keys() {
+            if self.nodes.contains_key(name) {
+                conflicts.push(Conflict::Node(name.clone()));
+            }
+        }
+        for (name, enumerator) in &other.enumerators {
+            match self.enumerators.get(name) {
+                Some(existing) if existing.values != enumerator.values => {
+                    conflicts.push(Conflict::Enumerator(name.clone()))
+                }
+                _ => (),
+            }
+        }
+        for (name, other) in &other.globals {
+            match self.globals.get(name) {
+                Some(kind) if kind != other => conflicts.push(Conflict::Global {
+                    name: name.clone(),
+                    kind: kind.clone(),
+                    other: other.clone(),
+                }),
+                _ => (),
+            }
+        }
+        conflicts.sort_by_key(|conflict| conflict.to_string());
+        conflicts
+    }
+
+    /// Adds the nodes, enumerators and globals of the other grammar
+    ///
+    /// Nothing is added if there are any conflicts
+    pub fn merge(&mut self, other: &Grammar) -> Result<(), MergeError> {
+        let conflicts = self.conflicts(other);
+        if !conflicts.is_empty() {
+            return Err(MergeError { conflicts });
+        }
+        for (name, node) in &other.nodes {
+            self.nodes.insert(name.clone(), node.clone());
+        }
+        for (name, enumerator) in &other.enumerators {
+            self.enumerators.insert(name.clone(), enumerator.clone());
+        }
+        for (name, kind) in &other.globals {
+            self.globals.insert(name.clone(), kind.clone());
+        }
+        Ok(())
+    }
+
+    /// Adds the other grammar with all its names moved to the namespace
+    ///
+    /// See `namespaced` for the names that are moved
+    pub fn import_as(&mut self, other: &Grammar, namespace: &str) -> Result<(), MergeError> {
+        self.merge(&other.namespaced(namespace))
+    }
+
+    /// Copy of the grammar with its nodes, enumerators and globals moved to the namespace
+    ///
+    /// References to names the grammar does not define stay as they are,
+    /// so an imported grammar can use the nodes of the grammar it is imported into.
+    /// Operator levels of precedence rules keep their names, they are node names
+    /// only visible in the parse tree.
+    pub fn namespaced(&self, namespace: &str) -> Grammar {
+        let mut grammar = Grammar {
+            nodes: Map::new(),
+            enumerators: Map::new(),
+            globals: Map::new(),
+            eof: self.eof,
+        };
+        for node in self.nodes.values() {
+            let mut node = node.clone();
+            node.name = qualify(namespace, &node.name);
+            let rename = Rename {
+                grammar: self,
+                namespace,
+                variables: &node.variables,
+            };
+            rename.rules(&mut node.rules);
+            grammar.nodes.insert(node.name.clone(), node);
+        }
+        let variables = Map::new();
+        let rename = Rename {
+            grammar: self,
+            namespace,
+            variables: &variables,
+        };
+        for enumerator in self.enumerators.values() {
+            let mut enumerator = enumerator.clone();
+            enumerator.name = qualify(namespace, &enumerator.name);
+            for value in &mut enumerator.values {
+                rename.token(value);
+            }
+            grammar
+                .enumerators
+                .insert(enumerator.name.clone(), enumerator);
+        }
+        for (name, kind) in &self.globals {
+            grammar
+                .globals
+                .insert(qualify(namespace, name), kind.clone());
+        }
+        grammar
+    }
+}
+
+/// Moves the references of a node to the namespace
+struct Rename<'a> {
+    /// Grammar being moved, only names defined in it are moved
+    grammar: &'a Grammar,
+    namespace: &'a str,
+    /// Variables of the node, they shadow the globals in comparisons
+    variables: &'a Map<String, VariableKind>,
+}
+
+impl Rename<'_> {
+    fn rules(&self, rules: &mut Rules) {
+        for rule in rules {
+            self.rule(rule);
+        }
+    }
+
+    fn rule(&self, rule: &mut Rule) {
+        match rule {
+            Rule::Is {
+                token,
+                rules,
+                parameters,
+            }
+            | Rule::Isnt {
+                token,
+                rules,
+                parameters,
+            }
+            | Rule::While {
+                token,
+                rules,
+                parameters,
+            }
+            | Rule::Until {
+                token,
+                rules,
+                parameters,
+            } => {
+                self.token(token);
+                self.rules(rules);
+                self.parameters(parameters);
+            }
+            Rule::Maybe {
+                token,
+                is,
+                isnt,
+                parameters,
+            } => {
+                self.token(token);
+                self.rules(is);
+                self.rules(isnt);
+                self.parameters(parameters);
+            }
+            Rule::IsOneOf { tokens } | Rule::UntilOneOf { tokens } => {
+                for one_of in tokens {
+                    self.token(&mut one_of.token);
+                    self.rules(&mut one_of.rules);
+                    self.parameters(&mut one_of.parameters);
+                }
+            }
+            Rule::MaybeOneOf { is_one_of, isnt } => {
+                for one_of in is_one_of {
+                    self.token(&mut one_of.token);
+                    self.rules(&mut one_of.rules);
+                    self.parameters(&mut one_of.parameters);
+                }
+                self.rules(isnt);
+            }
+            Rule::Loop { rules } => self.rules(rules),
+            Rule::Precedence {
+                operand,
+                levels,
+                parameters,
+            } => {
+                self.token(operand);
+                for level in levels {
+                    for operator in &mut level.operators {
+                        self.token(operator);
+                    }
+                }
+                self.parameters(parameters);
+            }
+            Rule::Command { command } => match command {
+                Commands::Compare {
+                    left, right, rules, ..
+                } => {
+                    self.variable(left);
+                    self.variable(right);
+                    self.rules(rules);
+                }
+                Commands::CompareLiteral {
+                    variable, rules, ..
+                } => {
+                    self.variable(variable);
+                    self.rules(rules);
+                }
+                Commands::Error { .. }
+                | Commands::HardError { .. }
+                | Commands::Goto { .. }
+                | Commands::Label { .. }
+                | Commands::Print { .. } => (),
+            },
+            Rule::Debug { .. } => (),
+        }
+    }
+
+    fn token(&self, token: &mut MatchToken) {
+        match token {
+            MatchToken::Node(name) if self.grammar.nodes.contains_key(name) => {
+                *name = qualify(self.namespace, name)
+            }
+            MatchToken::Enumerator(name) if self.grammar.enumerators.contains_key(name) => {
+                *name = qualify(self.namespace, name)
+            }
+            _ => (),
+        }
+    }
+
+    fn parameters(&self, parameters: &mut [Parameters]) {
+        for parameter in parameters {
+            match parameter {
+                Parameters::Global(name)
+                | Parameters::IncrementGlobal(name)
+                | Parameters::AddGlobal(name, _)
+                | Parameters::ResetGlobal(name)
+                | Parameters::TrueGlobal(name)
+                | Parameters::FalseGlobal(name) => self.global(name),
+                _ => (),
+            }
+        }
+    }
+
+    /// Variable of a comparison, a global if the node has no variable of the name
+    fn variable(&self, name: &mut String) {
+        if !self.variables.contains_key(name) {
+            self.global(name);
+        }
+    }
+
+    fn global(&self, name: &mut String) {
+        if self.grammar.globals.contains_key(name) {
+            *name = qualify(self.namespace, name);
+        }
+    }
+}
+
+impl Lexer {
+    /// Pattern tokens and modes of the other lexer that are already defined differently
+    pub fn conflicts(&self, other: &Lexer) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for token in &other.patterns {
+            let conflict = self
+                .patterns
+                .iter()
+                .any(|existing| existing.name == token.name && existing.pattern != token.pattern);
+            if conflict {
+                conflicts.push(Conflict::Pattern(token.name.clone()));
+            }
+        }
+        for mode in &other.modes {
+            let conflict = self
+                .modes
+                .iter()
+                .any(|existing| existing.name == mode.name && existing != mode);
+            if conflict {
+                conflicts.push(Conflict::Mode(mode.name.clone()));
+            }
+        }
+        conflicts
+    }
+
+    /// Adds the tokens, pattern tokens and modes of the other lexer
+    ///
+    /// The patterns are added after the existing ones, so they lose to them on matches
+    /// of the same length. Preprocessor steps and functions are not added,
+    /// the lexer keeps preprocessing the way it did. Nothing is added if there are any conflicts.
+    pub fn merge(&mut self, other: &Lexer) -> Result<(), MergeError> {
+        let conflicts = self.conflicts(other);
+        if !conflicts.is_empty() {
+            return Err(MergeError { conflicts });
+        }
+        for token in &other.token_kinds {
+            if !self.token_kinds.contains(token) {
+                self.add_token(token.clone());
+            }
+        }
+        for token in &other.patterns {
+            let defined = self
+                .patterns
+                .iter()
+                .any(|existing| existing.name == token.name);
+            if !defined {
+                self.add_pattern(token.name.clone(), token.pattern.clone());
+            }
+        }
+        for mode in &other.modes {
+            if !self.modes.iter().any(|existing| existing.name == mode.name) {
+                self.add_mode(mode.clone());
+            }
+        }
+        Ok(())
+    }
+}
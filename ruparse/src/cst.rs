@@ -0,0 +1,292 @@
+//! Lossless concrete syntax trees
+//!
+//! The parse tree only keeps the values set to variables and the preprocessors can remove
+//! whitespace and comments before parsing. `ParseResult::cst` puts every token of the text
+//! into the node it is in and attaches the text between the tokens to them as trivia,
+//! so printing the tree gives back the text byte for byte.
+//!
+//! A token gets the trivia after it up to the end of its line as trailing trivia,
+//! the rest of the trivia up to the next token is the leading trivia of that token.
+//! The trivia at the end of the text is the leading trivia of the Eof token.
+//!
+//! ```ignore
+//! let tokens = parser.lexer.lex_utf8(text)?;
+//! let result = parser.parse(&tokens, text)?;
+//! let cst = result.cst(&tokens);
+//! assert_eq!(cst.to_string(), text);
+//! ```
+use core::fmt;
+use core::ops::Range;
+
+use crate::{
+    lexer::{Token, TokenKinds},
+    parser::{Node, Nodes, ParseResult},
+};
+
+// Choose between std and alloc
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        extern crate std;
+        use std::prelude::v1::*;
+    } else {
+        extern crate alloc;
+        use alloc::string::*;
+        use alloc::vec::*;
+    }
+}
+
+/// Tree with all the tokens and trivia of the text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst<'a> {
+    pub root: CstNode,
+    /// End of the text, holds the trivia after the last token
+    pub eof: CstToken,
+    text: &'a str,
+}
+
+/// Node of the parse tree with the nodes and tokens in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    pub name: String,
+    /// Nodes and tokens in the order of the text
+    pub children: Vec<CstElement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstElement {
+    Node(CstNode),
+    Token(CstToken),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstToken {
+    pub kind: TokenKinds,
+    /// Byte range of the token in the text
+    pub span: Range<usize>,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+/// Text between tokens
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    /// Byte range of the trivia in the text
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// Whitespace on a single line
+    Whitespace,
+    /// Line break
+    Eol,
+    /// Text removed by the preprocessors, like comments
+    ///
+    /// Split at line breaks and without the whitespace around it
+    Skipped,
+}
+
+impl ParseResult<'_> {
+    /// Lossless tree of the result
+    ///
+    /// `tokens` are the tokens the result was parsed from
+    pub fn cst(&self, tokens: &[Token]) -> Cst<'_> {
+        let text = self.text();
+        let mut tokens = with_trivia(tokens, text).into_iter().peekable();
+        let mut root = CstNode {
+            name: self.entry.name.clone(),
+            children: Vec::new(),
+        };
+        build(&self.entry, &mut root, usize::MAX, &mut tokens);
+        let eof = tokens.next().unwrap_or(CstToken {
+            kind: TokenKinds::Control(crate::lexer::ControlTokenKind::Eof),
+            span: text.len()..text.len(),
+            leading: Vec::new(),
+            trailing: Vec::new(),
+        });
+        Cst { root, eof, text }
+    }
+}
+
+/// Adds the nodes and tokens that end before `end` to the node
+fn build<I: Iterator<Item = CstToken>>(
+    node: &Node,
+    cst: &mut CstNode,
+    end: usize,
+    tokens: &mut core::iter::Peekable<I>,
+) {
+    let mut children = node
+        .children()
+        .filter_map(|child| match child {
+            Nodes::Node(node) => Some(node),
+            Nodes::Token(_) => None,
+        })
+        .peekable();
+    loop {
+        let next = tokens
+            .peek()
+            .filter(|token| token.span.end <= end && !token.kind.is_eof())
+            .map(|token| token.span.start);
+        match (children.peek(), next) {
+            (Some(child), Some(start)) if child.span().start > start => {
+                cst.children.push(CstElement::Token(tokens.next().unwrap()))
+            }
+            (Some(_), _) => {
+                let child = children.next().unwrap();
+                let mut node = CstNode {
+                    name: child.name.clone(),
+                    children: Vec::new(),
+                };
+                build(child, &mut node, child.span().end.min(end), tokens);
+                cst.children.push(CstElement::Node(node));
+            }
+            (None, Some(_)) => cst.children.push(CstElement::Token(tokens.next().unwrap())),
+            (None, None) => break,
+        }
+    }
+}
+
+/// Tokens without whitespace, the text between them is their trivia
+fn with_trivia(tokens: &[Token], text: &str) -> Vec<CstToken> {
+    let mut result: Vec<CstToken> = Vec::new();
+    let mut end = 0;
+    for token in tokens.iter().filter(|token| !token.kind.is_whitespace()) {
+        let start = token.index.max(end);
+        let gap = end..start;
+        // trailing trivia of the previous token ends with its line
+        let split = match result.last() {
+            Some(_) => match text[gap.clone()].find('\n') {
+                Some(idx) => gap.start + idx + 1,
+                None => gap.end,
+            },
+            None => gap.start,
+        };
+        if let Some(previous) = result.last_mut() {
+            previous.trailing = trivia(text, gap.start..split);
+        }
+        result.push(CstToken {
+            kind: token.kind.clone(),
+            span: start..(token.index + token.len).max(start),
+            leading: trivia(text, split..gap.end),
+            trailing: Vec::new(),
+        });
+        end = (token.index + token.len).max(start);
+    }
+    result
+}
+
+/// Splits the text between tokens into whitespace, line breaks and skipped text
+fn trivia(text: &str, span: Range<usize>) -> Vec<Trivia> {
+    let mut trivia = Vec::new();
+    let mut idx = span.start;
+    while idx < span.end {
+        let rest = &text[idx..span.end];
+        let (kind, len) = if rest.starts_with('\n') {
+            (TriviaKind::Eol, 1)
+        } else if rest.starts_with(|c: char| c.is_whitespace()) {
+            let len = rest
+                .find(|c: char| c == '\n' || !c.is_whitespace())
+                .unwrap_or(rest.len());
+            (TriviaKind::Whitespace, len)
+        } else {
+            let line = rest.find('\n').unwrap_or(rest.len());
+            (TriviaKind::Skipped, rest[..line].trim_end().len())
+        };
+        trivia.push(Trivia {
+            kind,
+            span: idx..idx + len,
+        });
+        idx += len;
+    }
+    trivia
+}
+
+impl Cst<'_> {
+    /// Text the tree was built from
+    pub fn text(&self) -> &str {
+        self.text
+    }
+
+    /// Text of the token, trivia or any other range of the tree
+    pub fn slice(&self, span: Range<usize>) -> &str {
+        &self.text[span]
+    }
+}
+
+impl CstNode {
+    /// Tokens in the node and in the nodes in it
+    pub fn tokens(&self) -> Vec<&CstToken> {
+        let mut tokens = Vec::new();
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => tokens.extend(node.tokens()),
+                CstElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// Byte range of the tokens of the node without the trivia around them
+    pub fn span(&self) -> Option<Range<usize>> {
+        let tokens = self.tokens();
+        Some(tokens.first()?.span.start..tokens.last()?.span.end)
+    }
+
+    /// Trivia in front of the first token of the node
+    pub fn leading_trivia(&self) -> &[Trivia] {
+        match self.tokens().into_iter().next() {
+            Some(token) => &token.leading,
+            None => &[],
+        }
+    }
+
+    /// Trivia after the last token of the node
+    pub fn trailing_trivia(&self) -> &[Trivia] {
+        match self.tokens().into_iter().last() {
+            Some(token) => &token.trailing,
+            None => &[],
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => node.write(f, text)?,
+                CstElement::Token(token) => token.write(f, text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CstToken {
+    /// Byte range of the token with its trivia
+    pub fn full_span(&self) -> Range<usize> {
+        let start = self
+            .leading
+            .first()
+            .map_or(self.span.start, |t| t.span.start);
+        let end = self.trailing.last().map_or(self.span.end, |t| t.span.end);
+        start..end
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+        for trivia in &self.leading {
+            f.write_str(&text[trivia.span.clone()])?;
+        }
+        f.write_str(&text[self.span.clone()])?;
+        for trivia in &self.trailing {
+            f.write_str(&text[trivia.span.clone()])?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the text of the tree with all its trivia, the same as the parsed text
+impl fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.root.write(f, self.text)?;
+        self.eof.write(f, self.text)
+    }
+}
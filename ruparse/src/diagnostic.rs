@@ -0,0 +1,221 @@
+//! Human readable reports for lexer, parser and grammar errors
+//!
+//! The renderer prints the line of the source text the error points to and underlines
+//! the token that caused it:
+//!
+//! ```text
+//! error: Expected text, found ";"
+//!  --> 2:7
+//!   |
+//! 2 |   a = ;
+//!   |       ^
+//!   = note: let <ident> = <value>
+//! ```
+use core::fmt::Write;
+
+use crate::{
+    dsl::DslError,
+    grammar::MatchToken,
+    lexer::{ControlTokenKind, PreprocessorError, TextLocation, TokenKinds},
+    parser::{ParseError, ParseErrors},
+};
+
+// Choose between std and alloc
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        extern crate std;
+        use std::prelude::v1::*;
+    } else {
+        extern crate alloc;
+        use alloc::string::*;
+        use alloc::vec::*;
+        use alloc::format;
+    }
+}
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders errors together with the source text they come from
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    /// Use ANSI escape codes to color the output
+    pub colors: bool,
+}
+
+/// Everything the renderer needs to know about an error
+struct Diagnostic {
+    message: String,
+    location: TextLocation,
+    /// Length of the underlined text in bytes
+    len: usize,
+    /// Additional lines printed after the source snippet
+    notes: Vec<(&'static str, String)>,
+}
+
+impl Renderer {
+    pub fn new() -> Renderer {
+        Renderer { colors: false }
+    }
+
+    /// Renders a parser error
+    ///
+    /// Errors of the kind `ExpectedOneOf` list all the expected tokens and
+    /// the documentation of the failing node is added as a note
+    pub fn render_parse_error(&self, err: &ParseError, text: &str) -> String {
+        let mut notes = Vec::new();
+        if let ParseErrors::ExpectedOneOf { expected, found } = err.kind() {
+            let expected: Vec<String> = expected.iter().map(describe_match_token).collect();
+            notes.push(("expected", expected.join(", ")));
+            notes.push(("found", describe_token(found)));
+        }
+        if let Some(docs) = err.node_docs() {
+            notes.push(("note", dedent(docs)));
+        }
+        self.render(
+            &Diagnostic {
+                message: format!("{}", err.kind()),
+                location: err.location().clone(),
+                len: err.span().len(),
+                notes,
+            },
+            text,
+        )
+    }
+
+    /// Renders a lexer error
+    pub fn render_preprocessor_error(&self, err: &PreprocessorError, text: &str) -> String {
+        self.render(
+            &Diagnostic {
+                message: err.message.clone(),
+                location: err.location.clone(),
+                len: err.len,
+                notes: Vec::new(),
+            },
+            text,
+        )
+    }
+
+    /// Renders an error in the text of a grammar
+    pub fn render_dsl_error(&self, err: &DslError, text: &str) -> String {
+        self.render(
+            &Diagnostic {
+                message: format!("{}", err.kind()),
+                location: err.location().clone(),
+                len: err.span().len(),
+                notes: Vec::new(),
+            },
+            text,
+        )
+    }
+
+    fn render(&self, diagnostic: &Diagnostic, text: &str) -> String {
+        let (red, blue, bold, reset) = match self.colors {
+            true => (RED, BLUE, BOLD, RESET),
+            false => ("", "", "", ""),
+        };
+        let line_number = diagnostic.location.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let mut out = String::new();
+
+        // Writing to a String never fails
+        let _ = writeln!(
+            out,
+            "{red}error{reset}{bold}: {}{reset}",
+            diagnostic.message
+        );
+        let _ = writeln!(
+            out,
+            "{gutter}{blue}-->{reset} {}:{}",
+            diagnostic.location.line, diagnostic.location.column
+        );
+
+        // Errors that are not related to any token (location 0:0) have no snippet
+        let line = match diagnostic.location.line {
+            0 => None,
+            n => text.split('\n').nth(n - 1),
+        };
+        if let Some(line) = line {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let column = diagnostic.location.column.saturating_sub(1);
+            let start = line
+                .char_indices()
+                .nth(column)
+                .map_or(line.len(), |(idx, _)| idx);
+            // The underline ends at the end of the line for multi-line tokens
+            let end = (start + diagnostic.len).min(line.len());
+            // Tabs are kept so the carets line up with the text
+            let padding: String = line[..start]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let carets = "^".repeat(line[start..end].chars().count().max(1));
+            let _ = writeln!(out, "{gutter} {blue}|{reset}");
+            let _ = writeln!(out, "{blue}{line_number} |{reset} {line}");
+            let _ = writeln!(out, "{gutter} {blue}|{reset} {padding}{red}{carets}{reset}");
+        }
+
+        for (label, note) in &diagnostic.notes {
+            let mut lines = note.lines();
+            let first = lines.next().unwrap_or_default();
+            let _ = writeln!(out, "{gutter} {blue}={reset} {bold}{label}{reset}: {first}");
+            // Following lines are aligned with the first one
+            let indent = " ".repeat(label.len() + 2);
+            for line in lines {
+                let line = format!("{gutter}   {indent}{line}");
+                let _ = writeln!(out, "{}", line.trim_end());
+            }
+        }
+        out
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Name of the token kind for error messages
+pub(crate) fn describe_token(token: &TokenKinds) -> String {
+    match token {
+        TokenKinds::Token(token) => format!("\"{}\"", token),
+        TokenKinds::Complex(kind) => kind.clone(),
+        TokenKinds::Text => "text".to_string(),
+        TokenKinds::Whitespace => "whitespace".to_string(),
+        TokenKinds::Control(ControlTokenKind::Eol) => "end of line".to_string(),
+        TokenKinds::Control(ControlTokenKind::Eof) => "end of file".to_string(),
+    }
+}
+
+pub(crate) fn describe_match_token(token: &MatchToken) -> String {
+    match token {
+        MatchToken::Token(token) => describe_token(token),
+        MatchToken::Node(name) => format!("<{}>", name),
+        MatchToken::Word(word) => format!("\"{}\"", word),
+        MatchToken::Enumerator(name) => format!("<{}>", name),
+        MatchToken::Any => "any token".to_string(),
+    }
+}
+
+/// Removes the indentation shared by all the lines but the first one
+///
+/// Docs written in multi-line string literals are indented like the code around them
+pub(crate) fn dedent(docs: &str) -> String {
+    let indent = docs
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut lines = docs.lines();
+    let mut result = lines.next().unwrap_or_default().trim_end().to_string();
+    for line in lines {
+        result.push('\n');
+        result.push_str(line.get(indent..).unwrap_or(line.trim_start()).trim_end());
+    }
+    result
+}
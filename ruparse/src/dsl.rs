@@ -0,0 +1,1330 @@
+//! Text format for grammars
+//!
+//! A grammar is a list of nodes, enumerators and global variables.
+//! Rules start with a keyword and nest in braces, parameters follow an arrow:
+//!
+//! ```text
+//! eof = true
+//! global imports: list
+//!
+//! enum operators { '+' '-' "and" }
+//!
+//! /// let <ident> = <value>
+//! node KWLet {
+//!     var identifier: node
+//!     var value: node
+//!     sync ';'
+//!     is "let" -> node_start
+//!     is text -> set(identifier), harderror(true)
+//!     maybe ':' {
+//!         is <type> -> set(type)
+//!     }
+//!     is one of {
+//!         '=' { is <expression> -> set(value) }
+//!         ';' -> return
+//!     }
+//! }
+//! ```
+//!
+//! Tokens are written as:
+//! - `'+'` for a token of the lexer
+//! - `"let"` for a word
+//! - `<value>` for a node
+//! - `%operators` for an enumerator
+//! - `@string` for a token made by the preprocessor
+//! - `text`, `whitespace`, `eol`, `eof` and `any`
+//!
+//! Rules are `is`, `isnt`, `maybe ... else { }`, `while`, `until`, `is one of { }`,
+//! `maybe one of { } else { }`, `until one of { }`, `loop { }`,
+//! `precedence <operand> { left sum: '+' '-' }` and the commands
+//! `compare(a == b) { }`, `error("message")`, `harderror(true)`, `goto(label)`,
+//! `label(name)`, `print("message")` and `debug(variable)`. The right side of a comparison
+//! can also be a constant: `compare(depth > -1)`, `compare(done == true)`,
+//! `compare(name == "main")`.
+//!
+//! Variables are `node`, `list`, `bool`, `number`, `span` (byte range of everything
+//! set to it), `tokens` (list of tokens) and `text` (text of the last value set to it).
+//!
+//! Parameters are `set`, `global`, `increment`, `decrement`, `increment_global`, `true`,
+//! `false`, `true_global`, `false_global`, `reset`, `reset_global` taking a variable,
+//! `add(variable, -2)`, `add_global(variable, 2)`, `capture(variable, unquote)`,
+//! `capture(variable, int)`, `print("message")`, `debug(variable)`, `back(steps)`,
+//! `break(blocks)`, `harderror(true)`, `goto(label)`, `return`, `node_start` and `node_end`.
+//!
+//! Names of nodes, enumerators and globals can be in a namespace, `<math::expr>`
+//! references the node `expr` of a grammar imported as `math` (see `compose`).
+//!
+//! Comments start with `//`, comments starting with `///` are the documentation
+//! of the node after them.
+//!
+//! `parse_with_source_map` also remembers where the rules are written,
+//! so errors of the validator can point to the lines of the text.
+use core::fmt;
+use core::ops::Range;
+use core::str::FromStr;
+
+use crate::{
+    compose::SEPARATOR,
+    grammar::{
+        validator::{each_rule, RulePath, ValidationResult},
+        Commands, Comparison, Enumerator, Grammar, Literal, MatchToken, Node, OneOf, OperatorKind,
+        OperatorLevel, Parameters, Rule, Rules, Transform, VariableKind,
+    },
+    lexer::{ControlTokenKind, TextLocation, TokenKinds},
+    Map,
+};
+
+use serde::{Deserialize, Serialize};
+
+// Choose between std and alloc
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        extern crate std;
+        use std::prelude::v1::*;
+    } else {
+        extern crate alloc;
+        use alloc::string::*;
+        use alloc::vec::*;
+        use alloc::vec;
+        use alloc::format;
+    }
+}
+
+/// Parses a grammar from its text format
+pub fn parse(text: &str) -> Result<Grammar, DslError> {
+    parse_with_source_map(text).map(|(grammar, _)| grammar)
+}
+
+/// Parses a grammar and remembers where its nodes, enumerators and rules are written
+pub fn parse_with_source_map(text: &str) -> Result<(Grammar, SourceMap), DslError> {
+    let lexemes = lex(text)?;
+    DslParser {
+        lexemes,
+        idx: 0,
+        grammar: Grammar::new(),
+        rules: Vec::new(),
+        source_map: SourceMap::default(),
+    }
+    .parse()
+}
+
+/// Locations of the nodes, enumerators and rules in the text of a grammar
+///
+/// Rules are found by the same paths the validator reports
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    locations: Map<RulePath, TextLocation>,
+}
+
+impl SourceMap {
+    /// Location of the rule or of the closest rule containing it
+    ///
+    /// Parameters and tokens are not in the map, their rules are
+    pub fn locate(&self, path: &RulePath) -> Option<&TextLocation> {
+        (1..=path.0.len())
+            .rev()
+            .find_map(|len| self.locations.get(&RulePath(path.0[..len].to_vec())))
+    }
+
+    /// Adds the locations to the errors and warnings of the validator
+    pub fn annotate(&self, result: &mut ValidationResult) {
+        for error in &mut result.errors {
+            error.location = self.locate(&error.path).cloned();
+        }
+        for warning in &mut result.warnings {
+            warning.location = self.locate(&warning.path).cloned();
+        }
+    }
+}
+
+/// Prints a grammar in the text format
+///
+/// Globals, enumerators, nodes and variables are sorted by name so the output
+/// does not depend on the order of the maps. Parsing the text gives back the same grammar.
+pub fn print(grammar: &Grammar) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        indent: 0,
+    };
+    printer.grammar(grammar);
+    printer.out.push('\n');
+    printer.out
+}
+
+impl fmt::Display for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&print(self))
+    }
+}
+
+impl FromStr for Grammar {
+    type Err = DslError;
+
+    fn from_str(text: &str) -> Result<Grammar, DslError> {
+        parse(text)
+    }
+}
+
+/// Error in the text of a grammar
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DslError {
+    kind: DslErrors,
+    location: TextLocation,
+    /// Byte range of the text where the error occurred
+    span: Range<usize>,
+}
+
+impl DslError {
+    /// Kind of the error
+    pub fn kind(&self) -> &DslErrors {
+        &self.kind
+    }
+
+    /// Line and column where the error occurred
+    pub fn location(&self) -> &TextLocation {
+        &self.location
+    }
+
+    /// Byte range in the text where the error occurred
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+}
+
+impl fmt::Display for DslError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}:{}",
+            self.kind, self.location.line, self.location.column
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DslError {}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum DslErrors {
+    /// Character that can not start anything
+    UnexpectedChar(char),
+    /// Quoted text without the closing quote
+    UnterminatedString,
+    /// Unknown escape sequence in quoted text
+    InvalidEscape(char),
+    /// Expected something else
+    Expected { expected: String, found: String },
+    /// Unknown parameter
+    UnknownParameter(String),
+    /// Unknown kind of a variable
+    UnknownVariableKind(String),
+    /// Synchronization tokens must be tokens of the lexer
+    NotAToken(String),
+    /// Expected a number
+    InvalidNumber(String),
+    /// Node defined more than once
+    DuplicateNode(String),
+    /// Enumerator defined more than once
+    DuplicateEnumerator(String),
+    /// Variable declared more than once
+    DuplicateVariable(String),
+    /// Documentation that is not followed by a node
+    DanglingDocs,
+}
+
+impl fmt::Display for DslErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DslErrors::UnexpectedChar(c) => write!(f, "Unexpected character {:?}", c),
+            DslErrors::UnterminatedString => write!(f, "Missing closing quote"),
+            DslErrors::InvalidEscape(c) => write!(f, "Invalid escape sequence \\{}", c),
+            DslErrors::Expected { expected, found } => {
+                write!(f, "Expected {}, found {}", expected, found)
+            }
+            DslErrors::UnknownParameter(name) => write!(f, "Unknown parameter: {}", name),
+            DslErrors::UnknownVariableKind(name) => {
+                write!(f, "Unknown variable kind: {}", name)
+            }
+            DslErrors::NotAToken(token) => {
+                write!(f, "Synchronization token must be a token: {}", token)
+            }
+            DslErrors::InvalidNumber(number) => write!(f, "Invalid number: {}", number),
+            DslErrors::DuplicateNode(name) => write!(f, "Node defined twice: {}", name),
+            DslErrors::DuplicateEnumerator(name) => {
+                write!(f, "Enumerator defined twice: {}", name)
+            }
+            DslErrors::DuplicateVariable(name) => write!(f, "Variable declared twice: {}", name),
+            DslErrors::DanglingDocs => write!(f, "Documentation must be followed by a node"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    /// Name or keyword
+    Ident(String),
+    /// Text in double quotes
+    Word(String),
+    /// Text in single quotes
+    Token(String),
+    /// One line of documentation without the slashes
+    Doc(String),
+    Punct(&'static str),
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+struct Lexeme {
+    kind: Kind,
+    location: TextLocation,
+    span: Range<usize>,
+}
+
+impl Lexeme {
+    fn describe(&self) -> String {
+        match &self.kind {
+            Kind::Ident(ident) => ident.clone(),
+            Kind::Word(word) => format!("{:?}", word),
+            Kind::Token(token) => format!("'{}'", token),
+            Kind::Doc(_) => "documentation".to_string(),
+            Kind::Punct(punct) => format!("'{}'", punct),
+            Kind::Eof => "end of file".to_string(),
+        }
+    }
+
+    fn error(&self, kind: DslErrors) -> DslError {
+        DslError {
+            kind,
+            location: self.location.clone(),
+            span: self.span.clone(),
+        }
+    }
+}
+
+/// Longer punctuation first
+const PUNCTUATION: [&str; 17] = [
+    "->", "==", "!=", ">=", "<=", "{", "}", "(", ")", "<", ">", ",", ":", "=", "%", "@", "-",
+];
+
+fn lex(text: &str) -> Result<Vec<Lexeme>, DslError> {
+    let mut lexemes = Vec::new();
+    let mut line = 0;
+    let mut line_start = 0;
+    let mut idx = 0;
+    while idx < text.len() {
+        let rest = &text[idx..];
+        let c = rest.chars().next().unwrap();
+        let location = TextLocation::new(line, text[line_start..idx].chars().count());
+        let error = |kind, len| DslError {
+            kind,
+            location: location.clone(),
+            span: idx..idx + len,
+        };
+        if c == '\n' {
+            line += 1;
+            line_start = idx + 1;
+            idx += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            idx += c.len_utf8();
+            continue;
+        }
+        if rest.starts_with("//") {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            if let Some(doc) = rest[..len].strip_prefix("///") {
+                let doc = doc.strip_suffix('\r').unwrap_or(doc);
+                lexemes.push(Lexeme {
+                    kind: Kind::Doc(doc.strip_prefix(' ').unwrap_or(doc).to_string()),
+                    location,
+                    span: idx..idx + len,
+                });
+            }
+            idx += len;
+            continue;
+        }
+        let (kind, len) = if c.is_alphanumeric() || c == '_' {
+            let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+            let mut len = rest.find(|c| !is_ident(c)).unwrap_or(rest.len());
+            // names in a namespace are a single name, `math::expr`
+            while let Some(next) = rest[len..].strip_prefix(SEPARATOR) {
+                match next.chars().next() {
+                    Some(c) if is_ident(c) => (),
+                    _ => break,
+                }
+                len += SEPARATOR.len() + next.find(|c| !is_ident(c)).unwrap_or(next.len());
+            }
+            (Kind::Ident(rest[..len].to_string()), len)
+        } else if c == '"' || c == '\'' {
+            let mut value = String::new();
+            let mut chars = rest.char_indices().skip(1);
+            let len = loop {
+                match chars.next() {
+                    Some((i, q)) if q == c => break i + 1,
+                    Some((i, '\\')) => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 't')) => value.push('\t'),
+                        Some((_, 'r')) => value.push('\r'),
+                        Some((_, e @ ('\\' | '\'' | '"'))) => value.push(e),
+                        Some((_, e)) => return Err(error(DslErrors::InvalidEscape(e), i + 2)),
+                        None => return Err(error(DslErrors::UnterminatedString, rest.len())),
+                    },
+                    Some((_, '\n')) | None => {
+                        let len = rest.find('\n').unwrap_or(rest.len());
+                        return Err(error(DslErrors::UnterminatedString, len));
+                    }
+                    Some((_, other)) => value.push(other),
+                }
+            };
+            match c {
+                '"' => (Kind::Word(value), len),
+                _ => (Kind::Token(value), len),
+            }
+        } else {
+            match PUNCTUATION.iter().find(|punct| rest.starts_with(**punct)) {
+                Some(punct) => (Kind::Punct(punct), punct.len()),
+                None => return Err(error(DslErrors::UnexpectedChar(c), c.len_utf8())),
+            }
+        };
+        lexemes.push(Lexeme {
+            kind,
+            location,
+            span: idx..idx + len,
+        });
+        idx += len;
+    }
+    lexemes.push(Lexeme {
+        kind: Kind::Eof,
+        location: TextLocation::new(line, text[line_start..].chars().count()),
+        span: text.len()..text.len(),
+    });
+    Ok(lexemes)
+}
+
+struct DslParser {
+    lexemes: Vec<Lexeme>,
+    idx: usize,
+    grammar: Grammar,
+    /// Locations of the rules of the current node in the order they start
+    rules: Vec<TextLocation>,
+    source_map: SourceMap,
+}
+
+impl DslParser {
+    fn parse(mut self) -> Result<(Grammar, SourceMap), DslError> {
+        loop {
+            let mut docs = Vec::new();
+            while let Kind::Doc(doc) = &self.peek().kind {
+                docs.push(doc.clone());
+                self.idx += 1;
+            }
+            let lexeme = self.next();
+            match &lexeme.kind {
+                Kind::Ident(keyword) if keyword == "node" => {
+                    let docs = match docs.is_empty() {
+                        true => None,
+                        false => Some(docs.join("\n")),
+                    };
+                    self.parse_node(docs)?;
+                }
+                _ if !docs.is_empty() => return Err(lexeme.error(DslErrors::DanglingDocs)),
+                Kind::Ident(keyword) if keyword == "enum" => self.parse_enumerator()?,
+                Kind::Ident(keyword) if keyword == "global" => {
+                    let (name, kind) = self.parse_variable()?;
+                    self.grammar.globals.insert(name, kind);
+                }
+                Kind::Ident(keyword) if keyword == "eof" => {
+                    self.expect("=")?;
+                    self.grammar.eof = self.parse_bool()?;
+                }
+                Kind::Eof => return Ok((self.grammar, self.source_map)),
+                _ => return Err(expected("node, enum, global or eof", &lexeme)),
+            }
+        }
+    }
+
+    fn parse_node(&mut self, docs: Option<String>) -> Result<(), DslError> {
+        let lexeme = self.peek().clone();
+        let name = self.ident()?;
+        if self.grammar.nodes.contains_key(&name) {
+            return Err(lexeme.error(DslErrors::DuplicateNode(name)));
+        }
+        let path = RulePath(vec![name.clone()]);
+        self.source_map
+            .locations
+            .insert(path.clone(), lexeme.location.clone());
+        let mut node = Node {
+            name,
+            rules: Vec::new(),
+            variables: Map::new(),
+            docs,
+            sync: Vec::new(),
+        };
+        self.expect("{")?;
+        loop {
+            let lexeme = self.peek().clone();
+            match &lexeme.kind {
+                Kind::Punct("}") => {
+                    self.idx += 1;
+                    break;
+                }
+                Kind::Ident(keyword) if keyword == "var" => {
+                    self.idx += 1;
+                    loop {
+                        let lexeme = self.peek().clone();
+                        let (name, kind) = self.parse_variable()?;
+                        if node.variables.contains_key(&name) {
+                            return Err(lexeme.error(DslErrors::DuplicateVariable(name)));
+                        }
+                        node.variables.insert(name, kind);
+                        if !self.eat(",") {
+                            break;
+                        }
+                    }
+                }
+                Kind::Ident(keyword) if keyword == "sync" => {
+                    self.idx += 1;
+                    while self.starts_token() {
+                        let lexeme = self.peek().clone();
+                        match self.parse_token()? {
+                            MatchToken::Token(token) => node.sync.push(token),
+                            _ => return Err(lexeme.error(DslErrors::NotAToken(lexeme.describe()))),
+                        }
+                    }
+                }
+                _ => node.rules.push(self.parse_rule()?),
+            }
+        }
+        // Rules are visited in the same order they were parsed
+        let mut locations = self.rules.drain(..);
+        let locations_map = &mut self.source_map.locations;
+        each_rule(&node.rules, &path, "rules", &mut |_, path| {
+            if let Some(location) = locations.next() {
+                locations_map.insert(path.clone(), location);
+            }
+        });
+        self.grammar.add_node(node);
+        Ok(())
+    }
+
+    fn parse_enumerator(&mut self) -> Result<(), DslError> {
+        let lexeme = self.peek().clone();
+        let name = self.ident()?;
+        if self.grammar.enumerators.contains_key(&name) {
+            return Err(lexeme.error(DslErrors::DuplicateEnumerator(name)));
+        }
+        self.source_map
+            .locations
+            .insert(RulePath(vec![name.clone()]), lexeme.location.clone());
+        self.expect("{")?;
+        let mut values = Vec::new();
+        while !self.eat("}") {
+            values.push(self.parse_token()?);
+        }
+        self.grammar
+            .enumerators
+            .insert(name.clone(), Enumerator { name, values });
+        Ok(())
+    }
+
+    /// name: kind
+    fn parse_variable(&mut self) -> Result<(String, VariableKind), DslError> {
+        let name = self.ident()?;
+        self.expect(":")?;
+        let lexeme = self.peek().clone();
+        let kind = match self.ident()?.as_str() {
+            "node" => VariableKind::Node,
+            "list" => VariableKind::NodeList,
+            "bool" => VariableKind::Boolean,
+            "number" => VariableKind::Number,
+            "span" => VariableKind::Span,
+            "tokens" => VariableKind::TokenList,
+            "text" => VariableKind::Text,
+            other => return Err(lexeme.error(DslErrors::UnknownVariableKind(other.to_string()))),
+        };
+        Ok((name, kind))
+    }
+
+    fn parse_rules(&mut self) -> Result<Rules, DslError> {
+        self.expect("{")?;
+        let mut rules = Vec::new();
+        while !self.eat("}") {
+            rules.push(self.parse_rule()?);
+        }
+        Ok(rules)
+    }
+
+    /// Rules in braces or no rules
+    fn parse_maybe_rules(&mut self) -> Result<Rules, DslError> {
+        match self.peek().kind {
+            Kind::Punct("{") => self.parse_rules(),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn parse_rule(&mut self) -> Result<Rule, DslError> {
+        let lexeme = self.next();
+        self.rules.push(lexeme.location.clone());
+        let keyword = match &lexeme.kind {
+            Kind::Ident(keyword) => keyword.as_str(),
+            _ => return Err(expected("rule", &lexeme)),
+        };
+        let rule = match keyword {
+            "is" | "maybe" | "until" if self.eat_ident("one") => {
+                self.expect_ident("of")?;
+                let tokens = self.parse_one_of()?;
+                match keyword {
+                    "is" => Rule::IsOneOf { tokens },
+                    "until" => Rule::UntilOneOf { tokens },
+                    _ => Rule::MaybeOneOf {
+                        is_one_of: tokens,
+                        isnt: self.parse_else()?,
+                    },
+                }
+            }
+            "is" | "isnt" | "while" | "until" => {
+                let token = self.parse_token()?;
+                let parameters = self.parse_parameters()?;
+                let rules = self.parse_maybe_rules()?;
+                match keyword {
+                    "is" => Rule::Is {
+                        token,
+                        rules,
+                        parameters,
+                    },
+                    "isnt" => Rule::Isnt {
+                        token,
+                        rules,
+                        parameters,
+                    },
+                    "while" => Rule::While {
+                        token,
+                        rules,
+                        parameters,
+                    },
+                    _ => Rule::Until {
+                        token,
+                        rules,
+                        parameters,
+                    },
+                }
+            }
+            "maybe" => Rule::Maybe {
+                token: self.parse_token()?,
+                parameters: self.parse_parameters()?,
+                is: self.parse_maybe_rules()?,
+                isnt: self.parse_else()?,
+            },
+            "loop" => Rule::Loop {
+                rules: self.parse_rules()?,
+            },
+            "precedence" => {
+                let operand = self.parse_token()?;
+                let parameters = self.parse_parameters()?;
+                self.expect("{")?;
+                let mut levels = Vec::new();
+                while !self.eat("}") {
+                    levels.push(self.parse_level()?);
+                }
+                Rule::Precedence {
+                    operand,
+                    levels,
+                    parameters,
+                }
+            }
+            "compare" => {
+                self.expect("(")?;
+                let left = self.ident()?;
+                let lexeme = self.next();
+                let comparison = match lexeme.kind {
+                    Kind::Punct("==") => Comparison::Equal,
+                    Kind::Punct("!=") => Comparison::NotEqual,
+                    Kind::Punct(">") => Comparison::GreaterThan,
+                    Kind::Punct("<") => Comparison::LessThan,
+                    Kind::Punct(">=") => Comparison::GreaterThanOrEqual,
+                    Kind::Punct("<=") => Comparison::LessThanOrEqual,
+                    _ => return Err(expected("comparison", &lexeme)),
+                };
+                let command = match self.parse_literal()? {
+                    Some(value) => {
+                        self.expect(")")?;
+                        Commands::CompareLiteral {
+                            variable: left,
+                            comparison,
+                            value,
+                            rules: self.parse_maybe_rules()?,
+                        }
+                    }
+                    None => {
+                        let right = self.ident()?;
+                        self.expect(")")?;
+                        Commands::Compare {
+                            left,
+                            right,
+                            comparison,
+                            rules: self.parse_maybe_rules()?,
+                        }
+                    }
+                };
+                Rule::Command { command }
+            }
+            "error" => Rule::Command {
+                command: Commands::Error {
+                    message: self.parse_argument(Self::word)?,
+                },
+            },
+            "harderror" => Rule::Command {
+                command: Commands::HardError {
+                    set: self.parse_argument(Self::parse_bool)?,
+                },
+            },
+            "goto" => Rule::Command {
+                command: Commands::Goto {
+                    label: self.parse_argument(Self::ident)?,
+                },
+            },
+            "label" => Rule::Command {
+                command: Commands::Label {
+                    name: self.parse_argument(Self::ident)?,
+                },
+            },
+            "print" => Rule::Command {
+                command: Commands::Print {
+                    message: self.parse_argument(Self::word)?,
+                },
+            },
+            "debug" => Rule::Debug {
+                target: self.parse_optional_argument()?,
+            },
+            _ => return Err(expected("rule", &lexeme)),
+        };
+        Ok(rule)
+    }
+
+    /// Alternatives of a `one of` rule
+    fn parse_one_of(&mut self) -> Result<Vec<OneOf>, DslError> {
+        self.expect("{")?;
+        let mut tokens = Vec::new();
+        while !self.eat("}") {
+            tokens.push(OneOf {
+                token: self.parse_token()?,
+                parameters: self.parse_parameters()?,
+                rules: self.parse_maybe_rules()?,
+            });
+        }
+        Ok(tokens)
+    }
+
+    fn parse_else(&mut self) -> Result<Rules, DslError> {
+        match self.eat_ident("else") {
+            true => self.parse_rules(),
+            false => Ok(Vec::new()),
+        }
+    }
+
+    /// kind name: operators
+    fn parse_level(&mut self) -> Result<OperatorLevel, DslError> {
+        let lexeme = self.peek().clone();
+        let kind = match self.ident()?.as_str() {
+            "left" => OperatorKind::Left,
+            "right" => OperatorKind::Right,
+            "prefix" => OperatorKind::Prefix,
+            "postfix" => OperatorKind::Postfix,
+            _ => return Err(expected("left, right, prefix or postfix", &lexeme)),
+        };
+        let name = self.ident()?;
+        self.expect(":")?;
+        let mut operators = vec![self.parse_token()?];
+        while self.starts_token() {
+            operators.push(self.parse_token()?);
+        }
+        Ok(OperatorLevel {
+            name,
+            operators,
+            kind,
+        })
+    }
+
+    fn parse_parameters(&mut self) -> Result<Vec<Parameters>, DslError> {
+        let mut parameters = Vec::new();
+        if !self.eat("->") {
+            return Ok(parameters);
+        }
+        loop {
+            let lexeme = self.peek().clone();
+            let parameter = match self.ident()?.as_str() {
+                "set" => Parameters::Set(self.parse_argument(Self::ident)?),
+                "global" => Parameters::Global(self.parse_argument(Self::ident)?),
+                "increment" => Parameters::Increment(self.parse_argument(Self::ident)?),
+                "decrement" => Parameters::Decrement(self.parse_argument(Self::ident)?),
+                "increment_global" => {
+                    Parameters::IncrementGlobal(self.parse_argument(Self::ident)?)
+                }
+                "add" => {
+                    let (name, amount) = self.parse_argument(Self::variable_and_amount)?;
+                    Parameters::Add(name, amount)
+                }
+                "add_global" => {
+                    let (name, amount) = self.parse_argument(Self::variable_and_amount)?;
+                    Parameters::AddGlobal(name, amount)
+                }
+                "reset" => Parameters::Reset(self.parse_argument(Self::ident)?),
+                "reset_global" => Parameters::ResetGlobal(self.parse_argument(Self::ident)?),
+                "capture" => {
+                    let (name, transform) = self.parse_argument(Self::variable_and_transform)?;
+                    Parameters::Capture(name, transform)
+                }
+                "true" => Parameters::True(self.parse_argument(Self::ident)?),
+                "false" => Parameters::False(self.parse_argument(Self::ident)?),
+                "true_global" => Parameters::TrueGlobal(self.parse_argument(Self::ident)?),
+                "false_global" => Parameters::FalseGlobal(self.parse_argument(Self::ident)?),
+                "print" => Parameters::Print(self.parse_argument(Self::word)?),
+                "debug" => Parameters::Debug(self.parse_optional_argument()?),
+                "back" => Parameters::Back(self.parse_argument(Self::number)?),
+                "return" => Parameters::Return,
+                "break" => Parameters::Break(self.parse_argument(Self::number)?),
+                "harderror" => Parameters::HardError(self.parse_argument(Self::parse_bool)?),
+                "goto" => Parameters::Goto(self.parse_argument(Self::ident)?),
+                "node_start" => Parameters::NodeStart,
+                "node_end" => Parameters::NodeEnd,
+                other => return Err(lexeme.error(DslErrors::UnknownParameter(other.to_string()))),
+            };
+            parameters.push(parameter);
+            if !self.eat(",") {
+                return Ok(parameters);
+            }
+        }
+    }
+
+    /// (argument)
+    fn parse_argument<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, DslError>,
+    ) -> Result<T, DslError> {
+        self.expect("(")?;
+        let argument = parse(self)?;
+        self.expect(")")?;
+        Ok(argument)
+    }
+
+    /// variable, amount
+    fn variable_and_amount(&mut self) -> Result<(String, i32), DslError> {
+        let name = self.ident()?;
+        self.expect(",")?;
+        Ok((name, self.signed()?))
+    }
+
+    /// variable, transform
+    fn variable_and_transform(&mut self) -> Result<(String, Transform), DslError> {
+        let name = self.ident()?;
+        self.expect(",")?;
+        let lexeme = self.peek().clone();
+        let transform = match self.ident()?.as_str() {
+            "unquote" => Transform::Unquote,
+            "int" => Transform::Int,
+            _ => return Err(expected("unquote or int", &lexeme)),
+        };
+        Ok((name, transform))
+    }
+
+    /// Number, `true`, `false` or text in double quotes, nothing if it is a variable
+    fn parse_literal(&mut self) -> Result<Option<Literal>, DslError> {
+        let literal = match &self.peek().kind {
+            Kind::Word(_) => Literal::Text(self.word()?),
+            Kind::Punct("-") => Literal::Number(self.signed()?),
+            Kind::Ident(ident) if ident == "true" || ident == "false" => {
+                Literal::Boolean(self.parse_bool()?)
+            }
+            Kind::Ident(ident) if ident.starts_with(|c: char| c.is_ascii_digit()) => {
+                Literal::Number(self.number()?)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(literal))
+    }
+
+    /// (variable) or nothing
+    fn parse_optional_argument(&mut self) -> Result<Option<String>, DslError> {
+        match self.peek().kind {
+            Kind::Punct("(") => Ok(Some(self.parse_argument(Self::ident)?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn starts_token(&self) -> bool {
+        match &self.peek().kind {
+            Kind::Word(_) | Kind::Token(_) => true,
+            Kind::Punct(punct) => matches!(*punct, "<" | "%" | "@"),
+            Kind::Ident(ident) => matches!(
+                ident.as_str(),
+                "text" | "whitespace" | "eol" | "eof" | "any"
+            ),
+            _ => false,
+        }
+    }
+
+    fn parse_token(&mut self) -> Result<MatchToken, DslError> {
+        let lexeme = self.next();
+        let token = match &lexeme.kind {
+            Kind::Token(token) => MatchToken::Token(TokenKinds::Token(token.clone())),
+            Kind::Word(word) => MatchToken::Word(word.clone()),
+            Kind::Punct("<") => {
+                let name = self.ident()?;
+                self.expect(">")?;
+                MatchToken::Node(name)
+            }
+            Kind::Punct("%") => MatchToken::Enumerator(self.ident()?),
+            Kind::Punct("@") => MatchToken::Token(TokenKinds::Complex(self.ident()?)),
+            Kind::Ident(ident) => match ident.as_str() {
+                "text" => MatchToken::Token(TokenKinds::Text),
+                "whitespace" => MatchToken::Token(TokenKinds::Whitespace),
+                "eol" => MatchToken::Token(TokenKinds::Control(ControlTokenKind::Eol)),
+                "eof" => MatchToken::Token(TokenKinds::Control(ControlTokenKind::Eof)),
+                "any" => MatchToken::Any,
+                _ => return Err(expected("token", &lexeme)),
+            },
+            _ => return Err(expected("token", &lexeme)),
+        };
+        Ok(token)
+    }
+
+    fn parse_bool(&mut self) -> Result<bool, DslError> {
+        let lexeme = self.next();
+        match &lexeme.kind {
+            Kind::Ident(ident) if ident == "true" => Ok(true),
+            Kind::Ident(ident) if ident == "false" => Ok(false),
+            _ => Err(expected("true or false", &lexeme)),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, DslError> {
+        let lexeme = self.next();
+        match lexeme.kind {
+            Kind::Ident(ident) => Ok(ident),
+            _ => Err(expected("name", &lexeme)),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, DslError> {
+        let lexeme = self.next();
+        match lexeme.kind {
+            Kind::Word(word) => Ok(word),
+            _ => Err(expected("text in double quotes", &lexeme)),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, DslError> {
+        let lexeme = self.peek().clone();
+        let number = self.ident()?;
+        number
+            .parse()
+            .map_err(|_| lexeme.error(DslErrors::InvalidNumber(number)))
+    }
+
+    /// Number with an optional minus
+    fn signed(&mut self) -> Result<i32, DslError> {
+        match self.eat("-") {
+            true => self.number::<i32>().map(|number| -number),
+            false => self.number(),
+        }
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<(), DslError> {
+        let lexeme = self.next();
+        match lexeme.kind {
+            Kind::Punct(found) if found == punct => Ok(()),
+            _ => Err(expected(&format!("'{}'", punct), &lexeme)),
+        }
+    }
+
+    fn expect_ident(&mut self, ident: &str) -> Result<(), DslError> {
+        match self.eat_ident(ident) {
+            true => Ok(()),
+            false => Err(expected(ident, self.peek())),
+        }
+    }
+
+    /// Skips the punctuation if it is next
+    fn eat(&mut self, punct: &str) -> bool {
+        match self.peek().kind {
+            Kind::Punct(found) if found == punct => {
+                self.idx += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Skips the keyword if it is next
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        match &self.peek().kind {
+            Kind::Ident(found) if found == ident => {
+                self.idx += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn peek(&self) -> &Lexeme {
+        &self.lexemes[self.idx]
+    }
+
+    /// Returns the next lexeme, the last one is always Eof
+    fn next(&mut self) -> Lexeme {
+        let lexeme = self.lexemes[self.idx].clone();
+        if self.idx + 1 < self.lexemes.len() {
+            self.idx += 1;
+        }
+        lexeme
+    }
+}
+
+fn expected(expected: &str, found: &Lexeme) -> DslError {
+    found.error(DslErrors::Expected {
+        expected: expected.to_string(),
+        found: found.describe(),
+    })
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+}
+
+impl Printer {
+    fn grammar(&mut self, grammar: &Grammar) {
+        if !grammar.eof {
+            self.line("eof = false");
+        }
+        for (name, kind) in sorted(&grammar.globals) {
+            self.line(&format!("global {}: {}", name, kind_name(kind)));
+        }
+        for (_, enumerator) in sorted(&grammar.enumerators) {
+            self.separate();
+            let values: Vec<String> = enumerator.values.iter().map(token_text).collect();
+            self.line(&format!(
+                "enum {} {{ {} }}",
+                enumerator.name,
+                values.join(" ")
+            ));
+        }
+        for (_, node) in sorted(&grammar.nodes) {
+            self.separate();
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        if let Some(docs) = &node.docs {
+            for line in docs.split('\n') {
+                match line.is_empty() {
+                    true => self.line("///"),
+                    false => self.line(&format!("/// {}", line)),
+                }
+            }
+        }
+        self.line(&format!("node {} {{", node.name));
+        self.indent += 1;
+        for (name, kind) in sorted(&node.variables) {
+            self.line(&format!("var {}: {}", name, kind_name(kind)));
+        }
+        if !node.sync.is_empty() {
+            let sync: Vec<String> = node
+                .sync
+                .iter()
+                .map(|token| token_text(&MatchToken::Token(token.clone())))
+                .collect();
+            self.line(&format!("sync {}", sync.join(" ")));
+        }
+        for rule in &node.rules {
+            self.rule(rule);
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn rule(&mut self, rule: &Rule) {
+        match rule {
+            Rule::Is {
+                token,
+                rules,
+                parameters,
+            } => self.matched("is", token, parameters, rules),
+            Rule::Isnt {
+                token,
+                rules,
+                parameters,
+            } => self.matched("isnt", token, parameters, rules),
+            Rule::While {
+                token,
+                rules,
+                parameters,
+            } => self.matched("while", token, parameters, rules),
+            Rule::Until {
+                token,
+                rules,
+                parameters,
+            } => self.matched("until", token, parameters, rules),
+            Rule::Maybe {
+                token,
+                is,
+                isnt,
+                parameters,
+            } => {
+                self.matched("maybe", token, parameters, is);
+                self.otherwise(isnt);
+            }
+            Rule::IsOneOf { tokens } => self.one_of("is", tokens),
+            Rule::UntilOneOf { tokens } => self.one_of("until", tokens),
+            Rule::MaybeOneOf { is_one_of, isnt } => {
+                self.one_of("maybe", is_one_of);
+                self.otherwise(isnt);
+            }
+            Rule::Loop { rules } => {
+                self.line("loop");
+                self.block(rules);
+            }
+            Rule::Precedence {
+                operand,
+                levels,
+                parameters,
+            } => {
+                self.line(&format!(
+                    "precedence {}{} {{",
+                    token_text(operand),
+                    parameters_text(parameters)
+                ));
+                self.indent += 1;
+                for level in levels {
+                    let kind = match level.kind {
+                        OperatorKind::Left => "left",
+                        OperatorKind::Right => "right",
+                        OperatorKind::Prefix => "prefix",
+                        OperatorKind::Postfix => "postfix",
+                    };
+                    let operators: Vec<String> = level.operators.iter().map(token_text).collect();
+                    self.line(&format!("{} {}: {}", kind, level.name, operators.join(" ")));
+                }
+                self.indent -= 1;
+                self.line("}");
+            }
+            Rule::Command { command } => match command {
+                Commands::Compare {
+                    left,
+                    right,
+                    comparison,
+                    rules,
+                } => {
+                    let comparison = comparison_text(comparison);
+                    self.line(&format!("compare({} {} {})", left, comparison, right));
+                    self.block(rules);
+                }
+                Commands::CompareLiteral {
+                    variable,
+                    comparison,
+                    value,
+                    rules,
+                } => {
+                    let value = match value {
+                        Literal::Number(number) => number.to_string(),
+                        Literal::Boolean(value) => value.to_string(),
+                        Literal::Text(text) => quote(text, '"'),
+                    };
+                    let comparison = comparison_text(comparison);
+                    self.line(&format!("compare({} {} {})", variable, comparison, value));
+                    self.block(rules);
+                }
+                Commands::Error { message } => {
+                    self.line(&format!("error({})", quote(message, '"')))
+                }
+                Commands::HardError { set } => self.line(&format!("harderror({})", set)),
+                Commands::Goto { label } => self.line(&format!("goto({})", label)),
+                Commands::Label { name } => self.line(&format!("label({})", name)),
+                Commands::Print { message } => {
+                    self.line(&format!("print({})", quote(message, '"')))
+                }
+            },
+            Rule::Debug { target } => match target {
+                Some(target) => self.line(&format!("debug({})", target)),
+                None => self.line("debug"),
+            },
+        }
+    }
+
+    /// keyword token -> parameters { rules }
+    fn matched(
+        &mut self,
+        keyword: &str,
+        token: &MatchToken,
+        parameters: &[Parameters],
+        rules: &Rules,
+    ) {
+        self.line(&format!(
+            "{} {}{}",
+            keyword,
+            token_text(token),
+            parameters_text(parameters)
+        ));
+        self.block(rules);
+    }
+
+    fn one_of(&mut self, keyword: &str, tokens: &[OneOf]) {
+        self.line(&format!("{} one of {{", keyword));
+        self.indent += 1;
+        for one_of in tokens {
+            self.line(&format!(
+                "{}{}",
+                token_text(&one_of.token),
+                parameters_text(&one_of.parameters)
+            ));
+            self.block(&one_of.rules);
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn otherwise(&mut self, rules: &Rules) {
+        if !rules.is_empty() {
+            self.out.push_str(" else");
+            self.open(rules);
+        }
+    }
+
+    /// Rules in braces after the last line, nothing if there are no rules
+    fn block(&mut self, rules: &Rules) {
+        if !rules.is_empty() {
+            self.open(rules);
+        }
+    }
+
+    fn open(&mut self, rules: &Rules) {
+        self.out.push_str(" {");
+        self.indent += 1;
+        for rule in rules {
+            self.rule(rule);
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    /// Starts a new line, the line is ended by the next one
+    fn line(&mut self, line: &str) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(line);
+    }
+
+    /// Empty line between definitions
+    fn separate(&mut self) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+    }
+}
+
+fn sorted<V>(map: &Map<String, V>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<(&String, &V)> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+fn kind_name(kind: &VariableKind) -> &'static str {
+    match kind {
+        VariableKind::Node => "node",
+        VariableKind::NodeList => "list",
+        VariableKind::Boolean => "bool",
+        VariableKind::Number => "number",
+        VariableKind::Span => "span",
+        VariableKind::TokenList => "tokens",
+        VariableKind::Text => "text",
+    }
+}
+
+fn comparison_text(comparison: &Comparison) -> &'static str {
+    match comparison {
+        Comparison::Equal => "==",
+        Comparison::NotEqual => "!=",
+        Comparison::GreaterThan => ">",
+        Comparison::LessThan => "<",
+        Comparison::GreaterThanOrEqual => ">=",
+        Comparison::LessThanOrEqual => "<=",
+    }
+}
+
+fn token_text(token: &MatchToken) -> String {
+    match token {
+        MatchToken::Token(TokenKinds::Token(token)) => quote(token, '\''),
+        MatchToken::Token(TokenKinds::Complex(kind)) => format!("@{}", kind),
+        MatchToken::Token(TokenKinds::Text) => "text".to_string(),
+        MatchToken::Token(TokenKinds::Whitespace) => "whitespace".to_string(),
+        MatchToken::Token(TokenKinds::Control(ControlTokenKind::Eol)) => "eol".to_string(),
+        MatchToken::Token(TokenKinds::Control(ControlTokenKind::Eof)) => "eof".to_string(),
+        MatchToken::Node(name) => format!("<{}>", name),
+        MatchToken::Word(word) => quote(word, '"'),
+        MatchToken::Enumerator(name) => format!("%{}", name),
+        MatchToken::Any => "any".to_string(),
+    }
+}
+
+/// Parameters after an arrow, nothing if there are no parameters
+fn parameters_text(parameters: &[Parameters]) -> String {
+    if parameters.is_empty() {
+        return String::new();
+    }
+    let parameters: Vec<String> = parameters
+        .iter()
+        .map(|parameter| match parameter {
+            Parameters::Set(name) => format!("set({})", name),
+            Parameters::Global(name) => format!("global({})", name),
+            Parameters::Increment(name) => format!("increment({})", name),
+            Parameters::Decrement(name) => format!("decrement({})", name),
+            Parameters::IncrementGlobal(name) => format!("increment_global({})", name),
+            Parameters::Add(name, amount) => format!("add({}, {})", name, amount),
+            Parameters::AddGlobal(name, amount) => format!("add_global({}, {})", name, amount),
+            Parameters::Reset(name) => format!("reset({})", name),
+            Parameters::ResetGlobal(name) => format!("reset_global({})", name),
+            Parameters::Capture(name, Transform::Unquote) => format!("capture({}, unquote)", name),
+            Parameters::Capture(name, Transform::Int) => format!("capture({}, int)", name),
+            Parameters::True(name) => format!("true({})", name),
+            Parameters::False(name) => format!("false({})", name),
+            Parameters::TrueGlobal(name) => format!("true_global({})", name),
+            Parameters::FalseGlobal(name) => format!("false_global({})", name),
+            Parameters::Print(message) => format!("print({})", quote(message, '"')),
+            Parameters::Debug(Some(name)) => format!("debug({})", name),
+            Parameters::Debug(None) => "debug".to_string(),
+            Parameters::Back(steps) => format!("back({})", steps),
+            Parameters::Return => "return".to_string(),
+            Parameters::Break(blocks) => format!("break({})", blocks),
+            Parameters::HardError(set) => format!("harderror({})", set),
+            Parameters::Goto(label) => format!("goto({})", label),
+            Parameters::NodeStart => "node_start".to_string(),
+            Parameters::NodeEnd => "node_end".to_string(),
+        })
+        .collect();
+    format!(" -> {}", parameters.join(", "))
+}
+
+fn quote(text: &str, quote: char) -> String {
+    let mut out = String::new();
+    out.push(quote);
+    for c in text.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\\' => out.push_str("\\\\"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
@@ -126,6 +126,21 @@ pub enum Rule {
     },
     /// Searches in the tokens until one of the tokens is matched
     UntilOneOf { tokens: Vec<OneOf> },
+    /// Parses operands separated by operators into nested nodes
+    ///
+    /// Operators of higher levels bind tighter, `a + b * c` becomes `a + (b * c)`
+    /// and each operator creates a node named after its level
+    ///
+    /// Binary nodes have the variables `left`, `operator` and `right`,
+    /// prefix and postfix nodes have the variables `operator` and `operand`
+    Precedence {
+        /// Matches the operands
+        operand: MatchToken,
+        /// Operator levels from the lowest to the highest precedence
+        levels: Vec<OperatorLevel>,
+        /// Parameters that will be used with the resulting tree
+        parameters: Vec<Parameters>,
+    },
     /// Performs a command
     ///
     /// The command will be executed without matching a token
@@ -141,6 +156,28 @@ pub struct OneOf {
     pub parameters: Vec<Parameters>,
 }
 
+/// Operators that share the same precedence
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OperatorLevel {
+    /// Name of the nodes created by the operators
+    pub name: String,
+    pub operators: Vec<MatchToken>,
+    pub kind: OperatorKind,
+}
+
+/// How the operators of a level are used
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorKind {
+    /// Binary operator grouped from the left: `a - b - c` is `(a - b) - c`
+    Left,
+    /// Binary operator grouped from the right: `a = b = c` is `a = (b = c)`
+    Right,
+    /// Unary operator in front of the operand
+    Prefix,
+    /// Unary operator after the operand
+    Postfix,
+}
+
 /// Commands that can be executed
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Commands {
@@ -155,6 +192,17 @@ pub enum Commands {
         /// Rules that will be executed if the comparison is true
         rules: Rules,
     },
+    /// Compares a variable to a constant and executes rules if the comparison is true
+    ///
+    /// The variable is looked up in the node first and in the globals after
+    CompareLiteral {
+        variable: String,
+        comparison: Comparison,
+        /// Constant of the same kind as the variable
+        value: Literal,
+        /// Rules that will be executed if the comparison is true
+        rules: Rules,
+    },
     /// Returns an error from node
     Error {
         message: String,
@@ -190,10 +238,55 @@ pub enum Comparison {
     LessThanOrEqual,
 }
 
+/// Conversion of the text of a captured value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Transform {
+    /// Text without the quotes around it, escapes are kept as they are
+    ///
+    /// Captured to a variable of kind Text
+    Unquote,
+    /// Text parsed as a decimal integer
+    ///
+    /// Captured to a variable of kind Number, the value fails to match if it is not a number
+    Int,
+}
+
+impl Transform {
+    /// Kind of the variables the result can be captured to
+    pub fn kind(&self) -> VariableKind {
+        match self {
+            Transform::Unquote => VariableKind::Text,
+            Transform::Int => VariableKind::Number,
+        }
+    }
+}
+
+/// Constant a variable can be compared to
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Literal {
+    /// Compared to a variable of kind Number
+    Number(i32),
+    /// Compared to a variable of kind Boolean
+    Boolean(bool),
+    /// Compared to a variable of kind Text, an empty variable is not equal to any text
+    Text(String),
+}
+
+impl Literal {
+    /// Kind of the variables the constant can be compared to
+    pub fn kind(&self) -> VariableKind {
+        match self {
+            Literal::Number(_) => VariableKind::Number,
+            Literal::Boolean(_) => VariableKind::Boolean,
+            Literal::Text(_) => VariableKind::Text,
+        }
+    }
+}
+
 /// A token that will be matched
 ///
 /// Can be a token kind or a node name
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum MatchToken {
     /// A token kind
     Token(TokenKinds),
@@ -218,6 +311,12 @@ pub struct Node {
     pub variables: Map<String, VariableKind>,
     /// Documentation for the node
     pub docs: Option<String>,
+    /// Synchronization tokens used for error recovery
+    ///
+    /// If the node fails with a hard error while recovering, the parser records the error,
+    /// skips tokens until after one of these and continues as if the node matched
+    #[serde(default)]
+    pub sync: Vec<TokenKinds>,
 }
 
 /// A variable that can be used in a node
@@ -231,6 +330,12 @@ pub enum VariableKind {
     Boolean,
     /// Holds a number
     Number,
+    /// Holds the byte range of the text covered by all the values set to it
+    Span,
+    /// Holds a list of tokens, nodes can not be set to it
+    TokenList,
+    /// Holds the text of the last value set to it
+    Text,
 }
 
 /// Parameters that can be used on a rule if it is matched
@@ -246,6 +351,16 @@ pub enum Parameters {
     Decrement(String),
     /// Adds 1 to a global variable of type Count
     IncrementGlobal(String),
+    /// Adds a number to a variable of type Count
+    Add(String, i32),
+    /// Adds a number to a global variable of type Count
+    AddGlobal(String, i32),
+    /// Sets a variable of any kind back to its empty value
+    Reset(String),
+    /// Sets a global variable of any kind back to its empty value
+    ResetGlobal(String),
+    /// Sets the text of the value converted by the transform to a variable
+    Capture(String, Transform),
     /// Sets a variable to true
     True(String),
     /// Sets a variable to false
@@ -299,6 +414,17 @@ pub struct Enumerator {
 pub mod validator {
     use super::*;
     use crate::lexer::*;
+    use core::fmt;
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "std")] {
+            use std::collections::{BTreeMap, BTreeSet, VecDeque};
+        } else {
+            use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+            use alloc::format;
+            use alloc::vec;
+        }
+    }
 
     impl Lexer {
         pub fn validate_tokens(&self, result: &mut ValidationResult) {
@@ -311,58 +437,91 @@ pub mod validator {
                 tokens.push(token.clone());
                 // check for collisions
                 if self.token_kinds.iter().filter(|t| *t == token).count() > 1 {
-                    result.errors.push(ValidationError {
-                        kind: ValidationErrors::TokenCollision(token.clone()),
-                        node_name: "__lexer__".to_string(),
-                    });
+                    result.errors.push(ValidationError::at(
+                        ValidationErrors::TokenCollision(token.clone()),
+                        "__lexer__",
+                        &RulePath::default(),
+                    ));
                 }
                 // check if token is empty
                 if token.is_empty() {
-                    result.errors.push(ValidationError {
-                        kind: ValidationErrors::EmptyToken,
-                        node_name: "__lexer__".to_string(),
-                    });
+                    result.errors.push(ValidationError::at(
+                        ValidationErrors::EmptyToken,
+                        "__lexer__",
+                        &RulePath::default(),
+                    ));
                 }
                 // check if it starts with a number
                 let first = token.chars().next().unwrap();
                 if first.is_numeric() {
-                    result.warnings.push(ValidationWarning {
-                        kind: ValidationWarnings::UnusualToken(
-                            token.clone(),
-                            TokenErrors::StartsNumeric,
-                        ),
-                        node_name: "__lexer__".to_string(),
-                    });
+                    result.warnings.push(ValidationWarning::at(
+                        ValidationWarnings::UnusualToken(token.clone(), TokenErrors::StartsNumeric),
+                        "__lexer__",
+                        &RulePath::default(),
+                    ));
                 }
 
                 // check if it contains a whitespace
                 if token.chars().any(|c| c.is_whitespace()) {
-                    result.warnings.push(ValidationWarning {
-                        kind: ValidationWarnings::UnusualToken(
+                    result.warnings.push(ValidationWarning::at(
+                        ValidationWarnings::UnusualToken(
                             token.clone(),
                             TokenErrors::ContainsWhitespace,
                         ),
-                        node_name: "__lexer__".to_string(),
-                    });
+                        "__lexer__",
+                        &RulePath::default(),
+                    ));
                 }
 
                 // check if it is longer than 2 characters
                 if token.len() > 2 {
-                    result.warnings.push(ValidationWarning {
-                        kind: ValidationWarnings::UnusualToken(token.clone(), TokenErrors::TooLong),
-                        node_name: "__lexer__".to_string(),
-                    });
+                    result.warnings.push(ValidationWarning::at(
+                        ValidationWarnings::UnusualToken(token.clone(), TokenErrors::TooLong),
+                        "__lexer__",
+                        &RulePath::default(),
+                    ));
                 }
 
                 // check if it is not ascii
                 if !token.chars().all(|c| c.is_ascii()) {
-                    result.warnings.push(ValidationWarning {
-                        kind: ValidationWarnings::UnusualToken(
-                            token.clone(),
-                            TokenErrors::NotAscii,
-                        ),
-                        node_name: "__lexer__".to_string(),
-                    });
+                    result.warnings.push(ValidationWarning::at(
+                        ValidationWarnings::UnusualToken(token.clone(), TokenErrors::NotAscii),
+                        "__lexer__",
+                        &RulePath::default(),
+                    ));
+                }
+            }
+            let patterns = self
+                .patterns
+                .iter()
+                .chain(self.modes.iter().flat_map(|mode| mode.patterns()));
+            for pattern in patterns {
+                // a pattern that matches nothing would never advance the lexer
+                if pattern.pattern.is_nullable() {
+                    result.errors.push(ValidationError::at(
+                        ValidationErrors::NullablePattern(pattern.name.clone()),
+                        "__lexer__",
+                        &RulePath::default(),
+                    ));
+                }
+            }
+            for mode in &self.modes {
+                if mode.enter.is_empty() || mode.exit.is_empty() {
+                    result.errors.push(ValidationError::at(
+                        ValidationErrors::EmptyToken,
+                        "__lexer__",
+                        &RulePath::default(),
+                    ));
+                }
+                for from in &mode.from {
+                    let found = from == DEFAULT_MODE || self.modes.iter().any(|m| m.name == *from);
+                    if !found {
+                        result.errors.push(ValidationError::at(
+                            ValidationErrors::ModeNotFound(from.clone()),
+                            "__lexer__",
+                            &RulePath::default(),
+                        ));
+                    }
                 }
             }
         }
@@ -377,50 +536,63 @@ pub mod validator {
             for node in self.nodes.values() {
                 self.validate_node(node, lexer, &mut result);
             }
+            self.validate_termination(&mut result);
+            self.validate_alternatives(&mut result);
 
             result
         }
 
         pub fn validate_node(&self, node: &Node, lexer: &Lexer, result: &mut ValidationResult) {
-            let mut laf = LostAndFound::new();
-            for rule in &node.rules {
-                self.validate_rule(rule, node, lexer, &mut laf, result);
+            let mut ctx = ValidationContext {
+                node,
+                lexer,
+                laf: LostAndFound::new(),
+                result,
+            };
+            let path = RulePath(vec![node.name.clone()]);
+            for (i, token) in node.sync.iter().enumerate() {
+                let token = MatchToken::Token(token.clone());
+                let path = path.with(format!("sync[{}]", i));
+                self.validate_token(&token, &mut ctx, &path);
             }
-            laf.pass(result, &node.name);
+            for (i, rule) in node.rules.iter().enumerate() {
+                let path = path.with(format!("rules[{}]", i));
+                self.validate_rule(rule, &mut ctx, &path);
+            }
+            ctx.laf.pass(ctx.result, &node.name);
         }
 
-        pub fn validate_rule(
-            &self,
-            rule: &Rule,
-            node: &Node,
-            lexer: &Lexer,
-            laf: &mut LostAndFound,
-            result: &mut ValidationResult,
-        ) {
+        /// Validates the rule and the rules in its blocks
+        ///
+        /// `path` leads to the rule, errors in its parameters and blocks get longer paths
+        pub fn validate_rule(&self, rule: &Rule, ctx: &mut ValidationContext, path: &RulePath) {
             match rule {
                 Rule::Is {
                     token,
                     rules,
                     parameters,
                 } => {
-                    self.validate_token(token, node, lexer, laf, result);
-                    self.validate_parameters(parameters, node, laf, result);
-                    self.validate_ruleblock(rules, node, lexer, laf, result)
+                    self.validate_token(token, ctx, path);
+                    self.validate_parameters(parameters, ctx, path);
+                    self.validate_capture(token, parameters, ctx, path);
+                    self.validate_ruleblock(rules, ctx, path, "Is.rules")
                 }
                 Rule::Isnt {
                     token,
                     rules,
                     parameters,
                 } => {
-                    self.validate_token(token, node, lexer, laf, result);
-                    self.validate_parameters(parameters, node, laf, result);
-                    self.validate_ruleblock(rules, node, lexer, laf, result)
+                    self.validate_token(token, ctx, path);
+                    self.validate_parameters(parameters, ctx, path);
+                    self.validate_ruleblock(rules, ctx, path, "Isnt.rules")
                 }
                 Rule::IsOneOf { tokens } => {
-                    for one_of in tokens {
-                        self.validate_token(&one_of.token, node, lexer, laf, result);
-                        self.validate_parameters(&one_of.parameters, node, laf, result);
-                        self.validate_ruleblock(&one_of.rules, node, lexer, laf, result)
+                    for (i, one_of) in tokens.iter().enumerate() {
+                        let path = path.with(format!("IsOneOf.tokens[{}]", i));
+                        self.validate_token(&one_of.token, ctx, &path);
+                        self.validate_parameters(&one_of.parameters, ctx, &path);
+                        self.validate_capture(&one_of.token, &one_of.parameters, ctx, &path);
+                        self.validate_ruleblock(&one_of.rules, ctx, &path, "rules")
                     }
                 }
                 Rule::Maybe {
@@ -429,47 +601,75 @@ pub mod validator {
                     isnt,
                     parameters,
                 } => {
-                    self.validate_token(token, node, lexer, laf, result);
-                    self.validate_parameters(parameters, node, laf, result);
-                    self.validate_ruleblock(is, node, lexer, laf, result);
-                    self.validate_ruleblock(isnt, node, lexer, laf, result);
+                    self.validate_token(token, ctx, path);
+                    self.validate_parameters(parameters, ctx, path);
+                    self.validate_capture(token, parameters, ctx, path);
+                    self.validate_ruleblock(is, ctx, path, "Maybe.is");
+                    self.validate_ruleblock(isnt, ctx, path, "Maybe.isnt");
                 }
                 Rule::MaybeOneOf { is_one_of, isnt } => {
-                    for OneOf { token, rules, parameters } in is_one_of {
-                        self.validate_token(token, node, lexer, laf, result);
-                        self.validate_parameters(parameters, node, laf, result);
-                        self.validate_ruleblock(rules, node, lexer, laf, result);
+                    for (
+                        i,
+                        OneOf {
+                            token,
+                            rules,
+                            parameters,
+                        },
+                    ) in is_one_of.iter().enumerate()
+                    {
+                        let path = path.with(format!("MaybeOneOf.is_one_of[{}]", i));
+                        self.validate_token(token, ctx, &path);
+                        self.validate_parameters(parameters, ctx, &path);
+                        self.validate_capture(token, parameters, ctx, &path);
+                        self.validate_ruleblock(rules, ctx, &path, "rules");
                     }
-                    self.validate_ruleblock(isnt, node, lexer, laf, result);
+                    self.validate_ruleblock(isnt, ctx, path, "MaybeOneOf.isnt");
                 }
                 Rule::While {
                     token,
                     rules,
                     parameters,
                 } => {
-                    self.validate_token(token, node, lexer, laf, result);
-                    self.validate_parameters(parameters, node, laf, result);
-                    self.validate_ruleblock(rules, node, lexer, laf, result)
-                }
-                Rule::Loop { rules } => {
-                    self.validate_ruleblock(rules, node, lexer, laf, result)
+                    self.validate_token(token, ctx, path);
+                    self.validate_parameters(parameters, ctx, path);
+                    self.validate_capture(token, parameters, ctx, path);
+                    self.validate_ruleblock(rules, ctx, path, "While.rules")
                 }
+                Rule::Loop { rules } => self.validate_ruleblock(rules, ctx, path, "Loop.rules"),
                 Rule::Until {
                     token,
                     rules,
                     parameters,
                 } => {
-                    self.validate_token(token, node, lexer, laf, result);
-                    self.validate_parameters(parameters, node, laf, result);
-                    self.validate_ruleblock(rules, node, lexer, laf, result)
+                    self.validate_token(token, ctx, path);
+                    self.validate_parameters(parameters, ctx, path);
+                    self.validate_capture(token, parameters, ctx, path);
+                    self.validate_ruleblock(rules, ctx, path, "Until.rules")
                 }
                 Rule::UntilOneOf { tokens } => {
-                    for one_of in tokens {
-                        self.validate_token(&one_of.token, node, lexer, laf, result);
-                        self.validate_parameters(&one_of.parameters, node, laf, result);
-                        self.validate_ruleblock(&one_of.rules, node, lexer, laf, result)
+                    for (i, one_of) in tokens.iter().enumerate() {
+                        let path = path.with(format!("UntilOneOf.tokens[{}]", i));
+                        self.validate_token(&one_of.token, ctx, &path);
+                        self.validate_parameters(&one_of.parameters, ctx, &path);
+                        self.validate_capture(&one_of.token, &one_of.parameters, ctx, &path);
+                        self.validate_ruleblock(&one_of.rules, ctx, &path, "rules")
                     }
                 }
+                Rule::Precedence {
+                    operand,
+                    levels,
+                    parameters,
+                } => {
+                    let operand_path = path.with("Precedence.operand".to_string());
+                    self.validate_token(operand, ctx, &operand_path);
+                    for (i, level) in levels.iter().enumerate() {
+                        let path = path.with(format!("Precedence.levels[{}]", i));
+                        for operator in &level.operators {
+                            self.validate_token(operator, ctx, &path);
+                        }
+                    }
+                    self.validate_parameters(parameters, ctx, path);
+                }
                 Rule::Command { command } => match command {
                     Commands::Compare {
                         left,
@@ -480,152 +680,209 @@ pub mod validator {
                         match self.globals.get(left) {
                             Some(var) => match var {
                                 VariableKind::Number => (),
-                                _ => result.errors.push(ValidationError {
-                                    kind: ValidationErrors::CantUseVariable(left.clone()),
-                                    node_name: node.name.clone(),
-                                }),
+                                _ => ctx.result.errors.push(ValidationError::at(
+                                    ValidationErrors::CantUseVariable(left.clone()),
+                                    &ctx.node.name,
+                                    path,
+                                )),
                             },
-                            None => {
-                                match node.variables.get(left) {
-                                    Some(var) => match var {
-                                        VariableKind::Number => (),
-                                        _ => result.errors.push(ValidationError {
-                                            kind: ValidationErrors::CantUseVariable(left.clone()),
-                                            node_name: node.name.clone(),
-                                        }),
-                                    },
-                                    None => {
-                                        result.errors.push(ValidationError {
-                                            kind: ValidationErrors::GlobalNotFound(left.clone()),
-                                            node_name: node.name.clone(),
-                                        });
-                                    }
+                            None => match ctx.node.variables.get(left) {
+                                Some(var) => match var {
+                                    VariableKind::Number => (),
+                                    _ => ctx.result.errors.push(ValidationError::at(
+                                        ValidationErrors::CantUseVariable(left.clone()),
+                                        &ctx.node.name,
+                                        path,
+                                    )),
+                                },
+                                None => {
+                                    ctx.result.errors.push(ValidationError::at(
+                                        ValidationErrors::GlobalNotFound(left.clone()),
+                                        &ctx.node.name,
+                                        path,
+                                    ));
                                 }
                             }
                         }
                         match self.globals.get(right) {
                             Some(var) => match var {
                                 VariableKind::Number => (),
-                                _ => result.errors.push(ValidationError {
-                                    kind: ValidationErrors::CantUseVariable(right.clone()),
-                                    node_name: node.name.clone(),
-                                }),
+                                _ => ctx.result.errors.push(ValidationError::at(
+                                    ValidationErrors::CantUseVariable(right.clone()),
+                                    &ctx.node.name,
+                                    path,
+                                )),
                             },
-                            None => {
-                                match node.variables.get(right) {
-                                    Some(var) => match var {
-                                        VariableKind::Number => (),
-                                        _ => result.errors.push(ValidationError {
-                                            kind: ValidationErrors::CantUseVariable(right.clone()),
-                                            node_name: node.name.clone(),
-                                        }),
-                                    },
-                                    None => {
-                                        result.errors.push(ValidationError {
-                                            kind: ValidationErrors::GlobalNotFound(right.clone()),
-                                            node_name: node.name.clone(),
-                                        });
-                                    }
+                            None => match ctx.node.variables.get(right) {
+                                Some(var) => match var {
+                                    VariableKind::Number => (),
+                                    _ => ctx.result.errors.push(ValidationError::at(
+                                        ValidationErrors::CantUseVariable(right.clone()),
+                                        &ctx.node.name,
+                                        path,
+                                    )),
+                                },
+                                None => {
+                                    ctx.result.errors.push(ValidationError::at(
+                                        ValidationErrors::GlobalNotFound(right.clone()),
+                                        &ctx.node.name,
+                                        path,
+                                    ));
                                 }
                             }
                         }
-                        for rule in rules {
-                            self.validate_rule(rule, node, lexer, laf, result);
+                        for (i, rule) in rules.iter().enumerate() {
+                            let path = path.with(format!("Compare.rules[{}]", i));
+                            self.validate_rule(rule, ctx, &path);
+                        }
+                    }
+                    Commands::CompareLiteral {
+                        variable,
+                        comparison: _,
+                        value,
+                        rules,
+                    } => {
+                        let kind = ctx
+                            .node
+                            .variables
+                            .get(variable)
+                            .or_else(|| self.globals.get(variable));
+                        match kind {
+                            Some(kind) if *kind == value.kind() => (),
+                            Some(_) => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(variable.clone()),
+                                &ctx.node.name,
+                                path,
+                            )),
+                            None => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(variable.clone()),
+                                &ctx.node.name,
+                                path,
+                            )),
+                        }
+                        for (i, rule) in rules.iter().enumerate() {
+                            let path = path.with(format!("CompareLiteral.rules[{}]", i));
+                            self.validate_rule(rule, ctx, &path);
                         }
                     }
                     Commands::Error { message: _ } => (),
                     Commands::HardError { set: _ } => (),
                     Commands::Goto { label } => {
-                        laf.lost_labels.push(label.clone());
+                        ctx.laf.lost_labels.push((label.clone(), path.clone()));
                     }
                     Commands::Label { name } => {
-                        if laf.found_labels.contains(&name) {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::DuplicateLabel(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                        if ctx.laf.found_labels.iter().any(|(found, _)| found == name) {
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::DuplicateLabel(name.clone()),
+                                &ctx.node.name,
+                                path,
+                            ));
                         }
-                        laf.found_labels.push(name.clone());
+                        ctx.laf.found_labels.push((name.clone(), path.clone()));
                     }
                     Commands::Print { message: _ } => (),
                 },
-                Rule::Debug { target } => {
-                    match target {
-                        Some(name) => match node.variables.get(name) {
-                            Some(_) => (),
-                            None => {
-                                result.errors.push(ValidationError {
-                                    kind: ValidationErrors::VariableNotFound(name.clone()),
-                                    node_name: node.name.clone(),
-                                });
-                            }
-                        },
-                        None => (),
-                    }
-                }
+                Rule::Debug { target } => match target {
+                    Some(name) => match ctx.node.variables.get(name) {
+                        Some(_) => (),
+                        None => {
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(name.clone()),
+                                &ctx.node.name,
+                                path,
+                            ));
+                        }
+                    },
+                    None => (),
+                },
             }
         }
 
+        /// Validates a block of rules, `field` names the block in the rule at `path`
         pub fn validate_ruleblock(
             &self,
             ruleblock: &Vec<Rule>,
-            node: &Node,
-            lexer: &Lexer,
-            laf: &mut LostAndFound,
-            result: &mut ValidationResult,
+            ctx: &mut ValidationContext,
+            path: &RulePath,
+            field: &str,
         ) {
-            let steps = laf.steps;
-            for rule in ruleblock {
-                laf.steps += 1;
-                self.validate_rule(rule, node, lexer, laf, result);
+            let steps = ctx.laf.steps;
+            for (i, rule) in ruleblock.iter().enumerate() {
+                ctx.laf.steps += 1;
+                let path = path.with(format!("{}[{}]", field, i));
+                self.validate_rule(rule, ctx, &path);
+            }
+            ctx.laf.steps = steps;
+        }
+
+        /// Error for a name that is not defined
+        ///
+        /// Names in a namespace nothing is defined in are reported as a missing namespace
+        fn missing(&self, name: &str, error: ValidationErrors) -> ValidationErrors {
+            let namespace = match crate::compose::split(name) {
+                Some((namespace, _)) => namespace,
+                None => return error,
+            };
+            let prefix = crate::compose::qualify(namespace, "");
+            let defined = self
+                .nodes
+                .keys()
+                .chain(self.enumerators.keys())
+                .any(|name| name.starts_with(&prefix));
+            match defined {
+                true => error,
+                false => ValidationErrors::NamespaceNotFound(namespace.to_string()),
             }
-            laf.steps = steps;
         }
 
         pub fn validate_token(
             &self,
             token: &MatchToken,
-            node: &Node,
-            lexer: &Lexer,
-            _laf: &mut LostAndFound,
-            result: &mut ValidationResult,
+            ctx: &mut ValidationContext,
+            path: &RulePath,
         ) {
             match token {
                 MatchToken::Node(name) => {
                     if !self.nodes.contains_key(name) {
-                        result.errors.push(ValidationError {
-                            kind: ValidationErrors::NodeNotFound(name.clone()),
-                            node_name: node.name.clone(),
-                        });
+                        ctx.result.errors.push(ValidationError::at(
+                            self.missing(name, ValidationErrors::NodeNotFound(name.clone())),
+                            &ctx.node.name,
+                            path,
+                        ));
                     }
                 }
                 MatchToken::Enumerator(enumerator) => {
                     if !self.enumerators.contains_key(enumerator) {
-                        result.errors.push(ValidationError {
-                            kind: ValidationErrors::EnumeratorNotFound(enumerator.clone()),
-                            node_name: node.name.clone(),
-                        });
+                        let kind = ValidationErrors::EnumeratorNotFound(enumerator.clone());
+                        ctx.result.errors.push(ValidationError::at(
+                            self.missing(enumerator, kind),
+                            &ctx.node.name,
+                            path,
+                        ));
                     }
                 }
-                MatchToken::Any => result.warnings.push(ValidationWarning {
-                    kind: ValidationWarnings::UsedDepricated(Depricated::Any),
-                    node_name: node.name.clone(),
-                }),
+                MatchToken::Any => ctx.result.warnings.push(ValidationWarning::at(
+                    ValidationWarnings::UsedDepricated(Depricated::Any),
+                    &ctx.node.name,
+                    path,
+                )),
                 MatchToken::Token(kind) => match kind {
                     TokenKinds::Token(txt) => {
                         if txt.is_empty() {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::EmptyToken,
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::EmptyToken,
+                                &ctx.node.name,
+                                path,
+                            ));
                             return;
                         }
                         // check if token is in the lexer
-                        if !lexer.token_kinds.iter().any(|k| k == txt) {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::TokenNotFound(txt.clone()),
-                                node_name: node.name.clone(),
-                            });
+                        if !ctx.lexer.has_token(txt) {
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::TokenNotFound(txt.clone()),
+                                &ctx.node.name,
+                                path,
+                            ));
                         }
                     }
                     _ => {}
@@ -634,260 +891,444 @@ pub mod validator {
             }
         }
 
+        /// Nodes can not be set to variables that hold tokens
+        fn validate_capture(
+            &self,
+            token: &MatchToken,
+            parameters: &[Parameters],
+            ctx: &mut ValidationContext,
+            path: &RulePath,
+        ) {
+            if !matches!(token, MatchToken::Node(_)) {
+                return;
+            }
+            for (i, parameter) in parameters.iter().enumerate() {
+                let (name, kind) = match parameter {
+                    Parameters::Set(name) => (name, ctx.node.variables.get(name)),
+                    Parameters::Global(name) => (name, self.globals.get(name)),
+                    _ => continue,
+                };
+                if kind == Some(&VariableKind::TokenList) {
+                    ctx.result.errors.push(ValidationError::at(
+                        ValidationErrors::CantUseVariable(name.clone()),
+                        &ctx.node.name,
+                        &path.with(format!("parameters[{}]", i)),
+                    ));
+                }
+            }
+        }
+
         pub fn validate_parameters(
             &self,
             parameters: &Vec<Parameters>,
-            node: &Node,
-            laf: &mut LostAndFound,
-            result: &mut ValidationResult,
+            ctx: &mut ValidationContext,
+            path: &RulePath,
         ) {
-            for parameter in parameters {
+            for (i, parameter) in parameters.iter().enumerate() {
+                let path = path.with(format!("parameters[{}]", i));
                 match parameter {
-                    Parameters::Set(name) => match node.variables.get(name) {
+                    Parameters::Set(name) => match ctx.node.variables.get(name) {
                         Some(var) => match var {
                             VariableKind::Node => (),
                             VariableKind::NodeList => (),
-                            VariableKind::Boolean => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::Number => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
+                            VariableKind::Span => (),
+                            VariableKind::TokenList => (),
+                            VariableKind::Text => (),
+                            VariableKind::Boolean => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::Number => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
                         },
                         None => {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::VariableNotFound(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     },
                     Parameters::Global(name) => match self.globals.get(name) {
                         Some(var) => match var {
                             VariableKind::Node => (),
                             VariableKind::NodeList => (),
-                            VariableKind::Boolean => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::Number => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
+                            VariableKind::Span => (),
+                            VariableKind::TokenList => (),
+                            VariableKind::Text => (),
+                            VariableKind::Boolean => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::Number => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
                         },
                         None => {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::GlobalNotFound(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::GlobalNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     },
-                    Parameters::Increment(name) => match node.variables.get(name) {
+                    Parameters::Increment(name) => match ctx.node.variables.get(name) {
                         Some(var) => match var {
                             VariableKind::Number => (),
-                            VariableKind::Node => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::NodeList => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::Boolean => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
+                            VariableKind::Span | VariableKind::TokenList | VariableKind::Text => {
+                                ctx.result.errors.push(ValidationError::at(
+                                    ValidationErrors::CantUseVariable(name.clone()),
+                                    &ctx.node.name,
+                                    &path,
+                                ))
+                            }
+                            VariableKind::Node => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::NodeList => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::Boolean => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
                         },
                         None => {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::VariableNotFound(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     },
-                    Parameters::Decrement(name) => match node.variables.get(name) {
+                    Parameters::Decrement(name) => match ctx.node.variables.get(name) {
                         Some(var) => match var {
                             VariableKind::Number => (),
-                            VariableKind::Node => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::NodeList => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::Boolean => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
+                            VariableKind::Span | VariableKind::TokenList | VariableKind::Text => {
+                                ctx.result.errors.push(ValidationError::at(
+                                    ValidationErrors::CantUseVariable(name.clone()),
+                                    &ctx.node.name,
+                                    &path,
+                                ))
+                            }
+                            VariableKind::Node => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::NodeList => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::Boolean => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
                         },
                         None => {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::VariableNotFound(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     },
                     Parameters::IncrementGlobal(name) => match self.globals.get(name) {
                         Some(var) => match var {
                             VariableKind::Number => (),
-                            VariableKind::Node => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::NodeList => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::Boolean => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
+                            VariableKind::Span | VariableKind::TokenList | VariableKind::Text => {
+                                ctx.result.errors.push(ValidationError::at(
+                                    ValidationErrors::CantUseVariable(name.clone()),
+                                    &ctx.node.name,
+                                    &path,
+                                ))
+                            }
+                            VariableKind::Node => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::NodeList => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::Boolean => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
                         },
                         None => {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::GlobalNotFound(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::GlobalNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
+                        }
+                    },
+                    Parameters::Add(name, _) => match ctx.node.variables.get(name) {
+                        Some(VariableKind::Number) => (),
+                        Some(_) => ctx.result.errors.push(ValidationError::at(
+                            ValidationErrors::CantUseVariable(name.clone()),
+                            &ctx.node.name,
+                            &path,
+                        )),
+                        None => {
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     },
-                    Parameters::True(name) => match node.variables.get(name) {
+                    Parameters::AddGlobal(name, _) => match self.globals.get(name) {
+                        Some(VariableKind::Number) => (),
+                        Some(_) => ctx.result.errors.push(ValidationError::at(
+                            ValidationErrors::CantUseVariable(name.clone()),
+                            &ctx.node.name,
+                            &path,
+                        )),
+                        None => {
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::GlobalNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
+                        }
+                    },
+                    Parameters::Reset(name) => {
+                        if !ctx.node.variables.contains_key(name) {
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
+                        }
+                    }
+                    Parameters::ResetGlobal(name) => {
+                        if !self.globals.contains_key(name) {
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::GlobalNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
+                        }
+                    }
+                    Parameters::Capture(name, transform) => match ctx.node.variables.get(name) {
+                        Some(kind) if *kind == transform.kind() => (),
+                        Some(_) => ctx.result.errors.push(ValidationError::at(
+                            ValidationErrors::CantUseVariable(name.clone()),
+                            &ctx.node.name,
+                            &path,
+                        )),
+                        None => {
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
+                        }
+                    },
+                    Parameters::True(name) => match ctx.node.variables.get(name) {
                         Some(var) => match var {
                             VariableKind::Boolean => (),
-                            VariableKind::Node => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::NodeList => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::Number => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
+                            VariableKind::Span | VariableKind::TokenList | VariableKind::Text => {
+                                ctx.result.errors.push(ValidationError::at(
+                                    ValidationErrors::CantUseVariable(name.clone()),
+                                    &ctx.node.name,
+                                    &path,
+                                ))
+                            }
+                            VariableKind::Node => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::NodeList => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::Number => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
                         },
                         None => {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::VariableNotFound(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     },
-                    Parameters::False(name) => match node.variables.get(name) {
+                    Parameters::False(name) => match ctx.node.variables.get(name) {
                         Some(var) => match var {
                             VariableKind::Boolean => (),
-                            VariableKind::Node => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::NodeList => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::Number => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
+                            VariableKind::Span | VariableKind::TokenList | VariableKind::Text => {
+                                ctx.result.errors.push(ValidationError::at(
+                                    ValidationErrors::CantUseVariable(name.clone()),
+                                    &ctx.node.name,
+                                    &path,
+                                ))
+                            }
+                            VariableKind::Node => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::NodeList => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::Number => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
                         },
                         None => {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::VariableNotFound(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::VariableNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     },
                     Parameters::TrueGlobal(name) => match self.globals.get(name) {
                         Some(var) => match var {
                             VariableKind::Boolean => (),
-                            VariableKind::Node => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::NodeList => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::Number => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
+                            VariableKind::Span | VariableKind::TokenList | VariableKind::Text => {
+                                ctx.result.errors.push(ValidationError::at(
+                                    ValidationErrors::CantUseVariable(name.clone()),
+                                    &ctx.node.name,
+                                    &path,
+                                ))
+                            }
+                            VariableKind::Node => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::NodeList => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::Number => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
                         },
                         None => {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::GlobalNotFound(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::GlobalNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     },
                     Parameters::FalseGlobal(name) => match self.globals.get(name) {
                         Some(var) => match var {
                             VariableKind::Boolean => (),
-                            VariableKind::Node => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::NodeList => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
-                            VariableKind::Number => result.errors.push(ValidationError {
-                                kind: ValidationErrors::CantUseVariable(name.clone()),
-                                node_name: node.name.clone(),
-                            }),
+                            VariableKind::Span | VariableKind::TokenList | VariableKind::Text => {
+                                ctx.result.errors.push(ValidationError::at(
+                                    ValidationErrors::CantUseVariable(name.clone()),
+                                    &ctx.node.name,
+                                    &path,
+                                ))
+                            }
+                            VariableKind::Node => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::NodeList => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
+                            VariableKind::Number => ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CantUseVariable(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            )),
                         },
                         None => {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::GlobalNotFound(name.clone()),
-                                node_name: node.name.clone(),
-                            });
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::GlobalNotFound(name.clone()),
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     },
                     Parameters::Print(_) => {
-                        result.warnings.push(ValidationWarning {
-                            kind: ValidationWarnings::UsedPrint,
-                            node_name: node.name.clone(),
-                        });
+                        ctx.result.warnings.push(ValidationWarning::at(
+                            ValidationWarnings::UsedPrint,
+                            &ctx.node.name,
+                            &path,
+                        ));
                     }
                     Parameters::Debug(node_option) => {
                         match node_option {
-                            Some(name) => match node.variables.get(name) {
+                            Some(name) => match ctx.node.variables.get(name) {
                                 Some(_) => (),
                                 None => {
-                                    result.errors.push(ValidationError {
-                                        kind: ValidationErrors::VariableNotFound(name.clone()),
-                                        node_name: node.name.clone(),
-                                    });
+                                    ctx.result.errors.push(ValidationError::at(
+                                        ValidationErrors::VariableNotFound(name.clone()),
+                                        &ctx.node.name,
+                                        &path,
+                                    ));
                                 }
                             },
                             None => (),
                         }
-                        result.warnings.push(ValidationWarning {
-                            kind: ValidationWarnings::UsedDebug,
-                            node_name: node.name.clone(),
-                        });
+                        ctx.result.warnings.push(ValidationWarning::at(
+                            ValidationWarnings::UsedDebug,
+                            &ctx.node.name,
+                            &path,
+                        ));
                     }
                     Parameters::Back(n) => {
-                        result.warnings.push(ValidationWarning {
-                            kind: ValidationWarnings::UsedDepricated(Depricated::Back),
-                            node_name: node.name.clone(),
-                        });
-                        if *n as usize > laf.steps {
-                            result.errors.push(ValidationError {
-                                kind: ValidationErrors::CannotGoBackMoreThan {
+                        ctx.result.warnings.push(ValidationWarning::at(
+                            ValidationWarnings::UsedDepricated(Depricated::Back),
+                            &ctx.node.name,
+                            &path,
+                        ));
+                        if *n as usize > ctx.laf.steps {
+                            ctx.result.errors.push(ValidationError::at(
+                                ValidationErrors::CannotGoBackMoreThan {
                                     steps: *n as usize,
-                                    max: laf.steps,
+                                    max: ctx.laf.steps,
                                 },
-                                node_name: node.name.clone(),
-                            });
+                                &ctx.node.name,
+                                &path,
+                            ));
                         }
                     }
                     Parameters::Return => (),
                     Parameters::Break(_) => (),
                     Parameters::HardError(_) => (),
                     Parameters::Goto(label) => {
-                        laf.lost_labels.push(label.clone());
+                        ctx.laf.lost_labels.push((label.clone(), path.clone()));
                     }
                     Parameters::NodeStart => (),
                     Parameters::NodeEnd => (),
@@ -896,6 +1337,817 @@ pub mod validator {
         }
     }
 
+    impl Grammar {
+        /// Checks that every node can be reached from the entry node
+        ///
+        /// Nodes that are never matched are reported as warnings
+        pub fn validate_reachability(&self, entry: &str, result: &mut ValidationResult) {
+            if !self.nodes.contains_key(entry) {
+                result.errors.push(ValidationError::at(
+                    ValidationErrors::NodeNotFound(entry.to_string()),
+                    "__parser__",
+                    &RulePath::default(),
+                ));
+                return;
+            }
+            let mut reached = BTreeSet::new();
+            let mut stack = vec![entry];
+            while let Some(name) = stack.pop() {
+                if !reached.insert(name) {
+                    continue;
+                }
+                let node = match self.nodes.get(name) {
+                    Some(node) => node,
+                    None => continue,
+                };
+                let mut tokens = Vec::new();
+                each_rule(
+                    &node.rules,
+                    &RulePath::default(),
+                    "rules",
+                    &mut |rule, _| rule_tokens(rule, &mut tokens),
+                );
+                let mut enumerators = BTreeSet::new();
+                while let Some(token) = tokens.pop() {
+                    match token {
+                        MatchToken::Node(name) => stack.push(name),
+                        MatchToken::Enumerator(name) => {
+                            if let (true, Some(enumerator)) =
+                                (enumerators.insert(name), self.enumerators.get(name))
+                            {
+                                tokens.extend(&enumerator.values);
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            let mut unreachable: Vec<&String> = self
+                .nodes
+                .keys()
+                .filter(|name| !reached.contains(name.as_str()))
+                .collect();
+            unreachable.sort();
+            for name in unreachable {
+                result.warnings.push(ValidationWarning::at(
+                    ValidationWarnings::UnreachableNode,
+                    name,
+                    &RulePath(vec![name.clone()]),
+                ));
+            }
+        }
+
+        /// Finds loops that can repeat without consuming tokens and left recursion
+        ///
+        /// Both make the parser run forever or overflow the stack
+        pub fn validate_termination(&self, result: &mut ValidationResult) {
+            let mut analysis = Analysis::new(self);
+            let mut names: Vec<&String> = self.nodes.keys().collect();
+            names.sort();
+            // Nodes matched before anything is consumed
+            let mut first: BTreeMap<&str, Vec<(String, RulePath)>> = BTreeMap::new();
+            for name in &names {
+                let node = &self.nodes[*name];
+                analysis.found.clear();
+                analysis.block(&node.rules, "rules", &RulePath::default(), true);
+                let mut edges = Vec::new();
+                for found in analysis.found.drain(..) {
+                    match found {
+                        Found::Loop(path) => {
+                            let path = path.prepend(&node.name);
+                            result.errors.push(ValidationError::at(
+                                ValidationErrors::NullableLoop(path.clone()),
+                                &node.name,
+                                &path,
+                            ))
+                        }
+                        Found::First(target, path) => edges.push((target, path)),
+                        Found::Terminal(_) => (),
+                    }
+                }
+                first.insert(name.as_str(), edges);
+            }
+
+            // Each cycle is reported once, in the node with the lowest name
+            for name in names {
+                let mut previous: BTreeMap<&str, (&str, &RulePath)> = BTreeMap::new();
+                let mut queue = VecDeque::from([name.as_str()]);
+                let mut cycle = None;
+                'search: while let Some(current) = queue.pop_front() {
+                    for (target, path) in first.get(current).into_iter().flatten() {
+                        if target == name {
+                            cycle = Some((current, path));
+                            break 'search;
+                        }
+                        if target.as_str() > name.as_str()
+                            && first.contains_key(target.as_str())
+                            && !previous.contains_key(target.as_str())
+                        {
+                            previous.insert(target, (current, path));
+                            queue.push_back(target);
+                        }
+                    }
+                }
+                let (mut current, last) = match cycle {
+                    Some(cycle) => cycle,
+                    None => continue,
+                };
+                let mut paths = vec![last];
+                while current != name {
+                    let (before, path) = previous[current];
+                    paths.push(path);
+                    current = before;
+                }
+                let mut path = RulePath(vec![name.clone()]);
+                for part in paths.iter().rev() {
+                    path.0.extend(part.0.iter().cloned());
+                }
+                // The rule where the cycle starts
+                let start = paths[paths.len() - 1].clone().prepend(name);
+                result.errors.push(ValidationError::at(
+                    ValidationErrors::LeftRecursion(path),
+                    name,
+                    &start,
+                ));
+            }
+        }
+    }
+
+    impl Grammar {
+        /// Warns about alternatives that are tried in order and can start with the same token
+        ///
+        /// Checks the alternatives of `IsOneOf`, `MaybeOneOf` and the values of enumerators.
+        /// An alternative is shadowed if an earlier one always matches the tokens it starts with.
+        /// Alternatives are ambiguous if they share some of the tokens, unless the later one
+        /// is more general than the earlier one.
+        pub fn validate_alternatives(&self, result: &mut ValidationResult) {
+            let mut analysis = Analysis::new(self);
+            let first = analysis.first_sets();
+
+            let mut names: Vec<&String> = self.nodes.keys().collect();
+            names.sort();
+            for name in names {
+                let mut rules = Vec::new();
+                let path = RulePath(vec![name.clone()]);
+                each_rule(
+                    &self.nodes[name].rules,
+                    &path,
+                    "rules",
+                    &mut |rule, path| rules.push((rule, path.clone())),
+                );
+                for (rule, path) in rules {
+                    let alternatives = match rule {
+                        Rule::IsOneOf { tokens } => tokens,
+                        Rule::MaybeOneOf { is_one_of, .. } => is_one_of,
+                        _ => continue,
+                    };
+                    let alternatives = alternatives
+                        .iter()
+                        .map(|one_of| {
+                            let tokens = analysis.first_of(
+                                &one_of.token,
+                                &one_of.parameters,
+                                &one_of.rules,
+                                &first,
+                            );
+                            (&one_of.token, tokens)
+                        })
+                        .collect();
+                    analysis.compare_alternatives(alternatives, name, &path, result);
+                }
+            }
+
+            let mut names: Vec<&String> = self.enumerators.keys().collect();
+            names.sort();
+            for name in names {
+                let alternatives = self.enumerators[name]
+                    .values
+                    .iter()
+                    .map(|value| (value, analysis.first_of(value, &[], &Vec::new(), &first)))
+                    .collect();
+                let path = RulePath(vec![name.clone()]);
+                analysis.compare_alternatives(alternatives, name, &path, result);
+            }
+        }
+    }
+
+    /// Path to a rule in a node, like `KWFunction > rules[3] > Maybe.is[0]`
+    #[derive(
+        Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+    )]
+    pub struct RulePath(pub Vec<String>);
+
+    impl RulePath {
+        fn with(&self, segment: String) -> RulePath {
+            let mut path = self.clone();
+            path.0.push(segment);
+            path
+        }
+
+        fn prepend(mut self, segment: &str) -> RulePath {
+            self.0.insert(0, segment.to_string());
+            self
+        }
+    }
+
+    impl fmt::Display for RulePath {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0.join(" > "))
+        }
+    }
+
+    /// Calls `f` for every rule in the block and in the blocks of the rules
+    ///
+    /// `field` names the block in the rule at `path`, the rules get paths like `field[i]`
+    pub(crate) fn each_rule<'a>(
+        rules: &'a Rules,
+        path: &RulePath,
+        field: &str,
+        f: &mut dyn FnMut(&'a Rule, &RulePath),
+    ) {
+        for (i, rule) in rules.iter().enumerate() {
+            let path = path.with(format!("{}[{}]", field, i));
+            f(rule, &path);
+            match rule {
+                Rule::Is { rules, .. } => each_rule(rules, &path, "Is.rules", f),
+                Rule::Isnt { rules, .. } => each_rule(rules, &path, "Isnt.rules", f),
+                Rule::While { rules, .. } => each_rule(rules, &path, "While.rules", f),
+                Rule::Until { rules, .. } => each_rule(rules, &path, "Until.rules", f),
+                Rule::Loop { rules } => each_rule(rules, &path, "Loop.rules", f),
+                Rule::Command {
+                    command: Commands::Compare { rules, .. },
+                } => each_rule(rules, &path, "Compare.rules", f),
+                Rule::Command {
+                    command: Commands::CompareLiteral { rules, .. },
+                } => each_rule(rules, &path, "CompareLiteral.rules", f),
+                Rule::Maybe { is, isnt, .. } => {
+                    each_rule(is, &path, "Maybe.is", f);
+                    each_rule(isnt, &path, "Maybe.isnt", f);
+                }
+                Rule::IsOneOf { tokens } => {
+                    for (k, one_of) in tokens.iter().enumerate() {
+                        let path = path.with(format!("IsOneOf.tokens[{}]", k));
+                        each_rule(&one_of.rules, &path, "rules", f);
+                    }
+                }
+                Rule::UntilOneOf { tokens } => {
+                    for (k, one_of) in tokens.iter().enumerate() {
+                        let path = path.with(format!("UntilOneOf.tokens[{}]", k));
+                        each_rule(&one_of.rules, &path, "rules", f);
+                    }
+                }
+                Rule::MaybeOneOf { is_one_of, isnt } => {
+                    for (k, one_of) in is_one_of.iter().enumerate() {
+                        let path = path.with(format!("MaybeOneOf.is_one_of[{}]", k));
+                        each_rule(&one_of.rules, &path, "rules", f);
+                    }
+                    each_rule(isnt, &path, "MaybeOneOf.isnt", f);
+                }
+                Rule::Precedence { .. } | Rule::Command { .. } | Rule::Debug { .. } => (),
+            }
+        }
+    }
+
+    /// Collects the tokens matched by the rule, without the rules in its blocks
+    fn rule_tokens<'a>(rule: &'a Rule, tokens: &mut Vec<&'a MatchToken>) {
+        match rule {
+            Rule::Is { token, .. }
+            | Rule::Isnt { token, .. }
+            | Rule::While { token, .. }
+            | Rule::Until { token, .. }
+            | Rule::Maybe { token, .. } => tokens.push(token),
+            Rule::IsOneOf { tokens: one_of }
+            | Rule::UntilOneOf { tokens: one_of }
+            | Rule::MaybeOneOf {
+                is_one_of: one_of, ..
+            } => tokens.extend(one_of.iter().map(|one_of| &one_of.token)),
+            Rule::Precedence {
+                operand, levels, ..
+            } => {
+                tokens.push(operand);
+                for level in levels {
+                    tokens.extend(&level.operators);
+                }
+            }
+            Rule::Loop { .. } | Rule::Command { .. } | Rule::Debug { .. } => (),
+        }
+    }
+
+    /// Returns true if matching `a` means that `b` would match too
+    fn covers(a: &MatchToken, b: &MatchToken) -> bool {
+        match (a, b) {
+            (MatchToken::Any, _) => true,
+            (MatchToken::Token(TokenKinds::Text), MatchToken::Word(_)) => true,
+            _ => a == b,
+        }
+    }
+
+    /// Message that leaves a block of rules
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    enum Exit {
+        Return,
+        /// Leaves this many blocks
+        Break(usize),
+        Goto(String),
+    }
+
+    /// How a block of rules can end when it does not consume any tokens
+    #[derive(Debug, Clone, Default)]
+    struct Flow {
+        /// Continues after the block
+        complete: bool,
+        exits: BTreeSet<Exit>,
+    }
+
+    impl Flow {
+        fn complete() -> Flow {
+            Flow {
+                complete: true,
+                exits: BTreeSet::new(),
+            }
+        }
+
+        fn union(mut self, other: Flow) -> Flow {
+            self.complete |= other.complete;
+            self.exits.extend(other.exits);
+            self
+        }
+
+        fn is_empty(&self) -> bool {
+            !self.complete && self.exits.is_empty()
+        }
+    }
+
+    enum Found {
+        /// Loop that can repeat without consuming tokens
+        Loop(RulePath),
+        /// Node matched before anything is consumed
+        First(String, RulePath),
+        /// Token matched before anything is consumed
+        Terminal(MatchToken),
+    }
+
+    struct Analysis<'a> {
+        grammar: &'a Grammar,
+        /// Nodes that can match without consuming tokens
+        nullable: BTreeSet<String>,
+        /// Enumerators being expanded, enumerators can contain themselves
+        expanding: Vec<String>,
+        found: Vec<Found>,
+    }
+
+    impl<'a> Analysis<'a> {
+        fn new(grammar: &'a Grammar) -> Self {
+            let mut analysis = Analysis {
+                grammar,
+                nullable: BTreeSet::new(),
+                expanding: Vec::new(),
+                found: Vec::new(),
+            };
+            // Nodes that can match without consuming tokens, grows until nothing changes
+            loop {
+                let mut changed = false;
+                for node in grammar.nodes.values() {
+                    if analysis.nullable.contains(&node.name) {
+                        continue;
+                    }
+                    let flow = analysis.block(&node.rules, "rules", &RulePath::default(), false);
+                    if flow.complete || flow.exits.contains(&Exit::Return) {
+                        analysis.nullable.insert(node.name.clone());
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            analysis
+        }
+
+        /// Tokens each node can start with
+        fn first_sets(&mut self) -> BTreeMap<String, Vec<MatchToken>> {
+            let mut first = BTreeMap::new();
+            let mut edges = BTreeMap::new();
+            for node in self.grammar.nodes.values() {
+                self.found.clear();
+                self.block(&node.rules, "rules", &RulePath::default(), true);
+                let mut tokens = Vec::new();
+                let mut nodes = Vec::new();
+                for found in self.found.drain(..) {
+                    match found {
+                        Found::Terminal(token) => push_unique(&mut tokens, token),
+                        Found::First(name, _) => nodes.push(name),
+                        Found::Loop(_) => (),
+                    }
+                }
+                first.insert(node.name.clone(), tokens);
+                edges.insert(node.name.clone(), nodes);
+            }
+            // Nodes inherit the tokens of the nodes they start with
+            loop {
+                let mut changed = false;
+                for (name, nodes) in &edges {
+                    for target in nodes {
+                        let tokens = match first.get(target) {
+                            Some(tokens) if target != name => tokens.clone(),
+                            _ => continue,
+                        };
+                        let own = first.get_mut(name).unwrap();
+                        for token in tokens {
+                            if !own.contains(&token) {
+                                own.push(token);
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            first
+        }
+
+        /// Tokens an alternative can start with
+        fn first_of(
+            &mut self,
+            token: &MatchToken,
+            parameters: &[Parameters],
+            rules: &Rules,
+            first: &BTreeMap<String, Vec<MatchToken>>,
+        ) -> Vec<MatchToken> {
+            self.found.clear();
+            self.matched(
+                token,
+                parameters,
+                rules,
+                "rules",
+                &RulePath::default(),
+                true,
+            );
+            let mut tokens = Vec::new();
+            for found in self.found.drain(..) {
+                match found {
+                    Found::Terminal(token) => push_unique(&mut tokens, token),
+                    Found::First(name, _) => {
+                        for token in first.get(&name).into_iter().flatten() {
+                            push_unique(&mut tokens, token.clone());
+                        }
+                    }
+                    Found::Loop(_) => (),
+                }
+            }
+            tokens
+        }
+
+        /// Tokens that always match if the alternative starts with them
+        fn covered(&mut self, token: &MatchToken, tokens: &mut Vec<MatchToken>) {
+            match token {
+                MatchToken::Node(_) => (),
+                MatchToken::Enumerator(name) => {
+                    let enumerator = match self.grammar.enumerators.get(name) {
+                        Some(enumerator) if !self.expanding.contains(name) => enumerator,
+                        _ => return,
+                    };
+                    self.expanding.push(name.clone());
+                    for value in &enumerator.values {
+                        self.covered(value, tokens);
+                    }
+                    self.expanding.pop();
+                }
+                token => push_unique(tokens, token.clone()),
+            }
+        }
+
+        fn compare_alternatives(
+            &mut self,
+            alternatives: Vec<(&MatchToken, Vec<MatchToken>)>,
+            name: &str,
+            path: &RulePath,
+            result: &mut ValidationResult,
+        ) {
+            for (j, (token, tokens)) in alternatives.iter().enumerate() {
+                let later = Alternative {
+                    index: j,
+                    token: (*token).clone(),
+                };
+                for (i, (earlier, earlier_tokens)) in alternatives[..j].iter().enumerate() {
+                    let mut covered = Vec::new();
+                    self.covered(earlier, &mut covered);
+                    let by = Alternative {
+                        index: i,
+                        token: (*earlier).clone(),
+                    };
+                    let shadowed = !tokens.is_empty()
+                        && tokens.iter().all(|b| covered.iter().any(|a| covers(a, b)));
+                    if shadowed {
+                        result.warnings.push(ValidationWarning::at(
+                            ValidationWarnings::ShadowedAlternative {
+                                shadowed: later,
+                                by,
+                            },
+                            name,
+                            path,
+                        ));
+                        break;
+                    }
+                    let general = earlier_tokens
+                        .iter()
+                        .all(|a| tokens.iter().any(|b| covers(b, a)));
+                    if general {
+                        continue;
+                    }
+                    let shared: Vec<MatchToken> = earlier_tokens
+                        .iter()
+                        .flat_map(|a| tokens.iter().map(move |b| (a, b)))
+                        .filter_map(|(a, b)| match (covers(a, b), covers(b, a)) {
+                            (true, _) => Some(b.clone()),
+                            (_, true) => Some(a.clone()),
+                            _ => None,
+                        })
+                        .fold(Vec::new(), |mut shared, token| {
+                            push_unique(&mut shared, token);
+                            shared
+                        });
+                    if !shared.is_empty() {
+                        result.warnings.push(ValidationWarning::at(
+                            ValidationWarnings::AmbiguousAlternatives {
+                                first: by,
+                                second: later.clone(),
+                                tokens: shared,
+                            },
+                            name,
+                            path,
+                        ));
+                    }
+                }
+            }
+        }
+
+        /// Follows the paths through the rules that do not consume tokens
+        ///
+        /// `record` is true if nothing was consumed before the block
+        fn block(&mut self, rules: &Rules, field: &str, path: &RulePath, record: bool) -> Flow {
+            let labels: Vec<Option<&String>> = rules
+                .iter()
+                .map(|rule| match rule {
+                    Rule::Command {
+                        command: Commands::Label { name },
+                    } => Some(name),
+                    _ => None,
+                })
+                .collect();
+            // Rules that can be reached without consuming tokens
+            let mut reached = vec![false; rules.len() + 1];
+            reached[0] = true;
+            let mut flow = Flow::default();
+            let mut visited = vec![false; rules.len()];
+            let mut i = 0;
+            while i < rules.len() {
+                if !reached[i] || visited[i] {
+                    i += 1;
+                    continue;
+                }
+                visited[i] = true;
+                let rule_path = path.with(format!("{}[{}]", field, i));
+                let rule = self.rule(&rules[i], &rule_path, record);
+                if rule.complete {
+                    reached[i + 1] = true;
+                }
+                let mut next = i + 1;
+                for exit in rule.exits {
+                    match exit {
+                        Exit::Break(1) => flow.complete = true,
+                        Exit::Break(n) => {
+                            flow.exits.insert(Exit::Break(n - 1));
+                        }
+                        Exit::Goto(label) => {
+                            match labels.iter().position(|name| *name == Some(&label)) {
+                                Some(j) => {
+                                    reached[j] = true;
+                                    next = next.min(j);
+                                }
+                                None => {
+                                    flow.exits.insert(Exit::Goto(label));
+                                }
+                            }
+                        }
+                        Exit::Return => {
+                            flow.exits.insert(Exit::Return);
+                        }
+                    }
+                }
+                i = next;
+            }
+            // Rules only reached after consuming tokens are checked for loops
+            for (i, rule) in rules.iter().enumerate() {
+                if !visited[i] {
+                    self.rule(rule, &path.with(format!("{}[{}]", field, i)), false);
+                }
+            }
+            flow.complete |= reached[rules.len()];
+            flow
+        }
+
+        fn rule(&mut self, rule: &Rule, path: &RulePath, record: bool) -> Flow {
+            match rule {
+                Rule::Is {
+                    token,
+                    rules,
+                    parameters,
+                } => self.matched(token, parameters, rules, "Is.rules", path, record),
+                Rule::Until {
+                    token,
+                    rules,
+                    parameters,
+                } => {
+                    // Skips any tokens before the match
+                    if record {
+                        self.found.push(Found::Terminal(MatchToken::Any));
+                    }
+                    self.matched(token, parameters, rules, "Until.rules", path, record)
+                }
+                Rule::Isnt { rules, .. } => self.block(rules, "Isnt.rules", path, record),
+                Rule::IsOneOf { tokens } => self.one_of(tokens, "IsOneOf.tokens", path, record),
+                Rule::UntilOneOf { tokens } => {
+                    if record {
+                        self.found.push(Found::Terminal(MatchToken::Any));
+                    }
+                    self.one_of(tokens, "UntilOneOf.tokens", path, record)
+                }
+                Rule::Maybe {
+                    token,
+                    is,
+                    isnt,
+                    parameters,
+                } => self
+                    .matched(token, parameters, is, "Maybe.is", path, record)
+                    .union(self.block(isnt, "Maybe.isnt", path, record)),
+                Rule::MaybeOneOf { is_one_of, isnt } => self
+                    .one_of(is_one_of, "MaybeOneOf.is_one_of", path, record)
+                    .union(self.block(isnt, "MaybeOneOf.isnt", path, record)),
+                Rule::While {
+                    token,
+                    rules,
+                    parameters,
+                } => {
+                    let iteration =
+                        self.matched(token, parameters, rules, "While.rules", path, record);
+                    if iteration.complete {
+                        self.found.push(Found::Loop(path.clone()));
+                    }
+                    Flow {
+                        complete: true,
+                        exits: iteration.exits,
+                    }
+                }
+                Rule::Loop { rules } => {
+                    let iteration = self.block(rules, "Loop.rules", path, record);
+                    if iteration.complete {
+                        self.found.push(Found::Loop(path.clone()));
+                    }
+                    Flow {
+                        complete: false,
+                        exits: iteration.exits,
+                    }
+                }
+                Rule::Precedence {
+                    operand, levels, ..
+                } => {
+                    for (i, level) in levels.iter().enumerate() {
+                        if level.kind != OperatorKind::Prefix {
+                            continue;
+                        }
+                        let path = path.with(format!("Precedence.levels[{}]", i));
+                        for operator in &level.operators {
+                            self.token(operator, &path, record);
+                        }
+                    }
+                    Flow {
+                        complete: self.token(
+                            operand,
+                            &path.with("Precedence.operand".to_string()),
+                            record,
+                        ),
+                        exits: BTreeSet::new(),
+                    }
+                }
+                Rule::Command { command } => match command {
+                    Commands::Compare { rules, .. } => {
+                        Flow::complete().union(self.block(rules, "Compare.rules", path, record))
+                    }
+                    Commands::CompareLiteral { rules, .. } => {
+                        let rules = self.block(rules, "CompareLiteral.rules", path, record);
+                        Flow::complete().union(rules)
+                    }
+                    Commands::Error { .. } => Flow::default(),
+                    Commands::Goto { label } => Flow {
+                        complete: false,
+                        exits: BTreeSet::from([Exit::Goto(label.clone())]),
+                    },
+                    Commands::HardError { .. }
+                    | Commands::Label { .. }
+                    | Commands::Print { .. } => Flow::complete(),
+                },
+                Rule::Debug { .. } => Flow::complete(),
+            }
+        }
+
+        /// Token followed by its parameters and rules
+        fn matched(
+            &mut self,
+            token: &MatchToken,
+            parameters: &[Parameters],
+            rules: &Rules,
+            field: &str,
+            path: &RulePath,
+            record: bool,
+        ) -> Flow {
+            let nullable = self.token(token, path, record);
+            let flow = self.block(rules, field, path, record && nullable);
+            if !nullable || flow.is_empty() {
+                return Flow::default();
+            }
+            // Messages of the parameters are handled before the ones of the rules
+            let exit = parameters.iter().find_map(|parameter| match parameter {
+                Parameters::Return => Some(Exit::Return),
+                Parameters::Break(n) => Some(Exit::Break(*n)),
+                Parameters::Goto(label) => Some(Exit::Goto(label.clone())),
+                _ => None,
+            });
+            match exit {
+                Some(exit) => Flow {
+                    complete: false,
+                    exits: BTreeSet::from([exit]),
+                },
+                None => flow,
+            }
+        }
+
+        fn one_of(&mut self, tokens: &[OneOf], field: &str, path: &RulePath, record: bool) -> Flow {
+            let mut flow = Flow::default();
+            for (i, one_of) in tokens.iter().enumerate() {
+                let path = path.with(format!("{}[{}]", field, i));
+                flow = flow.union(self.matched(
+                    &one_of.token,
+                    &one_of.parameters,
+                    &one_of.rules,
+                    "rules",
+                    &path,
+                    record,
+                ));
+            }
+            flow
+        }
+
+        /// Returns true if the token can match without consuming anything
+        fn token(&mut self, token: &MatchToken, path: &RulePath, record: bool) -> bool {
+            match token {
+                MatchToken::Node(name) => {
+                    if record {
+                        let path = path.with(format!("<{}>", name));
+                        self.found.push(Found::First(name.clone(), path));
+                    }
+                    self.nullable.contains(name)
+                }
+                MatchToken::Enumerator(name) => {
+                    let enumerator = match self.grammar.enumerators.get(name) {
+                        Some(enumerator) if !self.expanding.contains(name) => enumerator,
+                        _ => return false,
+                    };
+                    self.expanding.push(name.clone());
+                    let path = path.with(format!("%{}", name));
+                    let mut nullable = false;
+                    for value in &enumerator.values {
+                        nullable |= self.token(value, &path, record);
+                    }
+                    self.expanding.pop();
+                    nullable
+                }
+                token => {
+                    if record {
+                        self.found.push(Found::Terminal(token.clone()));
+                    }
+                    false
+                }
+            }
+        }
+    }
+
+    fn push_unique(tokens: &mut Vec<MatchToken>, token: MatchToken) {
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+
+    /// Alternative of `IsOneOf`, `MaybeOneOf` or a value of an enumerator
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Alternative {
+        /// Position in the alternatives
+        pub index: usize,
+        pub token: MatchToken,
+    }
+
     pub struct ValidationResult {
         pub errors: Vec<ValidationError>,
         pub warnings: Vec<ValidationWarning>,
@@ -948,9 +2200,32 @@ pub mod validator {
     pub struct ValidationError {
         pub kind: ValidationErrors,
         pub node_name: String,
+        /// Rule that caused the error, empty for errors of the lexer
+        pub path: RulePath,
+        /// Line and column in the text of the grammar, see `dsl::SourceMap`
+        pub location: Option<TextLocation>,
     }
 
-    #[derive(Serialize, Deserialize, Debug, Clone)]
+    impl ValidationError {
+        /// Error of the rule at the path, the location is filled in from the source map
+        pub fn at(kind: ValidationErrors, node_name: &str, path: &RulePath) -> Self {
+            Self {
+                kind,
+                node_name: node_name.to_string(),
+                path: path.clone(),
+                location: None,
+            }
+        }
+    }
+
+    impl fmt::Display for ValidationError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:?}", self.kind)?;
+            write_location(f, &self.node_name, &self.path, &self.location)
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     pub enum ValidationErrors {
         NodeNotFound(String),
         EnumeratorNotFound(String),
@@ -962,19 +2237,68 @@ pub mod validator {
         DuplicateLabel(String),
         LabelNotFound(String),
         TokenCollision(String),
+        /// Pattern token can match an empty string
+        NullablePattern(String),
         CannotGoBackMoreThan{
             steps: usize,
             max: usize,
-        }
+        },
+        /// `While` or `Loop` that can repeat without consuming tokens
+        NullableLoop(RulePath),
+        /// Node that can match itself before consuming any tokens
+        LeftRecursion(RulePath),
+        /// Reference to a namespace no grammar was imported as, see `compose`
+        NamespaceNotFound(String),
+        /// Lexer mode entered from a mode that does not exist
+        ModeNotFound(String),
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct ValidationWarning {
         pub kind: ValidationWarnings,
         pub node_name: String,
+        /// Rule that caused the warning, empty for warnings of the lexer
+        pub path: RulePath,
+        /// Line and column in the text of the grammar, see `dsl::SourceMap`
+        pub location: Option<TextLocation>,
     }
 
-    #[derive(Serialize, Deserialize, Debug, Clone)]
+    impl ValidationWarning {
+        /// Warning of the rule at the path, the location is filled in from the source map
+        pub fn at(kind: ValidationWarnings, node_name: &str, path: &RulePath) -> Self {
+            Self {
+                kind,
+                node_name: node_name.to_string(),
+                path: path.clone(),
+                location: None,
+            }
+        }
+    }
+
+    impl fmt::Display for ValidationWarning {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:?}", self.kind)?;
+            write_location(f, &self.node_name, &self.path, &self.location)
+        }
+    }
+
+    fn write_location(
+        f: &mut fmt::Formatter,
+        node_name: &str,
+        path: &RulePath,
+        location: &Option<TextLocation>,
+    ) -> fmt::Result {
+        match path.0.is_empty() {
+            true => write!(f, " in {}", node_name)?,
+            false => write!(f, " at {}", path)?,
+        }
+        match location {
+            Some(location) => write!(f, ", {}:{}", location.line, location.column),
+            None => Ok(()),
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     pub enum ValidationWarnings {
         UnusedVariable(String),
         UsedDebug,
@@ -982,9 +2306,24 @@ pub mod validator {
         UsedDepricated(Depricated),
         UnusualToken(String, TokenErrors),
         UnusedLabel(String),
+        /// Node that is never matched when parsing from the entry node
+        UnreachableNode,
+        /// Alternatives that can start with the same tokens
+        ///
+        /// The second one is only tried if the first one does not match
+        AmbiguousAlternatives {
+            first: Alternative,
+            second: Alternative,
+            tokens: Vec<MatchToken>,
+        },
+        /// Alternative that never matches because an earlier one always matches first
+        ShadowedAlternative {
+            shadowed: Alternative,
+            by: Alternative,
+        },
     }
 
-    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     pub enum TokenErrors {
         NotAscii,
         ContainsWhitespace,
@@ -992,7 +2331,7 @@ pub mod validator {
         StartsNumeric,
     }
 
-    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     pub enum Depricated {
         /// The node is depricated
         ///
@@ -1002,11 +2341,21 @@ pub mod validator {
         Any,
     }
 
+    /// Node whose rules are validated and where the results go
+    pub struct ValidationContext<'a> {
+        pub node: &'a Node,
+        pub lexer: &'a Lexer,
+        pub laf: LostAndFound,
+        pub result: &'a mut ValidationResult,
+    }
+
     /// This is a structure that keeps track of things that are hard to find
     #[derive(Debug)]
     pub struct LostAndFound {
-        pub lost_labels: Vec<String>,
-        pub found_labels: Vec<String>,
+        /// Labels used by gotos and where
+        pub lost_labels: Vec<(String, RulePath)>,
+        /// Labels and where they are defined
+        pub found_labels: Vec<(String, RulePath)>,
         /// The maximum number of steps that can be taken back
         pub steps: usize,
     }
@@ -1021,20 +2370,26 @@ pub mod validator {
         }
 
         pub fn pass(&self, result: &mut ValidationResult, node_name: &str) {
-            for looking_for in &self.lost_labels {
-                if !self.found_labels.contains(looking_for) {
-                    result.errors.push(ValidationError {
-                        kind: ValidationErrors::LabelNotFound(looking_for.clone()),
-                        node_name: node_name.to_string(),
-                    });
+            for (looking_for, path) in &self.lost_labels {
+                if !self
+                    .found_labels
+                    .iter()
+                    .any(|(found, _)| found == looking_for)
+                {
+                    result.errors.push(ValidationError::at(
+                        ValidationErrors::LabelNotFound(looking_for.clone()),
+                        node_name,
+                        path,
+                    ));
                 }
             }
-            for found in &self.found_labels {
-                if !self.lost_labels.contains(found) {
-                    result.warnings.push(ValidationWarning {
-                        kind: ValidationWarnings::UnusedLabel(found.clone()),
-                        node_name: node_name.to_string(),
-                    });
+            for (found, path) in &self.found_labels {
+                if !self.lost_labels.iter().any(|(lost, _)| lost == found) {
+                    result.warnings.push(ValidationWarning::at(
+                        ValidationWarnings::UnusedLabel(found.clone()),
+                        node_name,
+                        path,
+                    ));
                 }
             }
         }
@@ -1,5 +1,9 @@
+use core::ops::Range;
+
 use serde::{Deserialize, Serialize};
 
+use crate::preprocessor::PreprocessorStep;
+
 // Choose between std and alloc
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {
@@ -12,6 +16,7 @@ cfg_if::cfg_if! {
         use alloc::vec::*;
         use alloc::vec;
         use alloc::format;
+        use alloc::boxed::Box;
         use core::fmt;
     }
 }
@@ -29,11 +34,14 @@ pub enum TokenKinds {
 
 impl TokenKinds {
     pub fn is_whitespace(&self) -> bool {
-        match self {
-            TokenKinds::Whitespace => true,
-            TokenKinds::Control(ControlTokenKind::Eol) => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            TokenKinds::Whitespace | TokenKinds::Control(ControlTokenKind::Eol)
+        )
+    }
+
+    pub fn is_eof(&self) -> bool {
+        matches!(self, TokenKinds::Control(ControlTokenKind::Eof))
     }
 }
 
@@ -64,14 +72,536 @@ impl fmt::Display for PreprocessorError {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "LexerData")]
 pub struct Lexer {
     /// Possible token kinds
     pub(crate) token_kinds: Vec<String>,
-    longest_token_size: usize,
+    /// Prefix tree of all the token kinds
+    ///
+    /// Kept in sync with `token_kinds` by `add_token`, it is not serialized
+    /// and is rebuilt from the token kinds after deserialization
+    #[serde(skip)]
+    pub(crate) trie: TokenTrie,
+    /// Tokens matched by patterns
+    #[serde(default)]
+    pub(crate) patterns: Vec<PatternToken>,
+    /// Modes the lexer can switch to, see `LexerMode`
+    #[serde(default)]
+    pub(crate) modes: Vec<LexerMode>,
+    /// Built-in preprocessor steps, executed before the preprocessor functions
+    #[serde(default)]
+    pub steps: Vec<PreprocessorStep>,
+    /// Custom preprocessor functions
+    ///
+    /// These can not be serialized, prefer `steps` for grammars that are saved
     #[serde(skip, default)]
     pub preprocessors: Vec<Preprocessor>,
 }
 
+/// Serialized fields of the lexer
+#[derive(Deserialize)]
+struct LexerData {
+    token_kinds: Vec<String>,
+    #[serde(default)]
+    patterns: Vec<PatternToken>,
+    #[serde(default)]
+    modes: Vec<LexerMode>,
+    #[serde(default)]
+    steps: Vec<PreprocessorStep>,
+}
+
+impl From<LexerData> for Lexer {
+    fn from(data: LexerData) -> Lexer {
+        let mut trie = TokenTrie::new();
+        for token in &data.token_kinds {
+            trie.insert(token);
+        }
+        Lexer {
+            token_kinds: data.token_kinds,
+            trie,
+            patterns: data.patterns,
+            modes: data.modes,
+            steps: data.steps,
+            preprocessors: Vec::new(),
+        }
+    }
+}
+
+/// Prefix tree (trie) over the bytes of the registered tokens
+///
+/// The lexer walks the trie from the current position to find the longest token
+/// that starts there, so the cost of matching only depends on the length of the
+/// longest token and not on the number of tokens.
+///
+/// Tokens are stored as UTF-8 bytes. Matching is always started on a char boundary,
+/// so a match of a valid UTF-8 token also ends on a char boundary.
+#[derive(Debug, Clone)]
+pub struct TokenTrie {
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TrieNode {
+    /// Outgoing edges sorted by byte
+    children: Vec<(u8, usize)>,
+    /// True if a token ends in this node
+    terminal: bool,
+}
+
+impl TokenTrie {
+    pub fn new() -> TokenTrie {
+        TokenTrie {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+
+    /// Inserts a token into the trie
+    ///
+    /// Inserting the same token twice has no effect
+    pub fn insert(&mut self, token: &str) {
+        let mut current = 0;
+        for byte in token.bytes() {
+            current = match self.nodes[current]
+                .children
+                .binary_search_by_key(&byte, |(b, _)| *b)
+            {
+                Ok(idx) => self.nodes[current].children[idx].1,
+                Err(idx) => {
+                    let next = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[current].children.insert(idx, (byte, next));
+                    next
+                }
+            };
+        }
+        // empty tokens are never matched
+        if current != 0 {
+            self.nodes[current].terminal = true;
+        }
+    }
+
+    /// Returns the length in bytes of the longest token that is a prefix of `bytes`
+    pub fn longest_match(&self, bytes: &[u8]) -> Option<usize> {
+        self.walk(bytes).0
+    }
+
+    /// Walks the trie along `bytes`
+    ///
+    /// Returns the length of the longest match and whether the walk ran out of bytes
+    /// while a longer token could still match
+    fn walk(&self, bytes: &[u8]) -> (Option<usize>, bool) {
+        let mut current = 0;
+        let mut longest = None;
+        for (i, byte) in bytes.iter().enumerate() {
+            current = match self.nodes[current]
+                .children
+                .binary_search_by_key(byte, |(b, _)| *b)
+            {
+                Ok(idx) => self.nodes[current].children[idx].1,
+                Err(_) => return (longest, false),
+            };
+            if self.nodes[current].terminal {
+                longest = Some(i + 1);
+            }
+        }
+        (longest, !self.nodes[current].children.is_empty())
+    }
+}
+
+impl Default for TokenTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A token that is matched by a pattern instead of a literal string
+///
+/// The lexer emits it as `TokenKinds::Complex(name)`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PatternToken {
+    /// Name of the complex token
+    pub name: String,
+    pub pattern: Pattern,
+}
+
+/// Name of the mode the lexer starts in, it lexes the tokens added to the lexer
+pub const DEFAULT_MODE: &str = "default";
+
+/// State of the lexer with its own set of tokens
+///
+/// The lexer enters the mode when it lexes the enter token in one of the `from` modes
+/// and goes back when it lexes the exit token. Inside the mode only the tokens of the mode
+/// and the enter tokens of the modes that can be entered from it are lexed,
+/// everything else is text. Whitespace and new lines are lexed as usual,
+/// an exit token `"\n"` leaves the mode at the end of the line.
+///
+/// ```rust
+/// # use ruparse::lexer::{Lexer, LexerMode};
+/// let mut lexer = Lexer::new();
+/// lexer.add_token("+".to_string());
+/// let mut string = LexerMode::new("string", "\"", "\"");
+/// string.add_token("\\\"".to_string());
+/// lexer.add_mode(string);
+///
+/// let tokens = lexer.lex_utf8("a + \"b+\\\"c\"").unwrap();
+/// assert_eq!(tokens.len(), 10);
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "LexerModeData")]
+pub struct LexerMode {
+    pub name: String,
+    /// Token that enters the mode, lexed as a token of the mode it is entered from
+    pub enter: String,
+    /// Token that leaves the mode, lexed as a token of the mode
+    pub exit: String,
+    /// Modes the mode can be entered from, only the default mode if not changed
+    pub from: Vec<String>,
+    /// Whether the tokens and patterns of the default mode are lexed in the mode as well
+    pub inherit: bool,
+    /// Tokens of the mode sorted, including the exit token
+    tokens: Vec<String>,
+    /// Rebuilt from the tokens after deserialization
+    #[serde(skip)]
+    trie: TokenTrie,
+    patterns: Vec<PatternToken>,
+}
+
+/// Serialized fields of a lexer mode
+#[derive(Deserialize)]
+struct LexerModeData {
+    name: String,
+    enter: String,
+    exit: String,
+    from: Vec<String>,
+    inherit: bool,
+    tokens: Vec<String>,
+    patterns: Vec<PatternToken>,
+}
+
+impl From<LexerModeData> for LexerMode {
+    fn from(data: LexerModeData) -> LexerMode {
+        let mut trie = TokenTrie::new();
+        for token in &data.tokens {
+            trie.insert(token);
+        }
+        LexerMode {
+            name: data.name,
+            enter: data.enter,
+            exit: data.exit,
+            from: data.from,
+            inherit: data.inherit,
+            tokens: data.tokens,
+            trie,
+            patterns: data.patterns,
+        }
+    }
+}
+
+impl LexerMode {
+    pub fn new(name: &str, enter: &str, exit: &str) -> LexerMode {
+        let mut mode = LexerMode {
+            name: name.to_string(),
+            enter: enter.to_string(),
+            exit: exit.to_string(),
+            from: vec![DEFAULT_MODE.to_string()],
+            inherit: false,
+            tokens: Vec::new(),
+            trie: TokenTrie::new(),
+            patterns: Vec::new(),
+        };
+        mode.add_token(exit.to_string());
+        mode
+    }
+
+    /// The mode can also be entered inside of itself, like nested block comments
+    pub fn nested(mut self) -> LexerMode {
+        self.from.push(self.name.clone());
+        self
+    }
+
+    /// The tokens of the default mode are lexed in the mode as well
+    pub fn inherit(mut self) -> LexerMode {
+        self.inherit = true;
+        self
+    }
+
+    pub fn add_token(&mut self, token: String) {
+        if let Err(idx) = self.tokens.binary_search(&token) {
+            self.trie.insert(&token);
+            self.tokens.insert(idx, token);
+        }
+    }
+
+    /// Adds a token that is matched by a pattern in the mode, see `Lexer::add_pattern`
+    pub fn add_pattern(&mut self, name: String, pattern: Pattern) {
+        self.patterns.push(PatternToken { name, pattern });
+    }
+
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    pub fn patterns(&self) -> &[PatternToken] {
+        &self.patterns
+    }
+}
+
+/// The trie is built from the tokens and is left out
+impl PartialEq for LexerMode {
+    fn eq(&self, other: &LexerMode) -> bool {
+        self.name == other.name
+            && self.enter == other.enter
+            && self.exit == other.exit
+            && self.from == other.from
+            && self.inherit == other.inherit
+            && self.tokens == other.tokens
+            && self.patterns == other.patterns
+    }
+}
+
+/// Pattern that matches a piece of text
+///
+/// Patterns are greedy and never backtrack: a repetition consumes as much as it can
+/// and a choice commits to the first alternative that matches.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// Matches a literal string
+    Literal(String),
+    /// Matches a single character of the class
+    Class(CharClass),
+    /// Matches the patterns one after another
+    Sequence(Vec<Pattern>),
+    /// Matches the first pattern that matches
+    Choice(Vec<Pattern>),
+    /// Matches the pattern repeatedly
+    Repeat {
+        pattern: Box<Pattern>,
+        /// Minimal number of repetitions
+        min: usize,
+        /// Maximal number of repetitions (unlimited if None)
+        max: Option<usize>,
+    },
+    /// Matches a region between two delimiters, e.g. strings or block comments
+    ///
+    /// The region is not matched if it is not closed
+    Delimited {
+        start: String,
+        end: String,
+        /// Character that makes the following character part of the region
+        escape: Option<char>,
+        /// Whether the region can contain new lines
+        multiline: bool,
+    },
+}
+
+/// A set of characters
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum CharClass {
+    /// Any character
+    Any,
+    Alphabetic,
+    Numeric,
+    Alphanumeric,
+    Whitespace,
+    /// One of the characters in the string
+    OneOf(String),
+    /// Characters in the inclusive range
+    Range(char, char),
+    /// Characters that are not in the class
+    Not(Box<CharClass>),
+    /// Characters that are in any of the classes
+    Union(Vec<CharClass>),
+}
+
+impl CharClass {
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            CharClass::Any => true,
+            CharClass::Alphabetic => c.is_alphabetic(),
+            CharClass::Numeric => c.is_numeric(),
+            CharClass::Alphanumeric => c.is_alphanumeric(),
+            CharClass::Whitespace => c.is_whitespace(),
+            CharClass::OneOf(chars) => chars.contains(c),
+            CharClass::Range(from, to) => (*from..=*to).contains(&c),
+            CharClass::Not(class) => !class.contains(c),
+            CharClass::Union(classes) => classes.iter().any(|class| class.contains(c)),
+        }
+    }
+}
+
+impl Pattern {
+    /// Returns the length in bytes of the match at the start of `text`
+    ///
+    /// `hit_end` is set if the end of the text was reached while matching,
+    /// meaning that a longer text could be matched differently
+    pub fn match_at(&self, text: &str, hit_end: &mut bool) -> Option<usize> {
+        self.match_from(text, 0, hit_end, &mut Vec::new())
+    }
+
+    /// Returns the length in bytes of the match at the byte index `at` of `text`
+    ///
+    /// `scans` remembers how far the delimited patterns looked for their end delimiter,
+    /// so a region that is not closed is scanned only once, see `DelimitedScan`
+    fn match_from(
+        &self,
+        text: &str,
+        at: usize,
+        hit_end: &mut bool,
+        scans: &mut Vec<DelimitedScan>,
+    ) -> Option<usize> {
+        match self {
+            Pattern::Literal(literal) => match_literal(literal, &text[at..], hit_end),
+            Pattern::Class(class) => match text[at..].chars().next() {
+                Some(c) if class.contains(c) => Some(c.len_utf8()),
+                Some(_) => None,
+                None => {
+                    *hit_end = true;
+                    None
+                }
+            },
+            Pattern::Sequence(patterns) => {
+                let mut len = 0;
+                for pattern in patterns {
+                    len += pattern.match_from(text, at + len, hit_end, scans)?;
+                }
+                Some(len)
+            }
+            Pattern::Choice(patterns) => patterns
+                .iter()
+                .find_map(|pattern| pattern.match_from(text, at, hit_end, scans)),
+            Pattern::Repeat { pattern, min, max } => {
+                let mut len = 0;
+                let mut count = 0;
+                while max.is_none_or(|max| count < max) {
+                    match pattern.match_from(text, at + len, hit_end, scans) {
+                        // an empty match would repeat forever
+                        Some(0) | None => break,
+                        Some(l) => len += l,
+                    }
+                    count += 1;
+                }
+                match count >= *min {
+                    true => Some(len),
+                    false => None,
+                }
+            }
+            Pattern::Delimited {
+                start,
+                end,
+                escape,
+                multiline,
+            } => {
+                let from = at + match_literal(start, &text[at..], hit_end)?;
+                let pattern = self as *const Pattern as usize;
+                // The region stops at the same characters as a region scanned before that
+                // contains its start. Only a start delimiter that ends with the escape
+                // character (or an empty one) can start in the middle of an escape.
+                let synced = |scan: &DelimitedScan| {
+                    scan.pattern == pattern
+                        && (scan.from == from
+                            || (scan.from < from
+                                && from <= scan.to
+                                && escape.is_none_or(|escape| {
+                                    !start.is_empty() && !start.ends_with(escape)
+                                })))
+                };
+                let (mut i, from) = match scans.iter().find(|scan| synced(scan)) {
+                    // the region was stopped by a new line, it can not be closed
+                    Some(scan) if !scan.hit_end => return None,
+                    Some(scan) => (scan.to, scan.from),
+                    None => (from, from),
+                };
+                // Index from which the scan continues when more text is added, the end
+                // delimiter could start at the first index that reached the end of the text
+                let mut resume = None;
+                let mut stop = |to: usize, hit_end: bool| {
+                    scans.retain(|scan| scan.pattern != pattern);
+                    scans.push(DelimitedScan {
+                        pattern,
+                        from,
+                        to,
+                        hit_end,
+                    });
+                };
+                loop {
+                    let mut hit = false;
+                    if let Some(end_len) = match_literal(end, &text[i..], &mut hit) {
+                        return Some(i + end_len - at);
+                    }
+                    if hit {
+                        *hit_end = true;
+                        resume.get_or_insert(i);
+                    }
+                    let c = match text[i..].chars().next() {
+                        Some(c) => c,
+                        None => {
+                            *hit_end = true;
+                            stop(resume.unwrap_or(i), true);
+                            return None;
+                        }
+                    };
+                    if c == '\n' && !multiline {
+                        stop(i, false);
+                        return None;
+                    }
+                    if Some(c) == *escape {
+                        match text[i + c.len_utf8()..].chars().next() {
+                            Some(escaped) => i += escaped.len_utf8(),
+                            None => {
+                                *hit_end = true;
+                                stop(resume.unwrap_or(i), true);
+                                return None;
+                            }
+                        }
+                    }
+                    i += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// Returns true if the pattern can match an empty string
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            Pattern::Literal(literal) => literal.is_empty(),
+            Pattern::Class(_) => false,
+            Pattern::Sequence(patterns) => patterns.iter().all(|p| p.is_nullable()),
+            Pattern::Choice(patterns) => patterns.iter().any(|p| p.is_nullable()),
+            Pattern::Repeat { pattern, min, .. } => *min == 0 || pattern.is_nullable(),
+            Pattern::Delimited { start, end, .. } => start.is_empty() && end.is_empty(),
+        }
+    }
+}
+
+fn match_literal(literal: &str, text: &str, hit_end: &mut bool) -> Option<usize> {
+    if text.starts_with(literal) {
+        return Some(literal.len());
+    }
+    if literal.starts_with(text) {
+        *hit_end = true;
+    }
+    None
+}
+
+/// How far a delimited pattern looked for the end of a region that is not closed
+///
+/// Every region that starts inside of the scanned part reaches the same characters, so
+/// it stops at the same place and the lexer does not scan the rest of the line
+/// (or the rest of the text) again at every start delimiter.
+#[derive(Debug, Clone)]
+struct DelimitedScan {
+    /// Address of the pattern, the patterns do not move while the lexer is borrowed
+    pattern: usize,
+    /// Byte index of the region after the start delimiter
+    from: usize,
+    /// Byte index where the scan stopped
+    to: usize,
+    /// Whether the scan stopped at the end of the text instead of a new line
+    ///
+    /// The scan continues from `to` if more text is added
+    hit_end: bool,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub struct Token {
     /// Index of the token in the text
@@ -84,6 +614,83 @@ pub struct Token {
     pub kind: TokenKinds,
 }
 
+impl Token {
+    /// Byte range of the token in the text
+    pub fn span(&self) -> Range<usize> {
+        self.index..self.index + self.len
+    }
+}
+
+/// A change of the text
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct TextEdit {
+    /// Byte range of the replaced text in the text before the edit
+    pub range: Range<usize>,
+    /// Text that replaces the range
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, replacement: String) -> TextEdit {
+        TextEdit { range, replacement }
+    }
+
+    /// Byte index in the text after the edit of a byte that was after the replaced range
+    pub fn moved(&self, index: usize) -> usize {
+        index - self.range.end + self.range.start + self.replacement.len()
+    }
+}
+
+/// Moves tokens that come after an edit to their new place in the text
+#[derive(Debug, Clone)]
+pub(crate) struct Shift {
+    /// Difference in bytes
+    bytes: isize,
+    /// Difference in lines
+    lines: isize,
+    /// Line on which the edit ends in the old text
+    ///
+    /// Only the tokens on this line move to a different column
+    line: usize,
+    /// Difference in columns on the line of the edit
+    columns: isize,
+}
+
+impl Shift {
+    /// Creates the shift that moves the old token to the place of the new one
+    pub(crate) fn between(old: &Token, new: &Token) -> Shift {
+        Shift {
+            bytes: new.index as isize - old.index as isize,
+            lines: new.location.line as isize - old.location.line as isize,
+            line: old.location.line,
+            columns: new.location.column as isize - old.location.column as isize,
+        }
+    }
+
+    pub(crate) fn index(&self, index: usize) -> usize {
+        index.saturating_add_signed(self.bytes)
+    }
+
+    pub(crate) fn location(&self, location: &TextLocation) -> TextLocation {
+        let column = match location.line == self.line {
+            true => location.column.saturating_add_signed(self.columns),
+            false => location.column,
+        };
+        TextLocation {
+            line: location.line.saturating_add_signed(self.lines),
+            column,
+        }
+    }
+
+    pub(crate) fn token(&self, token: &Token) -> Token {
+        Token {
+            index: self.index(token.index),
+            location: self.location(&token.location),
+            ..token.clone()
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub struct TextLocation {
     pub line: usize,
@@ -102,7 +709,10 @@ impl Lexer {
     pub fn new() -> Lexer {
         Lexer {
             token_kinds: Vec::new(),
-            longest_token_size: 0,
+            trie: TokenTrie::new(),
+            patterns: Vec::new(),
+            modes: Vec::new(),
+            steps: Vec::new(),
             preprocessors: Vec::new(),
         }
     }
@@ -110,24 +720,18 @@ impl Lexer {
     pub fn add_tokens(&mut self, tokens: &[String]) {
         self.token_kinds.reserve(tokens.len());
         for token in tokens {
-            if token.len() > self.longest_token_size {
-                self.longest_token_size = token.len();
-            }
             self.add_token(token.clone());
         }
     }
 
     pub fn add_token(&mut self, token: String) {
-        if token.len() > self.longest_token_size {
-            self.longest_token_size = token.len();
-        }
+        self.trie.insert(&token);
         // find the right place to insert the token
         //
         //  1. find the first token that is longer than the new token
         //  2. insert the new token before the first token that is longer
         //
         // This way the tokens are sorted by length
-        // This is important for optimization of the lexer
         let index = self
             .token_kinds
             .iter()
@@ -136,113 +740,341 @@ impl Lexer {
         self.token_kinds.insert(index, token);
     }
 
-    /// Lexer for UTF-8 text
-    pub fn lex_utf8(&self, text: &str) -> Result<Vec<Token>, PreprocessorError> {
-        let chars = text.char_indices().collect::<Vec<(usize, char)>>();
-        let len = chars.len();
-        // the allocation is a guess, but it should be close enough
-        let mut tokens = Vec::with_capacity(chars.len() / 4);
-        let mut i = 0;
-        let mut line = 0;
-        let mut column = 0;
-        'chars: while i < len {
-            // Take new line into account
-            if chars[i].1 == '\n' {
-                line += 1;
-                column = 0;
-                tokens.push(Token {
-                    index: chars[i].0,
-                    len: 1,
-                    location: TextLocation::new(line, column),
-                    kind: TokenKinds::Control(ControlTokenKind::Eol),
-                });
-                i += 1;
-                continue;
-            }
+    /// Adds a token that is matched by a pattern
+    ///
+    /// Matched text is emitted as `TokenKinds::Complex(name)`.
+    /// The longest match wins, literal tokens win over patterns of the same length
+    /// and patterns added earlier win over the later ones.
+    pub fn add_pattern(&mut self, name: String, pattern: Pattern) {
+        self.patterns.push(PatternToken { name, pattern });
+    }
 
-            // Match token kinds
-            'tokens: for token_kind in self.token_kinds.iter().rev() {
-                let tok_chars = token_kind.char_indices();
-                let tok_len = tok_chars.count();
-                if i + tok_len > len {
-                    // All the remaining tokens are longer than the remaining text
-                    //
-                    // This is a performance optimization
-                    continue;
-                }
-                for (j, (_, c)) in token_kind.char_indices().enumerate() {
-                    if c != chars[i + j].1 {
-                        continue 'tokens;
-                    }
+    /// Adds a mode the lexer switches to at its enter token
+    ///
+    /// The enter token is added to the modes it is entered from.
+    /// A mode added again with the same name replaces the old one.
+    pub fn add_mode(&mut self, mut mode: LexerMode) {
+        self.modes.retain(|existing| existing.name != mode.name);
+        for from in mode.from.clone() {
+            if from == DEFAULT_MODE {
+                if !self.token_kinds.contains(&mode.enter) {
+                    self.add_token(mode.enter.clone());
                 }
-                tokens.push(Token {
-                    index: chars[i].0,
-                    len: token_kind.len(),
-                    location: TextLocation::new(line, column),
-                    kind: TokenKinds::Token(token_kind.clone()),
-                });
-                i += tok_len;
-                column += tok_len;
-                continue 'chars;
+            } else if from == mode.name {
+                mode.add_token(mode.enter.clone());
+            } else if let Some(other) = self.modes.iter_mut().find(|other| other.name == from) {
+                other.add_token(mode.enter.clone());
+            }
+        }
+        for other in &self.modes {
+            if other.from.contains(&mode.name) {
+                mode.add_token(other.enter.clone());
             }
+        }
+        self.modes.push(mode);
+    }
 
-            // Match whitespace
-            if chars[i].1.is_whitespace() {
-                tokens.push(Token {
-                    index: chars[i].0,
-                    len: 1,
-                    location: TextLocation::new(line, column),
-                    kind: TokenKinds::Whitespace,
-                });
-                i += 1;
-                column += 1;
-                continue;
+    pub fn modes(&self) -> &[LexerMode] {
+        &self.modes
+    }
+
+    /// Whether the token is lexed in the default mode or in any of the modes
+    pub fn has_token(&self, token: &str) -> bool {
+        self.token_kinds.iter().any(|kind| kind == token)
+            || self.modes.iter().any(|mode| {
+                mode.tokens
+                    .binary_search_by(|t| t.as_str().cmp(token))
+                    .is_ok()
+            })
+    }
+
+    /// Mode the cursor is in, None for the default mode
+    fn mode(&self, cursor: &LexCursor) -> Option<&LexerMode> {
+        cursor.modes.last().map(|&idx| &self.modes[idx])
+    }
+
+    /// Leaves the mode at its exit token and enters the mode of an enter token
+    fn switch_mode(&self, cursor: &mut LexCursor, token: &str) {
+        let current = match self.mode(cursor) {
+            Some(mode) if mode.exit == token => {
+                cursor.modes.pop();
+                return;
             }
+            Some(mode) => mode.name.as_str(),
+            None => DEFAULT_MODE,
+        };
+        let entered = self
+            .modes
+            .iter()
+            .position(|mode| mode.enter == token && mode.from.iter().any(|from| from == current));
+        if let Some(idx) = entered {
+            cursor.modes.push(idx);
+        }
+    }
 
-            // Match text until next whitespace/token/eof
-            let mut j = 0;
-            let mut token_len = 0;
-            'word: while i + j < len {
-                if chars[i + j].1.is_whitespace() {
+    /// Finds the longest literal or pattern token of the mode at the byte index `at` of `text`
+    ///
+    /// Returns the length of the token and the name of the pattern (None for literal tokens)
+    /// and whether the end of the text was reached while matching
+    fn longest_token<'a>(
+        &'a self,
+        text: &str,
+        at: usize,
+        mode: Option<&'a LexerMode>,
+        scans: &mut Vec<DelimitedScan>,
+    ) -> (Option<(usize, Option<&'a str>)>, bool) {
+        let default = mode.is_none_or(|mode| mode.inherit);
+        let bytes = &text.as_bytes()[at..];
+        let (literal, mut hit_end) = match default {
+            true => self.trie.walk(bytes),
+            false => (None, false),
+        };
+        let (literal, hit_end_mode) = match mode {
+            Some(mode) => {
+                let (len, hit_end) = mode.trie.walk(bytes);
+                (literal.max(len), hit_end)
+            }
+            None => (literal, false),
+        };
+        hit_end |= hit_end_mode;
+        let mut longest = literal.map(|len| (len, None));
+        let patterns = mode
+            .into_iter()
+            .flat_map(|mode| &mode.patterns)
+            .chain(self.patterns.iter().filter(|_| default));
+        for PatternToken { name, pattern } in patterns {
+            let len = match pattern.match_from(text, at, &mut hit_end, scans) {
+                Some(len) if len > 0 => len,
+                _ => continue,
+            };
+            if longest.is_none_or(|(longest, _)| len > longest) {
+                longest = Some((len, Some(name.as_str())));
+            }
+        }
+        (longest, hit_end)
+    }
+
+    /// Lexer for UTF-8 text
+    pub fn lex_utf8(&self, text: &str) -> Result<Vec<Token>, PreprocessorError> {
+        // the allocation is a guess, but it should be close enough
+        let mut tokens = Vec::with_capacity(text.len() / 4);
+        let mut cursor = LexCursor::default();
+        while let Some(token) = self.lex_token(text, &mut cursor, true) {
+            tokens.push(token);
+        }
+        tokens.push(cursor.eof(text.len()));
+
+        self.preprocess(text, tokens)
+    }
+
+    /// Lexes the text again after an edit
+    ///
+    /// `tokens` are the tokens of the text before the edit and `text` is the text after it.
+    ///
+    /// Only the tokens around the edit are lexed and preprocessed again, the tokens after
+    /// them are moved to their new place. The lexed window includes a couple of unchanged
+    /// tokens on both sides of the edit, so the preprocessors can merge the edited tokens
+    /// with their neighbours. If preprocessing the window fails (for example because
+    /// the edit opened a string), the whole text is lexed again.
+    /// Lexers with modes always lex the whole text again, the tokens do not tell
+    /// which mode the lexer was in.
+    pub fn relex(
+        &self,
+        tokens: &[Token],
+        edit: &TextEdit,
+        text: &str,
+    ) -> Result<Vec<Token>, PreprocessorError> {
+        if !self.modes.is_empty() {
+            return self.lex_utf8(text);
+        }
+        // Number of unchanged tokens lexed again on each side of the edit
+        const CONTEXT: usize = 2;
+        let mut start = tokens
+            .partition_point(|token| token.index < edit.range.start)
+            .saturating_sub(CONTEXT);
+        // The window starts and ends at tokens that were not changed by the preprocessors,
+        // merged tokens can start in the middle of a region
+        let is_raw = |token: &Token, cursor: &LexCursor| {
+            self.lex_token(text, &mut cursor.clone(), true)
+                .is_some_and(|raw| raw.kind == token.kind && raw.len == token.len)
+        };
+        let mut cursor = loop {
+            let token = match tokens.get(start) {
+                Some(token) if start > 0 => token,
+                _ => break LexCursor::default(),
+            };
+            let cursor = LexCursor {
+                index: token.index,
+                line: token.location.line - 1,
+                column: token.location.column - 1,
+                ..Default::default()
+            };
+            if is_raw(token, &cursor) {
+                break cursor;
+            }
+            start -= 1;
+        };
+        let mut end = tokens.partition_point(|token| token.index < edit.range.end);
+        let mut raw = Vec::new();
+        // Number of raw tokens in a row that start where an old token starts
+        let mut synced = 0;
+        // Lex until the cursor is back in sync with the old tokens
+        loop {
+            while end < tokens.len() && edit.moved(tokens[end].index) < cursor.index {
+                end += 1;
+            }
+            let aligned = end < tokens.len()
+                && edit.moved(tokens[end].index) == cursor.index
+                && (tokens[end].kind.is_eof() || is_raw(&tokens[end], &cursor));
+            if aligned {
+                if synced >= CONTEXT || tokens[end].kind.is_eof() {
                     break;
                 }
-                token_len += chars[i + j].1.len_utf8();
-                j += 1;
-                for token_kind in &self.token_kinds {
-                    let start = i + j;
-                    let tok_len = token_kind.chars().count();
-                    let end = if i + j + tok_len < len {
-                        i + j + tok_len
-                    } else {
-                        break 'word;
-                    };
-                    let token = &text[chars[start].0..chars[end].0];
-                    if token == *token_kind {
-                        break 'word;
+                synced += 1;
+            }
+            match self.lex_token(text, &mut cursor, true) {
+                Some(token) => {
+                    if !aligned
+                        && !matches!(
+                            token.kind,
+                            TokenKinds::Whitespace | TokenKinds::Control(ControlTokenKind::Eol)
+                        )
+                    {
+                        synced = 0;
                     }
+                    raw.push(token)
+                }
+                None => {
+                    raw.push(cursor.eof(text.len()));
+                    end = tokens.len();
+                    break;
                 }
             }
-            tokens.push(Token {
-                index: chars[i].0,
-                len: token_len,
-                location: TextLocation::new(line, column),
-                kind: TokenKinds::Text,
+        }
+        let window = match self.preprocess(text, raw) {
+            Ok(window) => window,
+            Err(_) => return self.lex_utf8(text),
+        };
+
+        let mut result = Vec::with_capacity(start + window.len() + tokens.len() - end);
+        result.extend_from_slice(&tokens[..start]);
+        result.extend(window);
+        if let Some(first) = tokens.get(end) {
+            let moved = Token {
+                index: cursor.index,
+                location: TextLocation::new(cursor.line, cursor.column),
+                ..first.clone()
+            };
+            let shift = Shift::between(first, &moved);
+            result.extend(tokens[end..].iter().map(|token| shift.token(token)));
+        }
+        Ok(result)
+    }
+
+    /// Lexes a single token starting at the cursor
+    ///
+    /// Returns None at the end of the text
+    ///
+    /// If `complete` is false, the text is only a prefix of the input and None is also
+    /// returned when the token could continue past the end of the text.
+    /// The cursor is left at the start of that token so that lexing can resume
+    /// once more text is available, it remembers how far the token was scanned.
+    fn lex_token(&self, text: &str, cursor: &mut LexCursor, complete: bool) -> Option<Token> {
+        let bytes = text.as_bytes();
+        let len = bytes.len();
+        let i = cursor.index;
+        if i >= len {
+            return None;
+        }
+        let c = next_char(text, i);
+        // Take new line into account
+        if c == '\n' {
+            cursor.index += 1;
+            cursor.line += 1;
+            cursor.column = 0;
+            self.switch_mode(cursor, "\n");
+            return Some(Token {
+                index: i,
+                len: 1,
+                location: TextLocation::new(cursor.line, cursor.column),
+                kind: TokenKinds::Control(ControlTokenKind::Eol),
             });
-            column += j;
-            i += j;
         }
-        tokens.push(Token {
-            index: i,
-            len: 0,
-            location: TextLocation::new(line, column),
-            kind: TokenKinds::Control(ControlTokenKind::Eof),
-        });
 
-        for preprocessor in &self.preprocessors {
-            tokens = preprocessor(text, tokens)?;
+        // Match the longest token
+        let mode = self.mode(cursor);
+        match self.longest_token(text, i, mode, &mut cursor.scans) {
+            (_, true) if !complete => return None,
+            (Some((tok_len, pattern)), _) => {
+                let token = &text[i..i + tok_len];
+                let location = TextLocation::new(cursor.line, cursor.column);
+                let kind = match pattern {
+                    Some(name) => TokenKinds::Complex(name.to_string()),
+                    None => TokenKinds::Token(token.to_string()),
+                };
+                cursor.advance(token);
+                if pattern.is_none() {
+                    self.switch_mode(cursor, token);
+                }
+                return Some(Token {
+                    index: i,
+                    len: tok_len,
+                    location,
+                    kind,
+                });
+            }
+            (None, _) => (),
         }
 
-        Ok(tokens)
+        // Match whitespace
+        if c.is_whitespace() {
+            let location = TextLocation::new(cursor.line, cursor.column);
+            cursor.index += c.len_utf8();
+            cursor.column += 1;
+            return Some(Token {
+                index: i,
+                len: c.len_utf8(),
+                location,
+                kind: TokenKinds::Whitespace,
+            });
+        }
+
+        // Match text until next whitespace/token/eof,
+        // continuing where the text was cut off by the end of the last chunk
+        let (mut j, mut chars) = match cursor.partial_text.take() {
+            Some((len, chars)) => (i + len, chars),
+            None => (i + c.len_utf8(), 1),
+        };
+        while j < len {
+            let c = next_char(text, j);
+            if c.is_whitespace() {
+                break;
+            }
+            match self.longest_token(text, j, mode, &mut cursor.scans) {
+                // the text ends here even if the token could still get longer
+                (Some(_), _) => break,
+                (None, true) if !complete => {
+                    cursor.partial_text = Some((j - i, chars));
+                    return None;
+                }
+                (None, _) => (),
+            }
+            j += c.len_utf8();
+            chars += 1;
+        }
+        if j >= len && !complete {
+            cursor.partial_text = Some((j - i, chars));
+            return None;
+        }
+        let location = TextLocation::new(cursor.line, cursor.column);
+        cursor.index = j;
+        cursor.column += chars;
+        Some(Token {
+            index: i,
+            len: j - i,
+            location,
+            kind: TokenKinds::Text,
+        })
     }
 
     /// Lexer for ascii-only text
@@ -250,102 +1082,94 @@ impl Lexer {
         let chars = text.as_bytes();
         // the allocation is a guess, but it should be close enough
         let mut tokens = Vec::with_capacity(chars.len() / 4);
-        let mut i = 0;
-        let mut line = 0;
-        let mut column = 0;
+        let mut cursor = LexCursor::default();
         let len = chars.len();
-        'chars: while i < len {
+        while cursor.index < len {
+            let i = cursor.index;
             // Take new line into account
-            if chars[i] == '\n' as u8 {
-                line += 1;
-                column = 0;
-                i += 1;
+            if chars[i] == b'\n' {
+                cursor.advance("\n");
                 tokens.push(Token {
                     index: i,
                     len: 1,
-                    location: TextLocation::new(line, column),
+                    location: TextLocation::new(cursor.line, cursor.column),
                     kind: TokenKinds::Control(ControlTokenKind::Eol),
                 });
+                self.switch_mode(&mut cursor, "\n");
                 continue;
             }
 
-            for token_kind in self.token_kinds.iter().rev() {
-                let tok_len = token_kind.len();
-                if i + tok_len > len {
-                    // All the remaining tokens are longer than the remaining text
-                    //
-                    // This is a performance optimization
-                    continue;
-                }
-                let token = &chars[i..i + tok_len];
-                if token == token_kind.as_bytes() {
-                    tokens.push(Token {
-                        index: i,
-                        len: tok_len,
-                        location: TextLocation::new(line, column),
-                        kind: TokenKinds::Token(token_kind.clone()),
-                    });
-                    i += tok_len;
-                    column += tok_len;
-                    continue 'chars;
+            // Match the longest token
+            let mode = self.mode(&cursor);
+            if let (Some((tok_len, pattern)), _) =
+                self.longest_token(text, i, mode, &mut cursor.scans)
+            {
+                let token = &text[i..i + tok_len];
+                tokens.push(Token {
+                    index: i,
+                    len: tok_len,
+                    location: TextLocation::new(cursor.line, cursor.column),
+                    kind: match pattern {
+                        Some(name) => TokenKinds::Complex(name.to_string()),
+                        None => TokenKinds::Token(token.to_string()),
+                    },
+                });
+                cursor.advance(token);
+                if pattern.is_none() {
+                    self.switch_mode(&mut cursor, token);
                 }
+                continue;
             }
 
             // Match whitespace
-            if (chars[i] as char).is_whitespace() {
+            if chars[i].is_ascii_whitespace() {
                 tokens.push(Token {
                     index: i,
                     len: 1,
-                    location: TextLocation::new(line, column),
+                    location: TextLocation::new(cursor.line, cursor.column),
                     kind: TokenKinds::Whitespace,
                 });
-                i += 1;
-                column += 1;
+                cursor.advance(&text[i..i + 1]);
                 continue;
             }
 
             // Match text until next whitespace/token/eof
-            let mut j = 0;
-            'word: while i + j < len {
-                if (chars[i + j] as char).is_whitespace() {
-                    break;
-                }
+            let mut j = 1;
+            while i + j < len
+                && !chars[i + j].is_ascii_whitespace()
+                && self
+                    .longest_token(text, i + j, mode, &mut cursor.scans)
+                    .0
+                    .is_none()
+            {
                 j += 1;
-                for token_kind in &self.token_kinds {
-                    let start = i + j;
-                    let tok_len = token_kind.len();
-                    let end = if i + j + tok_len <= len {
-                        i + j + tok_len
-                    } else {
-                        break 'word;
-                    };
-                    let token = &text[start..end];
-                    if token == *token_kind {
-                        break 'word;
-                    }
-                }
             }
             tokens.push(Token {
                 index: i,
                 len: j,
-                location: TextLocation::new(line, column),
+                location: TextLocation::new(cursor.line, cursor.column),
                 kind: TokenKinds::Text,
             });
-            column += j;
-            i += j;
+            cursor.advance(&text[i..i + j]);
         }
 
-        tokens.push(Token {
-            index: i,
-            len: 0,
-            location: TextLocation::new(line, column),
-            kind: TokenKinds::Control(ControlTokenKind::Eof),
-        });
+        tokens.push(cursor.eof(len));
 
+        self.preprocess(text, tokens)
+    }
+
+    /// Runs all the preprocessors on the tokens
+    pub fn preprocess(
+        &self,
+        text: &str,
+        mut tokens: Vec<Token>,
+    ) -> Result<Vec<Token>, PreprocessorError> {
+        for step in &self.steps {
+            tokens = step.apply(text, tokens)?;
+        }
         for preprocessor in &self.preprocessors {
             tokens = preprocessor(text, tokens)?;
         }
-
         Ok(tokens)
     }
 
@@ -375,3 +1199,241 @@ impl Lexer {
         &text[token.index..token.index + token.len]
     }
 }
+
+/// Position of the lexer in the text
+#[derive(Debug, Clone, Default)]
+struct LexCursor {
+    /// Byte index of the next token
+    index: usize,
+    line: usize,
+    column: usize,
+    /// Indexes of the entered modes, the last one is the current mode
+    modes: Vec<usize>,
+    /// Regions of the delimited patterns that were not closed
+    scans: Vec<DelimitedScan>,
+    /// Length in bytes and in chars of the text token at the cursor lexed so far,
+    /// if it was cut off by the end of the text
+    partial_text: Option<(usize, usize)>,
+}
+
+impl LexCursor {
+    /// Moves the cursor past the token
+    fn advance(&mut self, token: &str) {
+        self.index += token.len();
+        for c in token.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
+        }
+    }
+
+    /// Moves the cursor to the start of the text after the text before it was dropped
+    fn drop_lexed(&mut self) {
+        let dropped = self.index;
+        self.index = 0;
+        self.scans.retain_mut(|scan| {
+            scan.from = match scan.from.checked_sub(dropped) {
+                Some(from) => from,
+                None => return false,
+            };
+            scan.to -= dropped;
+            true
+        });
+    }
+
+    fn eof(&self, index: usize) -> Token {
+        Token {
+            index,
+            len: 0,
+            location: TextLocation::new(self.line, self.column),
+            kind: TokenKinds::Control(ControlTokenKind::Eof),
+        }
+    }
+}
+
+/// Lexer for UTF-8 text that arrives in chunks
+///
+/// Tokens are emitted as soon as they are complete. A token that is split across
+/// chunks is emitted after the chunk containing its end has been pushed.
+/// Token indexes and locations are relative to the start of the whole input.
+///
+/// ```rust
+/// # use ruparse::lexer::{ChunkLexer, Lexer};
+/// let mut lexer = Lexer::new();
+/// lexer.add_token("+=".to_string());
+///
+/// let mut chunks = ChunkLexer::new(&lexer);
+/// let mut tokens = chunks.push("a +");
+/// tokens.extend(chunks.push("= b"));
+/// tokens.extend(chunks.finish());
+/// assert_eq!(tokens.len(), 6);
+/// ```
+pub struct ChunkLexer<'a> {
+    lexer: &'a Lexer,
+    /// Text that has not been consumed yet (or the whole text if it is retained)
+    buffer: String,
+    /// Byte index of the start of the buffer in the whole input
+    offset: usize,
+    cursor: LexCursor,
+    /// Whether the already lexed text is kept for the preprocessors
+    retain_text: bool,
+}
+
+impl<'a> ChunkLexer<'a> {
+    pub fn new(lexer: &'a Lexer) -> ChunkLexer<'a> {
+        ChunkLexer {
+            lexer,
+            buffer: String::new(),
+            offset: 0,
+            cursor: LexCursor::default(),
+            retain_text: false,
+        }
+    }
+
+    /// Keeps the whole input in memory so that the preprocessors can be run on the tokens
+    pub fn retain_text(mut self) -> ChunkLexer<'a> {
+        self.retain_text = true;
+        self
+    }
+
+    /// Adds a chunk of text and returns all the tokens that were completed by it
+    pub fn push(&mut self, chunk: &str) -> Vec<Token> {
+        self.buffer.push_str(chunk);
+        self.lex(false)
+    }
+
+    /// Lexes the rest of the input and returns the remaining tokens including the Eof token
+    pub fn finish(&mut self) -> Vec<Token> {
+        let mut tokens = self.lex(true);
+        tokens.push(self.cursor.eof(self.offset + self.buffer.len()));
+        tokens
+    }
+
+    /// Returns the whole input if the text is retained
+    pub fn text(&self) -> Option<&str> {
+        match self.retain_text {
+            true => Some(&self.buffer),
+            false => None,
+        }
+    }
+
+    /// Runs the preprocessors of the lexer on the tokens
+    ///
+    /// Requires the text to be retained
+    pub fn preprocess(&self, tokens: Vec<Token>) -> Result<Vec<Token>, PreprocessorError> {
+        match self.text() {
+            Some(text) => self.lexer.preprocess(text, tokens),
+            None => Err(PreprocessorError {
+                message: "Text must be retained to run preprocessors".to_string(),
+                location: TextLocation::new(self.cursor.line, self.cursor.column),
+                len: 0,
+            }),
+        }
+    }
+
+    fn lex(&mut self, complete: bool) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        while let Some(mut token) = self
+            .lexer
+            .lex_token(&self.buffer, &mut self.cursor, complete)
+        {
+            token.index += self.offset;
+            tokens.push(token);
+        }
+        // drop the consumed text
+        if !self.retain_text {
+            self.buffer.drain(..self.cursor.index);
+            self.offset += self.cursor.index;
+            self.cursor.drop_lexed();
+        }
+        tokens
+    }
+}
+
+#[cfg(feature = "std")]
+impl Lexer {
+    /// Lexes UTF-8 text from a reader
+    ///
+    /// The text is read in chunks and never held in memory as a whole.
+    /// Preprocessors are not run on the tokens, use `ChunkLexer::retain_text` for that.
+    pub fn lex_reader<R: std::io::BufRead>(&self, reader: R) -> ReaderTokens<'_, R> {
+        ReaderTokens {
+            chunks: ChunkLexer::new(self),
+            reader,
+            pending: std::collections::VecDeque::new(),
+            leftover: Vec::new(),
+            done: false,
+        }
+    }
+}
+
+/// Iterator of tokens read from a `BufRead`
+///
+/// Created by `Lexer::lex_reader`
+#[cfg(feature = "std")]
+pub struct ReaderTokens<'a, R: std::io::BufRead> {
+    chunks: ChunkLexer<'a>,
+    reader: R,
+    pending: std::collections::VecDeque<Token>,
+    /// Bytes of a char that was split between two reads
+    leftover: Vec<u8>,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::BufRead> ReaderTokens<'_, R> {
+    /// Reads the next chunk and lexes it
+    fn fill(&mut self) -> std::io::Result<()> {
+        let read = {
+            let buf = self.reader.fill_buf()?;
+            self.leftover.extend_from_slice(buf);
+            buf.len()
+        };
+        self.reader.consume(read);
+        if read == 0 {
+            if !self.leftover.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "stream did not end with a valid UTF-8 character",
+                ));
+            }
+            self.pending.extend(self.chunks.finish());
+            self.done = true;
+            return Ok(());
+        }
+        let valid = match std::str::from_utf8(&self.leftover) {
+            Ok(text) => text.len(),
+            // an incomplete char at the end is completed by the next read
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+        };
+        let text = std::str::from_utf8(&self.leftover[..valid]).unwrap();
+        self.pending.extend(self.chunks.push(text));
+        self.leftover.drain(..valid);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::BufRead> Iterator for ReaderTokens<'_, R> {
+    type Item = std::io::Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.done {
+            if let Err(err) = self.fill() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// Returns the char starting at the byte index `i`
+fn next_char(text: &str, i: usize) -> char {
+    // `i` is always on a char boundary and less than the length of the text
+    text[i..].chars().next().unwrap()
+}
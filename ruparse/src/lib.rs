@@ -1,9 +1,18 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 pub mod api;
+pub mod codegen;
+pub mod compose;
+pub mod cst;
+pub mod diagnostic;
+pub mod dsl;
 pub mod grammar;
 pub mod lexer;
+pub mod observer;
 pub mod parser;
+pub mod preprocessor;
+pub mod railroad;
+pub mod visit;
 
 // Choose between std and alloc
 cfg_if::cfg_if! {
@@ -17,6 +26,7 @@ cfg_if::cfg_if! {
         pub use alloc::string::*;
         pub use alloc::vec::*;
         use alloc::vec;
+        use alloc::boxed::Box;
 
         pub type Map<K, V> = alloc::collections::BTreeMap<K, V>;
     }
@@ -42,13 +52,116 @@ impl Parser {
         }
     }
 
-    pub fn parse(
+    /// Validates the grammar and checks that all nodes can be reached from the entry node
+    pub fn validate(&self) -> grammar::validator::ValidationResult {
+        let mut result = self.grammar.validate(&self.lexer);
+        self.grammar
+            .validate_reachability(&self.parser.entry, &mut result);
+        result
+    }
+
+    /// Adds the grammar of the other parser under the namespace and its tokens to the lexer
+    ///
+    /// The entry node and the settings of this parser are kept,
+    /// see `Grammar::import_as` and `Lexer::merge`
+    pub fn import_as(&mut self, other: &Parser, namespace: &str) -> Result<(), compose::MergeError> {
+        let grammar = other.grammar.namespaced(namespace);
+        let mut conflicts = self.grammar.conflicts(&grammar);
+        conflicts.extend(self.lexer.conflicts(&other.lexer));
+        if !conflicts.is_empty() {
+            return Err(compose::MergeError { conflicts });
+        }
+        self.grammar.merge(&grammar)?;
+        self.lexer.merge(&other.lexer)
+    }
+
+    pub fn parse<'src>(
         &self,
         tokens: &Vec<lexer::Token>,
-        text: &str,
-    ) -> Result<parser::ParseResult, parser::ParseError> {
+        text: &'src str,
+    ) -> Result<parser::ParseResult<'src>, parser::ParseError> {
         self.parser.parse(&self.grammar, &self.lexer, text, tokens)
     }
+
+    /// Parses the tokens and tells the observer about every node, rule and parameter
+    ///
+    /// Use `observer::Profiler` to find out where the parser spends its time
+    pub fn parse_observed<'src>(
+        &self,
+        tokens: &Vec<lexer::Token>,
+        text: &'src str,
+        observer: &mut dyn observer::ParseObserver,
+    ) -> Result<parser::ParseResult<'src>, parser::ParseError> {
+        self.parser
+            .parse_observed(&self.grammar, &self.lexer, text, tokens, observer)
+    }
+
+    /// Parses the tokens and collects all the errors instead of stopping at the first one
+    ///
+    /// Nodes recover from hard errors by skipping to their synchronization tokens
+    pub fn parse_recovering<'src>(
+        &self,
+        tokens: &Vec<lexer::Token>,
+        text: &'src str,
+    ) -> parser::RecoveryResult<'src> {
+        self.parser
+            .parse_recovering(&self.grammar, &self.lexer, text, tokens)
+    }
+
+    /// Lexes and parses the text again after an edit
+    ///
+    /// `previous` and `old_tokens` are the results for the text before the edit
+    /// and `text` is the text after it. Only the tokens around the edit are lexed again
+    /// and the results of nodes that were not affected are reused if the previous
+    /// result comes from an incremental parser (see `parser::Parser::incremental`).
+    ///
+    /// Returns the new tokens together with the new tree, both are needed for the next edit.
+    /// The tree borrows the text, `into_owned` keeps it while the text is edited
+    pub fn reparse<'src>(
+        &self,
+        previous: &parser::ParseResult<'_>,
+        old_tokens: &[lexer::Token],
+        edit: &lexer::TextEdit,
+        text: &'src str,
+    ) -> Result<(Vec<lexer::Token>, parser::ParseResult<'src>), ReparseError> {
+        let tokens = self
+            .lexer
+            .relex(old_tokens, edit, text)
+            .map_err(ReparseError::Lexer)?;
+        let memo = previous.reusable(old_tokens, &tokens, edit);
+        let result = self
+            .parser
+            .reparse(&self.grammar, &self.lexer, text, &tokens, memo)
+            .map_err(|err| ReparseError::Parser(Box::new(err)))?;
+        Ok((tokens, result))
+    }
+}
+
+/// Error of lexing or parsing the text again after an edit
+#[derive(Debug)]
+pub enum ReparseError {
+    Lexer(lexer::PreprocessorError),
+    /// Boxed because parse errors are much larger than lexer errors
+    Parser(Box<parser::ParseError>),
+}
+
+impl core::fmt::Display for ReparseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ReparseError::Lexer(err) => write!(f, "{}", err),
+            ReparseError::Parser(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ReparseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReparseError::Lexer(_) => None,
+            ReparseError::Parser(err) => Some(err.as_ref()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -80,165 +193,2172 @@ mod tests {
             "Function".to_string(),
         ]);
 
-        // Parse the text
-        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        // Parse the text
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        assert_eq!(tokens.len(), 21);
+    }
+
+    #[test]
+    fn stringify() {
+        let mut parser = Parser::new();
+        let txt = "Functiond\t 1 +\n 2 * 3 - 4 /= 5";
+        // Tokens that will be recognized by the lexer
+        //
+        // White space is ignored by default
+        //
+        // Everything else is a text token
+        parser.lexer.add_tokens(&[
+            "+".to_string(),
+            "-".to_string(),
+            "*".to_string(),
+            "/=".to_string(),
+            "Function".to_string(),
+        ]);
+
+        // Parse the text
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        assert_eq!(parser.lexer.stringify_slice(&tokens, txt), txt);
+        assert_eq!(parser.lexer.stringify_slice(&tokens[0..1], txt), "Function");
+        assert_eq!(parser.lexer.stringify_slice(&tokens[1..5], txt), "d\t 1");
+    }
+
+    #[test]
+    fn unfinished_token() {
+        let mut parser = Parser::new();
+        let txt = "fun";
+        parser.lexer.add_token("function".to_string());
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        assert_eq!(tokens[0].kind, TokenKinds::Text);
+    }
+
+    #[test]
+    fn longest_match() {
+        let mut parser = Parser::new();
+        let txt = "a/=b//c/ \n/";
+        parser.lexer.add_tokens(&["/".to_string(), "//".to_string(), "/=".to_string()]);
+
+        for tokens in [
+            parser.lexer.lex_utf8(txt).unwrap(),
+            parser.lexer.lex_ascii(txt).unwrap(),
+        ] {
+            let kinds: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+            assert_eq!(
+                kinds,
+                vec![
+                    TokenKinds::Text,
+                    TokenKinds::Token("/=".to_string()),
+                    TokenKinds::Text,
+                    TokenKinds::Token("//".to_string()),
+                    TokenKinds::Text,
+                    TokenKinds::Token("/".to_string()),
+                    TokenKinds::Whitespace,
+                    TokenKinds::Control(lexer::ControlTokenKind::Eol),
+                    TokenKinds::Token("/".to_string()),
+                    TokenKinds::Control(lexer::ControlTokenKind::Eof),
+                ]
+            );
+            assert_eq!(tokens[8].location, lexer::TextLocation::new(1, 0));
+            assert_eq!(tokens[9].index, txt.len());
+        }
+
+        // the token set survives serialization
+        let json = serde_json::to_string(&parser.lexer).unwrap();
+        let lexer: lexer::Lexer = serde_json::from_str(&json).unwrap();
+        assert_eq!(lexer.lex_utf8(txt).unwrap(), parser.lexer.lex_utf8(txt).unwrap());
+        assert!(!json.contains("trie"));
+
+        // lexers saved before the trie existed rebuild it from their tokens
+        let json = r#"{"token_kinds":["/","//","/="],"longest_token_size":2}"#;
+        let lexer: lexer::Lexer = serde_json::from_str(json).unwrap();
+        assert_eq!(lexer.lex_utf8(txt).unwrap(), parser.lexer.lex_utf8(txt).unwrap());
+    }
+
+    #[test]
+    fn chunked_lexing() {
+        let mut lexer = lexer::Lexer::new();
+        lexer.add_tokens(&["+".to_string(), "+=".to_string(), "function".to_string()]);
+        let txt = "function ťřída+=1\n  štring+ functio";
+        let expected = lexer.lex_utf8(txt).unwrap();
+
+        // split the text at every char boundary
+        for (split, _) in txt.char_indices() {
+            let mut chunks = lexer::ChunkLexer::new(&lexer);
+            let mut tokens = chunks.push(&txt[..split]);
+            tokens.extend(chunks.push(&txt[split..]));
+            tokens.extend(chunks.finish());
+            assert_eq!(tokens, expected);
+        }
+
+        // a small buffer splits multi-byte chars between reads
+        let reader = std::io::BufReader::with_capacity(3, txt.as_bytes());
+        let tokens = lexer
+            .lex_reader(reader)
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn chunked_long_tokens() {
+        use lexer::Pattern;
+
+        let mut lexer = lexer::Lexer::new();
+        lexer.add_tokens(&["+".to_string(), "+=".to_string()]);
+        lexer.add_pattern(
+            "comment".to_string(),
+            Pattern::Delimited {
+                start: "/*".to_string(),
+                end: "*/".to_string(),
+                escape: None,
+                multiline: true,
+            },
+        );
+        let word = "a".repeat(100_000);
+        let txt = format!("{word}+ /*{word}\n{word}*/ {word}");
+        let expected = lexer.lex_utf8(&txt).unwrap();
+
+        // the long tokens are scanned from where the last byte left them
+        let mut chunks = lexer::ChunkLexer::new(&lexer);
+        let mut tokens = Vec::new();
+        for (i, _) in txt.char_indices() {
+            let pushed = chunks.push(&txt[i..i + 1]);
+            // the text is complete at the next token, even if the token could get longer
+            if i == word.len() {
+                assert_eq!(pushed, expected[..1]);
+            }
+            tokens.extend(pushed);
+        }
+        tokens.extend(chunks.finish());
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn pattern_tokens() {
+        use lexer::{CharClass, Pattern};
+
+        let mut lexer = lexer::Lexer::new();
+        lexer.add_tokens(&["+".to_string(), "//".to_string(), ".".to_string()]);
+        lexer.add_pattern(
+            "float".to_string(),
+            Pattern::Sequence(vec![
+                Pattern::Repeat {
+                    pattern: Box::new(Pattern::Class(CharClass::Numeric)),
+                    min: 1,
+                    max: None,
+                },
+                Pattern::Literal(".".to_string()),
+                Pattern::Repeat {
+                    pattern: Box::new(Pattern::Class(CharClass::Numeric)),
+                    min: 0,
+                    max: None,
+                },
+            ]),
+        );
+        lexer.add_pattern(
+            "int".to_string(),
+            Pattern::Repeat {
+                pattern: Box::new(Pattern::Class(CharClass::Range('0', '9'))),
+                min: 1,
+                max: None,
+            },
+        );
+        lexer.add_pattern(
+            "string".to_string(),
+            Pattern::Delimited {
+                start: "\"".to_string(),
+                end: "\"".to_string(),
+                escape: Some('\\'),
+                multiline: true,
+            },
+        );
+        lexer.add_pattern(
+            "comment".to_string(),
+            Pattern::Sequence(vec![
+                Pattern::Literal("//".to_string()),
+                Pattern::Repeat {
+                    pattern: Box::new(Pattern::Class(CharClass::Not(Box::new(
+                        CharClass::OneOf("\n".to_string()),
+                    )))),
+                    min: 0,
+                    max: None,
+                },
+            ]),
+        );
+        let txt = "x+12 + 3.5 \"a \\\" +\nb\" // 1 + 2\ny";
+
+        let json = serde_json::to_string(&lexer).unwrap();
+        let lexer: lexer::Lexer = serde_json::from_str(&json).unwrap();
+        let tokens = lexer.lex_utf8(txt).unwrap();
+        let tokens: Vec<_> = tokens.iter().filter(|t| !t.kind.is_whitespace()).collect();
+        let complex = |name: &str| TokenKinds::Complex(name.to_string());
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKinds::Text,
+                TokenKinds::Token("+".to_string()),
+                complex("int"),
+                TokenKinds::Token("+".to_string()),
+                complex("float"),
+                complex("string"),
+                complex("comment"),
+                TokenKinds::Text,
+                TokenKinds::Control(lexer::ControlTokenKind::Eof),
+            ]
+        );
+        assert_eq!(lexer.stringify(tokens[5], txt), "\"a \\\" +\nb\"");
+        // the string spans two lines
+        assert_eq!(tokens[6].location, lexer::TextLocation::new(1, 3));
+        assert_eq!(lexer.lex_ascii(txt).unwrap(), lexer.lex_utf8(txt).unwrap());
+
+        // patterns split between chunks
+        for split in 0..txt.len() {
+            let mut chunks = lexer::ChunkLexer::new(&lexer);
+            let mut tokens = chunks.push(&txt[..split]);
+            tokens.extend(chunks.push(&txt[split..]));
+            tokens.extend(chunks.finish());
+            assert_eq!(tokens, lexer.lex_utf8(txt).unwrap());
+        }
+    }
+
+    #[test]
+    fn unclosed_regions() {
+        use lexer::Pattern;
+
+        let mut lexer = lexer::Lexer::new();
+        lexer.add_pattern(
+            "comment".to_string(),
+            Pattern::Delimited {
+                start: "/*".to_string(),
+                end: "*/".to_string(),
+                escape: None,
+                multiline: true,
+            },
+        );
+        lexer.add_pattern(
+            "string".to_string(),
+            Pattern::Delimited {
+                start: "\"".to_string(),
+                end: "\"".to_string(),
+                escape: Some('\\'),
+                multiline: false,
+            },
+        );
+        let txt = "a\"b\\\"c\" d\n\"e\\\\\" /*f\n/*g*/";
+        let tokens = lexer.lex_utf8(txt).unwrap();
+        let tokens: Vec<_> = tokens
+            .iter()
+            .filter(|t| !t.kind.is_whitespace())
+            .map(|t| (t.kind.clone(), lexer.stringify(t, txt)))
+            .collect();
+        let complex = |name: &str| TokenKinds::Complex(name.to_string());
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKinds::Text, "a"),
+                (complex("string"), "\"b\\\"c\""),
+                (TokenKinds::Text, "d"),
+                (complex("string"), "\"e\\\\\""),
+                (complex("comment"), "/*f\n/*g*/"),
+                (TokenKinds::Control(lexer::ControlTokenKind::Eof), "__EOF__"),
+            ]
+        );
+
+        // every start delimiter of a region that is never closed is a possible token,
+        // the rest of the text is scanned only once and not for every one of them
+        let txt = "a/*b".repeat(100_000);
+        let tokens = lexer.lex_utf8(&txt).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].len, txt.len());
+        assert_eq!(lexer.lex_ascii(&txt).unwrap(), tokens);
+        let mut chunks = lexer::ChunkLexer::new(&lexer);
+        let mut chunked = Vec::new();
+        for chunk in txt.as_bytes().chunks(1000) {
+            chunked.extend(chunks.push(core::str::from_utf8(chunk).unwrap()));
+        }
+        chunked.extend(chunks.finish());
+        assert_eq!(chunked, tokens);
+    }
+
+    #[test]
+    fn preprocessor_steps() {
+        use preprocessor::{PreprocessorStep, Region};
+
+        let mut lexer = lexer::Lexer::new();
+        lexer.add_tokens(&["'".to_string(), "#".to_string()]);
+        lexer.steps.push(PreprocessorStep::Regions(vec![
+            Region {
+                start: vec!["'".to_string()],
+                end: Some("'".to_string()),
+                escape: None,
+                kind: Some("char".to_string()),
+                include_start: true,
+                include_end: true,
+            },
+            Region {
+                start: vec!["#".to_string()],
+                end: None,
+                escape: None,
+                kind: None,
+                include_start: false,
+                include_end: false,
+            },
+        ]));
+        lexer.steps.push(PreprocessorStep::Remove(vec![TokenKinds::Whitespace]));
+
+        let json = serde_json::to_string(&lexer).unwrap();
+        let lexer: lexer::Lexer = serde_json::from_str(&json).unwrap();
+
+        let txt = "a ' # ' # b ' c\nd";
+        let tokens = lexer.lex_utf8(txt).unwrap();
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKinds::Text,
+                TokenKinds::Complex("char".to_string()),
+                TokenKinds::Control(lexer::ControlTokenKind::Eol),
+                TokenKinds::Text,
+                TokenKinds::Control(lexer::ControlTokenKind::Eof),
+            ]
+        );
+        assert_eq!(lexer.stringify(&tokens[1], txt), "' # '");
+
+        let err = lexer.lex_utf8("a ' b").unwrap_err();
+        assert_eq!(err.location, lexer::TextLocation::new(0, 2));
+    }
+
+    #[test]
+    fn rules() {
+        let mut parser = Parser::new();
+        let txt = "let   danda=  1+60;";
+        parser.lexer.add_token("=".to_string());
+        parser.lexer.add_token(":".to_string());
+        parser.lexer.add_token("+".to_string());
+        parser.lexer.add_token(";".to_string());
+        parser.lexer.add_token("-".to_string());
+        parser.lexer.add_token("*".to_string());
+        parser.lexer.add_token("/".to_string());
+
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        let mut variables = Map::new();
+        variables.insert("ident".to_string(), VariableKind::Node);
+        variables.insert("type".to_string(), VariableKind::Node);
+        variables.insert("value".to_string(), VariableKind::Node);
+
+        parser.grammar.enumerators.insert(
+            "operators".to_string(),
+            grammar::Enumerator {
+                name: "operators".to_string(),
+                values: vec![
+                    grammar::MatchToken::Token(TokenKinds::Token("+".to_string())),
+                    grammar::MatchToken::Token(TokenKinds::Token("-".to_string())),
+                    grammar::MatchToken::Token(TokenKinds::Token("*".to_string())),
+                    grammar::MatchToken::Token(TokenKinds::Token("/".to_string())),
+                ],
+            },
+        );
+
+        parser.grammar.add_node(grammar::Node {
+            name: "KWLet".to_string(),
+            rules: vec![
+                // detect the keyword
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Word("let".to_string()),
+                    rules: vec![],
+                    parameters: vec![Parameters::HardError(true)],
+                },
+                // detect the ident
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Text),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("ident".to_string())],
+                },
+                // detect the type if it exists
+                grammar::Rule::Maybe {
+                    token: grammar::MatchToken::Token(TokenKinds::Token(":".to_string())),
+                    is: vec![grammar::Rule::Is {
+                        token: grammar::MatchToken::Token(TokenKinds::Text),
+                        rules: vec![],
+                        parameters: vec![Parameters::Set("type".to_string())],
+                    }],
+                    isnt: vec![],
+                    parameters: vec![],
+                },
+                // detect the value if it exists
+                grammar::Rule::Maybe {
+                    token: grammar::MatchToken::Token(TokenKinds::Token("=".to_string())),
+                    is: vec![grammar::Rule::Is {
+                        token: grammar::MatchToken::Node("value".to_string()),
+                        rules: vec![],
+                        parameters: vec![Parameters::Set("value".to_string())],
+                    }],
+                    isnt: vec![],
+                    parameters: vec![],
+                },
+                // consume the semicolon (optional)
+                grammar::Rule::Maybe {
+                    token: grammar::MatchToken::Token(TokenKinds::Token(";".to_string())),
+                    is: vec![],
+                    isnt: vec![],
+                    parameters: vec![],
+                },
+            ],
+            variables,
+            docs: Some("let <ident>[: <type>] [= <value>];".to_string()),
+            sync: vec![],
+        });
+        let mut variables = Map::new();
+        variables.insert("nodes".to_string(), VariableKind::NodeList);
+        parser.grammar.add_node(grammar::Node {
+            name: "value".to_string(),
+            rules: vec![
+                // detect the value[0]
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Text),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("nodes".to_string())],
+                },
+                // detect the operator
+                grammar::Rule::While {
+                    token: grammar::MatchToken::Enumerator("operators".to_string()),
+                    // detect the value[n]
+                    rules: vec![grammar::Rule::Is {
+                        token: grammar::MatchToken::Token(TokenKinds::Text),
+                        rules: vec![],
+                        parameters: vec![Parameters::Set("nodes".to_string())],
+                    }],
+                    parameters: vec![Parameters::Set("nodes".to_string())],
+                },
+            ],
+            variables,
+            docs: Some("value".to_string()),
+            sync: vec![],
+        });
+        parser.parser.entry = String::from("KWLet");
+
+        let dump = serde_json::to_string(&parser);
+
+        let mut file = std::fs::File::create("KWLet.json").unwrap();
+        match dump {
+            Ok(ref dump) => {
+                file.write_all(dump.as_bytes()).unwrap();
+            }
+            Err(err) => panic!("Failed to dump grammar: {}", err),
+        }
+
+        parser.parse(&tokens, txt).unwrap();
+    }
+
+    #[test]
+    fn error_recovery() {
+        let mut parser = Parser::new();
+        let txt = "let a = 1; let = 2; let b 3; let c = 4; let d";
+        parser
+            .lexer
+            .add_tokens(&["let".to_string(), "=".to_string(), ";".to_string()]);
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        let mut variables = Map::new();
+        variables.insert("ident".to_string(), VariableKind::Node);
+        variables.insert("value".to_string(), VariableKind::Node);
+        parser.grammar.add_node(grammar::Node {
+            name: "statement".to_string(),
+            rules: vec![
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Token("let".to_string())),
+                    rules: vec![],
+                    parameters: vec![Parameters::HardError(true)],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Text),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("ident".to_string())],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Token("=".to_string())),
+                    rules: vec![],
+                    parameters: vec![],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Text),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("value".to_string())],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Token(";".to_string())),
+                    rules: vec![],
+                    parameters: vec![],
+                },
+            ],
+            variables,
+            docs: None,
+            sync: vec![TokenKinds::Token(";".to_string())],
+        });
+        let mut variables = Map::new();
+        variables.insert("statements".to_string(), VariableKind::NodeList);
+        parser.grammar.add_node(grammar::Node {
+            name: "entry".to_string(),
+            rules: vec![grammar::Rule::While {
+                token: grammar::MatchToken::Node("statement".to_string()),
+                rules: vec![],
+                parameters: vec![Parameters::Set("statements".to_string())],
+            }],
+            variables,
+            docs: None,
+            sync: vec![],
+        });
+
+        // without recovery the first error stops the parser
+        assert!(parser.parse(&tokens, txt).is_err());
+
+        let recovered = parser.parse_recovering(&tokens, txt);
+        assert_eq!(recovered.errors.len(), 3);
+        let statements = recovered.result.entry.get_list("statements");
+        assert_eq!(statements.len(), 5);
+        let errors: Vec<_> = statements
+            .iter()
+            .map(|node| node.unwrap_node().is_error())
+            .collect();
+        assert_eq!(errors, vec![false, true, true, false, true]);
+        assert_eq!(
+            recovered.result.stringify_node(&statements[2], txt),
+            " let b 3;"
+        );
+        assert_eq!(
+            recovered.result.stringify_node(&statements[3], txt),
+            " let c = 4;"
+        );
+    }
+
+    #[test]
+    fn node_spans() {
+        let mut parser = Parser::new();
+        parser.lexer.add_tokens(&[";".to_string()]);
+        parser.grammar = r#"
+            eof = true
+            node entry {
+                var items: list
+                while <item> -> set(items)
+            }
+            node item {
+                is text
+                maybe ';'
+            }
+        "#
+        .parse()
+        .unwrap();
+        let txt = "a; b  \nc ;  \n";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        // the last consumed token is not mistaken for a token after the end of the entry
+        let result = parser.parse(&tokens, txt).unwrap();
+        let items: Vec<_> = result
+            .entry
+            .get_list("items")
+            .iter()
+            .map(|item| result.stringify_node(item, txt))
+            .collect();
+        // nodes end at their last consumed token, not at the token after it
+        // and not after the whitespace that follows it
+        assert_eq!(items, ["a;", " b", "c ;"]);
+        assert_eq!(result.entry.span(), 0..10);
+    }
+
+    #[test]
+    fn parse_error_details() {
+        let mut parser = Parser::new();
+        let txt = "let\n  a = ;";
+        parser
+            .lexer
+            .add_tokens(&["let".to_string(), "=".to_string(), ";".to_string()]);
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        let mut variables = Map::new();
+        variables.insert("value".to_string(), VariableKind::Node);
+        parser.grammar.add_node(grammar::Node {
+            name: "entry".to_string(),
+            rules: vec![
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Token("let".to_string())),
+                    rules: vec![],
+                    parameters: vec![],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Text),
+                    rules: vec![],
+                    parameters: vec![],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Token("=".to_string())),
+                    rules: vec![],
+                    parameters: vec![],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Text),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("value".to_string())],
+                },
+            ],
+            variables,
+            docs: Some("let <ident> = <value>".to_string()),
+            sync: vec![],
+        });
+
+        let err = parser.parse(&tokens, txt).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            parser::ParseErrors::ExpectedToken {
+                expected: TokenKinds::Text,
+                ..
+            }
+        ));
+        assert_eq!(err.location(), &lexer::TextLocation::new(1, 6));
+        assert_eq!(&txt[err.span()], ";");
+        assert_eq!(err.node_name(), Some("entry"));
+        assert_eq!(err.node_docs(), Some("let <ident> = <value>"));
+
+        let json = serde_json::to_string(&err).unwrap();
+        let loaded: parser::ParseError = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.span(), err.span());
+        assert_eq!(loaded.to_string(), err.to_string());
+
+        assert_eq!(err.kind().to_string(), "Expected text, found \";\"");
+        assert_eq!(
+            err.to_string(),
+            "Expected text, found \";\" at 2:7\nError in node: \"entry\"\nlet <ident> = <value>"
+        );
+
+        // the kind is part of the message, not the cause of the error
+        let err: Box<dyn std::error::Error> = Box::new(err);
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn render_diagnostics() {
+        let mut parser = Parser::new();
+        let txt = "let a = 1\nlet\tb = =;";
+        parser.lexer.add_tokens(&[
+            "let".to_string(),
+            "=".to_string(),
+            ";".to_string(),
+            "\"".to_string(),
+        ]);
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        parser.grammar.add_node(grammar::Node {
+            name: "entry".to_string(),
+            rules: vec![grammar::Rule::While {
+                token: grammar::MatchToken::Token(TokenKinds::Token("let".to_string())),
+                rules: vec![
+                    grammar::Rule::Is {
+                        token: grammar::MatchToken::Token(TokenKinds::Text),
+                        rules: vec![],
+                        parameters: vec![],
+                    },
+                    grammar::Rule::Is {
+                        token: grammar::MatchToken::Token(TokenKinds::Token("=".to_string())),
+                        rules: vec![],
+                        parameters: vec![],
+                    },
+                    grammar::Rule::IsOneOf {
+                        tokens: vec![
+                            grammar::OneOf {
+                                token: grammar::MatchToken::Token(TokenKinds::Text),
+                                rules: vec![],
+                                parameters: vec![],
+                            },
+                            grammar::OneOf {
+                                token: grammar::MatchToken::Word("none".to_string()),
+                                rules: vec![],
+                                parameters: vec![],
+                            },
+                        ],
+                    },
+                ],
+                parameters: vec![Parameters::HardError(true)],
+            }],
+            variables: Map::new(),
+            docs: Some("Let statements\n    let <ident> = <value>".to_string()),
+            sync: vec![],
+        });
+
+        let err = parser.parse(&tokens, txt).unwrap_err();
+        let renderer = diagnostic::Renderer::new();
+        assert_eq!(
+            renderer.render_parse_error(&err, txt),
+            format!(
+                "error: {}\n --> 2:9\n  |\n2 | let\tb = =;\n  |    \t    ^\n  \
+                 = expected: text, \"none\"\n  \
+                 = found: \"=\"\n  \
+                 = note: Let statements\n          let <ident> = <value>\n",
+                err.kind()
+            )
+        );
+
+        // colors only wrap the text
+        let colored = diagnostic::Renderer { colors: true }.render_parse_error(&err, txt);
+        assert!(colored.contains("\x1b[1;31m^\x1b[0m"));
+
+        let string = preprocessor::Region {
+            start: vec!["\"".to_string()],
+            end: Some("\"".to_string()),
+            escape: None,
+            kind: Some("string".to_string()),
+            include_start: true,
+            include_end: true,
+        };
+        let step = preprocessor::PreprocessorStep::Regions(vec![string]);
+        parser.lexer.steps.push(step);
+        let txt = "let ťext = \"abc";
+        let err = parser.lexer.lex_utf8(txt).unwrap_err();
+        assert_eq!(
+            renderer.render_preprocessor_error(&err, txt),
+            format!(
+                "error: {}\n --> 1:12\n  |\n1 | {}\n  |            ^^^^\n",
+                err.message, txt
+            )
+        );
+    }
+
+    #[test]
+    fn grammar_dsl() {
+        let mut parser = Parser::new();
+        let txt = "a b , c ;";
+        parser.lexer.add_tokens(&[",".to_string(), ";".to_string(), "+".to_string()]);
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        parser.grammar = r#"
+            global count: number
+            enum ops { '+' "plus" }
+
+            /// An item
+            ///   of a list
+            node item {
+                var name: node
+                is text -> set(name)
+            }
+
+            // the entry node
+            node entry {
+                var items: list, last: node
+                sync ';'
+                while <item> -> set(items), increment_global(count)
+                maybe ',' {
+                    is <item> -> set(items)
+                } else {
+                    error("missing comma")
+                }
+                is one of {
+                    %ops -> set(last)
+                    ';' -> set(last) { debug }
+                }
+            }
+        "#
+        .parse()
+        .unwrap();
+        let item = &parser.grammar.nodes["item"];
+        assert_eq!(item.docs.as_deref(), Some("An item\n  of a list"));
+        assert_eq!(
+            parser.grammar.nodes["entry"].sync,
+            [TokenKinds::Token(";".to_string())]
+        );
+
+        let result = parser.parse(&tokens, txt).unwrap();
+        assert_eq!(result.entry.get_list("items").len(), 3);
+        assert_eq!(*result.globals["count"].unwrap_number(), 2);
+        let last = result.entry.try_get_node("last").as_ref().unwrap();
+        assert_eq!(result.stringify_node(last, txt), ";");
+
+        let grammar: grammar::Grammar = r#"
+            eof = false
+            node entry {
+                var value: node, a: number, b: number
+                precedence text -> set(value) {
+                    right assign: '='
+                    postfix call: '(' '['
+                }
+                loop { until one of { eof -> break(1) any } }
+                compare(a >= b) { goto(end) }
+                label(end)
+                isnt "x\"y" -> back(2), print("\n")
+            }
+        "#
+        .parse()
+        .unwrap();
+        assert!(!grammar.eof);
+        let rules = &grammar.nodes["entry"].rules;
+        assert!(matches!(
+            &rules[0],
+            grammar::Rule::Precedence { levels, .. }
+                if levels[0].kind == grammar::OperatorKind::Right && levels[1].operators.len() == 2
+        ));
+        assert!(matches!(&rules[1], grammar::Rule::Loop { rules } if rules.len() == 1));
+        assert!(matches!(
+            &rules[2],
+            grammar::Rule::Command {
+                command: grammar::Commands::Compare {
+                    comparison: grammar::Comparison::GreaterThanOrEqual,
+                    ..
+                }
+            }
+        ));
+        assert!(matches!(
+            &rules[4],
+            grammar::Rule::Isnt { token: grammar::MatchToken::Word(word), parameters, .. }
+                if word == "x\"y" && matches!(parameters[..], [Parameters::Back(2), Parameters::Print(_)])
+        ));
+
+        // errors point to the line of the grammar
+        let errors = [
+            ("node a {\n    is <b> -> sett(x)\n}", dsl::DslErrors::UnknownParameter("sett".to_string()), (2, 15)),
+            ("node a {\n    is 'x\n}", dsl::DslErrors::UnterminatedString, (2, 8)),
+            ("node a {}\nnode a {}", dsl::DslErrors::DuplicateNode("a".to_string()), (2, 6)),
+            (
+                "node a {\n    var b: string\n}",
+                dsl::DslErrors::UnknownVariableKind("string".to_string()),
+                (2, 12),
+            ),
+            (
+                "node a {\n    maybe 'x' {\n}",
+                dsl::DslErrors::Expected {
+                    expected: "rule".to_string(),
+                    found: "end of file".to_string(),
+                },
+                (3, 2),
+            ),
+            ("/// docs\nenum a {}", dsl::DslErrors::DanglingDocs, (2, 1)),
+        ];
+        for (text, kind, (line, column)) in errors {
+            let err = dsl::parse(text).unwrap_err();
+            assert_eq!(err.kind(), &kind, "{}", text);
+            assert_eq!((err.location().line, err.location().column), (line, column), "{}", text);
+        }
+
+        let text = "node a {\n    is <b> -> sett(x)\n}";
+        let err = dsl::parse(text).unwrap_err();
+        assert_eq!(
+            diagnostic::Renderer::new().render_dsl_error(&err, text),
+            "error: Unknown parameter: sett\n --> 2:15\n  |\n2 |     is <b> -> sett(x)\n  |               ^^^^\n"
+        );
+    }
+
+    #[test]
+    fn grammar_export() {
+        use railroad::{DiagramKind, Element};
+
+        let text = r#"enum ops { '+' "plus" @int }
+
+/// docs
+///
+///   indented
+node entry {
+    var a: node
+    var b: list
+    while <item> -> set(b) {
+        is ',' -> node_end
+    }
+    maybe one of {
+        %ops -> set(a)
+        ';'
+    }
+    precedence text -> set(a) {
+        left sum: '+' '-'
+        prefix neg: '-'
+    }
+}
+
+node item {
+    is text
+    maybe '=' {
+        is text
+    } else {
+        error("missing \"=\"")
+    }
+}
+"#;
+        let grammar = dsl::parse(text).unwrap();
+        assert_eq!(dsl::print(&grammar), text);
+        assert_eq!(grammar.to_string(), text);
+
+        let terminal = |text: &str| Element::Terminal {
+            text: text.to_string(),
+        };
+        let class = |name: &str| Element::Class {
+            name: name.to_string(),
+        };
+        let diagrams = railroad::export(&grammar);
+        let names: Vec<&str> = diagrams.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["entry", "item", "ops"]);
+        assert_eq!(diagrams[0].docs.as_deref(), Some("docs\n\n  indented"));
+        assert_eq!(diagrams[2].kind, DiagramKind::Enumerator);
+        assert_eq!(
+            diagrams[2].diagram,
+            Element::Choice {
+                options: vec![terminal("+"), terminal("plus"), class("int")],
+            }
+        );
+        // nested sequences are flattened
+        let negation = Element::ZeroOrMore {
+            item: Box::new(terminal("-")),
+        };
+        assert_eq!(
+            diagrams[0].diagram,
+            Element::Sequence {
+                items: vec![
+                    Element::ZeroOrMore {
+                        item: Box::new(Element::Sequence {
+                            items: vec![
+                                Element::NonTerminal {
+                                    name: "item".to_string(),
+                                    kind: DiagramKind::Node,
+                                },
+                                terminal(","),
+                            ],
+                        }),
+                    },
+                    Element::Optional {
+                        item: Box::new(Element::Choice {
+                            options: vec![
+                                Element::NonTerminal {
+                                    name: "ops".to_string(),
+                                    kind: DiagramKind::Enumerator,
+                                },
+                                terminal(";"),
+                            ],
+                        }),
+                    },
+                    negation.clone(),
+                    class("text"),
+                    Element::ZeroOrMore {
+                        item: Box::new(Element::Sequence {
+                            items: vec![
+                                Element::Choice {
+                                    options: vec![terminal("+"), terminal("-")],
+                                },
+                                negation,
+                                class("text"),
+                            ],
+                        }),
+                    },
+                ],
+            }
+        );
+        assert_eq!(
+            diagrams[1].diagram,
+            Element::Sequence {
+                items: vec![
+                    class("text"),
+                    Element::Choice {
+                        options: vec![
+                            Element::Sequence {
+                                items: vec![terminal("="), class("text")],
+                            },
+                            Element::Comment {
+                                text: "error: missing \"=\"".to_string(),
+                            },
+                        ],
+                    },
+                ],
+            }
+        );
+        let json = serde_json::to_string(&diagrams[2]).unwrap();
+        assert!(json.contains(r#"{"terminal":{"text":"+"}}"#), "{}", json);
+    }
+
+    #[test]
+    fn validator_analysis() {
+        use grammar::validator::{RulePath, ValidationErrors, ValidationWarnings};
+
+        let path = |path: &str| RulePath(path.split(" > ").map(String::from).collect());
+
+        // optional node repeated by a while loop
+        let grammar: grammar::Grammar = r#"
+            node entry {
+                while <optional>
+                loop { is text }
+                loop { maybe text -> break(1) }
+            }
+            node optional {
+                maybe text
+            }
+        "#
+        .parse()
+        .unwrap();
+        let result = grammar.validate(&lexer::Lexer::new());
+        let kinds: Vec<_> = result.errors.iter().map(|err| &err.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                &ValidationErrors::NullableLoop(path("entry > rules[0]")),
+                &ValidationErrors::NullableLoop(path("entry > rules[2]")),
+            ]
+        );
+
+        // left recursion through an optional prefix
+        let grammar: grammar::Grammar = r#"
+            node expr {
+                is <term>
+                is '+'
+            }
+            node term {
+                maybe <prefix>
+                maybe <expr> -> return
+                is text
+            }
+            node prefix {
+                maybe '-'
+            }
+            node value {
+                is text
+                is <value>
+            }
+        "#
+        .parse()
+        .unwrap();
+        let result = grammar.validate(&lexer::Lexer::new());
+        let kinds: Vec<_> = result
+            .errors
+            .iter()
+            .map(|err| &err.kind)
+            .filter(|kind| matches!(kind, ValidationErrors::LeftRecursion(_)))
+            .collect();
+        assert_eq!(
+            kinds,
+            [&ValidationErrors::LeftRecursion(path(
+                "expr > rules[0] > <term> > rules[1] > <expr>"
+            ))]
+        );
+        assert_eq!(
+            path("expr > rules[0] > <term>").to_string(),
+            "expr > rules[0] > <term>"
+        );
+
+        // nodes that are never used from the entry
+        let mut parser = Parser::new();
+        parser.grammar = r#"
+            enum values { <used> text }
+            node entry {
+                is %values
+            }
+            node used {
+                is text
+            }
+            node unused {
+                is <used>
+            }
+        "#
+        .parse()
+        .unwrap();
+        let result = parser.validate();
+        assert!(result.errors.is_empty());
+        let warnings: Vec<_> = result
+            .warnings
+            .iter()
+            .map(|warning| (&warning.kind, warning.node_name.as_str()))
+            .collect();
+        assert_eq!(warnings, [(&ValidationWarnings::UnreachableNode, "unused")]);
+
+        parser.parser.entry = "missing".to_string();
+        let result = parser.validate();
+        assert_eq!(
+            result.errors[0].kind,
+            ValidationErrors::NodeNotFound("missing".to_string())
+        );
+    }
+
+    #[test]
+    fn validator_alternatives() {
+        use grammar::validator::{Alternative, ValidationWarnings};
+        use grammar::MatchToken;
+
+        let grammar: grammar::Grammar = r#"
+            enum values { text "fn" '+' }
+            node entry {
+                is one of {
+                    <call>
+                    <keyword>
+                    text
+                    '+'
+                }
+                maybe one of {
+                    any
+                    '+'
+                }
+            }
+            node call {
+                is text
+                is '('
+            }
+            node keyword {
+                is "let"
+            }
+        "#
+        .parse()
+        .unwrap();
+        let result = grammar.validate(&lexer::Lexer::new());
+        let warnings: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|warning| {
+                matches!(
+                    warning.kind,
+                    ValidationWarnings::AmbiguousAlternatives { .. }
+                        | ValidationWarnings::ShadowedAlternative { .. }
+                )
+            })
+            .map(|warning| (&warning.kind, warning.node_name.as_str()))
+            .collect();
+        let alternative = |index, token| Alternative { index, token };
+        let plus = || MatchToken::Token(TokenKinds::Token("+".to_string()));
+        assert_eq!(
+            warnings,
+            [
+                (
+                    &ValidationWarnings::AmbiguousAlternatives {
+                        first: alternative(0, MatchToken::Node("call".to_string())),
+                        second: alternative(1, MatchToken::Node("keyword".to_string())),
+                        tokens: vec![MatchToken::Word("let".to_string())],
+                    },
+                    "entry"
+                ),
+                (
+                    &ValidationWarnings::ShadowedAlternative {
+                        shadowed: alternative(1, plus()),
+                        by: alternative(0, MatchToken::Any),
+                    },
+                    "entry"
+                ),
+                (
+                    &ValidationWarnings::ShadowedAlternative {
+                        shadowed: alternative(1, MatchToken::Word("fn".to_string())),
+                        by: alternative(0, MatchToken::Token(TokenKinds::Text)),
+                    },
+                    "values"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn validator_paths() {
+        use grammar::validator::{RulePath, ValidationErrors};
+
+        let text = "node entry {\n    var items: list\n    maybe text {\n        is text -> set(items), set(missing)\n    }\n    goto(end)\n}\n";
+        let (grammar, source_map) = dsl::parse_with_source_map(text).unwrap();
+        let mut lexer = lexer::Lexer::new();
+        lexer.add_tokens(&["abc".to_string()]);
+        let mut result = grammar.validate(&lexer);
+        source_map.annotate(&mut result);
+
+        let missing = result
+            .errors
+            .iter()
+            .find(|err| err.kind == ValidationErrors::VariableNotFound("missing".to_string()))
+            .unwrap();
+        assert_eq!(
+            missing.path.to_string(),
+            "entry > rules[0] > Maybe.is[0] > parameters[1]"
+        );
+        assert_eq!(missing.location, Some(lexer::TextLocation::new(3, 8)));
+        assert_eq!(
+            missing.to_string(),
+            "VariableNotFound(\"missing\") at entry > rules[0] > Maybe.is[0] > parameters[1], 4:9"
+        );
+
+        let label = result
+            .errors
+            .iter()
+            .find(|err| err.kind == ValidationErrors::LabelNotFound("end".to_string()))
+            .unwrap();
+        assert_eq!(label.path.to_string(), "entry > rules[1]");
+        assert_eq!(label.location, Some(lexer::TextLocation::new(5, 4)));
+
+        // the lexer has no rules
+        assert_eq!(result.warnings[0].path, RulePath::default());
+        assert_eq!(
+            result.warnings[0].to_string(),
+            "UnusualToken(\"abc\", TooLong) in __lexer__"
+        );
+    }
+
+    #[test]
+    fn observer_profile() {
+        use observer::{ParseObserver, Profiler};
+
+        let mut parser = Parser::new();
+        parser.lexer.add_tokens(&[",".to_string()]);
+        parser.grammar = r#"
+            eof = false
+            node entry {
+                var value: node
+                is one of {
+                    <pair> -> set(value)
+                    <single> -> set(value)
+                }
+            }
+            node pair {
+                is text
+                is ','
+                is text
+            }
+            node single {
+                is text
+            }
+        "#
+        .parse()
+        .unwrap();
+        let txt = "a b";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        #[derive(Default)]
+        struct Trace(Vec<String>);
+
+        impl ParseObserver for Trace {
+            fn enter_node(&mut self, name: &str, token: usize) {
+                self.0.push(format!("enter {} {}", name, token));
+            }
+
+            fn exit_node(
+                &mut self,
+                name: &str,
+                token: usize,
+                result: Result<&parser::Node, &parser::ParseError>,
+            ) {
+                self.0.push(format!("exit {} {} {}", name, token, result.is_ok()));
+            }
+
+            fn rule_matched(&mut self, token: &grammar::MatchToken, at: usize) {
+                self.0.push(format!("matched {:?} {}", token, at));
+            }
+
+            fn rule_failed(
+                &mut self,
+                token: &grammar::MatchToken,
+                at: usize,
+                _error: &parser::ParseError,
+            ) {
+                self.0.push(format!("failed {:?} {}", token, at));
+            }
+
+            fn backtrack(&mut self, from: usize, to: usize) {
+                self.0.push(format!("backtrack {} {}", from, to));
+            }
+
+            fn parameter(&mut self, parameter: &grammar::Parameters) {
+                self.0.push(format!("parameter {:?}", parameter));
+            }
+        }
+
+        let mut trace = Trace::default();
+        let result = parser.parse_observed(&tokens, txt, &mut trace).unwrap();
+        assert_eq!(result.stringify_node(result.entry.try_get_node("value").as_ref().unwrap(), txt), "a");
+        assert_eq!(
+            trace.0,
+            [
+                "enter entry 0",
+                "enter pair 0",
+                "matched Token(Text) 0",
+                "failed Token(Token(\",\")) 1",
+                "backtrack 2 0",
+                "exit pair 0 false",
+                "failed Node(\"pair\") 0",
+                "enter single 0",
+                "matched Token(Text) 0",
+                "exit single 1 true",
+                "matched Node(\"single\") 0",
+                "parameter Set(\"value\")",
+                "exit entry 1 true",
+            ]
+        );
+
+        // every call of the clock is one unit of time
+        let mut time = 0;
+        let mut profiler = Profiler::with_clock(move || {
+            time += 1;
+            time
+        });
+        parser.parse_observed(&tokens, txt, &mut profiler).unwrap();
+        let profile = profiler.report();
+        let nodes: Vec<_> = profile
+            .nodes
+            .iter()
+            .map(|node| {
+                (
+                    node.name.as_str(),
+                    node.attempts,
+                    node.matches,
+                    node.time,
+                    node.self_time,
+                )
+            })
+            .collect();
+        assert_eq!(
+            nodes,
+            [("entry", 1, 1, 5, 3), ("pair", 1, 0, 1, 1), ("single", 1, 1, 1, 1)]
+        );
+        assert_eq!(profile.backtracks.len(), 1);
+        assert_eq!(profile.backtracks[0].node, "pair");
+        assert_eq!(profile.backtracks[0].tokens, 2);
+        assert!(profile.to_string().starts_with("node"));
+    }
+
+    #[test]
+    fn variables_and_literals() {
+        use grammar::validator::ValidationErrors;
+
+        let mut parser = Parser::new();
+        parser.lexer.add_tokens(&[";".to_string()]);
+        parser.grammar = r#"
+            eof = false
+            global depth: number
+            node entry {
+                var name: text
+                var head: span
+                var body: span
+                var words: tokens
+                var count: number
+                var done: bool
+                is text -> set(name), set(head), set(body), set(words), add(count, 3)
+                while text -> set(body), set(words), add(count, -1), add_global(depth, 2)
+                compare(name != "let") {
+                    error("expected let")
+                }
+                compare(count < 2) {
+                    is ';' -> true(done), reset(head)
+                }
+                compare(done == true)
+            }
+        "#
+        .parse()
+        .unwrap();
+        let printed = dsl::print(&parser.grammar);
+        assert!(printed.contains("add(count, -1)"));
+        assert!(printed.contains("compare(name != \"let\")"));
+        assert_eq!(dsl::print(&printed.parse().unwrap()), printed);
+        assert!(parser.grammar.validate(&parser.lexer).errors.is_empty());
+
+        let txt = "let a b ;";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        let result = parser.parse(&tokens, txt).unwrap();
+        let entry = &result.entry;
+        assert_eq!(entry.try_text("name").unwrap(), Some("let"));
+        assert_eq!(entry.try_span("head").unwrap(), None);
+        assert_eq!(entry.try_span("body").unwrap(), Some(0..7));
+        assert_eq!(entry.try_tokens("words").unwrap().len(), 3);
+        assert_eq!(entry.try_number("count").unwrap(), 1);
+        assert!(entry.try_bool("done").unwrap());
+        assert_eq!(*result.globals["depth"].unwrap_number(), 4);
+
+        let txt = "fn a ;";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        let err = parser.parse(&tokens, txt).unwrap_err();
+        assert!(matches!(err.kind(), parser::ParseErrors::Message(message) if message == "expected let"));
+
+        // nodes can not be set to tokens and constants must match the kind of the variable
+        let grammar: grammar::Grammar = r#"
+            node entry {
+                var words: tokens
+                var count: number
+                is <item> -> set(words)
+                compare(count == "one")
+                compare(missing > 1)
+                is text -> add(words, 1), reset(other)
+            }
+            node item {
+                is text
+            }
+        "#
+        .parse()
+        .unwrap();
+        let errors: Vec<_> = grammar
+            .validate(&lexer::Lexer::new())
+            .errors
+            .into_iter()
+            .map(|err| (err.kind, err.path.to_string()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (
+                    ValidationErrors::CantUseVariable("words".to_string()),
+                    "entry > rules[0] > parameters[0]".to_string()
+                ),
+                (
+                    ValidationErrors::CantUseVariable("count".to_string()),
+                    "entry > rules[1]".to_string()
+                ),
+                (
+                    ValidationErrors::VariableNotFound("missing".to_string()),
+                    "entry > rules[2]".to_string()
+                ),
+                (
+                    ValidationErrors::CantUseVariable("words".to_string()),
+                    "entry > rules[3] > parameters[0]".to_string()
+                ),
+                (
+                    ValidationErrors::VariableNotFound("other".to_string()),
+                    "entry > rules[3] > parameters[1]".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn captures() {
+        use grammar::validator::ValidationErrors;
+        use lexer::{CharClass, Pattern};
+
+        let mut parser = Parser::new();
+        parser.lexer.add_tokens(&["=".to_string(), ";".to_string()]);
+        parser.lexer.add_pattern(
+            "int".to_string(),
+            Pattern::Repeat {
+                pattern: Box::new(Pattern::Class(CharClass::Range('0', '9'))),
+                min: 1,
+                max: None,
+            },
+        );
+        parser.lexer.add_pattern(
+            "string".to_string(),
+            Pattern::Delimited {
+                start: "\"".to_string(),
+                end: "\"".to_string(),
+                escape: Some('\\'),
+                multiline: false,
+            },
+        );
+        parser.grammar = r#"
+            eof = false
+            node entry {
+                var key: node
+                var name: text
+                var size: number
+                var value: span
+                is text -> set(key)
+                is '='
+                is @string -> capture(name, unquote), set(value)
+                is @int -> capture(size, int), set(value)
+                is ';'
+            }
+        "#
+        .parse()
+        .unwrap();
+        assert!(parser.grammar.validate(&parser.lexer).errors.is_empty());
+
+        let txt = "key = \"a b\" 12;";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        let result = parser.parse(&tokens, txt).unwrap();
+        let entry = &result.entry;
+        assert_eq!(result.capture(entry, "key").unwrap(), Some("key"));
+        assert_eq!(result.capture(entry, "name").unwrap(), Some("a b"));
+        assert_eq!(result.capture(entry, "value").unwrap(), Some("\"a b\" 12"));
+        assert!(result.capture(entry, "size").is_err());
+        assert_eq!(entry.try_number("size").unwrap(), 12);
+        assert_eq!(result.slice(entry.try_get("key").unwrap().unwrap()), "key");
+
+        // an owned result keeps the text
+        let owned = {
+            let txt = txt.to_string();
+            parser.parse(&tokens, &txt).unwrap().into_owned()
+        };
+        assert_eq!(owned.capture(&owned.entry, "value").unwrap(), Some("\"a b\" 12"));
+
+        // a number that does not fit fails to match
+        let txt = "key = \"a\" 99999999999;";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        let err = parser.parse(&tokens, txt).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            parser::ParseErrors::CannotTransform(text, grammar::Transform::Int) if text == "99999999999"
+        ));
+
+        let grammar: grammar::Grammar = r#"
+            node entry {
+                var name: text
+                is text -> capture(name, int), capture(missing, unquote)
+            }
+        "#
+        .parse()
+        .unwrap();
+        let errors: Vec<_> = grammar
+            .validate(&lexer::Lexer::new())
+            .errors
+            .into_iter()
+            .map(|err| err.kind)
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ValidationErrors::CantUseVariable("name".to_string()),
+                ValidationErrors::VariableNotFound("missing".to_string()),
+            ]
+        );
+        assert_eq!(parser::unquote("'x'"), "x");
+        assert_eq!(parser::unquote("\"x'"), "\"x'");
+    }
+
+    #[test]
+    fn memoization() {
+        let mut parser = Parser::new();
+        let txt = "a b c ; d ; y";
+        parser.lexer.add_tokens(&[
+            ";".to_string(),
+            "x".to_string(),
+            "y".to_string(),
+            "z".to_string(),
+        ]);
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        parser
+            .grammar
+            .globals
+            .insert("tags".to_string(), VariableKind::NodeList);
+        parser
+            .grammar
+            .globals
+            .insert("count".to_string(), VariableKind::Number);
+        let mut variables = Map::new();
+        variables.insert("words".to_string(), VariableKind::NodeList);
+        parser.grammar.add_node(grammar::Node {
+            name: "words".to_string(),
+            rules: vec![
+                grammar::Rule::While {
+                    token: grammar::MatchToken::Token(TokenKinds::Text),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("words".to_string())],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Token(";".to_string())),
+                    rules: vec![],
+                    parameters: vec![
+                        Parameters::Global("tags".to_string()),
+                        Parameters::IncrementGlobal("count".to_string()),
+                    ],
+                },
+            ],
+            variables,
+            docs: None,
+            sync: vec![],
+        });
+        // all the alternatives start with the same nodes
+        for end in ["x", "y", "z"] {
+            let mut variables = Map::new();
+            variables.insert("words".to_string(), VariableKind::NodeList);
+            parser.grammar.add_node(grammar::Node {
+                name: format!("ends_with_{}", end),
+                rules: vec![
+                    grammar::Rule::While {
+                        token: grammar::MatchToken::Node("words".to_string()),
+                        rules: vec![],
+                        parameters: vec![Parameters::Set("words".to_string())],
+                    },
+                    grammar::Rule::Is {
+                        token: grammar::MatchToken::Token(TokenKinds::Token(end.to_string())),
+                        rules: vec![],
+                        parameters: vec![],
+                    },
+                ],
+                variables,
+                docs: None,
+                sync: vec![],
+            });
+        }
+        let mut variables = Map::new();
+        variables.insert("value".to_string(), VariableKind::Node);
+        parser.grammar.add_node(grammar::Node {
+            name: "entry".to_string(),
+            rules: vec![grammar::Rule::IsOneOf {
+                tokens: ["ends_with_x", "ends_with_z", "ends_with_y"]
+                    .iter()
+                    .map(|name| grammar::OneOf {
+                        token: grammar::MatchToken::Node(name.to_string()),
+                        rules: vec![],
+                        parameters: vec![Parameters::Set("value".to_string())],
+                    })
+                    .collect(),
+            }],
+            variables,
+            docs: None,
+            sync: vec![],
+        });
+
+        let plain = parser.parse(&tokens, txt).unwrap();
+        parser.parser.memoize = true;
+        let memoized = parser.parse(&tokens, txt).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&memoized.entry).unwrap(),
+            serde_json::to_value(&plain.entry).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&memoized.globals).unwrap(),
+            serde_json::to_value(&plain.globals).unwrap()
+        );
+        // writes of the failed alternatives are kept, the cached ones are repeated
+        assert_eq!(memoized.globals["count"].unwrap_number(), &6);
+        assert_eq!(memoized.globals["tags"].unwrap_node_list().len(), 6);
+    }
+
+    #[test]
+    fn memoization_reads_globals() {
+        let mut parser = Parser::new();
+        parser.lexer.add_tokens(&[";".to_string(), ",".to_string()]);
+        parser.grammar = r#"
+            eof = false
+            global tries: number
+            node entry {
+                var value: node
+                is one of {
+                    <first> -> set(value)
+                    <second> -> set(value)
+                    <third> -> set(value)
+                }
+            }
+            node first {
+                is <item> -> increment_global(tries)
+                is ';'
+            }
+            node second {
+                is <item> -> increment_global(tries)
+                is ','
+            }
+            node third {
+                var item: node
+                is <item> -> set(item)
+            }
+            node item {
+                var word: text
+                compare(tries == 1) {
+                    is text -> set(word)
+                }
+                compare(tries != 1) {
+                    is text
+                }
+            }
+        "#
+        .parse()
+        .unwrap();
+        let txt = "a";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        let plain = parser.parse(&tokens, txt).unwrap();
+        parser.parser.memoize = true;
+        // the item cached by the second attempt compared the global before it was written again
+        let memoized = parser.parse(&tokens, txt).unwrap();
+        assert_eq!(
+            serde_json::to_value(&memoized.entry).unwrap(),
+            serde_json::to_value(&plain.entry).unwrap()
+        );
+        let third = memoized.entry.try_get("value").unwrap().unwrap();
+        let item = third.try_node().unwrap().try_get("item").unwrap().unwrap();
+        assert_eq!(item.try_node().unwrap().try_text("word").unwrap(), None);
+        assert_eq!(memoized.globals["tries"].unwrap_number(), &2);
+    }
+
+    #[test]
+    fn precedence() {
+        use grammar::{OperatorKind, OperatorLevel};
+
+        let mut parser = Parser::new();
+        let txt = "a = b = -c + d * e ^ f ^ g - h!";
+        parser.lexer.add_tokens(&[
+            "=".to_string(),
+            "+".to_string(),
+            "-".to_string(),
+            "*".to_string(),
+            "^".to_string(),
+            "!".to_string(),
+        ]);
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        let level = |name: &str, operators: &[&str], kind| OperatorLevel {
+            name: name.to_string(),
+            operators: operators
+                .iter()
+                .map(|op| grammar::MatchToken::Token(TokenKinds::Token(op.to_string())))
+                .collect(),
+            kind,
+        };
+        let mut variables = Map::new();
+        variables.insert("value".to_string(), VariableKind::Node);
+        parser.grammar.add_node(grammar::Node {
+            name: "entry".to_string(),
+            rules: vec![grammar::Rule::Precedence {
+                operand: grammar::MatchToken::Token(TokenKinds::Text),
+                levels: vec![
+                    level("assign", &["="], OperatorKind::Right),
+                    level("sum", &["+", "-"], OperatorKind::Left),
+                    level("product", &["*"], OperatorKind::Left),
+                    level("negation", &["-"], OperatorKind::Prefix),
+                    level("power", &["^"], OperatorKind::Right),
+                    level("factorial", &["!"], OperatorKind::Postfix),
+                ],
+                parameters: vec![Parameters::Set("value".to_string())],
+            }],
+            variables,
+            docs: None,
+            sync: vec![],
+        });
+
+        // prints the tree with every operator node in parentheses
+        fn show(nodes: &parser::Nodes, lexer: &lexer::Lexer, txt: &str) -> String {
+            let node = match nodes {
+                parser::Nodes::Token(token) => return lexer.stringify(token, txt).to_string(),
+                parser::Nodes::Node(node) => node,
+            };
+            let mut parts: Vec<&parser::Nodes> = node
+                .variables
+                .values()
+                .map(|var| var.unwrap_node())
+                .collect();
+            parts.sort_by_key(|part| match part {
+                parser::Nodes::Token(token) => token.index,
+                parser::Nodes::Node(node) => node.first_string_idx,
+            });
+            let parts: Vec<String> = parts.iter().map(|part| show(part, lexer, txt)).collect();
+            format!("({})", parts.join(" "))
+        }
+
+        let result = parser.parse(&tokens, txt).unwrap();
+        let value = result.entry.variables["value"].unwrap_node();
+        assert_eq!(
+            show(value, &parser.lexer, txt),
+            "(a = (b = (((- c) + (d * (e ^ (f ^ g)))) - (h !))))"
+        );
+        assert_eq!(value.name(), "assign");
+        assert_eq!(value.len(), txt.len());
+
+        // an operator without its right side fails the rule
+        let txt = "a + b *";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        assert!(parser.parse(&tokens, txt).is_err());
+    }
+
+    #[test]
+    fn accessors() {
+        use api::AccessError;
+
+        let mut parser = Parser::new();
+        let txt = "a b , c ;";
+        parser.lexer.add_tokens(&[",".to_string(), ";".to_string()]);
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+
+        let mut variables = Map::new();
+        variables.insert("name".to_string(), VariableKind::Node);
+        parser.grammar.add_node(grammar::Node {
+            name: "item".to_string(),
+            rules: vec![grammar::Rule::Is {
+                token: grammar::MatchToken::Token(TokenKinds::Text),
+                rules: vec![],
+                parameters: vec![Parameters::Set("name".to_string())],
+            }],
+            variables,
+            docs: None,
+            sync: vec![],
+        });
+        let mut variables = Map::new();
+        variables.insert("items".to_string(), VariableKind::NodeList);
+        variables.insert("end".to_string(), VariableKind::Node);
+        variables.insert("count".to_string(), VariableKind::Number);
+        parser.grammar.add_node(grammar::Node {
+            name: "entry".to_string(),
+            rules: vec![
+                grammar::Rule::While {
+                    token: grammar::MatchToken::Node("item".to_string()),
+                    rules: vec![],
+                    parameters: vec![
+                        Parameters::Set("items".to_string()),
+                        Parameters::Increment("count".to_string()),
+                    ],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Token(",".to_string())),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("items".to_string())],
+                },
+                grammar::Rule::While {
+                    token: grammar::MatchToken::Node("item".to_string()),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("items".to_string())],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Token(TokenKinds::Token(";".to_string())),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("end".to_string())],
+                },
+            ],
+            variables,
+            docs: None,
+            sync: vec![],
+        });
+
+        let result = parser.parse(&tokens, txt).unwrap();
+        let entry = &result.entry;
+        assert_eq!(entry.try_number("count"), Ok(2));
+        assert_eq!(entry.try_list("items").unwrap().len(), 4);
+        assert_eq!(
+            entry.try_bool("count"),
+            Err(AccessError::WrongKind {
+                node: "entry".to_string(),
+                variable: "count".to_string(),
+                expected: grammar::VariableKind::Boolean,
+                found: grammar::VariableKind::Number,
+            })
+        );
+        assert_eq!(
+            entry.try_get("missing").err(),
+            Some(AccessError::MissingVariable {
+                node: "entry".to_string(),
+                variable: "missing".to_string(),
+            })
+        );
+        // the generated types report the same errors
+        assert_eq!(
+            codegen::bool_variable(&entry.variables, &entry.name, "count"),
+            Err(codegen::ConvertError::Access(entry.try_bool("count").unwrap_err()))
+        );
+
+        let end = entry.try_get("end").unwrap().unwrap();
+        assert_eq!(
+            end.try_name(),
+            Err(AccessError::IsToken(TokenKinds::Token(";".to_string())))
+        );
+        assert_eq!(end.try_number("count"), end.try_name().map(|_| 0));
+        assert_eq!(end.try_token().unwrap().span(), 8..9);
 
-        assert_eq!(tokens.len(), 21);
+        // the comma in the list is skipped
+        let names: Vec<&str> = entry
+            .try_list_of("items", "item")
+            .unwrap()
+            .map(|item| result.stringify_node(item.try_get("name").unwrap().unwrap(), txt))
+            .collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!(entry.try_list_of("end", "item").is_err());
+
+        // children come in the order of the text, nodes include the whitespace before them
+        let children: Vec<core::ops::Range<usize>> =
+            entry.children().map(|child| child.span()).collect();
+        assert_eq!(children, [0..1, 1..3, 4..5, 5..7, 8..9]);
+        assert_eq!(entry.children_named("item").count(), 3);
+        let item = entry.children().next().unwrap();
+        assert_eq!(
+            item.try_token(),
+            Err(AccessError::IsNode("item".to_string()))
+        );
     }
 
     #[test]
-    fn stringify() {
-        let mut parser = Parser::new();
-        let txt = "Functiond\t 1 +\n 2 * 3 - 4 /= 5";
-        // Tokens that will be recognized by the lexer
-        //
-        // White space is ignored by default
-        //
-        // Everything else is a text token
-        parser.lexer.add_tokens(&[
-            "+".to_string(),
-            "-".to_string(),
-            "*".to_string(),
-            "/=".to_string(),
-            "Function".to_string(),
-        ]);
+    fn tree_walking() {
+        use grammar::{OperatorKind, OperatorLevel};
+        use visit::{Hooks, HooksMut, Visitor, Walk};
 
-        // Parse the text
+        let mut parser = Parser::new();
+        let txt = "a + b * c + d";
+        parser
+            .lexer
+            .add_tokens(&["+".to_string(), "*".to_string()]);
         let tokens = parser.lexer.lex_utf8(txt).unwrap();
 
-        assert_eq!(parser.lexer.stringify_slice(&tokens, txt), txt);
-        assert_eq!(parser.lexer.stringify_slice(&tokens[0..1], txt), "Function");
-        assert_eq!(parser.lexer.stringify_slice(&tokens[1..5], txt), "d\t 1");
-    }
+        let level = |name: &str, operator: &str| OperatorLevel {
+            name: name.to_string(),
+            operators: vec![grammar::MatchToken::Token(TokenKinds::Token(
+                operator.to_string(),
+            ))],
+            kind: OperatorKind::Left,
+        };
+        let mut variables = Map::new();
+        variables.insert("value".to_string(), VariableKind::Node);
+        parser.grammar.add_node(grammar::Node {
+            name: "entry".to_string(),
+            rules: vec![grammar::Rule::Precedence {
+                operand: grammar::MatchToken::Token(TokenKinds::Text),
+                levels: vec![level("sum", "+"), level("product", "*")],
+                parameters: vec![Parameters::Set("value".to_string())],
+            }],
+            variables,
+            docs: None,
+            sync: vec![],
+        });
+        let mut result = parser.parse(&tokens, txt).unwrap();
 
-    #[test]
-    fn unfinished_token() {
-        let mut parser = Parser::new();
-        let txt = "fun";
-        parser.lexer.add_token("function".to_string());
-        let tokens = parser.lexer.lex_utf8(txt).unwrap();
-        assert_eq!(tokens[0].kind, TokenKinds::Text);
+        let show = |walked: visit::Walked| match walked.nodes {
+            parser::Nodes::Node(node) => node.name.clone(),
+            parser::Nodes::Token(token) => txt[token.span()].to_string(),
+        };
+        let names: Vec<String> = result.entry.depth_first().map(show).collect();
+        assert_eq!(names, ["sum", "sum", "a", "+", "product", "b", "*", "c", "+", "d"]);
+        let names: Vec<String> = result.entry.breadth_first().map(show).collect();
+        assert_eq!(names, ["sum", "sum", "+", "d", "a", "+", "product", "b", "*", "c"]);
+        let c = result.entry.depth_first().nth(7).unwrap();
+        let path: Vec<&str> = c.path.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(path, ["entry", "sum", "sum", "product"]);
+
+        assert_eq!(result.find_all("sum").count(), 2);
+        assert_eq!(result.find_all("entry").count(), 1);
+        assert_eq!(result.entry.find_all("product").count(), 1);
+
+        // prints the nodes in parentheses without the children of products
+        struct Show<'t>(&'t str, String);
+        impl<'a> Visitor<'a> for Show<'_> {
+            fn enter(&mut self, node: &'a parser::Node, _: &[&'a parser::Node]) -> Walk {
+                self.1.push_str(&format!("({} ", node.name));
+                match node.name.as_str() {
+                    "product" => Walk::Skip,
+                    _ => Walk::Continue,
+                }
+            }
+
+            fn leave(&mut self, _: &'a parser::Node, _: &[&'a parser::Node]) -> Walk {
+                self.1.push_str(") ");
+                Walk::Continue
+            }
+
+            fn token(&mut self, token: &'a lexer::Token, _: &[&'a parser::Node]) -> Walk {
+                self.1.push_str(&format!("{} ", &self.0[token.span()]));
+                Walk::Continue
+            }
+        }
+        let mut show = Show(txt, String::new());
+        assert_eq!(result.walk(&mut show), Walk::Continue);
+        assert_eq!(show.1, "(entry (sum (sum a + (product ) ) + d ) ) ");
+
+        let mut depth = 0;
+        let mut hooks = Hooks::new().enter("product", |_, path| {
+            depth = path.len();
+            Walk::Stop
+        });
+        assert_eq!(result.walk(&mut hooks), Walk::Stop);
+        drop(hooks);
+        assert_eq!(depth, 3);
+
+        let mut hooks = HooksMut::new().leave("product", |node, path| {
+            assert_eq!(path, ["entry", "sum", "sum"]);
+            node.name = "mul".to_string();
+            Walk::Continue
+        });
+        result.entry.walk_mut(&mut hooks);
+        assert_eq!(result.find_all("mul").count(), 1);
     }
 
     #[test]
-    fn rules() {
-        let mut parser = Parser::new();
-        let txt = "let   danda=  1+60;";
-        parser.lexer.add_token("=".to_string());
-        parser.lexer.add_token(":".to_string());
-        parser.lexer.add_token("+".to_string());
-        parser.lexer.add_token(";".to_string());
-        parser.lexer.add_token("-".to_string());
-        parser.lexer.add_token("*".to_string());
-        parser.lexer.add_token("/".to_string());
+    fn incremental_reparse() {
+        use lexer::TextEdit;
+        use preprocessor::{PreprocessorStep, Region};
 
-        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        let mut parser = Parser::new();
+        parser.parser.incremental = true;
+        parser.lexer.add_tokens(&[
+            "=".to_string(),
+            ";".to_string(),
+            "\"".to_string(),
+            "#".to_string(),
+        ]);
+        parser.lexer.steps.push(PreprocessorStep::Regions(vec![
+            Region {
+                start: vec!["\"".to_string()],
+                end: Some("\"".to_string()),
+                escape: None,
+                kind: Some("string".to_string()),
+                include_start: true,
+                include_end: true,
+            },
+            Region {
+                start: vec!["#".to_string()],
+                end: None,
+                escape: None,
+                kind: None,
+                include_start: false,
+                include_end: false,
+            },
+        ]));
+        parser.lexer.steps.push(PreprocessorStep::StripWhitespace);
 
+        parser
+            .grammar
+            .globals
+            .insert("count".to_string(), VariableKind::Number);
         let mut variables = Map::new();
-        variables.insert("ident".to_string(), VariableKind::Node);
-        variables.insert("type".to_string(), VariableKind::Node);
+        variables.insert("name".to_string(), VariableKind::Node);
         variables.insert("value".to_string(), VariableKind::Node);
-
-        parser.grammar.enumerators.insert(
-            "operators".to_string(),
-            grammar::Enumerator {
-                name: "operators".to_string(),
-                values: vec![
-                    grammar::MatchToken::Token(TokenKinds::Token("+".to_string())),
-                    grammar::MatchToken::Token(TokenKinds::Token("-".to_string())),
-                    grammar::MatchToken::Token(TokenKinds::Token("*".to_string())),
-                    grammar::MatchToken::Token(TokenKinds::Token("/".to_string())),
-                ],
-            },
-        );
-
         parser.grammar.add_node(grammar::Node {
-            name: "KWLet".to_string(),
+            name: "statement".to_string(),
             rules: vec![
-                // detect the keyword
                 grammar::Rule::Is {
-                    token: grammar::MatchToken::Word("let".to_string()),
+                    token: grammar::MatchToken::Token(TokenKinds::Text),
                     rules: vec![],
-                    parameters: vec![Parameters::HardError(true)],
+                    parameters: vec![Parameters::Set("name".to_string())],
                 },
-                // detect the ident
                 grammar::Rule::Is {
-                    token: grammar::MatchToken::Token(TokenKinds::Text),
+                    token: grammar::MatchToken::Token(TokenKinds::Token("=".to_string())),
                     rules: vec![],
-                    parameters: vec![Parameters::Set("ident".to_string())],
-                },
-                // detect the type if it exists
-                grammar::Rule::Maybe {
-                    token: grammar::MatchToken::Token(TokenKinds::Token(":".to_string())),
-                    is: vec![grammar::Rule::Is {
-                        token: grammar::MatchToken::Token(TokenKinds::Text),
-                        rules: vec![],
-                        parameters: vec![Parameters::Set("type".to_string())],
-                    }],
-                    isnt: vec![],
                     parameters: vec![],
                 },
-                // detect the value if it exists
-                grammar::Rule::Maybe {
-                    token: grammar::MatchToken::Token(TokenKinds::Token("=".to_string())),
-                    is: vec![grammar::Rule::Is {
-                        token: grammar::MatchToken::Node("value".to_string()),
-                        rules: vec![],
-                        parameters: vec![Parameters::Set("value".to_string())],
-                    }],
-                    isnt: vec![],
-                    parameters: vec![],
+                grammar::Rule::IsOneOf {
+                    tokens: vec![
+                        grammar::OneOf {
+                            token: grammar::MatchToken::Token(TokenKinds::Text),
+                            rules: vec![],
+                            parameters: vec![Parameters::Set("value".to_string())],
+                        },
+                        grammar::OneOf {
+                            token: grammar::MatchToken::Token(TokenKinds::Complex(
+                                "string".to_string(),
+                            )),
+                            rules: vec![],
+                            parameters: vec![Parameters::Set("value".to_string())],
+                        },
+                    ],
                 },
-                // consume the semicolon (optional)
-                grammar::Rule::Maybe {
+                grammar::Rule::Is {
                     token: grammar::MatchToken::Token(TokenKinds::Token(";".to_string())),
-                    is: vec![],
-                    isnt: vec![],
-                    parameters: vec![],
+                    rules: vec![],
+                    parameters: vec![Parameters::IncrementGlobal("count".to_string())],
                 },
             ],
             variables,
-            docs: Some("let <ident>[: <type>] [= <value>];".to_string()),
+            docs: None,
+            sync: vec![],
         });
         let mut variables = Map::new();
-        variables.insert("nodes".to_string(), VariableKind::NodeList);
+        variables.insert("statements".to_string(), VariableKind::NodeList);
         parser.grammar.add_node(grammar::Node {
-            name: "value".to_string(),
+            name: "entry".to_string(),
+            rules: vec![grammar::Rule::While {
+                token: grammar::MatchToken::Node("statement".to_string()),
+                rules: vec![],
+                parameters: vec![Parameters::Set("statements".to_string())],
+            }],
+            variables,
+            docs: None,
+            sync: vec![],
+        });
+
+        let mut txt = "a = 1;\nb = c; # comment\nd = \"e\";\n".to_string();
+        let mut tokens = parser.lexer.lex_utf8(&txt).unwrap();
+        let mut result = parser.parse(&tokens, &txt).unwrap().into_owned();
+
+        // every edit changes one statement, the other statements and the failed attempt
+        // to parse a statement at the end of the text are reused
+        let edits = [
+            // a value inside of a statement
+            (TextEdit::new(11..12, "\"x; y\"".to_string()), 3),
+            // a new line in front of everything moves all the other tokens
+            (TextEdit::new(0..0, "# first\nz = 0;\n".to_string()), 4),
+            // the comment is replaced with a statement containing its start
+            (TextEdit::new(34..43, "c = \"#\";".to_string()), 5),
+            // the edit ends in the middle of a string
+            (TextEdit::new(43..48, "e = \"f".to_string()), 5),
+        ];
+        for (edit, reused) in edits {
+            txt.replace_range(edit.range.clone(), &edit.replacement);
+            let full_tokens = parser.lexer.lex_utf8(&txt).unwrap();
+            let full = parser.parse(&full_tokens, &txt).unwrap();
+
+            let (new_tokens, new_result) = parser.reparse(&result, &tokens, &edit, &txt).unwrap();
+            assert_eq!(new_tokens, full_tokens);
+            assert_eq!(new_result.reused(), reused);
+            assert_eq!(full.reused(), 0);
+            assert_eq!(
+                serde_json::to_value(&new_result.entry).unwrap(),
+                serde_json::to_value(&full.entry).unwrap()
+            );
+            assert_eq!(
+                serde_json::to_value(&new_result.globals).unwrap(),
+                serde_json::to_value(&full.globals).unwrap()
+            );
+            tokens = new_tokens;
+            result = new_result.into_owned();
+        }
+        let statements = result.entry.get_list("statements");
+        assert_eq!(statements.len(), 5);
+        assert_eq!(result.globals["count"].unwrap_number(), &5);
+
+        // errors are the same as those of a full parse
+        let edit = TextEdit::new(txt.len() - 2..txt.len() - 1, "".to_string());
+        txt.replace_range(edit.range.clone(), &edit.replacement);
+        let full_tokens = parser.lexer.lex_utf8(&txt).unwrap();
+        let full = parser.parse(&full_tokens, &txt).unwrap_err();
+        match parser.reparse(&result, &tokens, &edit, &txt) {
+            Err(ReparseError::Parser(err)) => assert_eq!(format!("{:?}", err), format!("{:?}", full)),
+            other => panic!("expected a parser error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn codegen() {
+        let mut grammar = grammar::Grammar::new();
+        grammar.enumerators.insert(
+            "operators".to_string(),
+            grammar::Enumerator {
+                name: "operators".to_string(),
+                values: vec![
+                    grammar::MatchToken::Token(TokenKinds::Token("+=".to_string())),
+                    grammar::MatchToken::Token(TokenKinds::Token(";".to_string())),
+                    grammar::MatchToken::Word("let".to_string()),
+                ],
+            },
+        );
+        let mut variables = Map::new();
+        variables.insert("name".to_string(), VariableKind::Node);
+        grammar.add_node(grammar::Node {
+            name: "ident".to_string(),
+            rules: vec![grammar::Rule::Is {
+                token: grammar::MatchToken::Token(TokenKinds::Text),
+                rules: vec![],
+                parameters: vec![Parameters::Set("name".to_string())],
+            }],
+            variables,
+            docs: Some("Identifier\n\n```\na\n```".to_string()),
+            sync: vec![],
+        });
+        let mut variables = Map::new();
+        variables.insert("type".to_string(), VariableKind::NodeList);
+        variables.insert("returnType".to_string(), VariableKind::Node);
+        variables.insert("count".to_string(), VariableKind::Number);
+        grammar.add_node(grammar::Node {
+            name: "entry".to_string(),
             rules: vec![
-                // detect the value[0]
-                grammar::Rule::Is {
-                    token: grammar::MatchToken::Token(TokenKinds::Text),
+                grammar::Rule::While {
+                    token: grammar::MatchToken::Node("ident".to_string()),
                     rules: vec![],
-                    parameters: vec![Parameters::Set("nodes".to_string())],
+                    parameters: vec![Parameters::Set("type".to_string())],
                 },
-                // detect the operator
-                grammar::Rule::While {
+                grammar::Rule::Is {
                     token: grammar::MatchToken::Enumerator("operators".to_string()),
-                    // detect the value[n]
-                    rules: vec![grammar::Rule::Is {
-                        token: grammar::MatchToken::Token(TokenKinds::Text),
-                        rules: vec![],
-                        parameters: vec![Parameters::Set("nodes".to_string())],
-                    }],
-                    parameters: vec![Parameters::Set("nodes".to_string())],
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("type".to_string())],
+                },
+                grammar::Rule::Is {
+                    token: grammar::MatchToken::Node("ident".to_string()),
+                    rules: vec![],
+                    parameters: vec![Parameters::Set("returnType".to_string())],
                 },
             ],
             variables,
-            docs: Some("value".to_string()),
+            docs: None,
+            sync: vec![],
         });
-        parser.parser.entry = String::from("KWLet");
 
-        let dump = serde_json::to_string(&parser);
-
-        let mut file = std::fs::File::create("KWLet.json").unwrap();
-        match dump {
-            Ok(ref dump) => {
-                file.write_all(dump.as_bytes()).unwrap();
-            }
-            Err(err) => panic!("Failed to dump grammar: {}", err),
-        }
-
-        parser.parse(&tokens, txt).unwrap();
+        let code = codegen::Generator::new().generate(&grammar);
+        // examples in the docs are not Rust doctests
+        assert!(code.contains("/// Identifier\n///\n/// ```text\n/// a\n/// ```\n"));
+        assert!(code.contains("pub struct Ident {\n    pub name: Option<Token>,\n"));
+        assert!(code.contains("    pub count: i32,\n"));
+        assert!(code.contains("    pub return_type: Option<Box<Ident>>,\n"));
+        assert!(code.contains("    pub r#type: Vec<EntryType>,\n"));
+        // a variable set to different types gets its own enum
+        assert!(code.contains(
+            "pub enum EntryType {\n    Ident(Box<Ident>),\n    Operators(Operators),\n}"
+        ));
+        assert!(code.contains(
+            "pub enum Operators {\n    PlusEq(Token),\n    Semi(Token),\n    Let(Token),\n}"
+        ));
+        assert!(code.contains("use ::ruparse::lexer::{Token, TokenKinds};\n"));
+        // there are no globals
+        assert!(!code.contains("Map"));
     }
 
     #[test]
@@ -291,6 +2411,7 @@ mod tests {
             ],
             variables,
             docs: Some("string".to_string()),
+            sync: vec![],
         });
 
         let mut variables = Map::new();
@@ -324,6 +2445,7 @@ mod tests {
             ],
             variables,
             docs: Some("entry".to_string()),
+            sync: vec![],
         });
 
         let result = parser.parse(&tokens, txt).unwrap();
@@ -402,6 +2524,7 @@ mod tests {
             ],
             variables,
             docs: Some("string".to_string()),
+            sync: vec![],
         });
 
         let mut variables = Map::new();
@@ -435,6 +2558,7 @@ mod tests {
             ],
             variables,
             docs: Some("entry".to_string()),
+            sync: vec![],
         });
 
         let result = parser.parse(&tokens, &txt).unwrap();
@@ -467,4 +2591,343 @@ mod tests {
             " 500 * 9"
         );
     }
+
+    #[test]
+    fn composing_grammars() {
+        use compose::{Conflict, MergeError};
+        use grammar::validator::ValidationErrors;
+        use lexer::{CharClass, Pattern};
+
+        let digits = |min| Pattern::Repeat {
+            pattern: Box::new(Pattern::Class(CharClass::Range('0', '9'))),
+            min,
+            max: None,
+        };
+        let mut math = Parser::new();
+        math.lexer.add_tokens(&["+".to_string(), "-".to_string()]);
+        math.lexer.add_pattern("int".to_string(), digits(1));
+        math.grammar = r#"
+            global depth: number
+            enum ops { '+' '-' }
+            node expr {
+                is @int -> node_start, increment_global(depth)
+                while %ops {
+                    is @int
+                }
+            }
+        "#
+        .parse()
+        .unwrap();
+
+        let mut parser = Parser::new();
+        parser.lexer.add_tokens(&["=".to_string(), "-".to_string()]);
+        parser.grammar = r#"
+            node entry {
+                var value: node
+                is "let"
+                is text
+                is '='
+                is <math::expr> -> set(value)
+            }
+        "#
+        .parse()
+        .unwrap();
+        parser.import_as(&math, "math").unwrap();
+        assert!(parser.validate().errors.is_empty());
+        assert!(parser.grammar.globals.contains_key("math::depth"));
+        assert!(parser.grammar.enumerators.contains_key("math::ops"));
+
+        // the printed grammar keeps the namespaces
+        let printed = dsl::print(&parser.grammar);
+        assert!(printed.contains("%math::ops"));
+        assert!(printed.contains("increment_global(math::depth)"));
+        let reparsed: grammar::Grammar = printed.parse().unwrap();
+        assert_eq!(dsl::print(&reparsed), printed);
+
+        let txt = "let x = 1 + 2 - 3";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        let result = parser.parse(&tokens, txt).unwrap();
+        let value = result.entry.try_get_node("value").as_ref().unwrap();
+        assert_eq!(value.name(), "math::expr");
+        assert_eq!(result.slice(result.entry.try_get("value").unwrap().unwrap()), "1 + 2 - 3");
+
+        // nothing is merged when a name is taken
+        let mut other = math.clone();
+        other.lexer = lexer::Lexer::new();
+        other.lexer.add_pattern("int".to_string(), digits(2));
+        let err = parser.import_as(&other, "math").unwrap_err();
+        assert_eq!(
+            err,
+            MergeError {
+                conflicts: vec![
+                    Conflict::Node("math::expr".to_string()),
+                    Conflict::Pattern("int".to_string()),
+                ]
+            }
+        );
+        let mut grammar = math.grammar.clone();
+        grammar.globals.insert("depth".to_string(), VariableKind::Boolean);
+        let err = grammar.merge(&math.grammar).unwrap_err();
+        assert_eq!(
+            err.conflicts,
+            [
+                Conflict::Global {
+                    name: "depth".to_string(),
+                    kind: VariableKind::Boolean,
+                    other: VariableKind::Number,
+                },
+                Conflict::Node("expr".to_string()),
+            ]
+        );
+
+        let grammar: grammar::Grammar = r#"
+            node entry {
+                is <json::value>
+                is <math::value>
+            }
+        "#
+        .parse()
+        .unwrap();
+        let mut parser = Parser::new();
+        parser.grammar = grammar;
+        parser.import_as(&math, "math").unwrap();
+        let errors: Vec<_> = parser
+            .validate()
+            .errors
+            .into_iter()
+            .map(|err| err.kind)
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ValidationErrors::NamespaceNotFound("json".to_string()),
+                ValidationErrors::NodeNotFound("math::value".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_modes() {
+        use grammar::validator::ValidationErrors;
+        use lexer::{ChunkLexer, LexerMode, TextEdit, TokenKinds};
+
+        let mut parser = Parser::new();
+        parser.lexer.add_tokens(&["+".to_string()]);
+        let mut string = LexerMode::new("string", "\"", "\"");
+        string.add_token("\\\"".to_string());
+        string.add_token("\\\\".to_string());
+        parser.lexer.add_mode(string);
+        // string interpolation lexes the tokens of the default mode up to the closing brace
+        let mut interpolation = LexerMode::new("interpolation", "${", "}").inherit();
+        interpolation.from = vec!["string".to_string()];
+        parser.lexer.add_mode(interpolation);
+        parser
+            .lexer
+            .add_mode(LexerMode::new("comment", "/*", "*/").nested());
+        parser.lexer.add_mode(LexerMode::new("line", "//", "\n"));
+
+        let txt = "a + \"b + ${c + d} \\\" e\" /* x /* + */ y */ f // g + h\n+ i";
+        let show = |tokens: &[lexer::Token]| {
+            tokens
+                .iter()
+                .filter(|token| token.kind != TokenKinds::Whitespace)
+                .map(|token| match &token.kind {
+                    TokenKinds::Token(token) => format!("'{}'", token),
+                    TokenKinds::Text => txt[token.span()].to_string(),
+                    kind => format!("{:?}", kind),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        assert_eq!(
+            show(&tokens),
+            "a '+' '\"' b + '${' c '+' d '}' '\\\"' e '\"' '/*' x '/*' + '*/' y '*/' f '//' g + h \
+             Control(Eol) '+' i Control(Eof)"
+        );
+        assert_eq!(parser.lexer.lex_ascii(txt).unwrap(), tokens);
+        let mut chunks = ChunkLexer::new(&parser.lexer);
+        let mut chunked = Vec::new();
+        for chunk in txt.as_bytes().chunks(3) {
+            chunked.extend(chunks.push(core::str::from_utf8(chunk).unwrap()));
+        }
+        chunked.extend(chunks.finish());
+        assert_eq!(chunked, tokens);
+
+        // the tries of the modes are rebuilt after deserialization
+        let json = serde_json::to_string(&parser.lexer).unwrap();
+        assert!(!json.contains("trie"));
+        let lexer: lexer::Lexer = serde_json::from_str(&json).unwrap();
+        assert_eq!(lexer.lex_utf8(txt).unwrap(), tokens);
+
+        // an edit that opens a string changes the mode of everything after it
+        let edit = TextEdit::new(0..1, "\"".to_string());
+        let edited = format!("\"{}", &txt[1..]);
+        let relexed = parser.lexer.relex(&tokens, &edit, &edited).unwrap();
+        assert_eq!(relexed, parser.lexer.lex_utf8(&edited).unwrap());
+
+        parser.grammar = r#"
+            eof = false
+            node entry {
+                is '"'
+                is text
+                is '\\"'
+            }
+        "#
+        .parse()
+        .unwrap();
+        assert!(parser.validate().errors.is_empty());
+        let mut nested = LexerMode::new("nested", "{", "}");
+        nested.from = vec!["missing".to_string()];
+        parser.lexer.add_mode(nested);
+        let errors: Vec<_> = parser
+            .validate()
+            .errors
+            .into_iter()
+            .map(|err| err.kind)
+            .collect();
+        assert_eq!(errors, [ValidationErrors::ModeNotFound("missing".to_string())]);
+    }
+
+    #[test]
+    fn parse_limits() {
+        use core::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let mut parser = Parser::new();
+        parser.lexer.add_tokens(&["(".to_string(), ")".to_string()]);
+        parser.grammar = r#"
+            node entry {
+                maybe '(' {
+                    is <entry>
+                    is ')'
+                }
+            }
+            node spin {
+                loop { }
+            }
+        "#
+        .parse()
+        .unwrap();
+        let txt = "((((((((()))))))))";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        assert!(parser.parse(&tokens, txt).is_ok());
+
+        parser.parser.max_depth = Some(20);
+        assert!(parser.parse(&tokens, txt).is_ok());
+        parser.parser.max_depth = Some(5);
+        let err = parser.parse(&tokens, txt).unwrap_err();
+        assert!(matches!(err.kind(), parser::ParseErrors::TooDeep(5)));
+        // the limit is not recovered from
+        let recovered = parser.parse_recovering(&tokens, txt);
+        assert!(matches!(
+            recovered.errors.last().unwrap().kind(),
+            parser::ParseErrors::TooDeep(5)
+        ));
+        parser.parser.max_depth = None;
+
+        parser.parser.fuel = Some(1000);
+        assert!(parser.parse(&tokens, txt).is_ok());
+        parser.parser.entry = "spin".to_string();
+        let err = parser.parse(&tokens, txt).unwrap_err();
+        assert!(matches!(err.kind(), parser::ParseErrors::OutOfFuel(1000)));
+        parser.parser.fuel = None;
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        parser.parser.cancel = Some(cancel.clone());
+        parser.parser.entry = "entry".to_string();
+        assert!(parser.parse(&tokens, txt).is_ok());
+        cancel.store(true, Ordering::Relaxed);
+        parser.parser.entry = "spin".to_string();
+        let err = parser.parse(&tokens, txt).unwrap_err();
+        assert!(matches!(err.kind(), parser::ParseErrors::Cancelled));
+    }
+
+    #[test]
+    fn lossless_tree() {
+        use cst::{CstElement, TriviaKind};
+        use preprocessor::{PreprocessorStep, Region};
+
+        let mut parser = Parser::new();
+        parser
+            .lexer
+            .add_tokens(&["=".to_string(), ";".to_string(), "//".to_string()]);
+        parser.lexer.steps.push(PreprocessorStep::Regions(vec![Region {
+            start: vec!["//".to_string()],
+            end: None,
+            escape: None,
+            kind: None,
+            include_start: false,
+            include_end: false,
+        }]));
+        parser.lexer.steps.push(PreprocessorStep::StripWhitespace);
+        parser.grammar = r#"
+            node entry {
+                var lets: list
+                while <assignment> -> set(lets)
+            }
+            node assignment {
+                var name: node
+                is "let" -> node_start
+                is text -> set(name)
+                is '='
+                is text
+                is ';'
+            }
+        "#
+        .parse()
+        .unwrap();
+
+        let txt = "// header\nlet a = 1; // one\n\n  let b = 2;\n// end\n";
+        let tokens = parser.lexer.lex_utf8(txt).unwrap();
+        let result = parser.parse(&tokens, txt).unwrap();
+        let cst = result.cst(&tokens);
+        assert_eq!(cst.to_string(), txt);
+
+        let nodes: Vec<_> = cst
+            .root
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                CstElement::Node(node) => Some(node),
+                CstElement::Token(_) => None,
+            })
+            .collect();
+        assert_eq!(nodes.len(), 2);
+        assert!(nodes.iter().all(|node| node.name == "assignment"));
+        let text: Vec<_> = nodes[0]
+            .tokens()
+            .iter()
+            .map(|token| cst.slice(token.span.clone()))
+            .collect();
+        assert_eq!(text, ["let", "a", "=", "1", ";"]);
+        assert_eq!(cst.slice(nodes[1].span().unwrap()), "let b = 2;");
+
+        let trivia = |trivia: &[cst::Trivia]| {
+            trivia
+                .iter()
+                .map(|trivia| (trivia.kind, cst.slice(trivia.span.clone())))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            trivia(nodes[0].leading_trivia()),
+            [(TriviaKind::Skipped, "// header"), (TriviaKind::Eol, "\n")]
+        );
+        assert_eq!(
+            trivia(nodes[0].trailing_trivia()),
+            [
+                (TriviaKind::Whitespace, " "),
+                (TriviaKind::Skipped, "// one"),
+                (TriviaKind::Eol, "\n")
+            ]
+        );
+        assert_eq!(
+            trivia(nodes[1].leading_trivia()),
+            [(TriviaKind::Eol, "\n"), (TriviaKind::Whitespace, "  ")]
+        );
+        assert_eq!(
+            trivia(&cst.eof.leading),
+            [(TriviaKind::Skipped, "// end"), (TriviaKind::Eol, "\n")]
+        );
+    }
 }
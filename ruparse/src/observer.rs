@@ -0,0 +1,251 @@
+//! Hooks into the progress of the parser
+//!
+//! `Parser::parse_observed` calls a `ParseObserver` when it enters and leaves nodes,
+//! matches tokens, applies parameters and backtracks. Observers work without std,
+//! the `Profiler` uses them to count the attempts and time spent in every node.
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    grammar::{MatchToken, Parameters},
+    parser::{Node, ParseError},
+    Map,
+};
+
+// Choose between std and alloc
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        extern crate std;
+        use std::prelude::v1::*;
+    } else {
+        extern crate alloc;
+        use alloc::string::*;
+        use alloc::vec::*;
+        use alloc::boxed::Box;
+    }
+}
+
+/// Receives the events of the parser
+///
+/// Positions are indexes into the tokens. Events between `enter_node` and `exit_node`
+/// belong to that node. Nodes reused from the cache of a memoizing parser are not entered.
+/// All the methods do nothing by default.
+#[allow(unused_variables)]
+pub trait ParseObserver {
+    /// The parser starts parsing the node at the token
+    fn enter_node(&mut self, name: &str, token: usize) {}
+
+    /// The node is done, `token` is where the parser continues
+    fn exit_node(&mut self, name: &str, token: usize, result: Result<&Node, &ParseError>) {}
+
+    /// A token or a node of a rule matched at the token
+    fn rule_matched(&mut self, token: &MatchToken, at: usize) {}
+
+    /// A token or a node of a rule did not match at the token
+    fn rule_failed(&mut self, token: &MatchToken, at: usize, error: &ParseError) {}
+
+    /// The parser gives back the tokens it looked at from `to` up to `from`
+    ///
+    /// Happens when a node fails and when an operator is left to a lower precedence
+    fn backtrack(&mut self, from: usize, to: usize) {}
+
+    /// The parameter is applied to the matched value
+    fn parameter(&mut self, parameter: &Parameters) {}
+}
+
+/// Measures how the parser spends its time
+///
+/// ```rust
+/// let mut profiler = ruparse::observer::Profiler::new();
+/// // parser.parse_observed(&tokens, text, &mut profiler)
+/// println!("{}", profiler.report());
+/// ```
+pub struct Profiler {
+    /// Current time in any unit, only differences are used
+    clock: Option<Box<dyn FnMut() -> u64>>,
+    /// Entered nodes with the time they were entered and the time spent in their children
+    stack: Vec<(String, u64, u64)>,
+    nodes: Map<String, NodeProfile>,
+    backtracks: Map<(String, usize), Backtrack>,
+}
+
+impl Profiler {
+    /// Profiler measuring time in nanoseconds
+    #[cfg(feature = "std")]
+    pub fn new() -> Profiler {
+        let start = std::time::Instant::now();
+        Profiler::with_clock(move || start.elapsed().as_nanos() as u64)
+    }
+
+    /// Profiler only counting attempts, there is no clock without std
+    #[cfg(not(feature = "std"))]
+    pub fn new() -> Profiler {
+        Profiler {
+            clock: None,
+            stack: Vec::new(),
+            nodes: Map::new(),
+            backtracks: Map::new(),
+        }
+    }
+
+    /// Profiler measuring time with the clock
+    pub fn with_clock(clock: impl FnMut() -> u64 + 'static) -> Profiler {
+        Profiler {
+            clock: Some(Box::new(clock)),
+            stack: Vec::new(),
+            nodes: Map::new(),
+            backtracks: Map::new(),
+        }
+    }
+
+    fn now(&mut self) -> u64 {
+        match &mut self.clock {
+            Some(clock) => clock(),
+            None => 0,
+        }
+    }
+
+    /// Nodes sorted by the time spent in them and points of backtracking sorted by count
+    pub fn report(&self) -> Profile {
+        let mut nodes: Vec<NodeProfile> = self.nodes.values().cloned().collect();
+        nodes.sort_by(|a, b| {
+            b.time
+                .cmp(&a.time)
+                .then(b.attempts.cmp(&a.attempts))
+                .then(a.name.cmp(&b.name))
+        });
+        let mut backtracks: Vec<Backtrack> = self.backtracks.values().cloned().collect();
+        backtracks.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(b.tokens.cmp(&a.tokens))
+                .then(a.node.cmp(&b.node))
+                .then(a.token.cmp(&b.token))
+        });
+        Profile { nodes, backtracks }
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParseObserver for Profiler {
+    fn enter_node(&mut self, name: &str, _token: usize) {
+        let now = self.now();
+        self.stack.push((name.to_string(), now, 0));
+    }
+
+    fn exit_node(&mut self, name: &str, _token: usize, result: Result<&Node, &ParseError>) {
+        let now = self.now();
+        let (_, entered, children) = match self.stack.pop() {
+            Some(entry) => entry,
+            None => return,
+        };
+        let time = now.saturating_sub(entered);
+        if let Some(parent) = self.stack.last_mut() {
+            parent.2 += time;
+        }
+        let profile = self
+            .nodes
+            .entry(name.to_string())
+            .or_insert_with(|| NodeProfile {
+                name: name.to_string(),
+                ..Default::default()
+            });
+        profile.attempts += 1;
+        profile.matches += result.is_ok() as usize;
+        profile.time += time;
+        profile.self_time += time.saturating_sub(children);
+    }
+
+    fn backtrack(&mut self, from: usize, to: usize) {
+        let node = match self.stack.last() {
+            Some((node, _, _)) => node.clone(),
+            None => return,
+        };
+        let backtrack = self
+            .backtracks
+            .entry((node.clone(), to))
+            .or_insert_with(|| Backtrack {
+                node,
+                token: to,
+                count: 0,
+                tokens: 0,
+            });
+        backtrack.count += 1;
+        backtrack.tokens += from.saturating_sub(to);
+    }
+}
+
+/// Report of a `Profiler`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub nodes: Vec<NodeProfile>,
+    pub backtracks: Vec<Backtrack>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct NodeProfile {
+    pub name: String,
+    /// Number of times the node was parsed
+    pub attempts: usize,
+    /// Number of times the node matched
+    pub matches: usize,
+    /// Time spent in the node including the nodes in it
+    pub time: u64,
+    /// Time spent in the node without the nodes in it
+    pub self_time: u64,
+}
+
+/// Place where the parser gave back tokens
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Backtrack {
+    /// Node that failed or left an operator to its caller
+    pub node: String,
+    /// Token the parser went back to
+    pub token: usize,
+    pub count: usize,
+    /// Number of tokens given back in total
+    pub tokens: usize,
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .nodes
+            .iter()
+            .map(|node| node.name.len())
+            .chain(self.backtracks.iter().map(|backtrack| backtrack.node.len()))
+            .fold("backtracking".len(), usize::max);
+        writeln!(
+            f,
+            "{:width$} {:>8} {:>8} {:>12} {:>12}",
+            "node", "attempts", "matches", "time", "self"
+        )?;
+        for node in &self.nodes {
+            writeln!(
+                f,
+                "{:width$} {:>8} {:>8} {:>12} {:>12}",
+                node.name, node.attempts, node.matches, node.time, node.self_time
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:width$} {:>8} {:>8} {:>12}",
+            "backtracking", "token", "count", "tokens"
+        )?;
+        for backtrack in &self.backtracks {
+            writeln!(
+                f,
+                "{:width$} {:>8} {:>8} {:>12}",
+                backtrack.node, backtrack.token, backtrack.count, backtrack.tokens
+            )?;
+        }
+        Ok(())
+    }
+}
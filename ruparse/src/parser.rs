@@ -1,3 +1,6 @@
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use crate::Map;
 
 use serde::{Deserialize, Serialize};
@@ -5,8 +8,10 @@ use serde::{Deserialize, Serialize};
 const DEFAULT_ENTRY: &str = "entry";
 
 use crate::{
+    diagnostic::{describe_match_token, describe_token},
     grammar::{self, Grammar, MatchToken, OneOf},
-    lexer::{Lexer, TextLocation, Token, TokenKinds},
+    lexer::{Lexer, Shift, TextEdit, TextLocation, Token, TokenKinds},
+    observer::ParseObserver,
 };
 
 // Choose between std and alloc
@@ -15,13 +20,16 @@ cfg_if::cfg_if! {
         extern crate std;
         use std::prelude::v1::*;
         use std::fmt;
+        use std::sync::Arc;
+        use std::borrow::Cow;
     } else {
         extern crate alloc;
         use alloc::string::*;
         use alloc::vec::*;
         use alloc::vec;
         use core::fmt;
-        use alloc::format;
+        use alloc::sync::Arc;
+        use alloc::borrow::Cow;
     }
 }
 
@@ -30,42 +38,213 @@ pub struct Parser {
     pub entry: String,
     /// Option to enable error on eof
     pub eof_error: bool,
+    /// Option to cache the results of nodes by their position (packrat parsing)
+    ///
+    /// Backtracking over a node that has already been parsed at the same position
+    /// reuses the result instead of parsing the node again
+    #[serde(default)]
+    pub memoize: bool,
+    /// Option to keep the results of all the nodes in the parse result
+    ///
+    /// The kept results let the parser parse the text again after an edit
+    /// without parsing the nodes that were not affected by it
+    #[serde(default)]
+    pub incremental: bool,
+    /// Maximal number of nodes parsed inside of each other
+    ///
+    /// Deeper nodes fail the parse with `ParseErrors::TooDeep` before the stack overflows
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Maximal number of rules the parser executes
+    ///
+    /// Stops grammars that loop forever with `ParseErrors::OutOfFuel`
+    #[serde(default)]
+    pub fuel: Option<usize>,
+    /// Flag that stops the parse with `ParseErrors::Cancelled` once it is set
+    ///
+    /// The flag is checked every `CANCEL_INTERVAL` rules, it can be set from another thread
+    #[serde(skip)]
+    pub cancel: Option<Arc<AtomicBool>>,
 }
 
+/// Number of rules executed between the checks of the cancellation flag
+pub const CANCEL_INTERVAL: usize = 1024;
+
 impl Parser {
     pub fn new() -> Parser {
         Parser {
             entry: DEFAULT_ENTRY.to_string(),
             eof_error: false,
+            memoize: false,
+            incremental: false,
+            max_depth: None,
+            fuel: None,
+            cancel: None,
+        }
+    }
+
+    pub(crate) fn parse<'src>(
+        &self,
+        grammar: &Grammar,
+        lexer: &Lexer,
+        text: &'src str,
+        tokens: &Vec<Token>,
+    ) -> Result<ParseResult<'src>, ParseError> {
+        let mut state = ParseState::new(false, self.memo());
+        let mut result = self
+            .parse_entry(grammar, lexer, text, tokens, &mut state)
+            .map_err(|(err, _)| err)?;
+        result.memo = state.memo.filter(|memo| memo.keep_all);
+        Ok(result)
+    }
+
+    /// Parses the tokens and tells the observer what the parser is doing
+    pub(crate) fn parse_observed<'src>(
+        &self,
+        grammar: &Grammar,
+        lexer: &Lexer,
+        text: &'src str,
+        tokens: &Vec<Token>,
+        observer: &mut dyn ParseObserver,
+    ) -> Result<ParseResult<'src>, ParseError> {
+        let mut state = ParseState::new(false, self.memo());
+        state.observer = Some(observer);
+        let mut result = self
+            .parse_entry(grammar, lexer, text, tokens, &mut state)
+            .map_err(|(err, _)| err)?;
+        result.memo = state.memo.take().filter(|memo| memo.keep_all);
+        Ok(result)
+    }
+
+    /// Parses the text again after an edit
+    ///
+    /// `memo` holds the results of the previous parse that are still valid,
+    /// see `ParseResult::reusable`
+    pub(crate) fn reparse<'src>(
+        &self,
+        grammar: &Grammar,
+        lexer: &Lexer,
+        text: &'src str,
+        tokens: &Vec<Token>,
+        memo: Memo,
+    ) -> Result<ParseResult<'src>, ParseError> {
+        let mut state = ParseState::new(false, Some(memo));
+        let mut result = self
+            .parse_entry(grammar, lexer, text, tokens, &mut state)
+            .map_err(|(err, _)| err)?;
+        result.memo = state.memo;
+        Ok(result)
+    }
+
+    /// Cache for the results of nodes as configured by the options
+    fn memo(&self) -> Option<Memo> {
+        match (self.incremental, self.memoize) {
+            (true, _) => Some(Memo::new(true)),
+            (false, true) => Some(Memo::new(false)),
+            (false, false) => None,
         }
     }
 
-    pub(crate) fn parse(
+    /// Parses the text and recovers from errors in nodes with synchronization tokens
+    ///
+    /// Every recovered error is collected and the parser continues after the
+    /// synchronization token, so all the errors in the text can be reported at once.
+    /// If the entry node itself fails, its partial tree is returned together with the error.
+    pub(crate) fn parse_recovering<'src>(
+        &self,
+        grammar: &Grammar,
+        lexer: &Lexer,
+        text: &'src str,
+        tokens: &Vec<Token>,
+    ) -> RecoveryResult<'src> {
+        let mut state = ParseState::new(true, self.memo());
+        let mut result = match self.parse_entry(grammar, lexer, text, tokens, &mut state) {
+            Ok(result) => result,
+            Err((err, mut result)) => {
+                result.entry.recovered = true;
+                state.errors.push(err);
+                result
+            }
+        };
+        result.memo = state.memo.filter(|memo| memo.keep_all);
+        RecoveryResult {
+            result,
+            errors: state.errors,
+        }
+    }
+
+    fn parse_entry<'src>(
         &self,
         grammar: &Grammar,
         lexer: &Lexer,
-        text: &str,
+        text: &'src str,
         tokens: &Vec<Token>,
-    ) -> Result<ParseResult, ParseError> {
-        let mut cursor = Cursor {
-            idx: 0,
-            to_advance: false,
+        state: &mut ParseState,
+    ) -> Result<ParseResult<'src>, (ParseError, ParseResult<'src>)> {
+        let globals = match Node::variables_from_grammar(&grammar.globals) {
+            Ok(globals) => globals,
+            Err(err) => {
+                let entry = Node::new(self.entry.clone());
+                let globals = Map::new();
+                return Err((
+                    err,
+                    ParseResult {
+                        entry,
+                        globals,
+                        text: Cow::Borrowed(text),
+                        memo: None,
+                    },
+                ));
+            }
         };
-        let mut globals = Node::variables_from_grammar(&grammar.globals)?;
-        let entry = match self.parse_node(
+        let mut ctx = ParseContext {
             grammar,
             lexer,
-            &self.entry,
-            &mut cursor,
-            &mut globals,
             tokens,
             text,
-        ) {
+            cursor: Cursor {
+                idx: 0,
+                to_advance: false,
+            },
+            globals,
+            state,
+        };
+        let result = self.parse_node(&mut ctx, &self.entry);
+        let ParseContext {
+            mut cursor,
+            globals,
+            state,
+            ..
+        } = ctx;
+        // A limit fails the parse even if the nodes recovered from it
+        if let Some(kind) = state.stopped.take() {
+            let node = match result {
+                Ok(node) | Err((_, node)) => node,
+            };
+            let err = ParseError {
+                kind,
+                location: tokens[cursor.idx].location.clone(),
+                span: tokens[cursor.idx].span(),
+                node: Some(node.clone()),
+            };
+            let result = ParseResult {
+                entry: node,
+                globals,
+                text: Cow::Borrowed(text),
+                memo: None,
+            };
+            return Err((err, result));
+        }
+        let entry = match result {
             Ok(node) => {
                 if !grammar.eof {
                     node
                 } else {
                     // If the grammar has an eof token, we need to check if the cursor is at the end of the tokens
+                    // The last matched token is still under the cursor
+                    if cursor.to_advance && cursor.idx + 1 < tokens.len() {
+                        cursor.idx += 1;
+                    }
                     // Consume all the whitespace tokens
                     while cursor.idx < tokens.len() && tokens[cursor.idx].kind.is_whitespace() {
                         cursor.idx += 1;
@@ -75,74 +254,200 @@ impl Parser {
                     {
                         node
                     } else {
-                        return Err(ParseError {
+                        let err = ParseError {
                             kind: ParseErrors::MissingEof(tokens[cursor.idx].kind.clone()),
                             location: tokens[cursor.idx].location.clone(),
-                            node: Some(node),
-                        });
+                            span: tokens[cursor.idx].span(),
+                            node: Some(node.clone()),
+                        };
+                        let result = ParseResult {
+                            entry: node,
+                            globals,
+                            text: Cow::Borrowed(text),
+                            memo: None,
+                        };
+                        return Err((err, result));
                     }
                 }
             }
-            Err((err, _)) => return Err(err),
+            Err((mut err, node)) => {
+                if err.node.is_none() {
+                    err.node = Some(node.clone());
+                }
+                let result = ParseResult {
+                    entry: node,
+                    globals,
+                    text: Cow::Borrowed(text),
+                    memo: None,
+                };
+                return Err((err, result));
+            }
         };
 
-        Ok(ParseResult { entry, globals })
+        Ok(ParseResult {
+            entry,
+            globals,
+            text: Cow::Borrowed(text),
+            memo: None,
+        })
+    }
+
+    fn parse_node(&self, ctx: &mut ParseContext, name: &str) -> Result<Node, (ParseError, Node)> {
+        if ctx.state.memo.is_none() {
+            return self.parse_node_uncached(ctx, name);
+        }
+        let position = (ctx.cursor.idx, ctx.cursor.to_advance);
+        if let Some(memo) = &mut ctx.state.memo {
+            let keep_all = memo.keep_all;
+            match memo.get(position, name) {
+                // Most nodes are parsed only once at the same position, caching them
+                // would only cost time, so results are kept once the parser comes back
+                None if !keep_all => {
+                    memo.insert(position, name, MemoSlot::Seen);
+                    return self.parse_node_uncached(ctx, name);
+                }
+                // Moved results are brought up to date by `get`
+                None | Some(MemoSlot::Seen) | Some(MemoSlot::Moved(..)) => (),
+                // The node compared a global that has a different value now
+                Some(MemoSlot::Done(entry)) if !entry.still_valid(&ctx.globals, ctx.text) => (),
+                Some(MemoSlot::Done(entry)) => {
+                    #[cfg(feature = "debug")]
+                    println!("-- cached: {}, cursor: {:?}", name, ctx.cursor);
+                    ctx.cursor = entry.cursor.clone();
+                    let result = entry.result.clone();
+                    let errors = entry.errors.clone();
+                    let writes = entry.writes.clone();
+                    let reads = entry.reads.clone();
+                    let lookahead = entry.lookahead;
+                    for write in &writes {
+                        write.apply(&mut ctx.globals, ctx.text);
+                    }
+                    let writes_len = memo.writes.len();
+                    memo.reads.extend(reads.into_iter().map(|read| GlobalRead {
+                        writes: read.writes + writes_len,
+                        ..read
+                    }));
+                    memo.writes.extend(writes);
+                    memo.hits += 1;
+                    ctx.state.errors.extend(errors);
+                    ctx.state.peek(lookahead);
+                    return result;
+                }
+            }
+        }
+        let errors_len = ctx.state.errors.len();
+        let writes_len = ctx.state.memo.as_ref().map_or(0, |memo| memo.writes.len());
+        let reads_len = ctx.state.memo.as_ref().map_or(0, |memo| memo.reads.len());
+        // Tokens looked at by the node are counted separately from its parents
+        let parent_lookahead = core::mem::replace(&mut ctx.state.lookahead, ctx.cursor.idx);
+        let result = self.parse_node_uncached(ctx, name);
+        let lookahead = ctx.state.lookahead;
+        ctx.state.peek(parent_lookahead);
+        if let Some(memo) = &mut ctx.state.memo {
+            let entry = MemoEntry {
+                result: result.clone(),
+                cursor: ctx.cursor.clone(),
+                lookahead,
+                errors: ctx.state.errors[errors_len..].to_vec(),
+                writes: memo.writes[writes_len..].to_vec(),
+                reads: memo.reads[reads_len..]
+                    .iter()
+                    .map(|read| GlobalRead {
+                        writes: read.writes - writes_len,
+                        ..read.clone()
+                    })
+                    .collect(),
+            };
+            memo.insert(position, name, MemoSlot::Done(Arc::new(entry)));
+        }
+        result
     }
 
-    fn parse_node(
+    fn parse_node_uncached(
         &self,
-        grammar: &Grammar,
-        lexer: &Lexer,
+        ctx: &mut ParseContext,
+        name: &str,
+    ) -> Result<Node, (ParseError, Node)> {
+        let start = ctx.cursor.idx;
+        if let Some(kind) = ctx.state.enter(self) {
+            let err = ParseError {
+                kind,
+                location: ctx.tokens[start].location.clone(),
+                span: ctx.tokens[start].span(),
+                node: None,
+            };
+            return Err((err, Node::new(name.to_string())));
+        }
+        ctx.state
+            .observe(|observer| observer.enter_node(name, start));
+        // Tokens looked at by the node are counted separately from its parents
+        let parent_lookahead = core::mem::replace(&mut ctx.state.lookahead, start);
+        let result = self.parse_node_rules(ctx, name);
+        let furthest = ctx.state.lookahead;
+        ctx.state.peek(parent_lookahead);
+        if let Some(observer) = &mut ctx.state.observer {
+            if result.is_err() {
+                observer.backtrack(furthest, start);
+            }
+            observer.exit_node(
+                name,
+                ctx.cursor.idx + ctx.cursor.to_advance as usize,
+                result.as_ref().map_err(|(err, _)| err),
+            );
+        }
+        ctx.state.depth -= 1;
+        result
+    }
+
+    fn parse_node_rules(
+        &self,
+        ctx: &mut ParseContext,
         name: &str,
-        cursor: &mut Cursor,
-        globals: &mut Map<String, VariableKind>,
-        tokens: &Vec<Token>,
-        text: &str,
     ) -> Result<Node, (ParseError, Node)> {
         #[cfg(feature = "debug")]
-        println!("-- start: {}, cursor: {:?}", name, cursor);
-        let mut node = match Node::from_grammar(grammar, name) {
+        println!("-- start: {}, cursor: {:?}", name, ctx.cursor);
+        let mut node = match Node::from_grammar(ctx.grammar, name) {
             Ok(node) => node,
             Err(err) => return Err((err, Node::new(name.to_string()))),
         };
-        node.first_string_idx = tokens[cursor.idx].index;
+        node.first_string_idx = ctx.tokens[ctx.cursor.idx].index;
         // In case the node fails to parse, we want to restore the cursor to its original position
-        let cursor_clone = cursor.clone();
-        let rules = match grammar.nodes.get(name) {
+        let cursor_clone = ctx.cursor.clone();
+        // Errors recovered inside of this node are discarded if the node fails
+        let errors_len = ctx.state.errors.len();
+        let rules = match ctx.grammar.nodes.get(name) {
             Some(node) => &node.rules,
             None => {
                 return Err((
                     ParseError {
                         kind: ParseErrors::NodeNotFound(name.to_string()),
-                        location: tokens[cursor.idx].location.clone(),
+                        location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                        span: ctx.tokens[ctx.cursor.idx].span(),
                         node: Some(node.clone()),
                     },
                     node,
                 ))
             }
         };
-        let result = self.parse_rules(
-            grammar,
-            lexer,
-            rules,
-            cursor,
-            globals,
-            &cursor_clone,
-            &mut node,
-            tokens,
-            text,
-        );
+        let result = self.parse_rules(ctx, rules, &cursor_clone, &mut node);
 
         #[cfg(feature = "debug")]
-        println!("-- end: {}, cursor: {:?}", name, cursor);
+        println!("-- end: {}, cursor: {:?}", name, ctx.cursor);
 
-        // If the node has not set the last_string_idx, we set it to the end of the last token
+        // If the node has not set the last_string_idx, we set it to the end of the last consumed token
         if node.last_string_idx == 0 {
-            if cursor.idx >= tokens.len() {
-                node.last_string_idx = tokens.last().unwrap().index + tokens.last().unwrap().len;
-            } else {
-                node.last_string_idx = tokens[cursor.idx].index + tokens[cursor.idx].len;
+            // The token under the cursor is consumed only if the cursor is waiting to advance
+            let begin = cursor_clone.idx + cursor_clone.to_advance as usize;
+            let mut end = (ctx.cursor.idx + ctx.cursor.to_advance as usize).min(ctx.tokens.len());
+            // Trailing whitespace is not part of the node
+            while end > begin + 1 && ctx.tokens[end - 1].kind.is_whitespace() {
+                end -= 1;
             }
+            node.last_string_idx = if end > begin {
+                ctx.tokens[end - 1].index + ctx.tokens[end - 1].len
+            } else {
+                node.first_string_idx
+            };
         }
 
         match result {
@@ -152,7 +457,8 @@ impl Parser {
                 Msg::Break(n) => Err((
                     ParseError {
                         kind: ParseErrors::CannotBreak(n),
-                        location: tokens[cursor.idx].location.clone(),
+                        location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                        span: ctx.tokens[ctx.cursor.idx].span(),
                         node: Some(node.clone()),
                     },
                     node,
@@ -160,7 +466,8 @@ impl Parser {
                 Msg::Back(steps) => Err((
                     ParseError {
                         kind: ParseErrors::CannotGoBack(steps),
-                        location: tokens[cursor.idx].location.clone(),
+                        location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                        span: ctx.tokens[ctx.cursor.idx].span(),
                         node: Some(node.clone()),
                     },
                     node,
@@ -168,58 +475,114 @@ impl Parser {
                 Msg::Goto(label) => Err((
                     ParseError {
                         kind: ParseErrors::LabelNotFound(label),
-                        location: tokens[cursor.idx].location.clone(),
+                        location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                        span: ctx.tokens[ctx.cursor.idx].span(),
                         node: Some(node.clone()),
                     },
                     node,
                 )),
             },
-            Err(err) => {
+            Err(mut err) => {
                 #[cfg(feature = "debug")]
                 println!("error: {:?}", err);
-                *cursor = cursor_clone;
+                // Hard errors leave the node, so the error remembers where it happened
+                if node.harderror && err.node.is_none() {
+                    err.node = Some(node.clone());
+                }
+                if ctx.state.recover && node.harderror {
+                    let sync = &ctx.grammar.nodes[name].sync;
+                    if let Some(idx) = Self::synchronize(sync, &err, &cursor_clone, ctx.tokens) {
+                        #[cfg(feature = "debug")]
+                        println!("recovered: {}, cursor: {}", name, idx);
+                        ctx.state.errors.push(err);
+                        ctx.state.peek(idx);
+                        node.recovered = true;
+                        node.last_string_idx = ctx.tokens[idx].index + ctx.tokens[idx].len;
+                        // The sync token is consumed like any other matched token
+                        ctx.cursor.idx = idx;
+                        ctx.cursor.to_advance = !ctx.tokens[idx].kind.is_eof();
+                        return Ok(node);
+                    }
+                }
+                ctx.state.errors.truncate(errors_len);
+                ctx.cursor = cursor_clone;
                 Err((err, node))
             }
         }
     }
 
+    /// Finds the token to continue from after an error
+    ///
+    /// Skips from the location of the error to the first synchronization token.
+    /// Returns None if there are no synchronization tokens or if the parser would not move
+    fn synchronize(
+        sync: &[TokenKinds],
+        err: &ParseError,
+        start: &Cursor,
+        tokens: &[Token],
+    ) -> Option<usize> {
+        // The first token that has not been consumed yet
+        let begin = start.idx + start.to_advance as usize;
+        if sync.is_empty() || begin >= tokens.len() {
+            return None;
+        }
+        let location = (err.location.line, err.location.column);
+        let mut idx = begin
+            + tokens[begin..]
+                .partition_point(|tok| (tok.location.line, tok.location.column) < location);
+        while idx < tokens.len() {
+            if tokens[idx].kind.is_eof() || sync.contains(&tokens[idx].kind) {
+                break;
+            }
+            idx += 1;
+        }
+        if idx >= tokens.len() || (idx == begin && tokens[idx].kind.is_eof()) {
+            return None;
+        }
+        Some(idx)
+    }
+
     fn parse_rules(
         &self,
-        grammar: &Grammar,
-        lexer: &Lexer,
+        ctx: &mut ParseContext,
         rules: &Vec<grammar::Rule>,
-        cursor: &mut Cursor,
-        globals: &mut Map<String, VariableKind>,
         cursor_clone: &Cursor,
         node: &mut Node,
-        tokens: &Vec<Token>,
-        text: &str,
     ) -> Result<Msg, ParseError> {
         let mut advance = true;
         let mut msg_bus = MsgBus::new();
         let mut i = 0;
         while i < rules.len() {
             let rule = &rules[i];
-            if cursor.to_advance {
-                cursor.to_advance = false;
-                cursor.idx += 1;
-                if cursor.idx >= tokens.len() {
+            if let Some(kind) = ctx.state.step(self) {
+                return Err(ParseError {
+                    kind,
+                    location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                    span: ctx.tokens[ctx.cursor.idx].span(),
+                    node: Some(node.clone()),
+                });
+            }
+            if ctx.cursor.to_advance {
+                ctx.cursor.to_advance = false;
+                ctx.cursor.idx += 1;
+                if ctx.cursor.idx >= ctx.tokens.len() {
                     if self.eof_error {
                         return Err(ParseError {
                             kind: ParseErrors::Eof,
-                            location: tokens[cursor.idx - 1].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx - 1].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx - 1].span(),
                             node: Some(node.clone()),
                         });
                     } else {
-                        cursor.idx -= 1;
+                        ctx.cursor.idx -= 1;
                     }
                 }
             }
             #[cfg(feature = "debug")]
             println!(
                 "tok: <{}> kind: {:?} -- parent: {}",
-                lexer.stringify(&tokens[cursor.idx], text),
-                tokens[cursor.idx].kind,
+                ctx.lexer.stringify(&ctx.tokens[ctx.cursor.idx], ctx.text),
+                ctx.tokens[ctx.cursor.idx].kind,
                 node.name
             );
             #[cfg(feature = "debug")]
@@ -231,45 +594,14 @@ impl Parser {
                     rules,
                     parameters,
                 } => {
-                    match self.match_token(
-                        grammar,
-                        lexer,
-                        token,
-                        cursor,
-                        globals,
-                        cursor_clone,
-                        tokens,
-                        text,
-                    )? {
+                    match self.match_token(ctx, token, cursor_clone)? {
                         TokenCompare::Is(val) => {
                             let is_token = val.is_token();
-                            self.parse_parameters(
-                                grammar,
-                                lexer,
-                                parameters,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                node,
-                                &val,
-                                &mut msg_bus,
-                                tokens,
-                                text,
-                            )?;
+                            self.parse_parameters(ctx, parameters, node, &val, &mut msg_bus)?;
                             if is_token {
-                                cursor.to_advance = true;
+                                ctx.cursor.to_advance = true;
                             }
-                            self.parse_rules(
-                                grammar,
-                                lexer,
-                                rules,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                node,
-                                tokens,
-                                text,
-                            )?
+                            self.parse_rules(ctx, rules, cursor_clone, node)?
                             .push(&mut msg_bus);
                         }
                         TokenCompare::IsNot(err) => {
@@ -282,37 +614,17 @@ impl Parser {
                     rules,
                     parameters: _,
                 } => {
-                    match self.match_token(
-                        grammar,
-                        lexer,
-                        token,
-                        cursor,
-                        globals,
-                        cursor_clone,
-                        tokens,
-                        text,
-                    )? {
+                    match self.match_token(ctx, token, cursor_clone)? {
                         TokenCompare::Is(_) => {
                             err(
-                                ParseErrors::ExpectedToNotBe(tokens[cursor.idx].kind.clone()),
-                                cursor,
+                                ParseErrors::ExpectedToNotBe(ctx.tokens[ctx.cursor.idx].kind.clone()),
+                                ctx,
                                 cursor_clone,
-                                &tokens[cursor.idx].location,
                                 Some(node.clone()),
                             )?;
                         }
                         TokenCompare::IsNot(_) => {
-                            self.parse_rules(
-                                grammar,
-                                lexer,
-                                rules,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                node,
-                                tokens,
-                                text,
-                            )?
+                            self.parse_rules(ctx, rules, cursor_clone, node)?
                             .push(&mut msg_bus);
                         }
                     }
@@ -328,48 +640,17 @@ impl Parser {
                         use TokenCompare::*;
                         #[cfg(feature = "debug")]
                         println!("trying option: {:?}", token);
-                        match self.match_token(
-                            grammar,
-                            lexer,
-                            &token,
-                            cursor,
-                            globals,
-                            cursor_clone,
-                            tokens,
-                            text,
-                        )? {
+                        match self.match_token(ctx, &token, cursor_clone)? {
                             Is(val) => {
                                 #[cfg(feature = "debug")]
                                 println!("success");
                                 found = true;
                                 let is_token = val.is_token();
-                                self.parse_parameters(
-                                    grammar,
-                                    lexer,
-                                    parameters,
-                                    cursor,
-                                    globals,
-                                    cursor_clone,
-                                    node,
-                                    &val,
-                                    &mut msg_bus,
-                                    tokens,
-                                    text,
-                                )?;
+                                self.parse_parameters(ctx, parameters, node, &val, &mut msg_bus)?;
                                 if is_token {
-                                    cursor.to_advance = true;
+                                    ctx.cursor.to_advance = true;
                                 }
-                                self.parse_rules(
-                                    grammar,
-                                    lexer,
-                                    rules,
-                                    cursor,
-                                    globals,
-                                    cursor_clone,
-                                    node,
-                                    tokens,
-                                    text,
-                                )?
+                                self.parse_rules(ctx, rules, cursor_clone, node)?
                                 .push(&mut msg_bus);
                                 break;
                             }
@@ -384,7 +665,7 @@ impl Parser {
                                 None => {
                                     #[cfg(feature = "debug")]
                                     println!("recoverable error: {:?}", err);
-                                    cursor.to_advance = false;
+                                    ctx.cursor.to_advance = false;
                                 }
                             },
                         }
@@ -393,11 +674,10 @@ impl Parser {
                         err(
                             ParseErrors::ExpectedOneOf{
                                 expected: pos_tokens.iter().map(|x| x.token.clone()).collect(),
-                                found: tokens[cursor.idx].kind.clone(),
+                                found: ctx.tokens[ctx.cursor.idx].kind.clone(),
                             },
-                            cursor,
+                            ctx,
                             cursor_clone,
-                            &tokens[cursor.idx].location,
                             Some(node.clone()),
                         )?;
                     }
@@ -409,45 +689,14 @@ impl Parser {
                     parameters,
                 } => {
                     use TokenCompare::*;
-                    match self.match_token(
-                        grammar,
-                        lexer,
-                        token,
-                        cursor,
-                        globals,
-                        cursor_clone,
-                        tokens,
-                        text,
-                    )? {
+                    match self.match_token(ctx, token, cursor_clone)? {
                         Is(val) => {
                             let is_token = val.is_token();
-                            self.parse_parameters(
-                                grammar,
-                                lexer,
-                                parameters,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                node,
-                                &val,
-                                &mut msg_bus,
-                                tokens,
-                                text,
-                            )?;
+                            self.parse_parameters(ctx, parameters, node, &val, &mut msg_bus)?;
                             if is_token {
-                                cursor.to_advance = true;
+                                ctx.cursor.to_advance = true;
                             }
-                            self.parse_rules(
-                                grammar,
-                                lexer,
-                                is,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                node,
-                                tokens,
-                                text,
-                            )?
+                            self.parse_rules(ctx, is, cursor_clone, node)?
                             .push(&mut msg_bus);
                         }
                         IsNot(err) => {
@@ -459,17 +708,7 @@ impl Parser {
                                 }
                                 None => (),
                             }
-                            self.parse_rules(
-                                grammar,
-                                lexer,
-                                isnt,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                node,
-                                tokens,
-                                text,
-                            )?
+                            self.parse_rules(ctx, isnt, cursor_clone, node)?
                             .push(&mut msg_bus);
                         }
                     }
@@ -483,48 +722,17 @@ impl Parser {
                     } in is_one_of
                     {
                         use TokenCompare::*;
-                        match self.match_token(
-                            grammar,
-                            lexer,
-                            &token,
-                            cursor,
-                            globals,
-                            cursor_clone,
-                            tokens,
-                            text,
-                        )? {
+                        match self.match_token(ctx, &token, cursor_clone)? {
                             Is(val) => {
                                 found = true;
                                 let is_token = val.is_token();
-                                self.parse_parameters(
-                                    grammar,
-                                    lexer,
-                                    parameters,
-                                    cursor,
-                                    globals,
-                                    cursor_clone,
-                                    node,
-                                    &val,
-                                    &mut msg_bus,
-                                    tokens,
-                                    text,
-                                )?;
+                                self.parse_parameters(ctx, parameters, node, &val, &mut msg_bus)?;
                                 #[cfg(feature = "debug")]
                                 println!("is_token: {}", is_token);
                                 if is_token {
-                                    cursor.to_advance = true;
+                                    ctx.cursor.to_advance = true;
                                 }
-                                self.parse_rules(
-                                    grammar,
-                                    lexer,
-                                    rules,
-                                    cursor,
-                                    globals,
-                                    cursor_clone,
-                                    node,
-                                    tokens,
-                                    text,
-                                )?
+                                self.parse_rules(ctx, rules, cursor_clone, node)?
                                 .push(&mut msg_bus);
                                 break;
                             }
@@ -539,17 +747,7 @@ impl Parser {
                         }
                     }
                     if !found {
-                        self.parse_rules(
-                            grammar,
-                            lexer,
-                            isnt,
-                            cursor,
-                            globals,
-                            cursor_clone,
-                            node,
-                            tokens,
-                            text,
-                        )?
+                        self.parse_rules(ctx, isnt, cursor_clone, node)?
                         .push(&mut msg_bus);
                     }
                 }
@@ -558,45 +756,14 @@ impl Parser {
                     rules,
                     parameters,
                 } => {
-                    match self.match_token(
-                        grammar,
-                        lexer,
-                        token,
-                        cursor,
-                        globals,
-                        cursor_clone,
-                        tokens,
-                        text,
-                    )? {
+                    match self.match_token(ctx, token, cursor_clone)? {
                         TokenCompare::Is(val) => {
                             let is_token = val.is_token();
-                            self.parse_parameters(
-                                grammar,
-                                lexer,
-                                parameters,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                node,
-                                &val,
-                                &mut msg_bus,
-                                tokens,
-                                text,
-                            )?;
+                            self.parse_parameters(ctx, parameters, node, &val, &mut msg_bus)?;
                             if is_token {
-                                cursor.to_advance = true;
+                                ctx.cursor.to_advance = true;
                             }
-                            self.parse_rules(
-                                grammar,
-                                lexer,
-                                rules,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                node,
-                                tokens,
-                                text,
-                            )?
+                            self.parse_rules(ctx, rules, cursor_clone, node)?
                             .push(&mut msg_bus);
                             advance = false;
                         }
@@ -610,9 +777,9 @@ impl Parser {
                         },
                     }
                     #[cfg(feature = "debug")]
-                    println!("WHILE DONE, CURSOR.TO_ADVANCE = {}", cursor.to_advance);
+                    println!("WHILE DONE, CURSOR.TO_ADVANCE = {}", ctx.cursor.to_advance);
                     #[cfg(feature = "debug")]
-                    println!("\t - WHILE DONE, CURSOR.IDX = {}", cursor.idx);
+                    println!("\t - WHILE DONE, CURSOR.IDX = {}", ctx.cursor.idx);
                 }
                 grammar::Rule::Until {
                     token,
@@ -620,53 +787,37 @@ impl Parser {
                     parameters,
                 } => {
                     // search for the token and execute the rules when the token is found
-                    while let TokenCompare::IsNot(_) = self.match_token(
-                        grammar,
-                        lexer,
-                        token,
-                        cursor,
-                        globals,
-                        cursor_clone,
-                        tokens,
-                        text,
-                    )? {
+                    while let TokenCompare::IsNot(_) = self.match_token(ctx, token, cursor_clone)? {
                         // No need to handle the error here
-                        cursor.idx += 1;
-                        if cursor.idx >= tokens.len() {
+                        ctx.cursor.idx += 1;
+                        if ctx.cursor.idx >= ctx.tokens.len() {
                             return Err(ParseError {
                                 kind: ParseErrors::CouldNotFindToken(token.clone()),
-                                location: tokens[cursor.idx - 1].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx - 1].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx - 1].span(),
                                 node: Some(node.clone()),
                             });
                         }
                     }
                     self.parse_parameters(
-                        grammar,
-                        lexer,
+                        ctx,
                         parameters,
-                        cursor,
-                        globals,
-                        cursor_clone,
                         node,
-                        &Nodes::Token(tokens[cursor.idx].clone()),
+                        &Nodes::Token(ctx.tokens[ctx.cursor.idx].clone()),
                         &mut msg_bus,
-                        tokens,
-                        text,
                     )?;
-                    cursor.to_advance = true;
-                    self.parse_rules(
-                        grammar,
-                        lexer,
-                        rules,
-                        cursor,
-                        globals,
-                        cursor_clone,
-                        node,
-                        tokens,
-                        text,
-                    )?
+                    ctx.cursor.to_advance = true;
+                    self.parse_rules(ctx, rules, cursor_clone, node)?
                     .push(&mut msg_bus);
                 }
+                grammar::Rule::Precedence {
+                    operand,
+                    levels,
+                    parameters,
+                } => {
+                    let val = self.parse_precedence(ctx, operand, levels, 0, cursor_clone, node)?;
+                    self.parse_parameters(ctx, parameters, node, &val, &mut msg_bus)?;
+                }
                 grammar::Rule::Command { command } => match command {
                     grammar::Commands::Compare {
                         left,
@@ -679,7 +830,8 @@ impl Parser {
                             None => {
                                 return Err(ParseError {
                                     kind: ParseErrors::VariableNotFound(left.to_string()),
-                                    location: tokens[cursor.idx].location.clone(),
+                                    location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                    span: ctx.tokens[ctx.cursor.idx].span(),
                                     node: Some(node.clone()),
                                 })
                             }
@@ -689,7 +841,8 @@ impl Parser {
                             None => {
                                 return Err(ParseError {
                                     kind: ParseErrors::VariableNotFound(right.to_string()),
-                                    location: tokens[cursor.idx].location.clone(),
+                                    location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                    span: ctx.tokens[ctx.cursor.idx].span(),
                                     node: Some(node.clone()),
                                 })
                             }
@@ -758,25 +911,80 @@ impl Parser {
                                     vec![grammar::Comparison::NotEqual]
                                 }
                             }
+                            VariableKind::Text(Some(left)) => match right {
+                                VariableKind::Text(Some(right)) => ordered(left.cmp(right)),
+                                _ => vec![grammar::Comparison::NotEqual],
+                            },
+                            VariableKind::Text(None) => match right {
+                                VariableKind::Text(None) => vec![grammar::Comparison::Equal],
+                                _ => vec![grammar::Comparison::NotEqual],
+                            },
+                            VariableKind::Span(_) | VariableKind::TokenList(_) => {
+                                vec![grammar::Comparison::NotEqual]
+                            }
                         };
                         if comparisons.contains(comparison) {
-                            self.parse_rules(
-                                grammar,
-                                lexer,
-                                rules,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                node,
-                                tokens,
-                                text,
-                            )?
+                            self.parse_rules(ctx, rules, cursor_clone, node)?
+                            .push(&mut msg_bus);
+                        }
+                    }
+                    grammar::Commands::CompareLiteral {
+                        variable,
+                        comparison,
+                        value,
+                        rules,
+                    } => {
+                        let kind = match node.variables.get(variable) {
+                            Some(kind) => kind,
+                            None => match ctx.globals.get(variable) {
+                                Some(kind) => {
+                                    ctx.state.read(variable, kind);
+                                    kind
+                                }
+                                None => {
+                                    return Err(ParseError {
+                                        kind: ParseErrors::VariableNotFound(variable.to_string()),
+                                        location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                        span: ctx.tokens[ctx.cursor.idx].span(),
+                                        node: Some(node.clone()),
+                                    })
+                                }
+                            },
+                        };
+                        let comparisons = match (kind, value) {
+                            (VariableKind::Number(left), grammar::Literal::Number(right)) => {
+                                ordered(left.cmp(right))
+                            }
+                            (VariableKind::Boolean(left), grammar::Literal::Boolean(right)) => {
+                                ordered(left.cmp(right))
+                            }
+                            (VariableKind::Text(Some(left)), grammar::Literal::Text(right)) => {
+                                ordered(left.as_str().cmp(right.as_str()))
+                            }
+                            (VariableKind::Text(None), grammar::Literal::Text(_)) => {
+                                vec![grammar::Comparison::NotEqual]
+                            }
+                            _ => {
+                                return Err(ParseError {
+                                    kind: ParseErrors::CannotCompareVariable(
+                                        variable.to_string(),
+                                        kind.clone(),
+                                    ),
+                                    location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                    span: ctx.tokens[ctx.cursor.idx].span(),
+                                    node: Some(node.clone()),
+                                })
+                            }
+                        };
+                        if comparisons.contains(comparison) {
+                            self.parse_rules(ctx, rules, cursor_clone, node)?
                             .push(&mut msg_bus);
                         }
                     }
                     grammar::Commands::Error { message } => Err(ParseError {
                         kind: ParseErrors::Message(message.to_string()),
-                        location: tokens[cursor.idx].location.clone(),
+                        location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                        span: ctx.tokens[ctx.cursor.idx].span(),
                         node: Some(node.clone()),
                     })?,
                     grammar::Commands::HardError { set } => {
@@ -792,17 +1000,7 @@ impl Parser {
                     }
                 },
                 grammar::Rule::Loop { rules } => {
-                    self.parse_rules(
-                        grammar,
-                        lexer,
-                        rules,
-                        cursor,
-                        globals,
-                        cursor_clone,
-                        node,
-                        tokens,
-                        text,
-                    )?
+                    self.parse_rules(ctx, rules, cursor_clone, node)?
                     .push(&mut msg_bus);
                     advance = false;
                 }
@@ -810,7 +1008,7 @@ impl Parser {
                     tokens: match_tokens,
                 } => {
                     let mut found = false;
-                    while cursor.idx < tokens.len() {
+                    while ctx.cursor.idx < ctx.tokens.len() {
                         for OneOf {
                             token,
                             rules,
@@ -818,46 +1016,21 @@ impl Parser {
                         } in match_tokens
                         {
                             use TokenCompare::*;
-                            match self.match_token(
-                                grammar,
-                                lexer,
-                                token,
-                                cursor,
-                                globals,
-                                cursor_clone,
-                                tokens,
-                                text,
-                            )? {
+                            match self.match_token(ctx, token, cursor_clone)? {
                                 Is(val) => {
                                     found = true;
                                     let is_token = val.is_token();
                                     self.parse_parameters(
-                                        grammar,
-                                        lexer,
+                                        ctx,
                                         parameters,
-                                        cursor,
-                                        globals,
-                                        cursor_clone,
                                         node,
                                         &val,
                                         &mut msg_bus,
-                                        tokens,
-                                        text,
                                     )?;
                                     if is_token {
-                                        cursor.to_advance = true;
+                                        ctx.cursor.to_advance = true;
                                     }
-                                    self.parse_rules(
-                                        grammar,
-                                        lexer,
-                                        rules,
-                                        cursor,
-                                        globals,
-                                        cursor_clone,
-                                        node,
-                                        tokens,
-                                        text,
-                                    )?
+                                    self.parse_rules(ctx, rules, cursor_clone, node)?
                                     .push(&mut msg_bus);
                                     break;
                                 }
@@ -874,17 +1047,16 @@ impl Parser {
                         if found {
                             break;
                         }
-                        cursor.idx += 1;
+                        ctx.cursor.idx += 1;
                     }
                     if !found {
                         err(
                             ParseErrors::ExpectedOneOf{
                                 expected: match_tokens.iter().map(|x| x.token.clone()).collect(),
-                                found: tokens[cursor.idx].kind.clone(),
+                                found: ctx.tokens[ctx.cursor.idx].kind.clone(),
                             },
-                            cursor,
+                            ctx,
                             cursor_clone,
-                            &tokens[cursor.idx].location,
                             Some(node.clone()),
                         )?;
                     }
@@ -899,7 +1071,8 @@ impl Parser {
                                     None => {
                                         return Err(ParseError {
                                             kind: ParseErrors::VariableNotFound(ident.to_string()),
-                                            location: tokens[cursor.idx].location.clone(),
+                                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                            span: ctx.tokens[ctx.cursor.idx].span(),
                                             node: Some(node.clone()),
                                         })
                                     }
@@ -907,10 +1080,13 @@ impl Parser {
                                 println!("{:?}", kind);
                             }
                             None => {
-                                if cursor.idx >= tokens.len() {
+                                if ctx.cursor.idx >= ctx.tokens.len() {
                                     println!("Eof");
                                 } else {
-                                    println!("{:?}", lexer.stringify(&tokens[cursor.idx], text));
+                                    println!(
+                                        "{:?}",
+                                        ctx.lexer.stringify(&ctx.tokens[ctx.cursor.idx], ctx.text)
+                                    );
                                 }
                             }
                         }
@@ -969,19 +1145,32 @@ impl Parser {
 
     fn match_token(
         &self,
-        grammar: &Grammar,
-        lexer: &Lexer,
+        ctx: &mut ParseContext,
+        token: &grammar::MatchToken,
+        cursor_clone: &Cursor,
+    ) -> Result<TokenCompare, ParseError> {
+        let at = ctx.cursor.idx;
+        let result = self.compare_token(ctx, token, cursor_clone);
+        if let Some(observer) = &mut ctx.state.observer {
+            match &result {
+                Ok(TokenCompare::Is(_)) => observer.rule_matched(token, at),
+                Ok(TokenCompare::IsNot(err)) | Err(err) => observer.rule_failed(token, at, err),
+            }
+        }
+        result
+    }
+
+    fn compare_token(
+        &self,
+        ctx: &mut ParseContext,
         token: &grammar::MatchToken,
-        cursor: &mut Cursor,
-        globals: &mut Map<String, VariableKind>,
         cursor_clone: &Cursor,
-        tokens: &Vec<Token>,
-        text: &str,
     ) -> Result<TokenCompare, ParseError> {
         match token {
             grammar::MatchToken::Token(tok) => {
                 if *tok == TokenKinds::Control(crate::lexer::ControlTokenKind::Eof) {
-                    if cursor.idx >= tokens.len() {
+                    if ctx.cursor.idx >= ctx.tokens.len() {
+                        ctx.state.peek(ctx.tokens.len() - 1);
                         return Ok(TokenCompare::Is(Nodes::Token(Token {
                             kind: TokenKinds::Control(crate::lexer::ControlTokenKind::Eof),
                             index: 0,
@@ -990,18 +1179,21 @@ impl Parser {
                         })));
                     }
                 }
-                if cursor.idx >= tokens.len() {
+                if ctx.cursor.idx >= ctx.tokens.len() {
+                    ctx.state.peek(ctx.tokens.len() - 1);
                     return Ok(TokenCompare::IsNot(ParseError {
                         kind: ParseErrors::Eof,
-                        location: tokens[cursor.idx - 1].location.clone(),
+                        location: ctx.tokens[ctx.cursor.idx - 1].location.clone(),
+                        span: ctx.tokens[ctx.cursor.idx - 1].span(),
                         node: None,
                     }));
                 }
-                let mut current_token = &tokens[cursor.idx];
+                let mut current_token = &ctx.tokens[ctx.cursor.idx];
                 while current_token.kind.is_whitespace() {
-                    cursor.idx += 1;
-                    current_token = &tokens[cursor.idx];
+                    ctx.cursor.idx += 1;
+                    current_token = &ctx.tokens[ctx.cursor.idx];
                 }
+                ctx.state.peek(ctx.cursor.idx);
                 if *tok != current_token.kind {
                     return Ok(TokenCompare::IsNot(ParseError {
                         kind: ParseErrors::ExpectedToken {
@@ -1009,13 +1201,14 @@ impl Parser {
                             found: current_token.kind.clone(),
                         },
                         location: current_token.location.clone(),
+                        span: current_token.span(),
                         node: None,
                     }));
                 }
                 Ok(TokenCompare::Is(Nodes::Token(current_token.clone())))
             }
             grammar::MatchToken::Node(node_name) => {
-                match self.parse_node(grammar, lexer, node_name, cursor, globals, tokens, text) {
+                match self.parse_node(ctx, node_name) {
                     Ok(node) => return Ok(TokenCompare::Is(Nodes::Node(node))),
                     Err((err, node)) => match node.harderror {
                         true => return Err(err),
@@ -1024,19 +1217,21 @@ impl Parser {
                 };
             }
             grammar::MatchToken::Word(word) => {
-                let mut current_token = &tokens[cursor.idx];
+                let mut current_token = &ctx.tokens[ctx.cursor.idx];
                 while current_token.kind.is_whitespace() {
-                    cursor.idx += 1;
-                    current_token = &tokens[cursor.idx];
+                    ctx.cursor.idx += 1;
+                    current_token = &ctx.tokens[ctx.cursor.idx];
                 }
+                ctx.state.peek(ctx.cursor.idx);
                 if let TokenKinds::Text = current_token.kind {
-                    if word != &lexer.stringify(&current_token, text) {
+                    if word != &ctx.lexer.stringify(&current_token, ctx.text) {
                         return Ok(TokenCompare::IsNot(ParseError {
                             kind: ParseErrors::ExpectedWord {
                                 expected: word.clone(),
                                 found: current_token.kind.clone(),
                             },
                             location: current_token.location.clone(),
+                            span: current_token.span(),
                             node: None,
                         }));
                     }
@@ -1047,6 +1242,7 @@ impl Parser {
                             found: current_token.kind.clone(),
                         },
                         location: current_token.location.clone(),
+                        span: current_token.span(),
                         node: None,
                     }));
                 }
@@ -1056,49 +1252,42 @@ impl Parser {
                 #[cfg(feature = "debug")]
                 println!(
                     "keys: {:?}",
-                    grammar.enumerators.keys().collect::<Vec<&String>>()
+                    ctx.grammar.enumerators.keys().collect::<Vec<&String>>()
                 );
                 #[cfg(feature = "debug")]
                 println!("key: {enumerator}");
                 #[cfg(feature = "debug")]
-                println!("got: {}", grammar.enumerators.get(enumerator).is_some());
-                let enumerator = match grammar.enumerators.get(enumerator) {
+                println!("got: {}", ctx.grammar.enumerators.get(enumerator).is_some());
+                let enumerator = match ctx.grammar.enumerators.get(enumerator) {
                     Some(enumerator) => enumerator,
                     None => {
                         return Err(ParseError {
                             kind: ParseErrors::EnumeratorNotFound(enumerator.clone()),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         });
                     }
                 };
                 let mut i = 0;
-                let cursor_clone_local = cursor.clone();
+                let cursor_clone_local = ctx.cursor.clone();
                 let token = loop {
                     if i >= enumerator.values.len() {
                         return Ok(TokenCompare::IsNot(ParseError {
                             kind: ParseErrors::ExpectedOneOf{
                                 expected: enumerator.values.iter().map(|x| x.clone()).collect(),
-                                found: tokens[cursor.idx].kind.clone(),
+                                found: ctx.tokens[ctx.cursor.idx].kind.clone(),
                             },
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         }));
                     }
                     let token = &enumerator.values[i];
-                    match self.match_token(
-                        grammar,
-                        lexer,
-                        token,
-                        cursor,
-                        globals,
-                        cursor_clone,
-                        tokens,
-                        text,
-                    )? {
+                    match self.match_token(ctx, token, cursor_clone)? {
                         TokenCompare::Is(val) => break val,
                         TokenCompare::IsNot(err) => {
-                            *cursor = cursor_clone_local.clone();
+                            ctx.cursor = cursor_clone_local.clone();
                             if let Some(node) = &err.node {
                                 if node.harderror {
                                     return Err(err);
@@ -1113,27 +1302,148 @@ impl Parser {
                 Ok(TokenCompare::Is(token))
             }
             grammar::MatchToken::Any => {
-                let token = tokens[cursor.idx].clone();
+                ctx.state.peek(ctx.cursor.idx);
+                let token = ctx.tokens[ctx.cursor.idx].clone();
                 Ok(TokenCompare::Is(Nodes::Token(token)))
             }
         }
     }
 
+    /// Parses operands and operators of at least the given level
+    ///
+    /// Operators of lower levels are left to the callers,
+    /// so the operators of higher levels end up deeper in the tree.
+    /// A missing operand after a matched operator is a hard error of the node.
+    fn parse_precedence(
+        &self,
+        ctx: &mut ParseContext,
+        operand: &grammar::MatchToken,
+        levels: &[grammar::OperatorLevel],
+        min_level: usize,
+        cursor_clone: &Cursor,
+        node: &mut Node,
+    ) -> Result<Nodes, ParseError> {
+        if !ctx.cursor.advance(ctx.tokens.len()) {
+            return Err(ParseError {
+                kind: ParseErrors::Eof,
+                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                span: ctx.tokens[ctx.cursor.idx].span(),
+                node: None,
+            });
+        }
+        let prefix = self.match_operator(ctx, levels, true, cursor_clone)?;
+        let mut left = match prefix {
+            Some((level, operator)) => {
+                // Prefix operators take everything that binds tighter than them
+                let operand = self
+                    .parse_precedence(ctx, operand, levels, level, cursor_clone, node)
+                    .inspect_err(|_| node.harderror = true)?;
+                operator_node(
+                    &levels[level].name,
+                    vec![("operator", operator), ("operand", operand)],
+                )
+            }
+            None => match self.match_token(ctx, operand, cursor_clone)? {
+                TokenCompare::Is(val) => {
+                    if val.is_token() {
+                        ctx.cursor.to_advance = true;
+                    }
+                    val
+                }
+                TokenCompare::IsNot(err) => return Err(err),
+            },
+        };
+        loop {
+            let before = ctx.cursor.clone();
+            let (level, operator) = match self.match_operator(ctx, levels, false, cursor_clone)? {
+                Some(found) => found,
+                None => break,
+            };
+            if level < min_level {
+                // The operator is left to the caller
+                let from = ctx.cursor.idx;
+                ctx.state
+                    .observe(|observer| observer.backtrack(from, before.idx));
+                ctx.cursor = before;
+                break;
+            }
+            let kind = levels[level].kind;
+            left = match kind {
+                grammar::OperatorKind::Postfix => operator_node(
+                    &levels[level].name,
+                    vec![("operand", left), ("operator", operator)],
+                ),
+                _ => {
+                    // Operators grouped from the left leave the same level to this loop
+                    let min_level = match kind {
+                        grammar::OperatorKind::Left => level + 1,
+                        _ => level,
+                    };
+                    let right = self
+                        .parse_precedence(ctx, operand, levels, min_level, cursor_clone, node)
+                        .inspect_err(|_| node.harderror = true)?;
+                    operator_node(
+                        &levels[level].name,
+                        vec![("left", left), ("operator", operator), ("right", right)],
+                    )
+                }
+            };
+        }
+        Ok(left)
+    }
+
+    /// Matches an operator of any level
+    ///
+    /// Returns the level of the operator, the cursor is left unchanged if nothing matches
+    fn match_operator(
+        &self,
+        ctx: &mut ParseContext,
+        levels: &[grammar::OperatorLevel],
+        prefix: bool,
+        cursor_clone: &Cursor,
+    ) -> Result<Option<(usize, Nodes)>, ParseError> {
+        let before = ctx.cursor.clone();
+        if !ctx.cursor.advance(ctx.tokens.len()) {
+            return Ok(None);
+        }
+        for (i, level) in levels.iter().enumerate() {
+            if (level.kind == grammar::OperatorKind::Prefix) != prefix {
+                continue;
+            }
+            for operator in &level.operators {
+                let start = ctx.cursor.clone();
+                match self.match_token(ctx, operator, cursor_clone)? {
+                    TokenCompare::Is(val) => {
+                        if val.is_token() {
+                            ctx.cursor.to_advance = true;
+                        }
+                        return Ok(Some((i, val)));
+                    }
+                    TokenCompare::IsNot(err) => {
+                        if let Some(node) = &err.node {
+                            if node.harderror {
+                                return Err(err);
+                            }
+                        }
+                        ctx.cursor = start;
+                    }
+                }
+            }
+        }
+        ctx.cursor = before;
+        Ok(None)
+    }
+
     fn parse_parameters(
         &self,
-        _grammar: &Grammar,
-        _lexer: &Lexer,
+        ctx: &mut ParseContext,
         parameters: &Vec<grammar::Parameters>,
-        cursor: &mut Cursor,
-        globals: &mut Map<String, VariableKind>,
-        _cursor_clone: &Cursor,
         node: &mut Node,
         value: &Nodes,
         bus: &mut MsgBus,
-        tokens: &Vec<Token>,
-        _text: &str,
     ) -> Result<(), ParseError> {
         for parameter in parameters {
+            ctx.state.observe(|observer| observer.parameter(parameter));
             match parameter {
                 grammar::Parameters::Set(name) => {
                     let kind = match node.variables.get_mut(name) {
@@ -1141,29 +1451,20 @@ impl Parser {
                         None => {
                             return Err(ParseError {
                                 kind: ParseErrors::VariableNotFound(name.to_string()),
-                                location: tokens[cursor.idx].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
                                 node: None,
                             })
                         }
                     };
-                    match kind {
-                        VariableKind::Node(single) => {
-                            *single = Some(value.clone());
-                        }
-                        VariableKind::NodeList(list) => {
-                            list.push(value.clone());
-                        }
-                        VariableKind::Boolean(_) => Err(ParseError {
-                            kind: ParseErrors::CannotSetVariable(name.to_string(), kind.clone()),
-                            location: tokens[cursor.idx].location.clone(),
-                            node: None,
-                        })?,
-                        VariableKind::Number(_) => Err(ParseError {
+                    if !kind.set(value, ctx.text) {
+                        return Err(ParseError {
                             kind: ParseErrors::CannotSetVariable(name.to_string(), kind.clone()),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
-                        })?,
-                    };
+                        });
+                    }
                 }
                 grammar::Parameters::Print(_str) => {
                     #[cfg(feature = "std")]
@@ -1178,7 +1479,8 @@ impl Parser {
                                 None => {
                                     return Err(ParseError {
                                         kind: ParseErrors::VariableNotFound(_ident.to_string()),
-                                        location: tokens[cursor.idx].location.clone(),
+                                        location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                        span: ctx.tokens[ctx.cursor.idx].span(),
                                         node: None,
                                     })
                                 }
@@ -1189,10 +1491,13 @@ impl Parser {
                     None =>
                     {
                         #[cfg(feature = "std")]
-                        if cursor.idx >= tokens.len() {
+                        if ctx.cursor.idx >= ctx.tokens.len() {
                             println!("Eof");
                         } else {
-                            println!("{:?}", _lexer.stringify(&tokens[cursor.idx], _text));
+                            println!(
+                                "{:?}",
+                                ctx.lexer.stringify(&ctx.tokens[ctx.cursor.idx], ctx.text)
+                            );
                         }
                     }
                 },
@@ -1202,7 +1507,8 @@ impl Parser {
                         None => {
                             return Err(ParseError {
                                 kind: ParseErrors::VariableNotFound(ident.to_string()),
-                                location: tokens[cursor.idx].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
                                 node: None,
                             })
                         }
@@ -1210,17 +1516,23 @@ impl Parser {
                     match kind {
                         VariableKind::Node(_) => Err(ParseError {
                             kind: ParseErrors::UncountableVariable(ident.to_string(), kind.clone()),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         })?,
                         VariableKind::NodeList(_) => Err(ParseError {
                             kind: ParseErrors::UncountableVariable(ident.to_string(), kind.clone()),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         })?,
-                        VariableKind::Boolean(_) => Err(ParseError {
+                        VariableKind::Boolean(_)
+                        | VariableKind::Span(_)
+                        | VariableKind::TokenList(_)
+                        | VariableKind::Text(_) => Err(ParseError {
                             kind: ParseErrors::UncountableVariable(ident.to_string(), kind.clone()),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         })?,
                         VariableKind::Number(val) => {
@@ -1234,7 +1546,8 @@ impl Parser {
                         None => {
                             return Err(ParseError {
                                 kind: ParseErrors::VariableNotFound(ident.to_string()),
-                                location: tokens[cursor.idx].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
                                 node: None,
                             })
                         }
@@ -1242,17 +1555,23 @@ impl Parser {
                     match kind {
                         VariableKind::Node(_) => Err(ParseError {
                             kind: ParseErrors::UncountableVariable(ident.to_string(), kind.clone()),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         })?,
                         VariableKind::NodeList(_) => Err(ParseError {
                             kind: ParseErrors::UncountableVariable(ident.to_string(), kind.clone()),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         })?,
-                        VariableKind::Boolean(_) => Err(ParseError {
+                        VariableKind::Boolean(_)
+                        | VariableKind::Span(_)
+                        | VariableKind::TokenList(_)
+                        | VariableKind::Text(_) => Err(ParseError {
                             kind: ParseErrors::UncountableVariable(ident.to_string(), kind.clone()),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         })?,
                         VariableKind::Number(val) => {
@@ -1266,7 +1585,8 @@ impl Parser {
                         None => {
                             return Err(ParseError {
                                 kind: ParseErrors::VariableNotFound(variable.to_string()),
-                                location: tokens[cursor.idx].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
                                 node: None,
                             })
                         }
@@ -1279,7 +1599,8 @@ impl Parser {
                                 variable.to_string(),
                                 kind.clone(),
                             ),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         });
                     }
@@ -1290,7 +1611,8 @@ impl Parser {
                         None => {
                             return Err(ParseError {
                                 kind: ParseErrors::VariableNotFound(variable.to_string()),
-                                location: tokens[cursor.idx].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
                                 node: None,
                             })
                         }
@@ -1303,54 +1625,46 @@ impl Parser {
                                 variable.to_string(),
                                 kind.clone(),
                             ),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         });
                     }
                 }
                 grammar::Parameters::Global(variable) => {
-                    let kind = match globals.get_mut(variable) {
+                    let kind = match ctx.globals.get_mut(variable) {
                         Some(kind) => kind,
                         None => {
                             return Err(ParseError {
                                 kind: ParseErrors::VariableNotFound(variable.to_string()),
-                                location: tokens[cursor.idx].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
                                 node: None,
                             })
                         }
                     };
-                    match kind {
-                        VariableKind::Node(single) => {
-                            *single = Some(value.clone());
-                        }
-                        VariableKind::NodeList(list) => {
-                            list.push(value.clone());
-                        }
-                        VariableKind::Boolean(_) => Err(ParseError {
-                            kind: ParseErrors::CannotSetVariable(
-                                variable.to_string(),
-                                kind.clone(),
-                            ),
-                            location: tokens[cursor.idx].location.clone(),
-                            node: None,
-                        })?,
-                        VariableKind::Number(_) => Err(ParseError {
+                    if !kind.set(value, ctx.text) {
+                        return Err(ParseError {
                             kind: ParseErrors::CannotSetVariable(
                                 variable.to_string(),
                                 kind.clone(),
                             ),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
-                        })?,
-                    };
+                        });
+                    }
+                    ctx.state
+                        .record(|| GlobalWrite::Set(variable.clone(), value.clone()));
                 }
                 grammar::Parameters::IncrementGlobal(variable) => {
-                    let kind = match globals.get_mut(variable) {
+                    let kind = match ctx.globals.get_mut(variable) {
                         Some(kind) => kind,
                         None => {
                             return Err(ParseError {
                                 kind: ParseErrors::VariableNotFound(variable.to_string()),
-                                location: tokens[cursor.idx].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
                                 node: None,
                             })
                         }
@@ -1361,7 +1675,8 @@ impl Parser {
                                 variable.to_string(),
                                 kind.clone(),
                             ),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         })?,
                         VariableKind::NodeList(_) => Err(ParseError {
@@ -1369,29 +1684,37 @@ impl Parser {
                                 variable.to_string(),
                                 kind.clone(),
                             ),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         })?,
-                        VariableKind::Boolean(_) => Err(ParseError {
+                        VariableKind::Boolean(_)
+                        | VariableKind::Span(_)
+                        | VariableKind::TokenList(_)
+                        | VariableKind::Text(_) => Err(ParseError {
                             kind: ParseErrors::UncountableVariable(
                                 variable.to_string(),
                                 kind.clone(),
                             ),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         })?,
                         VariableKind::Number(val) => {
                             *val += 1;
                         }
                     };
+                    ctx.state
+                        .record(|| GlobalWrite::Increment(variable.clone()));
                 }
                 grammar::Parameters::TrueGlobal(variable) => {
-                    let kind = match globals.get_mut(variable) {
+                    let kind = match ctx.globals.get_mut(variable) {
                         Some(kind) => kind,
                         None => {
                             return Err(ParseError {
                                 kind: ParseErrors::VariableNotFound(variable.to_string()),
-                                location: tokens[cursor.idx].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
                                 node: None,
                             })
                         }
@@ -1404,18 +1727,22 @@ impl Parser {
                                 variable.to_string(),
                                 kind.clone(),
                             ),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         });
                     }
+                    ctx.state
+                        .record(|| GlobalWrite::Boolean(variable.clone(), true));
                 }
                 grammar::Parameters::FalseGlobal(variable) => {
-                    let kind = match globals.get_mut(variable) {
+                    let kind = match ctx.globals.get_mut(variable) {
                         Some(kind) => kind,
                         None => {
                             return Err(ParseError {
                                 kind: ParseErrors::VariableNotFound(variable.to_string()),
-                                location: tokens[cursor.idx].location.clone(),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
                                 node: None,
                             })
                         }
@@ -1428,19 +1755,144 @@ impl Parser {
                                 variable.to_string(),
                                 kind.clone(),
                             ),
-                            location: tokens[cursor.idx].location.clone(),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
                             node: None,
                         });
                     }
+                    ctx.state
+                        .record(|| GlobalWrite::Boolean(variable.clone(), false));
+                }
+                grammar::Parameters::Add(variable, amount) => {
+                    let kind = match node.variables.get_mut(variable) {
+                        Some(kind) => kind,
+                        None => {
+                            return Err(ParseError {
+                                kind: ParseErrors::VariableNotFound(variable.to_string()),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
+                                node: None,
+                            })
+                        }
+                    };
+                    if !kind.add(*amount) {
+                        return Err(ParseError {
+                            kind: ParseErrors::UncountableVariable(
+                                variable.to_string(),
+                                kind.clone(),
+                            ),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
+                            node: None,
+                        });
+                    }
+                }
+                grammar::Parameters::AddGlobal(variable, amount) => {
+                    let kind = match ctx.globals.get_mut(variable) {
+                        Some(kind) => kind,
+                        None => {
+                            return Err(ParseError {
+                                kind: ParseErrors::VariableNotFound(variable.to_string()),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
+                                node: None,
+                            })
+                        }
+                    };
+                    if !kind.add(*amount) {
+                        return Err(ParseError {
+                            kind: ParseErrors::UncountableVariable(
+                                variable.to_string(),
+                                kind.clone(),
+                            ),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
+                            node: None,
+                        });
+                    }
+                    ctx.state
+                        .record(|| GlobalWrite::Add(variable.clone(), *amount));
+                }
+                grammar::Parameters::Reset(variable) => match node.variables.get_mut(variable) {
+                    Some(kind) => kind.reset(),
+                    None => {
+                        return Err(ParseError {
+                            kind: ParseErrors::VariableNotFound(variable.to_string()),
+                            location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                            span: ctx.tokens[ctx.cursor.idx].span(),
+                            node: None,
+                        })
+                    }
+                },
+                grammar::Parameters::ResetGlobal(variable) => {
+                    match ctx.globals.get_mut(variable) {
+                        Some(kind) => kind.reset(),
+                        None => {
+                            return Err(ParseError {
+                                kind: ParseErrors::VariableNotFound(variable.to_string()),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
+                                node: None,
+                            })
+                        }
+                    }
+                    ctx.state.record(|| GlobalWrite::Reset(variable.clone()));
+                }
+                grammar::Parameters::Capture(variable, transform) => {
+                    let kind = match node.variables.get_mut(variable) {
+                        Some(kind) => kind,
+                        None => {
+                            return Err(ParseError {
+                                kind: ParseErrors::VariableNotFound(variable.to_string()),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
+                                node: None,
+                            })
+                        }
+                    };
+                    let captured = &ctx.text[value.span()];
+                    match (transform, &mut *kind) {
+                        (grammar::Transform::Unquote, VariableKind::Text(single)) => {
+                            *single = Some(unquote(captured).to_string());
+                        }
+                        (grammar::Transform::Int, VariableKind::Number(val)) => {
+                            *val = match captured.parse() {
+                                Ok(number) => number,
+                                Err(_) => {
+                                    return Err(ParseError {
+                                        kind: ParseErrors::CannotTransform(
+                                            captured.to_string(),
+                                            *transform,
+                                        ),
+                                        location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                        span: ctx.tokens[ctx.cursor.idx].span(),
+                                        node: None,
+                                    })
+                                }
+                            };
+                        }
+                        _ => {
+                            return Err(ParseError {
+                                kind: ParseErrors::CannotSetVariable(
+                                    variable.to_string(),
+                                    kind.clone(),
+                                ),
+                                location: ctx.tokens[ctx.cursor.idx].location.clone(),
+                                span: ctx.tokens[ctx.cursor.idx].span(),
+                                node: None,
+                            })
+                        }
+                    }
                 }
                 grammar::Parameters::HardError(value) => {
                     node.harderror = *value;
                 }
                 grammar::Parameters::NodeStart => {
-                    node.first_string_idx = tokens[cursor.idx].index;
+                    node.first_string_idx = ctx.tokens[ctx.cursor.idx].index;
                 }
                 grammar::Parameters::NodeEnd => {
-                    node.last_string_idx = tokens[cursor.idx].index + tokens[cursor.idx].len;
+                    node.last_string_idx =
+                        ctx.tokens[ctx.cursor.idx].index + ctx.tokens[ctx.cursor.idx].len;
                 }
                 grammar::Parameters::Back(steps) => {
                     bus.send(Msg::Back(*steps as usize));
@@ -1466,9 +1918,57 @@ enum TokenCompare {
 }
 
 #[derive(Debug)]
-pub struct ParseResult {
+pub struct ParseResult<'src> {
     pub entry: Node,
     pub globals: Map<String, VariableKind>,
+    /// Text that was parsed, captured values are views into it
+    ///
+    /// Borrowed from the caller unless the result is made owned by `into_owned`
+    pub(crate) text: Cow<'src, str>,
+    /// Results of all the nodes kept for reparsing, None unless the parser is incremental
+    memo: Option<Memo>,
+}
+
+impl ParseResult<'_> {
+    /// Result that owns its text, for keeping it after the text changes
+    ///
+    /// An incremental parser needs the previous result while the text is edited
+    pub fn into_owned(self) -> ParseResult<'static> {
+        ParseResult {
+            entry: self.entry,
+            globals: self.globals,
+            text: Cow::Owned(self.text.into_owned()),
+            memo: self.memo,
+        }
+    }
+
+    /// Results of the nodes that can be reused after the edit
+    ///
+    /// Results are kept for nodes that did not look at any of the tokens changed
+    /// by the edit, nodes after the edit are moved to their new place in the text.
+    /// Nothing is kept if the result does not come from an incremental parser.
+    pub(crate) fn reusable(&self, old_tokens: &[Token], tokens: &[Token], edit: &TextEdit) -> Memo {
+        match &self.memo {
+            Some(memo) => memo.reuse(old_tokens, tokens, edit),
+            None => Memo::new(true),
+        }
+    }
+
+    /// Number of nodes whose cached results were used instead of parsing them again
+    ///
+    /// Only counted for incremental parsers, for the others it is always 0
+    pub fn reused(&self) -> usize {
+        self.memo.as_ref().map_or(0, |memo| memo.hits)
+    }
+}
+
+/// Result of parsing with error recovery
+#[derive(Debug)]
+pub struct RecoveryResult<'src> {
+    /// Partial tree - nodes that failed to parse are marked as errors
+    pub result: ParseResult<'src>,
+    /// All the errors in the order they were found
+    pub errors: Vec<ParseError>,
 }
 
 pub mod map_tools {
@@ -1547,7 +2047,7 @@ pub mod map_tools {
 
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Nodes {
     Node(Node),
     Token(Token),
@@ -1583,13 +2083,15 @@ impl Nodes {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Node {
     pub name: String,
     pub variables: Map<String, VariableKind>,
     pub(crate) first_string_idx: usize,
     pub(crate) last_string_idx: usize,
     pub(crate) harderror: bool,
+    /// The node failed to parse and the parser skipped to one of its synchronization tokens
+    pub(crate) recovered: bool,
     pub docs: Option<String>,
 }
 
@@ -1601,6 +2103,7 @@ impl Node {
             first_string_idx: 0,
             last_string_idx: 0,
             harderror: false,
+            recovered: false,
             docs: None,
         }
     }
@@ -1612,6 +2115,7 @@ impl Node {
                 return Err(ParseError {
                     kind: ParseErrors::NodeNotFound(name.to_string()),
                     location: TextLocation::new(0, 0),
+                    span: 0..0,
                     node: None,
                 })
             }
@@ -1627,42 +2131,148 @@ impl Node {
     ) -> Result<Map<String, VariableKind>, ParseError> {
         let mut result = Map::new();
         for (key, value) in variables {
-            let var = match value {
-                crate::grammar::VariableKind::Node => VariableKind::Node(None),
-                crate::grammar::VariableKind::NodeList => VariableKind::NodeList(Vec::new()),
-                crate::grammar::VariableKind::Boolean => VariableKind::Boolean(false),
-                crate::grammar::VariableKind::Number => VariableKind::Number(0),
-            };
-            result.insert(key.clone(), var);
+            result.insert(key.clone(), VariableKind::empty(value));
         }
         Ok(result)
     }
 }
 
+/// Text without the matching quotes around it
+///
+/// Text that is not quoted is returned as it is
+pub fn unquote(text: &str) -> &str {
+    for quote in ['"', '\'', '`'] {
+        if let Some(inner) = text
+            .strip_prefix(quote)
+            .and_then(|text| text.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    text
+}
+
+/// Comparisons that hold for two values in the order
+fn ordered(ordering: core::cmp::Ordering) -> Vec<grammar::Comparison> {
+    match ordering {
+        core::cmp::Ordering::Equal => vec![
+            grammar::Comparison::Equal,
+            grammar::Comparison::GreaterThanOrEqual,
+            grammar::Comparison::LessThanOrEqual,
+        ],
+        core::cmp::Ordering::Greater => vec![
+            grammar::Comparison::NotEqual,
+            grammar::Comparison::GreaterThan,
+            grammar::Comparison::GreaterThanOrEqual,
+        ],
+        core::cmp::Ordering::Less => vec![
+            grammar::Comparison::NotEqual,
+            grammar::Comparison::LessThan,
+            grammar::Comparison::LessThanOrEqual,
+        ],
+    }
+}
+
+/// Creates the node of an operator from its parts in the order they appear in the text
+fn operator_node(name: &str, parts: Vec<(&str, Nodes)>) -> Nodes {
+    let bounds = |part: &Nodes| match part {
+        Nodes::Node(node) => (node.first_string_idx, node.last_string_idx),
+        Nodes::Token(token) => (token.index, token.index + token.len),
+    };
+    let mut node = Node::new(name.to_string());
+    if let (Some((_, first)), Some((_, last))) = (parts.first(), parts.last()) {
+        node.first_string_idx = bounds(first).0;
+        node.last_string_idx = bounds(last).1;
+    }
+    for (variable, part) in parts {
+        node.variables
+            .insert(variable.to_string(), VariableKind::Node(Some(part)));
+    }
+    Nodes::Node(node)
+}
+
+/// Fails at the token under the cursor and moves the cursor back
 fn err(
     error: ParseErrors,
-    cursor: &mut Cursor,
+    ctx: &mut ParseContext,
     cursor_clone: &Cursor,
-    location: &TextLocation,
     node: Option<Node>,
 ) -> Result<(), ParseError> {
-    *cursor = cursor_clone.clone();
+    let token = &ctx.tokens[ctx.cursor.idx];
+    ctx.cursor = cursor_clone.clone();
     Err(ParseError {
         kind: error,
-        location: location.clone(),
+        location: token.location.clone(),
+        span: token.span(),
         node,
     })
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum VariableKind {
     Node(Option<Nodes>),
     NodeList(Vec<Nodes>),
     Boolean(bool),
     Number(i32),
+    Span(Option<Range<usize>>),
+    TokenList(Vec<Token>),
+    Text(Option<String>),
 }
 
 impl VariableKind {
+    /// Value of a variable before anything is set to it
+    pub fn empty(kind: &grammar::VariableKind) -> VariableKind {
+        match kind {
+            grammar::VariableKind::Node => VariableKind::Node(None),
+            grammar::VariableKind::NodeList => VariableKind::NodeList(Vec::new()),
+            grammar::VariableKind::Boolean => VariableKind::Boolean(false),
+            grammar::VariableKind::Number => VariableKind::Number(0),
+            grammar::VariableKind::Span => VariableKind::Span(None),
+            grammar::VariableKind::TokenList => VariableKind::TokenList(Vec::new()),
+            grammar::VariableKind::Text => VariableKind::Text(None),
+        }
+    }
+
+    /// Sets the value to the variable, returns false if the variable can not hold it
+    fn set(&mut self, value: &Nodes, text: &str) -> bool {
+        match self {
+            VariableKind::Node(single) => *single = Some(value.clone()),
+            VariableKind::NodeList(list) => list.push(value.clone()),
+            VariableKind::Span(span) => {
+                let value = value.span();
+                *span = match span {
+                    Some(span) => Some(span.start.min(value.start)..span.end.max(value.end)),
+                    None => Some(value),
+                };
+            }
+            VariableKind::TokenList(list) => match value {
+                Nodes::Token(token) => list.push(token.clone()),
+                Nodes::Node(_) => return false,
+            },
+            VariableKind::Text(single) => {
+                *single = text.get(value.span()).map(|text| text.to_string());
+            }
+            VariableKind::Boolean(_) | VariableKind::Number(_) => return false,
+        }
+        true
+    }
+
+    /// Adds to the variable, returns false if it is not a number
+    fn add(&mut self, amount: i32) -> bool {
+        match self {
+            VariableKind::Number(val) => {
+                *val += amount;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets the variable back to its empty value
+    fn reset(&mut self) {
+        *self = VariableKind::empty(&self.kind());
+    }
+
     pub fn is_node(&self) -> bool {
         match self {
             VariableKind::Node(_) => true,
@@ -1691,6 +2301,18 @@ impl VariableKind {
         }
     }
 
+    pub fn is_span(&self) -> bool {
+        matches!(self, VariableKind::Span(_))
+    }
+
+    pub fn is_token_list(&self) -> bool {
+        matches!(self, VariableKind::TokenList(_))
+    }
+
+    pub fn is_text(&self) -> bool {
+        matches!(self, VariableKind::Text(_))
+    }
+
     pub fn unwrap_node(&self) -> &Nodes {
         match self {
             VariableKind::Node(node) => node.as_ref().unwrap(),
@@ -1718,25 +2340,86 @@ impl VariableKind {
             _ => panic!("unwrap_number called on {:#?}", self),
         }
     }
+
+    pub fn unwrap_span(&self) -> &Option<Range<usize>> {
+        match self {
+            VariableKind::Span(span) => span,
+            _ => panic!("unwrap_span called on {:#?}", self),
+        }
+    }
+
+    pub fn unwrap_token_list(&self) -> &Vec<Token> {
+        match self {
+            VariableKind::TokenList(list) => list,
+            _ => panic!("unwrap_token_list called on {:#?}", self),
+        }
+    }
+
+    pub fn unwrap_text(&self) -> Option<&str> {
+        match self {
+            VariableKind::Text(text) => text.as_deref(),
+            _ => panic!("unwrap_text called on {:#?}", self),
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ParseError {
     kind: ParseErrors,
     location: TextLocation,
+    /// Byte range of the token where the error occurred
+    span: Range<usize>,
     node: Option<Node>,
 }
 
+impl ParseError {
+    /// Kind of the error
+    pub fn kind(&self) -> &ParseErrors {
+        &self.kind
+    }
+
+    /// Line and column where the error occurred
+    pub fn location(&self) -> &TextLocation {
+        &self.location
+    }
+
+    /// Byte range in the text where the error occurred
+    ///
+    /// The range is empty if the error is not related to any token
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Node that failed to parse
+    pub fn node(&self) -> Option<&Node> {
+        self.node.as_ref()
+    }
+
+    /// Name of the node that failed to parse
+    pub fn node_name(&self) -> Option<&str> {
+        self.node.as_ref().map(|node| node.name.as_str())
+    }
+
+    /// Documentation of the node that failed to parse
+    pub fn node_docs(&self) -> Option<&str> {
+        self.node.as_ref().and_then(|node| node.docs.as_deref())
+    }
+}
+
 impl fmt::Debug for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?} at {:?}", self.kind, self.location)?;
+        write!(
+            f,
+            "{} at {}:{}",
+            self.kind, self.location.line, self.location.column
+        )?;
         match &self.node {
             Some(node) => {
-                let mut txt = format!("\nError in node: {:?}", node.name);
-                if let Some(docs) = &node.docs {
-                    txt.push_str(&format!("\n{}", docs));
+                write!(f, "\nError in node: {:?}", node.name)?;
+                match &node.docs {
+                    Some(docs) => write!(f, "\n{}", docs),
+                    None => Ok(()),
                 }
-                write!(f, "{}\n", txt)
             }
             None => Ok(()),
         }
@@ -1745,21 +2428,25 @@ impl fmt::Debug for ParseError {
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?} at {:?}", self.kind, self.location)?;
+        write!(
+            f,
+            "{} at {}:{}",
+            self.kind, self.location.line, self.location.column
+        )?;
         match &self.node {
             Some(node) => {
-                let mut txt = format!("\nError in node: {:?}", node.name);
-                if let Some(docs) = &node.docs {
-                    txt.push_str(&format!("\n{}", docs));
+                write!(f, "\nError in node: {:?}", node.name)?;
+                match &node.docs {
+                    Some(docs) => write!(f, "\n{}", docs),
+                    None => Ok(()),
                 }
-                write!(f, "{}\n", txt)
             }
             None => Ok(()),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub enum ParseErrors {
     /// Parser not fully implemented - My fault
     ParserNotFullyImplemented,
@@ -1782,6 +2469,10 @@ pub enum ParseErrors {
     UncountableVariable(String, VariableKind),
     /// Cannot set variable - Developer error
     CannotSetVariable(String, VariableKind),
+    /// Variable compared to a constant of a different kind - Developer error
+    CannotCompareVariable(String, VariableKind),
+    /// Captured text can not be converted by the transform
+    CannotTransform(String, grammar::Transform),
     /// Custom error message
     Message(String),
     /// Unexpected end of file
@@ -1803,6 +2494,12 @@ pub enum ParseErrors {
     ///
     /// This behaviour can be changed by setting the `eof` field in the grammar
     MissingEof(TokenKinds),
+    /// Nodes are nested deeper than `Parser::max_depth`
+    TooDeep(usize),
+    /// The parser executed more rules than `Parser::fuel`
+    OutOfFuel(usize),
+    /// The cancellation flag of the parser was set
+    Cancelled,
 
     /// Control key
     Ok,
@@ -1828,6 +2525,12 @@ impl fmt::Debug for ParseErrors {
             ParseErrors::CannotSetVariable(name, kind) => {
                 write!(f, "Cannot set variable: {}<{:?}>", name, kind)
             }
+            ParseErrors::CannotCompareVariable(name, kind) => {
+                write!(f, "Cannot compare variable: {}<{:?}>", name, kind)
+            }
+            ParseErrors::CannotTransform(text, transform) => {
+                write!(f, "Cannot transform {:?} with {:?}", text, transform)
+            }
             ParseErrors::Message(message) => write!(f, "{}", message),
             ParseErrors::Eof => write!(f, "Unexpected end of file"),
             ParseErrors::LabelNotFound(name) => write!(f, "Label not found: {}", name),
@@ -1840,10 +2543,69 @@ impl fmt::Debug for ParseErrors {
             ParseErrors::CouldNotFindToken(kind) => write!(f, "Could not find token {:?}", kind),
             ParseErrors::Ok => write!(f, "If you see this, it could be a bug in the parser"),
             ParseErrors::MissingEof(found) => write!(f, "Could not parse to the end of the file - found {:?}", found),
+            ParseErrors::TooDeep(max) => write!(f, "Nodes are nested deeper than {}", max),
+            ParseErrors::OutOfFuel(fuel) => write!(f, "Out of fuel after {} rules", fuel),
+            ParseErrors::Cancelled => write!(f, "Parsing was cancelled"),
+        }
+    }
+}
+
+/// Message for the users of the parsed language, tokens are described by their text
+impl fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrors::ExpectedToken { expected, found } => write!(
+                f,
+                "Expected {}, found {}",
+                describe_token(expected),
+                describe_token(found)
+            ),
+            ParseErrors::ExpectedWord { expected, found } => write!(
+                f,
+                "Expected \"{}\", found {}",
+                expected,
+                describe_token(found)
+            ),
+            ParseErrors::ExpectedToNotBe(kind) => write!(f, "Unexpected {}", describe_token(kind)),
+            ParseErrors::ExpectedOneOf { expected, found } => {
+                let expected: Vec<String> = expected.iter().map(describe_match_token).collect();
+                write!(
+                    f,
+                    "Expected one of {}, found {}",
+                    expected.join(", "),
+                    describe_token(found)
+                )
+            }
+            ParseErrors::CouldNotFindToken(token) => {
+                write!(f, "Could not find {}", describe_match_token(token))
+            }
+            ParseErrors::MissingEof(found) => {
+                write!(f, "Expected end of file, found {}", describe_token(found))
+            }
+            ParseErrors::UncountableVariable(name, _) => {
+                write!(f, "Variable {} can not be counted", name)
+            }
+            ParseErrors::CannotSetVariable(name, _) => {
+                write!(f, "Variable {} can not be set", name)
+            }
+            ParseErrors::CannotCompareVariable(name, _) => {
+                write!(f, "Variable {} can not be compared to the constant", name)
+            }
+            ParseErrors::CannotTransform(text, transform) => {
+                write!(f, "Can not convert \"{}\" with {:?}", text, transform)
+            }
+            // The other messages have no tokens or kinds in them
+            _ => fmt::Debug::fmt(self, f),
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for ParseErrors {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
 /// A cursor is used to keep track of the current position in the token stream and other useful information (no useful information yet)
 #[derive(Clone, Debug)]
 struct Cursor {
@@ -1856,6 +2618,483 @@ struct Cursor {
     to_advance: bool,
 }
 
+impl Cursor {
+    /// Moves past the matched token
+    ///
+    /// Returns false if there is no token to move to
+    fn advance(&mut self, len: usize) -> bool {
+        if self.to_advance {
+            if self.idx + 1 >= len {
+                return false;
+            }
+            self.idx += 1;
+            self.to_advance = false;
+        }
+        true
+    }
+}
+
+/// Text being parsed and the position of the parser in it
+///
+/// Passed to every step of the parser instead of each of its parts
+struct ParseContext<'a, 'o> {
+    grammar: &'a Grammar,
+    lexer: &'a Lexer,
+    tokens: &'a [Token],
+    text: &'a str,
+    cursor: Cursor,
+    globals: Map<String, VariableKind>,
+    state: &'a mut ParseState<'o>,
+}
+
+/// State shared by the whole parse
+struct ParseState<'o> {
+    /// Errors the parser has recovered from
+    errors: Vec<ParseError>,
+    /// Whether to recover from errors in nodes with synchronization tokens
+    recover: bool,
+    /// Cached results of nodes, None if memoization is disabled
+    memo: Option<Memo>,
+    /// Index of the furthest token looked at by the current node
+    lookahead: usize,
+    /// Observer told about the progress of the parser
+    observer: Option<&'o mut dyn ParseObserver>,
+    /// Number of nodes being parsed inside of each other
+    depth: usize,
+    /// Number of rules executed
+    steps: usize,
+    /// Limit that stopped the parse
+    stopped: Option<ParseErrors>,
+}
+
+impl<'o> ParseState<'o> {
+    fn new(recover: bool, memo: Option<Memo>) -> ParseState<'o> {
+        ParseState {
+            errors: Vec::new(),
+            recover,
+            memo,
+            lookahead: 0,
+            observer: None,
+            depth: 0,
+            steps: 0,
+            stopped: None,
+        }
+    }
+
+    /// Enters a node if it is not deeper than the maximal depth
+    ///
+    /// Returns the error of the reached limit, once a limit is reached nothing else can be parsed
+    fn enter(&mut self, parser: &Parser) -> Option<ParseErrors> {
+        if self.stopped.is_some() {
+            return self.stopped.clone();
+        }
+        match parser.max_depth {
+            Some(max) if self.depth >= max => self.stop(ParseErrors::TooDeep(max)),
+            _ => {
+                self.depth += 1;
+                None
+            }
+        }
+    }
+
+    /// Counts an executed rule and checks the fuel and the cancellation flag
+    ///
+    /// Returns the error of the reached limit
+    fn step(&mut self, parser: &Parser) -> Option<ParseErrors> {
+        if self.stopped.is_some() {
+            return self.stopped.clone();
+        }
+        self.steps += 1;
+        if let Some(fuel) = parser.fuel {
+            if self.steps > fuel {
+                return self.stop(ParseErrors::OutOfFuel(fuel));
+            }
+        }
+        if let Some(cancel) = &parser.cancel {
+            if self.steps % CANCEL_INTERVAL == 1 && cancel.load(Ordering::Relaxed) {
+                return self.stop(ParseErrors::Cancelled);
+            }
+        }
+        None
+    }
+
+    fn stop(&mut self, kind: ParseErrors) -> Option<ParseErrors> {
+        self.stopped = Some(kind);
+        self.stopped.clone()
+    }
+
+    /// Calls the observer if there is one
+    fn observe(&mut self, event: impl FnOnce(&mut dyn ParseObserver)) {
+        if let Some(observer) = &mut self.observer {
+            event(&mut **observer);
+        }
+    }
+
+    /// Remembers that the token at the index was looked at
+    ///
+    /// A result can only be reused after an edit if none of the tokens it looked at changed
+    fn peek(&mut self, idx: usize) {
+        self.lookahead = self.lookahead.max(idx);
+    }
+
+    /// Records a write to the globals if the results of nodes are cached
+    fn record(&mut self, write: impl FnOnce() -> GlobalWrite) {
+        if let Some(memo) = &mut self.memo {
+            memo.writes.push(write());
+        }
+    }
+
+    /// Records the value of a global compared by a node if the results of nodes are cached
+    fn read(&mut self, name: &str, value: &VariableKind) {
+        if let Some(memo) = &mut self.memo {
+            memo.reads.push(GlobalRead {
+                name: name.to_string(),
+                value: value.clone(),
+                writes: memo.writes.len(),
+            });
+        }
+    }
+}
+
+/// Number of edits a result is kept for without being used
+const MAX_SHIFTS: usize = 16;
+
+/// Results of nodes by their position in the tokens
+///
+/// The result of a node only depends on the node, the cursor and the globals it compares.
+/// The compared values are recorded with the result, which is only used while they are
+/// the same. The only other effect of parsing a node are writes to the globals, so those
+/// are recorded with the result and repeated when the cached result is used.
+#[derive(Clone)]
+pub(crate) struct Memo {
+    /// Results by the cursor (index and pending advance) and the node name
+    table: Map<(usize, bool), Map<String, MemoSlot>>,
+    /// Every write to the globals in the order they happened
+    writes: Vec<GlobalWrite>,
+    /// Every comparison of a global in the order they happened
+    reads: Vec<GlobalRead>,
+    /// Whether the results of all the nodes are kept for reparsing
+    /// or only the results of nodes that are parsed more than once
+    keep_all: bool,
+    /// Number of results used instead of parsing the node
+    hits: usize,
+}
+
+#[derive(Clone)]
+enum MemoSlot {
+    /// The node has been parsed at this position once
+    Seen,
+    Done(Arc<MemoEntry>),
+    /// Result from before the edits, it is moved by the shifts once it is used
+    Moved(Arc<MemoEntry>, Shifts),
+}
+
+type Shifts = Arc<Vec<MemoShift>>;
+
+/// How the results after an edit moved
+#[derive(Clone)]
+struct MemoShift {
+    /// Difference in the number of tokens
+    tokens: isize,
+    shift: Shift,
+}
+
+#[derive(Clone)]
+struct MemoEntry {
+    result: Result<Node, (ParseError, Node)>,
+    /// Cursor after the node was parsed
+    cursor: Cursor,
+    /// Index of the furthest token the node looked at
+    lookahead: usize,
+    /// Errors recovered while parsing the node
+    errors: Vec<ParseError>,
+    /// Writes to the globals made while parsing the node
+    writes: Vec<GlobalWrite>,
+    /// Globals compared while parsing the node
+    reads: Vec<GlobalRead>,
+}
+
+#[derive(Clone)]
+struct GlobalRead {
+    name: String,
+    value: VariableKind,
+    /// Number of writes made before the comparison
+    writes: usize,
+}
+
+#[derive(Clone)]
+enum GlobalWrite {
+    Set(String, Nodes),
+    Increment(String),
+    Add(String, i32),
+    Boolean(String, bool),
+    Reset(String),
+}
+
+impl Memo {
+    fn new(keep_all: bool) -> Memo {
+        Memo {
+            table: Map::new(),
+            writes: Vec::new(),
+            reads: Vec::new(),
+            keep_all,
+            hits: 0,
+        }
+    }
+
+    /// Keeps the results that did not look at any of the tokens changed by the edit
+    ///
+    /// Results after the edit are moved to the new positions of their tokens.
+    /// Moving a result means copying its whole tree, so it is only done once the result
+    /// is used again and results that were not used for [`MAX_SHIFTS`] edits are dropped.
+    fn reuse(&self, old_tokens: &[Token], tokens: &[Token], edit: &TextEdit) -> Memo {
+        let mut memo = Memo::new(true);
+        let (old_eof, eof) = match (old_tokens.last(), tokens.last()) {
+            (Some(old_eof), Some(eof)) => (old_eof, eof),
+            _ => return memo,
+        };
+        // Tokens in front of the edit did not change
+        let prefix = old_tokens
+            .iter()
+            .zip(tokens)
+            .take_while(|(old, new)| old.index + old.len <= edit.range.start && old == new)
+            .count();
+        // Tokens behind the edit only moved
+        let lines = eof.location.line as isize - old_eof.location.line as isize;
+        let suffix = old_tokens
+            .iter()
+            .rev()
+            .zip(tokens.iter().rev())
+            .take_while(|(old, new)| {
+                old.index >= edit.range.end
+                    && edit.moved(old.index) == new.index
+                    && old.kind == new.kind
+                    && old.len == new.len
+                    && old.location.line.checked_add_signed(lines) == Some(new.location.line)
+            })
+            .count();
+        let first_moved = old_tokens.len() - suffix;
+        let shift = match suffix {
+            0 => None,
+            _ => Some(Shift::between(
+                &old_tokens[first_moved],
+                &tokens[tokens.len() - suffix],
+            )),
+        };
+        let moved_by = tokens.len() as isize - old_tokens.len() as isize;
+        let shift = shift.map(|shift| MemoShift {
+            tokens: moved_by,
+            shift,
+        });
+        // Results moved by the same edits share the list of shifts
+        let mut shifts: Vec<(Shifts, Shifts)> = Vec::new();
+
+        for (&(idx, to_advance), slots) in &self.table {
+            for (name, slot) in slots {
+                let lookahead = match slot {
+                    MemoSlot::Seen => continue,
+                    MemoSlot::Done(entry) => entry.lookahead,
+                    MemoSlot::Moved(entry, moved) => {
+                        moved.iter().fold(entry.lookahead, |idx, shift| {
+                            idx.saturating_add_signed(shift.tokens)
+                        })
+                    }
+                };
+                if lookahead < prefix {
+                    memo.insert((idx, to_advance), name, slot.clone());
+                    continue;
+                }
+                let shift = match (idx >= first_moved, &shift) {
+                    (true, Some(shift)) => shift,
+                    _ => continue,
+                };
+                let (entry, previous) = match slot {
+                    MemoSlot::Done(entry) => (entry, None),
+                    MemoSlot::Moved(entry, previous) if previous.len() < MAX_SHIFTS => {
+                        (entry, Some(previous))
+                    }
+                    _ => continue,
+                };
+                let found = shifts.iter().find(|(key, _)| match previous {
+                    Some(previous) => Arc::ptr_eq(key, previous),
+                    None => key.is_empty(),
+                });
+                let moved = match found {
+                    Some((_, moved)) => moved.clone(),
+                    None => {
+                        let key = previous.cloned().unwrap_or_default();
+                        let mut moved = Vec::clone(&key);
+                        moved.push(shift.clone());
+                        let moved = Arc::new(moved);
+                        shifts.push((key, moved.clone()));
+                        moved
+                    }
+                };
+                let position = (idx.saturating_add_signed(moved_by), to_advance);
+                memo.insert(position, name, MemoSlot::Moved(entry.clone(), moved));
+            }
+        }
+        memo
+    }
+
+    /// Gets the result of the node, results from before an edit are moved first
+    fn get(&mut self, position: (usize, bool), name: &str) -> Option<&MemoSlot> {
+        let slot = self.table.get_mut(&position)?.get_mut(name)?;
+        if let MemoSlot::Moved(entry, shifts) = slot {
+            let mut entry = MemoEntry::clone(entry);
+            for shift in shifts.iter() {
+                entry.shift(shift.tokens, &shift.shift);
+            }
+            *slot = MemoSlot::Done(Arc::new(entry));
+        }
+        Some(slot)
+    }
+
+    fn insert(&mut self, position: (usize, bool), name: &str, slot: MemoSlot) {
+        self.table
+            .entry(position)
+            .or_default()
+            .insert(name.to_string(), slot);
+    }
+}
+
+impl fmt::Debug for Memo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The results are copies of parts of the tree, printing them would only add noise
+        let results: usize = self.table.values().map(|slots| slots.len()).sum();
+        f.debug_struct("Memo")
+            .field("results", &results)
+            .field("hits", &self.hits)
+            .finish()
+    }
+}
+
+impl MemoEntry {
+    /// Whether the globals compared by the node would still have the same values
+    ///
+    /// Writes made by the node itself before a comparison are repeated on a copy of the global
+    fn still_valid(&self, globals: &Map<String, VariableKind>, text: &str) -> bool {
+        self.reads.iter().all(|read| {
+            let Some(value) = globals.get(&read.name) else {
+                return false;
+            };
+            let mut global = Map::new();
+            global.insert(read.name.clone(), value.clone());
+            for write in &self.writes[..read.writes] {
+                write.apply(&mut global, text);
+            }
+            match (&global[&read.name], &read.value) {
+                (VariableKind::Number(now), VariableKind::Number(then)) => now == then,
+                (VariableKind::Boolean(now), VariableKind::Boolean(then)) => now == then,
+                (VariableKind::Text(now), VariableKind::Text(then)) => now == then,
+                _ => false,
+            }
+        })
+    }
+
+    /// Moves the result by the number of tokens and the shift in the text
+    fn shift(&mut self, tokens: isize, shift: &Shift) {
+        match &mut self.result {
+            Ok(node) => node.shift(shift),
+            Err((err, node)) => {
+                err.shift(shift);
+                node.shift(shift);
+            }
+        }
+        self.cursor.idx = self.cursor.idx.saturating_add_signed(tokens);
+        self.lookahead = self.lookahead.saturating_add_signed(tokens);
+        for err in &mut self.errors {
+            err.shift(shift);
+        }
+        for write in &mut self.writes {
+            if let GlobalWrite::Set(_, value) = write {
+                value.shift(shift);
+            }
+        }
+    }
+}
+
+impl Node {
+    /// Moves the node and all of its children to a different place in the text
+    fn shift(&mut self, shift: &Shift) {
+        self.first_string_idx = shift.index(self.first_string_idx);
+        self.last_string_idx = shift.index(self.last_string_idx);
+        for variable in self.variables.values_mut() {
+            match variable {
+                VariableKind::Node(Some(nodes)) => nodes.shift(shift),
+                VariableKind::NodeList(list) => {
+                    for nodes in list {
+                        nodes.shift(shift);
+                    }
+                }
+                VariableKind::Span(Some(span)) => {
+                    *span = shift.index(span.start)..shift.index(span.end);
+                }
+                VariableKind::TokenList(list) => {
+                    for token in list {
+                        *token = shift.token(token);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Nodes {
+    fn shift(&mut self, shift: &Shift) {
+        match self {
+            Nodes::Node(node) => node.shift(shift),
+            Nodes::Token(token) => *token = shift.token(token),
+        }
+    }
+}
+
+impl ParseError {
+    fn shift(&mut self, shift: &Shift) {
+        self.location = shift.location(&self.location);
+        self.span = shift.index(self.span.start)..shift.index(self.span.end);
+        if let Some(node) = &mut self.node {
+            node.shift(shift);
+        }
+    }
+}
+
+impl GlobalWrite {
+    /// Repeats the write
+    ///
+    /// The write has already succeeded once, so the variable exists and has the right kind
+    fn apply(&self, globals: &mut Map<String, VariableKind>, text: &str) {
+        match self {
+            GlobalWrite::Set(name, value) => {
+                if let Some(kind) = globals.get_mut(name) {
+                    kind.set(value, text);
+                }
+            }
+            GlobalWrite::Increment(name) => {
+                if let Some(VariableKind::Number(val)) = globals.get_mut(name) {
+                    *val += 1;
+                }
+            }
+            GlobalWrite::Add(name, amount) => {
+                if let Some(kind) = globals.get_mut(name) {
+                    kind.add(*amount);
+                }
+            }
+            GlobalWrite::Reset(name) => {
+                if let Some(kind) = globals.get_mut(name) {
+                    kind.reset();
+                }
+            }
+            GlobalWrite::Boolean(name, value) => {
+                if let Some(VariableKind::Boolean(val)) = globals.get_mut(name) {
+                    *val = *value;
+                }
+            }
+        }
+    }
+}
+
 struct MsgBus {
     messages: Vec<Msg>,
 }
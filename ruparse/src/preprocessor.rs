@@ -0,0 +1,282 @@
+//! Built-in preprocessor steps
+//!
+//! Unlike `lexer::Preprocessor` functions, steps are plain data and are serialized
+//! together with the lexer, so a lexer loaded from JSON preprocesses the tokens
+//! the same way as the one it was saved from.
+use serde::{Deserialize, Serialize};
+
+use crate::lexer::{ControlTokenKind, PreprocessorError, Token, TokenKinds};
+
+// Choose between std and alloc
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        extern crate std;
+        use std::prelude::v1::*;
+    } else {
+        extern crate alloc;
+        use alloc::string::*;
+        use alloc::vec::*;
+        use alloc::format;
+    }
+}
+
+/// A single step of preprocessing
+///
+/// Steps are executed in the order they were added to the lexer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum PreprocessorStep {
+    /// Merges regions like strings and comments into a single token
+    ///
+    /// All the regions are searched for in a single pass and the first region
+    /// that starts wins, so a string start inside of a comment is ignored and vice versa.
+    /// Regions are tried in the order they are listed.
+    Regions(Vec<Region>),
+    /// Turns text tokens that are numbers into complex tokens
+    Numbers(NumberLiterals),
+    /// Removes all the tokens of the given kinds
+    Remove(Vec<TokenKinds>),
+    /// Removes whitespace and end of line tokens
+    StripWhitespace,
+}
+
+/// A region of tokens that will be merged into a single token
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Region {
+    /// Sequence of tokens that starts the region
+    pub start: Vec<String>,
+    /// Token that ends the region
+    ///
+    /// If None, the region ends at the end of the line
+    pub end: Option<String>,
+    /// If the token in front of the end token ends with the escape, the region continues
+    pub escape: Option<String>,
+    /// Kind of the merged token
+    ///
+    /// If None, the region is removed (comments)
+    pub kind: Option<String>,
+    /// Whether the start tokens are part of the merged token
+    pub include_start: bool,
+    /// Whether the end token is part of the merged token
+    ///
+    /// The end of file is never included
+    pub include_end: bool,
+}
+
+/// Rules for number literals
+///
+/// A number is a text token made of ascii digits, optionally followed by a single
+/// alphabetic suffix. If the number is followed by a decimal point, it is a float
+/// and can not have a suffix.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NumberLiterals {
+    /// Kind of numbers without a suffix
+    pub integer: String,
+    /// Kind of numbers with a decimal point
+    pub float: String,
+    /// Token used as a decimal point
+    pub decimal_point: String,
+    /// Suffixes and the kinds of numbers they produce
+    ///
+    /// Numbers with an unknown suffix are integers
+    pub suffixes: Vec<(char, String)>,
+}
+
+impl PreprocessorStep {
+    pub fn apply(&self, text: &str, tokens: Vec<Token>) -> Result<Vec<Token>, PreprocessorError> {
+        match self {
+            PreprocessorStep::Regions(regions) => merge_regions(regions, text, tokens),
+            PreprocessorStep::Numbers(numbers) => numbers.apply(text, tokens),
+            PreprocessorStep::Remove(kinds) => Ok(tokens
+                .into_iter()
+                .filter(|token| !kinds.contains(&token.kind))
+                .collect()),
+            PreprocessorStep::StripWhitespace => Ok(tokens
+                .into_iter()
+                .filter(|token| !token.kind.is_whitespace())
+                .collect()),
+        }
+    }
+}
+
+impl Region {
+    /// Returns the number of start tokens if the region starts at the beginning of `tokens`
+    fn starts(&self, tokens: &[Token]) -> Option<usize> {
+        if self.start.is_empty() || tokens.len() < self.start.len() {
+            return None;
+        }
+        for (token, start) in tokens.iter().zip(&self.start) {
+            match &token.kind {
+                TokenKinds::Token(txt) if txt == start => (),
+                _ => return None,
+            }
+        }
+        Some(self.start.len())
+    }
+
+    /// Returns the index of the token that ends the region
+    fn find_end(&self, text: &str, tokens: &[Token], from: usize) -> Option<usize> {
+        let end = match &self.end {
+            Some(end) => end,
+            None => {
+                return tokens[from..]
+                    .iter()
+                    .position(|token| matches!(token.kind, TokenKinds::Control(_)))
+                    .map(|j| from + j)
+            }
+        };
+        for j in from..tokens.len() {
+            match &tokens[j].kind {
+                TokenKinds::Token(txt) if txt == end => (),
+                _ => continue,
+            }
+            let escaped = match &self.escape {
+                Some(escape) if j > from => {
+                    let prev = &tokens[j - 1];
+                    text[prev.index..prev.index + prev.len].ends_with(escape.as_str())
+                }
+                _ => false,
+            };
+            if !escaped {
+                return Some(j);
+            }
+        }
+        None
+    }
+}
+
+fn merge_regions(
+    regions: &[Region],
+    text: &str,
+    tokens: Vec<Token>,
+) -> Result<Vec<Token>, PreprocessorError> {
+    let mut new_tokens = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    'tokens: while i < tokens.len() {
+        for region in regions {
+            let start_len = match region.starts(&tokens[i..]) {
+                Some(len) => len,
+                None => continue,
+            };
+            let content = i + start_len;
+            let end = match region.find_end(text, &tokens, content) {
+                Some(end) => end,
+                None => {
+                    let last = &tokens[tokens.len() - 1];
+                    // Line regions only miss their end if the tokens do not end with Eof
+                    let message = match &region.end {
+                        Some(end) => format!("Expected a closing {:?}", end),
+                        None => "Expected the end of the line".to_string(),
+                    };
+                    return Err(PreprocessorError {
+                        message,
+                        location: tokens[i].location.clone(),
+                        len: last.index + last.len - tokens[i].index,
+                    });
+                }
+            };
+            let is_eof = tokens[end].kind == TokenKinds::Control(ControlTokenKind::Eof);
+            let include_end = region.include_end && !is_eof;
+            if let Some(kind) = &region.kind {
+                let first = match region.include_start {
+                    true => &tokens[i],
+                    false => &tokens[content],
+                };
+                let last = match include_end {
+                    true => &tokens[end],
+                    false => &tokens[end - 1],
+                };
+                new_tokens.push(Token {
+                    index: first.index,
+                    len: (last.index + last.len).saturating_sub(first.index),
+                    location: first.location.clone(),
+                    kind: TokenKinds::Complex(kind.clone()),
+                });
+            }
+            // the end of a line region stays in the tokens unless it is included
+            i = match region.end.is_some() || include_end {
+                true => end + 1,
+                false => end,
+            };
+            continue 'tokens;
+        }
+        new_tokens.push(tokens[i].clone());
+        i += 1;
+    }
+    Ok(new_tokens)
+}
+
+impl NumberLiterals {
+    fn apply(&self, text: &str, tokens: Vec<Token>) -> Result<Vec<Token>, PreprocessorError> {
+        let mut new_tokens = Vec::with_capacity(tokens.len());
+        let is_number = |token: &Token| {
+            let txt = &text[token.index..token.index + token.len];
+            !txt.is_empty() && txt.bytes().all(|b| b.is_ascii_digit())
+        };
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if token.kind != TokenKinds::Text {
+                new_tokens.push(token.clone());
+                i += 1;
+                continue;
+            }
+            let txt = &text[token.index..token.index + token.len];
+            // strip suffix character
+            let suffix = txt.chars().last().filter(|c| c.is_alphabetic());
+            let digits = match suffix {
+                Some(c) => &txt[..txt.len() - c.len_utf8()],
+                None => txt,
+            };
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                new_tokens.push(token.clone());
+                i += 1;
+                continue;
+            }
+            let is_point = |token: Option<&Token>| match token {
+                Some(Token {
+                    kind: TokenKinds::Token(txt),
+                    ..
+                }) => *txt == self.decimal_point,
+                _ => false,
+            };
+            if !is_point(tokens.get(i + 1)) {
+                // it's an integer (but could be another type if it has a suffix)
+                let kind = suffix
+                    .and_then(|c| self.suffixes.iter().find(|(s, _)| *s == c))
+                    .map_or(&self.integer, |(_, kind)| kind);
+                new_tokens.push(Token {
+                    kind: TokenKinds::Complex(kind.clone()),
+                    ..token.clone()
+                });
+                i += 1;
+                continue;
+            }
+            // it's a float (suffix is not allowed)
+            let mut len = tokens[i + 1].index + tokens[i + 1].len - token.index;
+            let mut consumed = 2;
+            match tokens.get(i + 2) {
+                Some(decimals) if decimals.kind == TokenKinds::Text => {
+                    len = decimals.index + decimals.len - token.index;
+                    if !is_number(decimals) {
+                        return Err(PreprocessorError {
+                            message: "Expected a float".to_string(),
+                            location: token.location.clone(),
+                            len,
+                        });
+                    }
+                    consumed = 3;
+                }
+                // it's a float without a decimal value
+                _ => (),
+            }
+            new_tokens.push(Token {
+                index: token.index,
+                len,
+                location: token.location.clone(),
+                kind: TokenKinds::Complex(self.float.clone()),
+            });
+            i += consumed;
+        }
+        Ok(new_tokens)
+    }
+}
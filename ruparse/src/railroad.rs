@@ -0,0 +1,329 @@
+//! Grammar export for railroad diagrams
+//!
+//! Rules are normalized into the elements railroad diagram tools draw: sequences, choices,
+//! optional and repeated parts, terminals and references to other diagrams.
+//! Parameters are left out and commands become comments.
+//! The result is serializable, `serde_json::to_string(&railroad::export(&grammar))`
+//! gives a document with one diagram for every node and enumerator.
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    grammar::{Commands, Grammar, Literal, MatchToken, OneOf, OperatorKind, Rule, Rules},
+    lexer::{ControlTokenKind, TokenKinds},
+};
+
+// Choose between std and alloc
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        extern crate std;
+        use std::prelude::v1::*;
+    } else {
+        extern crate alloc;
+        use alloc::string::*;
+        use alloc::vec::*;
+        use alloc::vec;
+        use alloc::format;
+        use alloc::boxed::Box;
+    }
+}
+
+/// Diagram of a node or an enumerator
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Diagram {
+    pub name: String,
+    pub kind: DiagramKind,
+    /// Documentation of the node
+    pub docs: Option<String>,
+    pub diagram: Element,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagramKind {
+    Node,
+    Enumerator,
+}
+
+/// Part of a diagram
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Element {
+    /// Elements one after another
+    Sequence { items: Vec<Element> },
+    /// One of the elements
+    Choice { options: Vec<Element> },
+    /// Element that can be left out
+    Optional { item: Box<Element> },
+    /// Element repeated any number of times
+    ZeroOrMore { item: Box<Element> },
+    /// Element repeated at least once
+    OneOrMore { item: Box<Element> },
+    /// Token or word written in the text
+    Terminal { text: String },
+    /// Kind of token like `text`, `eof` or a token made by the preprocessor
+    Class { name: String },
+    /// Reference to the diagram of a node or an enumerator
+    NonTerminal { name: String, kind: DiagramKind },
+    /// Anything the diagram can not show, like commands
+    Comment { text: String },
+    /// Nothing
+    Skip,
+}
+
+/// Exports the nodes and enumerators of a grammar sorted by name
+pub fn export(grammar: &Grammar) -> Vec<Diagram> {
+    let mut diagrams = Vec::new();
+    for node in grammar.nodes.values() {
+        diagrams.push(Diagram {
+            name: node.name.clone(),
+            kind: DiagramKind::Node,
+            docs: node.docs.clone(),
+            diagram: rules(&node.rules),
+        });
+    }
+    for enumerator in grammar.enumerators.values() {
+        diagrams.push(Diagram {
+            name: enumerator.name.clone(),
+            kind: DiagramKind::Enumerator,
+            docs: None,
+            diagram: choice(enumerator.values.iter().map(token).collect()),
+        });
+    }
+    diagrams.sort_by(|a, b| a.name.cmp(&b.name));
+    diagrams
+}
+
+fn rules(rules: &Rules) -> Element {
+    sequence(rules.iter().map(rule).collect())
+}
+
+fn rule(rule: &Rule) -> Element {
+    match rule {
+        Rule::Is {
+            token: t, rules: r, ..
+        } => matched(t, r),
+        Rule::Isnt {
+            token: t, rules: r, ..
+        } => sequence(vec![comment(format!("not {}", describe(t))), rules(r)]),
+        Rule::IsOneOf { tokens } => one_of(tokens),
+        Rule::Maybe {
+            token: t, is, isnt, ..
+        } => match isnt.is_empty() {
+            true => optional(matched(t, is)),
+            false => choice(vec![matched(t, is), rules(isnt)]),
+        },
+        Rule::MaybeOneOf { is_one_of, isnt } => {
+            let mut options: Vec<Element> = is_one_of.iter().map(alternative).collect();
+            options.push(rules(isnt));
+            choice(options)
+        }
+        Rule::While {
+            token: t, rules: r, ..
+        } => zero_or_more(matched(t, r)),
+        Rule::Loop { rules: r } => one_or_more(rules(r)),
+        Rule::Until {
+            token: t, rules: r, ..
+        } => sequence(vec![
+            comment(format!("skip to {}", describe(t))),
+            matched(t, r),
+        ]),
+        Rule::UntilOneOf { tokens } => {
+            sequence(vec![comment("skip to one of".to_string()), one_of(tokens)])
+        }
+        Rule::Precedence {
+            operand, levels, ..
+        } => {
+            let operators = |kinds: &[OperatorKind]| {
+                let options = levels
+                    .iter()
+                    .filter(|level| kinds.contains(&level.kind))
+                    .flat_map(|level| level.operators.iter().map(token))
+                    .collect::<Vec<_>>();
+                match options.is_empty() {
+                    true => None,
+                    false => Some(choice(options)),
+                }
+            };
+            let mut unary = Vec::new();
+            if let Some(prefix) = operators(&[OperatorKind::Prefix]) {
+                unary.push(zero_or_more(prefix));
+            }
+            unary.push(token(operand));
+            if let Some(postfix) = operators(&[OperatorKind::Postfix]) {
+                unary.push(zero_or_more(postfix));
+            }
+            let unary = sequence(unary);
+            match operators(&[OperatorKind::Left, OperatorKind::Right]) {
+                Some(binary) => sequence(vec![
+                    unary.clone(),
+                    zero_or_more(sequence(vec![binary, unary])),
+                ]),
+                None => unary,
+            }
+        }
+        Rule::Command { command } => match command {
+            Commands::Compare {
+                left,
+                right,
+                rules: r,
+                ..
+            } => optional(sequence(vec![
+                comment(format!("if {} and {} compare", left, right)),
+                rules(r),
+            ])),
+            Commands::CompareLiteral {
+                variable,
+                value,
+                rules: r,
+                ..
+            } => optional(sequence(vec![
+                comment(format!("if {} and {} compare", variable, literal(value))),
+                rules(r),
+            ])),
+            Commands::Error { message } => comment(format!("error: {}", message)),
+            Commands::Goto { label } => comment(format!("go to {}", label)),
+            Commands::Label { name } => comment(format!("label {}", name)),
+            Commands::HardError { .. } | Commands::Print { .. } => Element::Skip,
+        },
+        Rule::Debug { .. } => Element::Skip,
+    }
+}
+
+/// Token followed by the rules executed when it matches
+fn matched(t: &MatchToken, r: &Rules) -> Element {
+    sequence(vec![token(t), rules(r)])
+}
+
+fn alternative(one_of: &OneOf) -> Element {
+    matched(&one_of.token, &one_of.rules)
+}
+
+fn one_of(tokens: &[OneOf]) -> Element {
+    choice(tokens.iter().map(alternative).collect())
+}
+
+fn token(token: &MatchToken) -> Element {
+    match token {
+        MatchToken::Token(TokenKinds::Token(text)) | MatchToken::Word(text) => {
+            Element::Terminal { text: text.clone() }
+        }
+        MatchToken::Node(name) => Element::NonTerminal {
+            name: name.clone(),
+            kind: DiagramKind::Node,
+        },
+        MatchToken::Enumerator(name) => Element::NonTerminal {
+            name: name.clone(),
+            kind: DiagramKind::Enumerator,
+        },
+        _ => Element::Class {
+            name: describe(token),
+        },
+    }
+}
+
+fn describe(token: &MatchToken) -> String {
+    match token {
+        MatchToken::Token(TokenKinds::Token(text)) | MatchToken::Word(text) => text.clone(),
+        MatchToken::Token(TokenKinds::Complex(kind)) => kind.clone(),
+        MatchToken::Token(TokenKinds::Text) => "text".to_string(),
+        MatchToken::Token(TokenKinds::Whitespace) => "whitespace".to_string(),
+        MatchToken::Token(TokenKinds::Control(ControlTokenKind::Eol)) => "eol".to_string(),
+        MatchToken::Token(TokenKinds::Control(ControlTokenKind::Eof)) => "eof".to_string(),
+        MatchToken::Node(name) | MatchToken::Enumerator(name) => name.clone(),
+        MatchToken::Any => "any".to_string(),
+    }
+}
+
+fn literal(value: &Literal) -> String {
+    match value {
+        Literal::Number(number) => number.to_string(),
+        Literal::Boolean(value) => value.to_string(),
+        Literal::Text(text) => format!("\"{}\"", text),
+    }
+}
+
+fn comment(text: String) -> Element {
+    Element::Comment { text }
+}
+
+/// Flattens nested sequences and leaves out skipped elements
+fn sequence(items: Vec<Element>) -> Element {
+    let mut flat = Vec::new();
+    for item in items {
+        match item {
+            Element::Sequence { items } => flat.extend(items),
+            Element::Skip => (),
+            item => flat.push(item),
+        }
+    }
+    match flat.len() {
+        0 => Element::Skip,
+        1 => flat.pop().unwrap(),
+        _ => Element::Sequence { items: flat },
+    }
+}
+
+/// Flattens nested choices, an empty option makes the choice optional
+fn choice(options: Vec<Element>) -> Element {
+    let mut flat: Vec<Element> = Vec::new();
+    for option in options {
+        let options = match option {
+            Element::Choice { options } => options,
+            Element::Optional { item } => vec![*item, Element::Skip],
+            option => vec![option],
+        };
+        for option in options {
+            if !flat.contains(&option) {
+                flat.push(option);
+            }
+        }
+    }
+    let optional = flat.contains(&Element::Skip);
+    flat.retain(|option| option != &Element::Skip);
+    let choice = match flat.len() {
+        0 => return Element::Skip,
+        1 => flat.pop().unwrap(),
+        _ => Element::Choice { options: flat },
+    };
+    match optional {
+        true => optional_element(choice),
+        false => choice,
+    }
+}
+
+fn optional(item: Element) -> Element {
+    choice(vec![item, Element::Skip])
+}
+
+fn optional_element(item: Element) -> Element {
+    match item {
+        Element::Skip | Element::Optional { .. } | Element::ZeroOrMore { .. } => item,
+        Element::OneOrMore { item } => Element::ZeroOrMore { item },
+        item => Element::Optional {
+            item: Box::new(item),
+        },
+    }
+}
+
+fn zero_or_more(item: Element) -> Element {
+    match item {
+        Element::Skip => Element::Skip,
+        Element::Optional { item } | Element::ZeroOrMore { item } | Element::OneOrMore { item } => {
+            Element::ZeroOrMore { item }
+        }
+        item => Element::ZeroOrMore {
+            item: Box::new(item),
+        },
+    }
+}
+
+fn one_or_more(item: Element) -> Element {
+    match item {
+        Element::Skip => Element::Skip,
+        Element::Optional { item } | Element::ZeroOrMore { item } => Element::ZeroOrMore { item },
+        Element::OneOrMore { item } => Element::OneOrMore { item },
+        item => Element::OneOrMore {
+            item: Box::new(item),
+        },
+    }
+}
@@ -0,0 +1,450 @@
+//! Walking through parsed trees
+//!
+//! A [`Visitor`] is called before and after the children of every node and for every token,
+//! [`Hooks`] calls functions registered for node names. [`DepthFirst`] and [`BreadthFirst`]
+//! iterate over the values of a tree together with the nodes they are in.
+//!
+//! ```ignore
+//! let functions: Vec<&Node> = result.find_all("KWFunction").collect();
+//!
+//! let mut count = 0;
+//! let mut hooks = Hooks::new().enter("KWLet", |_, _| {
+//!     count += 1;
+//!     Walk::Continue
+//! });
+//! result.walk(&mut hooks);
+//! ```
+use crate::{
+    lexer::Token,
+    parser::{Node, Nodes, ParseResult, VariableKind},
+    Map,
+};
+
+// Choose between std and alloc
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        extern crate std;
+        use std::prelude::v1::*;
+        use std::collections::VecDeque;
+        use std::vec;
+    } else {
+        extern crate alloc;
+        use alloc::string::*;
+        use alloc::vec::*;
+        use alloc::vec;
+        use alloc::boxed::Box;
+        use alloc::collections::VecDeque;
+    }
+}
+
+/// What to do after a node was visited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    /// Visit the children of the node
+    Continue,
+    /// Skip the children of the node
+    ///
+    /// Returned after the children were visited it is the same as `Continue`
+    Skip,
+    /// End the walk
+    Stop,
+}
+
+/// Called for the nodes and tokens of a tree
+///
+/// `path` holds the nodes the value is in, starting with the root
+pub trait Visitor<'a> {
+    /// Called before the children of a node
+    fn enter(&mut self, _node: &'a Node, _path: &[&'a Node]) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called after the children of a node
+    fn leave(&mut self, _node: &'a Node, _path: &[&'a Node]) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called for every token
+    fn token(&mut self, _token: &'a Token, _path: &[&'a Node]) -> Walk {
+        Walk::Continue
+    }
+}
+
+/// Called for the nodes and tokens of a tree that can be changed
+///
+/// `path` holds the names of the nodes the value is in, starting with the root
+pub trait VisitorMut {
+    /// Called before the children of a node
+    fn enter(&mut self, _node: &mut Node, _path: &[String]) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called after the children of a node
+    fn leave(&mut self, _node: &mut Node, _path: &[String]) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called for every token
+    fn token(&mut self, _token: &mut Token, _path: &[String]) -> Walk {
+        Walk::Continue
+    }
+}
+
+type Hook<'a, 'f> = Box<dyn FnMut(&'a Node, &[&'a Node]) -> Walk + 'f>;
+type HookMut<'f> = Box<dyn FnMut(&mut Node, &[String]) -> Walk + 'f>;
+
+/// Visitor calling the functions registered for the name of each node
+pub struct Hooks<'a, 'f> {
+    enter: Map<String, Hook<'a, 'f>>,
+    leave: Map<String, Hook<'a, 'f>>,
+}
+
+impl<'a, 'f> Hooks<'a, 'f> {
+    pub fn new() -> Self {
+        Hooks {
+            enter: Map::new(),
+            leave: Map::new(),
+        }
+    }
+
+    /// Calls `hook` before the children of every node named `name`
+    pub fn enter(
+        mut self,
+        name: &str,
+        hook: impl FnMut(&'a Node, &[&'a Node]) -> Walk + 'f,
+    ) -> Self {
+        self.enter.insert(name.to_string(), Box::new(hook));
+        self
+    }
+
+    /// Calls `hook` after the children of every node named `name`
+    pub fn leave(
+        mut self,
+        name: &str,
+        hook: impl FnMut(&'a Node, &[&'a Node]) -> Walk + 'f,
+    ) -> Self {
+        self.leave.insert(name.to_string(), Box::new(hook));
+        self
+    }
+}
+
+impl Default for Hooks<'_, '_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Visitor<'a> for Hooks<'a, '_> {
+    fn enter(&mut self, node: &'a Node, path: &[&'a Node]) -> Walk {
+        match self.enter.get_mut(&node.name) {
+            Some(hook) => hook(node, path),
+            None => Walk::Continue,
+        }
+    }
+
+    fn leave(&mut self, node: &'a Node, path: &[&'a Node]) -> Walk {
+        match self.leave.get_mut(&node.name) {
+            Some(hook) => hook(node, path),
+            None => Walk::Continue,
+        }
+    }
+}
+
+/// Mutable visitor calling the functions registered for the name of each node
+pub struct HooksMut<'f> {
+    enter: Map<String, HookMut<'f>>,
+    leave: Map<String, HookMut<'f>>,
+}
+
+impl<'f> HooksMut<'f> {
+    pub fn new() -> Self {
+        HooksMut {
+            enter: Map::new(),
+            leave: Map::new(),
+        }
+    }
+
+    /// Calls `hook` before the children of every node named `name`
+    pub fn enter(
+        mut self,
+        name: &str,
+        hook: impl FnMut(&mut Node, &[String]) -> Walk + 'f,
+    ) -> Self {
+        self.enter.insert(name.to_string(), Box::new(hook));
+        self
+    }
+
+    /// Calls `hook` after the children of every node named `name`
+    pub fn leave(
+        mut self,
+        name: &str,
+        hook: impl FnMut(&mut Node, &[String]) -> Walk + 'f,
+    ) -> Self {
+        self.leave.insert(name.to_string(), Box::new(hook));
+        self
+    }
+}
+
+impl Default for HooksMut<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VisitorMut for HooksMut<'_> {
+    fn enter(&mut self, node: &mut Node, path: &[String]) -> Walk {
+        match self.enter.get_mut(&node.name) {
+            Some(hook) => hook(node, path),
+            None => Walk::Continue,
+        }
+    }
+
+    fn leave(&mut self, node: &mut Node, path: &[String]) -> Walk {
+        match self.leave.get_mut(&node.name) {
+            Some(hook) => hook(node, path),
+            None => Walk::Continue,
+        }
+    }
+}
+
+/// Visits the node and everything in it
+///
+/// Returns `Walk::Stop` if the visitor stopped the walk
+pub fn walk<'a, V: Visitor<'a>>(node: &'a Node, visitor: &mut V) -> Walk {
+    walk_node(node, &mut Vec::new(), visitor)
+}
+
+/// Visits the node and everything in it, the visitor can change the values
+///
+/// The children of a node are visited after `enter` returns, so changes to
+/// the variables of the node decide what is visited.
+/// Returns `Walk::Stop` if the visitor stopped the walk
+pub fn walk_mut<V: VisitorMut>(node: &mut Node, visitor: &mut V) -> Walk {
+    walk_node_mut(node, &mut Vec::new(), visitor)
+}
+
+fn walk_node<'a, V: Visitor<'a>>(
+    node: &'a Node,
+    path: &mut Vec<&'a Node>,
+    visitor: &mut V,
+) -> Walk {
+    match visitor.enter(node, path) {
+        Walk::Stop => return Walk::Stop,
+        Walk::Skip => (),
+        Walk::Continue => {
+            path.push(node);
+            for child in node.children() {
+                let walk = match child {
+                    Nodes::Node(child) => walk_node(child, path, visitor),
+                    Nodes::Token(token) => visitor.token(token, path),
+                };
+                if walk == Walk::Stop {
+                    return Walk::Stop;
+                }
+            }
+            path.pop();
+        }
+    }
+    match visitor.leave(node, path) {
+        Walk::Stop => Walk::Stop,
+        _ => Walk::Continue,
+    }
+}
+
+fn walk_node_mut<V: VisitorMut>(node: &mut Node, path: &mut Vec<String>, visitor: &mut V) -> Walk {
+    match visitor.enter(node, path) {
+        Walk::Stop => return Walk::Stop,
+        Walk::Skip => (),
+        Walk::Continue => {
+            path.push(node.name.clone());
+            let mut children = Vec::new();
+            for variable in node.variables.values_mut() {
+                match variable {
+                    VariableKind::Node(Some(child)) => children.push(child),
+                    VariableKind::NodeList(list) => children.extend(list.iter_mut()),
+                    _ => (),
+                }
+            }
+            children.sort_by_key(|child| child.span().start);
+            for child in children {
+                let walk = match child {
+                    Nodes::Node(child) => walk_node_mut(child, path, visitor),
+                    Nodes::Token(token) => visitor.token(token, path),
+                };
+                if walk == Walk::Stop {
+                    return Walk::Stop;
+                }
+            }
+            path.pop();
+        }
+    }
+    match visitor.leave(node, path) {
+        Walk::Stop => Walk::Stop,
+        _ => Walk::Continue,
+    }
+}
+
+/// Value of a tree with the nodes it is in
+#[derive(Debug, Clone)]
+pub struct Walked<'a> {
+    pub nodes: &'a Nodes,
+    /// Nodes the value is in, starting with the root
+    pub path: Vec<&'a Node>,
+}
+
+/// Iterates over everything in a node, each node comes before its children
+pub struct DepthFirst<'a> {
+    /// Children left to visit for each node in `path`
+    stack: Vec<vec::IntoIter<&'a Nodes>>,
+    path: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = Walked<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let nodes = match self.stack.last_mut()?.next() {
+                Some(nodes) => nodes,
+                None => {
+                    self.stack.pop();
+                    self.path.pop();
+                    continue;
+                }
+            };
+            let walked = Walked {
+                nodes,
+                path: self.path.clone(),
+            };
+            if let Nodes::Node(node) = nodes {
+                self.stack.push(node.children());
+                self.path.push(node);
+            }
+            return Some(walked);
+        }
+    }
+}
+
+/// Iterates over everything in a node level by level
+pub struct BreadthFirst<'a> {
+    queue: VecDeque<Walked<'a>>,
+}
+
+impl<'a> BreadthFirst<'a> {
+    fn push_children(&mut self, node: &'a Node, path: &[&'a Node]) {
+        for nodes in node.children() {
+            let mut path = path.to_vec();
+            path.push(node);
+            self.queue.push_back(Walked { nodes, path });
+        }
+    }
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = Walked<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let walked = self.queue.pop_front()?;
+        if let Nodes::Node(node) = walked.nodes {
+            self.push_children(node, &walked.path);
+        }
+        Some(walked)
+    }
+}
+
+impl Node {
+    /// Visits the node and everything in it
+    pub fn walk<'a, V: Visitor<'a>>(&'a self, visitor: &mut V) -> Walk {
+        walk(self, visitor)
+    }
+
+    /// Visits the node and everything in it, the visitor can change the values
+    pub fn walk_mut<V: VisitorMut>(&mut self, visitor: &mut V) -> Walk {
+        walk_mut(self, visitor)
+    }
+
+    /// Iterates over everything in the node, each node comes before its children
+    ///
+    /// The node itself is not included
+    pub fn depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst {
+            stack: vec![self.children()],
+            path: vec![self],
+        }
+    }
+
+    /// Iterates over everything in the node level by level
+    ///
+    /// The node itself is not included
+    pub fn breadth_first(&self) -> BreadthFirst<'_> {
+        let mut iter = BreadthFirst {
+            queue: VecDeque::new(),
+        };
+        iter.push_children(self, &[]);
+        iter
+    }
+
+    /// Returns the node and all the nodes in it named `name`, each node before its children
+    pub fn find_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> + 'a {
+        let this = match self.name == name {
+            true => Some(self),
+            false => None,
+        };
+        this.into_iter().chain(
+            self.depth_first()
+                .filter_map(move |walked| match walked.nodes {
+                    Nodes::Node(node) if node.name == name => Some(node),
+                    _ => None,
+                }),
+        )
+    }
+}
+
+impl ParseResult<'_> {
+    /// Visits the entry and then the global variables
+    ///
+    /// Paths of the global values start with the node the values are in, not the entry
+    pub fn walk<'a, V: Visitor<'a>>(&'a self, visitor: &mut V) -> Walk {
+        if walk(&self.entry, visitor) == Walk::Stop {
+            return Walk::Stop;
+        }
+        for nodes in self.global_values() {
+            let walk = match nodes {
+                Nodes::Node(node) => walk(node, visitor),
+                Nodes::Token(token) => visitor.token(token, &[]),
+            };
+            if walk == Walk::Stop {
+                return Walk::Stop;
+            }
+        }
+        Walk::Continue
+    }
+
+    /// Returns the nodes named `name` in the entry and the global variables
+    pub fn find_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> + 'a {
+        let globals = self
+            .global_values()
+            .into_iter()
+            .filter_map(|nodes| match nodes {
+                Nodes::Node(node) => Some(node),
+                Nodes::Token(_) => None,
+            });
+        self.entry
+            .find_all(name)
+            .chain(globals.flat_map(move |node| node.find_all(name)))
+    }
+
+    /// Values of the global variables in the order they appear in the text
+    fn global_values(&self) -> Vec<&Nodes> {
+        let mut values = Vec::new();
+        for variable in self.globals.values() {
+            match variable {
+                VariableKind::Node(Some(node)) => values.push(node),
+                VariableKind::NodeList(list) => values.extend(list),
+                _ => (),
+            }
+        }
+        values.sort_by_key(|nodes| nodes.span().start);
+        values
+    }
+}